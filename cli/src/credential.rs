@@ -49,7 +49,7 @@ pub async fn issue(args: CredentialIssueArgs) -> Result<()> {
     let credential_reader = BufReader::new(stdin());
     let mut credential: VerifiableCredential = serde_json::from_reader(credential_reader).unwrap();
     let proof_format = args.proof_options.proof_format.clone();
-    let jwk_opt: Option<JWK> = args.key.get_jwk_opt();
+    let jwk_opt: Option<JWK> = args.key.get_jwk_opt()?;
     let ssh_agent_sock_opt = if args.key.ssh_agent {
         Some(get_ssh_agent_sock())
     } else {
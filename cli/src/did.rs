@@ -4,24 +4,29 @@ use std::{
     path::PathBuf,
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::DateTime;
 use clap::{Args, Subcommand};
 use didkit::{
-    generate_proof,
+    did_document, did_lint, generate_proof, offline_tx,
     ssi::{
         did::{DIDMethodTransaction, Service, ServiceEndpoint, VerificationMethodMap},
         vc::OneOrMany,
     },
     ContextLoader, DIDCreate, DIDDeactivate, DIDDocumentOperation, DIDMethod, DIDRecover,
-    DIDResolver, DIDUpdate, DereferencingInputMetadata, LinkedDataProofOptions, ProofFormat,
-    ResolutionInputMetadata, ResolutionResult, VerifiablePresentation, DID_METHODS, JWK, URI,
+    DIDResolver, DIDUpdate, DereferencingInputMetadata, Document, LinkedDataProofOptions,
+    ProofFormat, ResolutionInputMetadata, ResolutionResult, VerifiablePresentation, JWK, URI,
 };
 use serde_json::json;
 
 use crate::{
-    get_ssh_agent_sock, metadata_properties_to_value, opts::ResolverOptions,
-    parse_service_endpoint, read_jwk_file_opt, IdAndDid, KeyArg, MetadataProperty, ProofOptions,
-    PublicKeyArg, PublicKeyArgEnum, PublicKeyProperty, VerificationRelationships,
+    did_methods,
+    did_peer::{self, DidPeerCmd},
+    did_web::{self, DidWebCmd},
+    get_ssh_agent_sock, metadata_properties_to_value,
+    opts::ResolverOptions,
+    parse_service_endpoint, read_json_file, read_jwk_file_opt, IdAndDid, KeyArg, MetadataProperty,
+    ProofOptions, PublicKeyArg, PublicKeyArgEnum, PublicKeyProperty, VerificationRelationships,
 };
 
 #[derive(Subcommand)]
@@ -39,6 +44,16 @@ pub enum DidCmd {
     ///
     /// Reads from standard input.
     SubmitTx,
+    /// Sign an unsigned DID method transaction
+    ///
+    /// Reads an unsigned transaction, as output with the --unsigned option, from standard input.
+    /// Outputs the signed transaction, for `didkit did submit-tx`.
+    SignTx(DidSignTxArgs),
+    /// Complete an unsigned DID method transaction with a signature from an external signer
+    ///
+    /// Reads an unsigned transaction from standard input. The signature must be of its
+    /// signingInput, with its algorithm and public key. Outputs the signed transaction.
+    CompleteTx(DidCompleteTxArgs),
     /// Update a DID.
     Update(DidUpdateArgs),
     /// Recover a DID.
@@ -47,10 +62,28 @@ pub enum DidCmd {
     Resolve(DidResolveArgs),
     /// Dereference a DID URL to a resource.
     Dereference(DidDereferenceArgs),
+    /// Check a DID document for DID Core conformance and common mistakes.
+    ///
+    /// Outputs findings as JSON, with severities. Exits with status 2 if there are errors.
+    Lint(DidLintArgs),
+    /// Compare two DID documents.
+    ///
+    /// Outputs the changes as JSON, with JSON Pointer paths.
+    Diff(DidDiffArgs),
+    /// Plan the DID document operations that update a DID to a target DID document.
+    ///
+    /// Outputs the plan as JSON, which may be applied with `didkit did update apply`.
+    Plan(DidPlanArgs),
     /// Authenticate with a DID.
     Authenticate(Box<DidAuthenticateArgs>),
     /// Deactivate a DID.
     Deactivate(DidDeactivateArgs),
+    /// Manage did:web DID documents.
+    #[clap(subcommand)]
+    Web(DidWebCmd),
+    /// Create did:peer DIDs.
+    #[clap(subcommand)]
+    Peer(DidPeerCmd),
 }
 
 #[derive(Args)]
@@ -71,6 +104,11 @@ pub struct DidCreateArgs {
     ///
     /// More info: https://identity.foundation/did-registration/#options
     options: Vec<MetadataProperty>,
+    /// Build the transaction from public keys only
+    ///
+    /// Supported for did:ion, whose create transactions need no signature.
+    #[clap(long, requires_all = ["update_key", "recovery_key"])]
+    public_keys_only: bool,
 }
 
 #[derive(Args)]
@@ -86,6 +124,16 @@ pub struct DidUpdateArgs {
     ///
     /// More info: https://identity.foundation/did-registration/#options
     options: Vec<MetadataProperty>,
+    /// Apply the update to a local DID document file instead of creating a DID method transaction
+    ///
+    /// For DID methods such as did:web whose DID documents are published as files.
+    #[clap(long, conflicts_with_all = ["new_update_key", "update_key"])]
+    document: Option<PathBuf>,
+    /// Build an unsigned transaction from public keys only, to sign with `didkit did sign-tx`
+    ///
+    /// The key options are then public keys. Supported for did:ion.
+    #[clap(long, conflicts_with = "document", requires_all = ["new_update_key", "update_key"])]
+    unsigned: bool,
     #[clap(subcommand)]
     cmd: DIDUpdateCmd,
 }
@@ -111,6 +159,11 @@ pub struct DidRecoverArgs {
     ///
     /// More info: https://identity.foundation/did-registration/#options
     options: Vec<MetadataProperty>,
+    /// Build an unsigned transaction from public keys only, to sign with `didkit did sign-tx`
+    ///
+    /// The key options are then public keys. Supported for did:ion.
+    #[clap(long, requires_all = ["recovery_key", "new_recovery_key", "new_update_key"])]
+    unsigned: bool,
 }
 
 #[derive(Args)]
@@ -123,10 +176,21 @@ pub struct DidResolveArgs {
     #[clap(short = 'i', name = "name=value")]
     /// DID resolution input metadata
     input_metadata: Vec<MetadataProperty>,
+    /// Resolve a specific version of the DID document
+    #[clap(long)]
+    version_id: Option<String>,
+    /// Resolve the version of the DID document that was valid at a time (RFC 3339)
+    #[clap(long, value_parser(parse_version_time))]
+    version_time: Option<String>,
     #[clap(flatten)]
     resolver_options: ResolverOptions,
 }
 
+fn parse_version_time(version_time: &str) -> Result<String> {
+    DateTime::parse_from_rfc3339(version_time).context("Parse version time")?;
+    Ok(version_time.to_string())
+}
+
 #[derive(Args)]
 pub struct DidDereferenceArgs {
     /// DID to dereference
@@ -141,6 +205,39 @@ pub struct DidDereferenceArgs {
     resolver_options: ResolverOptions,
 }
 
+#[derive(Args)]
+pub struct DidLintArgs {
+    /// DID to resolve and lint
+    #[clap(required_unless_present = "file", conflicts_with = "file")]
+    did: Option<String>,
+    /// DID document file to lint, instead of resolving a DID
+    #[clap(short, long)]
+    file: Option<PathBuf>,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct DidDiffArgs {
+    /// Old DID document file, or DID to resolve
+    old: String,
+    /// New DID document file, or DID to resolve
+    new: String,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct DidPlanArgs {
+    /// DID to update
+    did: String,
+    /// Target DID document file
+    #[clap(short, long)]
+    target: PathBuf,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
 #[derive(Args)]
 pub struct DidAuthenticateArgs {
     #[clap(flatten)]
@@ -163,6 +260,24 @@ pub struct DidDeactivateArgs {
     #[clap(short = 'o', name = "name=value")]
     /// Options for DID deactivate operation
     options: Vec<MetadataProperty>,
+    /// Build an unsigned transaction from a public key only, to sign with `didkit did sign-tx`
+    ///
+    /// The key option is then the public recovery key. Supported for did:ion.
+    #[clap(long, requires = "key")]
+    unsigned: bool,
+}
+
+#[derive(Args)]
+pub struct DidSignTxArgs {
+    #[clap(flatten)]
+    key: KeyArg,
+}
+
+#[derive(Args)]
+pub struct DidCompleteTxArgs {
+    /// Signature of the signing input of the unsigned transaction, base64url-encoded
+    #[clap(short, long)]
+    signature: String,
 }
 
 #[derive(Subcommand, Debug)]
@@ -181,6 +296,18 @@ pub enum DIDUpdateCmd {
     /// Remove a parameter in the DID document
     #[clap(subcommand)]
     Remove(DIDUpdateRemoveCmd),
+    /// Apply a plan output by `didkit did plan`
+    ///
+    /// DID method transactions contain a single operation, so a plan with multiple operations can
+    /// only be applied to a local DID document (the --document option), or as an unsigned did:ion
+    /// update (the --unsigned option).
+    Apply(DIDUpdateApplyArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct DIDUpdateApplyArgs {
+    /// Plan file
+    plan: PathBuf,
 }
 
 #[derive(Subcommand, Debug)]
@@ -234,18 +361,25 @@ pub async fn cli(cmd: DidCmd) -> Result<()> {
         DidCmd::Create(a) => create(a).await?,
         DidCmd::FromTx => from_tx().await?,
         DidCmd::SubmitTx => submit_tx().await?,
+        DidCmd::SignTx(a) => sign_tx(a).await?,
+        DidCmd::CompleteTx(a) => complete_tx(a).await?,
         DidCmd::Update(a) => update(a).await?,
         DidCmd::Recover(a) => recover(a).await?,
         DidCmd::Resolve(a) => resolve(a).await?,
         DidCmd::Dereference(a) => dereference(a).await?,
+        DidCmd::Lint(a) => lint(a).await?,
+        DidCmd::Diff(a) => diff(a).await?,
+        DidCmd::Plan(a) => plan(a).await?,
         DidCmd::Authenticate(a) => authenticate(*a).await?,
         DidCmd::Deactivate(a) => deactivate(a).await?,
+        DidCmd::Web(cmd) => did_web::cli(cmd)?,
+        DidCmd::Peer(cmd) => did_peer::cli(cmd)?,
     };
     Ok(())
 }
 
 pub async fn create(args: DidCreateArgs) -> Result<()> {
-    let method = DID_METHODS
+    let method = did_methods()
         .get(&args.method)
         .ok_or(anyhow!("Unable to get DID method"))?;
     let verification_key = read_jwk_file_opt(&args.verification_key)
@@ -258,6 +392,23 @@ pub async fn create(args: DidCreateArgs) -> Result<()> {
         metadata_properties_to_value(args.options).context("Parse options for DID Create")?;
     let options = serde_json::from_value(options).context("Unable to convert options")?;
 
+    if args.public_keys_only {
+        let (update_key, recovery_key) = match (update_key, recovery_key) {
+            (Some(update_key), Some(recovery_key)) => (update_key, recovery_key),
+            _ => bail!("Update and recovery public keys are required"),
+        };
+        let tx = offline_tx::create(
+            method.name(),
+            &update_key,
+            &recovery_key,
+            verification_key.as_ref(),
+        )
+        .context("Build DID Create transaction")?;
+        let stdout_writer = BufWriter::new(stdout());
+        serde_json::to_writer_pretty(stdout_writer, &tx).unwrap();
+        println!();
+        return Ok(());
+    }
     let tx = method
         .create(DIDCreate {
             recovery_key,
@@ -275,7 +426,7 @@ pub async fn create(args: DidCreateArgs) -> Result<()> {
 pub async fn from_tx() -> Result<()> {
     let stdin_reader = BufReader::new(stdin());
     let tx: DIDMethodTransaction = serde_json::from_reader(stdin_reader).unwrap();
-    let method = DID_METHODS
+    let method = did_methods()
         .get(&tx.did_method)
         .ok_or(anyhow!("Unable to get DID method"))?;
     let did = method
@@ -288,7 +439,7 @@ pub async fn from_tx() -> Result<()> {
 pub async fn submit_tx() -> Result<()> {
     let stdin_reader = BufReader::new(stdin());
     let tx: DIDMethodTransaction = serde_json::from_reader(stdin_reader).unwrap();
-    let method = DID_METHODS
+    let method = did_methods()
         .get(&tx.did_method)
         .ok_or(anyhow!("Unable to get DID method"))?;
     let result = method
@@ -301,6 +452,52 @@ pub async fn submit_tx() -> Result<()> {
     Ok(())
 }
 
+pub async fn sign_tx(args: DidSignTxArgs) -> Result<()> {
+    let stdin_reader = BufReader::new(stdin());
+    let unsigned: offline_tx::UnsignedTransaction =
+        serde_json::from_reader(stdin_reader).context("Read unsigned transaction")?;
+    let key = args.key.get_jwk()?;
+    let tx = unsigned.sign(&key).context("Sign transaction")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &tx).unwrap();
+    println!();
+    Ok(())
+}
+
+pub async fn complete_tx(args: DidCompleteTxArgs) -> Result<()> {
+    let stdin_reader = BufReader::new(stdin());
+    let unsigned: offline_tx::UnsignedTransaction =
+        serde_json::from_reader(stdin_reader).context("Read unsigned transaction")?;
+    let signature = base64::decode_config(args.signature.trim(), base64::URL_SAFE_NO_PAD)
+        .context("Decode signature")?;
+    let tx = unsigned
+        .complete(&signature)
+        .context("Complete transaction")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &tx).unwrap();
+    println!();
+    Ok(())
+}
+
+/// Build an unsigned transaction, for the --unsigned option
+fn unsigned_update(
+    did: &str,
+    update_key: Option<JWK>,
+    new_update_key: Option<JWK>,
+    operations: Vec<DIDDocumentOperation>,
+) -> Result<()> {
+    let (update_key, new_update_key) = match (update_key, new_update_key) {
+        (Some(update_key), Some(new_update_key)) => (update_key, new_update_key),
+        _ => bail!("Update and new update public keys are required"),
+    };
+    let unsigned = offline_tx::update(did, &update_key, &new_update_key, operations)
+        .context("Build unsigned DID Update transaction")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &unsigned).unwrap();
+    println!();
+    Ok(())
+}
+
 pub async fn update(args: DidUpdateArgs) -> Result<()> {
     let new_update_key =
         read_jwk_file_opt(&args.new_update_key).context("Read new update key for DID update")?;
@@ -311,6 +508,14 @@ pub async fn update(args: DidUpdateArgs) -> Result<()> {
     let options = serde_json::from_value(options).context("Unable to convert options")?;
 
     let (did, method, operation) = match args.cmd {
+        DIDUpdateCmd::Apply(apply_args) => {
+            let plan: did_document::UpdatePlan =
+                read_json_file(&apply_args.plan).context("Read update plan")?;
+            if args.unsigned {
+                return unsigned_update(&plan.did, update_key, new_update_key, plan.operations);
+            }
+            return apply_plan(plan, args.document, update_key, new_update_key, options);
+        }
         DIDUpdateCmd::SetVerificationMethod(args) => {
             update_set(DIDUpdateSetCmd::VerificationMethod(args)).await?
         }
@@ -324,6 +529,65 @@ pub async fn update(args: DidUpdateArgs) -> Result<()> {
         DIDUpdateCmd::Set(args) => update_set(args).await?,
         DIDUpdateCmd::Remove(args) => update_remove(args).await?,
     };
+    if let Some(path) = args.document {
+        let document: Document = read_json_file(&path).context("Read DID document")?;
+        if document.id != did {
+            bail!("DID document is for {}, not {}", document.id, did);
+        }
+        let document = did_document::apply_operation(&document, operation)
+            .context("Apply update to DID document")?;
+        did_web::write_document(&path, &document)?;
+        return Ok(());
+    }
+    if args.unsigned {
+        return unsigned_update(&did, update_key, new_update_key, vec![operation]);
+    }
+    let tx = method
+        .update(DIDUpdate {
+            did,
+            update_key,
+            new_update_key,
+            operation,
+            options,
+        })
+        .context("DID Update failed")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &tx).unwrap();
+    println!();
+    Ok(())
+}
+
+fn apply_plan(
+    plan: did_document::UpdatePlan,
+    document_path: Option<PathBuf>,
+    update_key: Option<JWK>,
+    new_update_key: Option<JWK>,
+    options: serde_json::Map<String, serde_json::Value>,
+) -> Result<()> {
+    let did = plan.did;
+    if let Some(path) = document_path {
+        let mut document: Document = read_json_file(&path).context("Read DID document")?;
+        if document.id != did {
+            bail!("DID document is for {}, not {}", document.id, did);
+        }
+        for operation in plan.operations {
+            document = did_document::apply_operation(&document, operation)
+                .context("Apply update to DID document")?;
+        }
+        did_web::write_document(&path, &document)?;
+        return Ok(());
+    }
+    let mut operations = plan.operations.into_iter();
+    let operation = match (operations.next(), operations.next()) {
+        (Some(operation), None) => operation,
+        (None, _) => bail!("Plan has no operations"),
+        (Some(_), Some(_)) => bail!(
+            "Plan has multiple operations, but a DID update transaction contains one operation"
+        ),
+    };
+    let method = did_methods()
+        .get_method(&did)
+        .map_err(|e| anyhow!("Unable to get DID method: {}", e))?;
     let tx = method
         .update(DIDUpdate {
             did,
@@ -430,7 +694,7 @@ pub async fn update_remove<'a>(
 }
 
 pub async fn recover(args: DidRecoverArgs) -> Result<()> {
-    let method = DID_METHODS
+    let method = did_methods()
         .get_method(&args.did)
         .map_err(|e| anyhow!("Unable to get DID method: {}", e))?;
     let new_verification_key = read_jwk_file_opt(&args.new_verification_key)
@@ -445,6 +709,27 @@ pub async fn recover(args: DidRecoverArgs) -> Result<()> {
         metadata_properties_to_value(args.options).context("Parse options for DID recovery")?;
     let options = serde_json::from_value(options).context("Unable to convert options")?;
 
+    if args.unsigned {
+        let (recovery_key, new_recovery_key, new_update_key) =
+            match (recovery_key, new_recovery_key, new_update_key) {
+                (Some(recovery_key), Some(new_recovery_key), Some(new_update_key)) => {
+                    (recovery_key, new_recovery_key, new_update_key)
+                }
+                _ => bail!("Recovery, new recovery and new update public keys are required"),
+            };
+        let unsigned = offline_tx::recover(
+            &args.did,
+            &recovery_key,
+            &new_recovery_key,
+            &new_update_key,
+            new_verification_key.as_ref(),
+        )
+        .context("Build unsigned DID Recover transaction")?;
+        let stdout_writer = BufWriter::new(stdout());
+        serde_json::to_writer_pretty(stdout_writer, &unsigned).unwrap();
+        println!();
+        return Ok(());
+    }
     let tx = method
         .recover(DIDRecover {
             did: args.did.clone(),
@@ -464,8 +749,14 @@ pub async fn recover(args: DidRecoverArgs) -> Result<()> {
 pub async fn resolve(args: DidResolveArgs) -> Result<()> {
     let resolver = args.resolver_options.to_resolver();
     let res_input_meta_value = metadata_properties_to_value(args.input_metadata).unwrap();
-    let res_input_meta: ResolutionInputMetadata =
+    let mut res_input_meta: ResolutionInputMetadata =
         serde_json::from_value(res_input_meta_value).unwrap();
+    if args.version_id.is_some() {
+        res_input_meta.version_id = args.version_id;
+    }
+    if args.version_time.is_some() {
+        res_input_meta.version_time = args.version_time;
+    }
     if args.with_metadata {
         let (res_meta, doc_opt, doc_meta_opt) = resolver.resolve(&args.did, &res_input_meta).await;
         let error = res_meta.error.is_some();
@@ -518,6 +809,81 @@ pub async fn dereference(args: DidDereferenceArgs) -> Result<()> {
     Ok(())
 }
 
+pub async fn lint(args: DidLintArgs) -> Result<()> {
+    let document: serde_json::Value = match (args.did, args.file) {
+        (_, Some(path)) => read_json_file(&path).context("Read DID document")?,
+        (Some(did), None) => {
+            let resolver = args.resolver_options.to_resolver();
+            let (res_meta, doc_data, _doc_meta_opt) = resolver
+                .resolve_representation(&did, &ResolutionInputMetadata::default())
+                .await;
+            if let Some(err) = res_meta.error {
+                bail!("Unable to resolve DID: {}", err);
+            }
+            serde_json::from_slice(&doc_data).context("Parse resolved DID document")?
+        }
+        (None, None) => bail!("Missing DID or DID document file"),
+    };
+    let findings = did_lint::lint(&document);
+    let error = findings
+        .iter()
+        .any(|finding| finding.severity == did_lint::Severity::Error);
+    let result = json!({
+        "valid": !error,
+        "findings": findings,
+    });
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &result).unwrap();
+    println!();
+    if error {
+        std::process::exit(2);
+    }
+    Ok(())
+}
+
+/// Read a DID document file, or resolve a DID if there is no such file
+async fn read_or_resolve_document(
+    document: &str,
+    resolver_options: &ResolverOptions,
+) -> Result<Document> {
+    let path = PathBuf::from(document);
+    if path.exists() || !document.starts_with("did:") {
+        return read_json_file(&path).context("Read DID document");
+    }
+    let resolver = resolver_options.to_resolver();
+    let (res_meta, doc_opt, _doc_meta_opt) = resolver
+        .resolve(document, &ResolutionInputMetadata::default())
+        .await;
+    if let Some(err) = res_meta.error {
+        bail!("Unable to resolve {}: {}", document, err);
+    }
+    doc_opt.ok_or_else(|| anyhow!("Missing DID document for {}", document))
+}
+
+pub async fn diff(args: DidDiffArgs) -> Result<()> {
+    let old = read_or_resolve_document(&args.old, &args.resolver_options).await?;
+    let new = read_or_resolve_document(&args.new, &args.resolver_options).await?;
+    let changes = did_document::diff(&old, &new).context("Compare DID documents")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &changes).unwrap();
+    println!();
+    Ok(())
+}
+
+pub async fn plan(args: DidPlanArgs) -> Result<()> {
+    let current = read_or_resolve_document(&args.did, &args.resolver_options).await?;
+    let target: Document = read_json_file(&args.target).context("Read target DID document")?;
+    let operations = did_document::plan(&current, &target).context("Plan DID update")?;
+    let plan = did_document::UpdatePlan {
+        did: current.id,
+        operations,
+    };
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &plan).unwrap();
+    println!();
+    Ok(())
+}
+
 pub async fn authenticate(args: DidAuthenticateArgs) -> Result<()> {
     let resolver = args.resolver_options.to_resolver();
     let mut context_loader = ContextLoader::default();
@@ -526,7 +892,7 @@ pub async fn authenticate(args: DidAuthenticateArgs) -> Result<()> {
         ..Default::default()
     };
     let proof_format = args.proof_options.proof_format.clone();
-    let jwk_opt: Option<JWK> = args.key.get_jwk_opt();
+    let jwk_opt: Option<JWK> = args.key.get_jwk_opt()?;
     let ssh_agent_sock_opt = if args.key.ssh_agent {
         Some(get_ssh_agent_sock())
     } else {
@@ -567,7 +933,7 @@ pub async fn authenticate(args: DidAuthenticateArgs) -> Result<()> {
 }
 
 pub async fn deactivate(args: DidDeactivateArgs) -> Result<()> {
-    let method = DID_METHODS
+    let method = did_methods()
         .get_method(&args.did)
         .map_err(|e| anyhow!("Unable to get DID method: {}", e))?;
     let key = read_jwk_file_opt(&args.key).context("Read key for DID deactivation")?;
@@ -575,6 +941,15 @@ pub async fn deactivate(args: DidDeactivateArgs) -> Result<()> {
         metadata_properties_to_value(args.options).context("Parse options for DID deactivation")?;
     let options = serde_json::from_value(options).context("Unable to convert options")?;
 
+    if args.unsigned {
+        let key = key.ok_or_else(|| anyhow!("Recovery public key is required"))?;
+        let unsigned = offline_tx::deactivate(&args.did, &key)
+            .context("Build unsigned DID Deactivate transaction")?;
+        let stdout_writer = BufWriter::new(stdout());
+        serde_json::to_writer_pretty(stdout_writer, &unsigned).unwrap();
+        println!();
+        return Ok(());
+    }
     let tx = method
         .deactivate(DIDDeactivate {
             did: args.did.clone(),
@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use didkit::{did_peer, VerificationRelationship, JWK};
+use serde_json::{json, Value};
+
+use crate::read_json_file;
+
+#[derive(Subcommand)]
+pub enum DidPeerCmd {
+    /// Create a did:peer DID from keys and services
+    ///
+    /// Numalgo 0 takes a single key. Numalgo 2 and 4 take any number of keys and services.
+    /// Outputs the DID; for numalgo 4, this is the long form DID.
+    ///
+    /// For a single key, `didkit key to did peer:2` may be used instead.
+    Create(DidPeerCreateArgs),
+}
+
+#[derive(Args)]
+pub struct DidPeerCreateArgs {
+    /// did:peer numalgo: 0, 2 or 4
+    #[clap(short, long, default_value_t = 2)]
+    numalgo: u8,
+    /// JWK file of a key to use for authentication and assertions
+    #[clap(short = 'v', long)]
+    verification_key: Vec<PathBuf>,
+    /// JWK file of a key to use for key agreement (e.g. X25519)
+    #[clap(short = 'e', long)]
+    key_agreement_key: Vec<PathBuf>,
+    /// Service, as a JSON object (e.g. a DIDCommMessaging service)
+    #[clap(short, long, value_parser(parse_service))]
+    service: Vec<Value>,
+}
+
+fn parse_service(service: &str) -> Result<Value> {
+    let service: Value = serde_json::from_str(service).context("Parse service JSON")?;
+    if !service.is_object() {
+        bail!("Service must be a JSON object");
+    }
+    Ok(service)
+}
+
+pub fn cli(cmd: DidPeerCmd) -> Result<()> {
+    match cmd {
+        DidPeerCmd::Create(args) => create(args)?,
+    };
+    Ok(())
+}
+
+pub fn create(args: DidPeerCreateArgs) -> Result<()> {
+    let mut keys: Vec<(JWK, VerificationRelationship)> = Vec::new();
+    for path in &args.key_agreement_key {
+        let jwk: JWK = read_json_file(path).context("Read key agreement key")?;
+        keys.push((jwk.to_public(), VerificationRelationship::KeyAgreement));
+    }
+    for path in &args.verification_key {
+        let jwk: JWK = read_json_file(path).context("Read verification key")?;
+        keys.push((jwk.to_public(), VerificationRelationship::Authentication));
+        keys.push((jwk.to_public(), VerificationRelationship::AssertionMethod));
+    }
+    let did = match args.numalgo {
+        0 => {
+            if args.key_agreement_key.len() + args.verification_key.len() != 1
+                || !args.service.is_empty()
+            {
+                bail!("Numalgo 0 requires exactly one key and no services");
+            }
+            did_peer::generate_numalgo0(&keys[0].0)?
+        }
+        2 => did_peer::generate_numalgo2(&keys, &args.service)?,
+        4 => {
+            let mut document = json!({
+                "@context": [
+                    "https://www.w3.org/ns/did/v1",
+                    "https://w3id.org/security/suites/jws-2020/v1"
+                ],
+            });
+            let mut verification_methods = Vec::new();
+            for (jwk, relationship) in keys {
+                let property = match relationship {
+                    VerificationRelationship::KeyAgreement => "keyAgreement",
+                    VerificationRelationship::Authentication => "authentication",
+                    _ => "assertionMethod",
+                };
+                // Both relationships of a verification key refer to the same verification method.
+                let id = match verification_methods
+                    .iter()
+                    .find(|(_, vm_jwk)| vm_jwk == &jwk)
+                {
+                    Some((id, _)) => id.clone(),
+                    None => {
+                        let id = format!("#key-{}", verification_methods.len() + 1);
+                        verification_methods.push((id.clone(), jwk));
+                        id
+                    }
+                };
+                match document[property].as_array_mut() {
+                    Some(ids) => ids.push(json!(id)),
+                    None => document[property] = json!([id]),
+                }
+            }
+            document["verificationMethod"] = verification_methods
+                .into_iter()
+                .map(|(id, jwk)| {
+                    json!({
+                        "id": id,
+                        "type": "JsonWebKey2020",
+                        "publicKeyJwk": jwk,
+                    })
+                })
+                .collect();
+            if !args.service.is_empty() {
+                document["service"] = Value::Array(args.service);
+            }
+            did_peer::generate_numalgo4(&document)?
+        }
+        numalgo => bail!("Unsupported numalgo: {numalgo}"),
+    };
+    println!("{did}");
+    Ok(())
+}
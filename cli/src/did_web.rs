@@ -0,0 +1,115 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use didkit::{
+    did_web::{self, WebKey},
+    ssi::{did::Service, vc::OneOrMany},
+    Document, VerificationRelationship,
+};
+
+use crate::{parse_service_endpoint, read_json_file, VerificationRelationships};
+
+#[derive(Subcommand)]
+pub enum DidWebCmd {
+    /// Create a did:web DID document
+    ///
+    /// Builds a DID document from one or more keys and services, and writes it to
+    /// `.well-known/did.json` (or `<path>/did.json` for a DID with a path) in the output
+    /// directory, which should mirror the web server root. Outputs the DID.
+    ///
+    /// To add keys with other verification relationships, use `didkit did update --document`.
+    Init(DidWebInitArgs),
+}
+
+#[derive(Args)]
+pub struct DidWebInitArgs {
+    /// Domain name and optional colon-separated path, e.g. `example.com` or
+    /// `example.com:user:alice`
+    #[clap(long)]
+    domain: String,
+    /// JWK file of a key to add as a verification method
+    #[clap(short, long, required = true)]
+    key_path: Vec<PathBuf>,
+    #[clap(flatten)]
+    verification_relationships: VerificationRelationships,
+    /// Service to add, as `<id>,<type>,<endpoint>`
+    ///
+    /// The id may be relative, e.g. `#linked-domain`. The endpoint is a URI or JSON object.
+    #[clap(short, long, value_parser(parse_service))]
+    service: Vec<Service>,
+    /// Directory to write the DID document in
+    #[clap(short, long, default_value = ".")]
+    output_dir: PathBuf,
+    /// Overwrite an existing DID document
+    #[clap(long)]
+    force: bool,
+}
+
+fn parse_service(service: &str) -> Result<Service> {
+    let mut parts = service.splitn(3, ',');
+    let (id, type_, endpoint) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(id), Some(type_), Some(endpoint)) => (id, type_, endpoint),
+        _ => bail!("Expected <id>,<type>,<endpoint>"),
+    };
+    Ok(Service {
+        id: id.to_string(),
+        type_: OneOrMany::One(type_.to_string()),
+        service_endpoint: Some(OneOrMany::One(parse_service_endpoint(endpoint)?)),
+        property_set: None,
+    })
+}
+
+pub fn cli(cmd: DidWebCmd) -> Result<()> {
+    match cmd {
+        DidWebCmd::Init(args) => init(args)?,
+    };
+    Ok(())
+}
+
+/// Write a DID document to a file, refusing to publish private key material
+pub fn write_document(path: &Path, document: &Document) -> Result<()> {
+    did_web::check_public(document)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("Create DID document directory")?;
+    }
+    let file = File::create(path).context("Create DID document file")?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, document)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn init(args: DidWebInitArgs) -> Result<()> {
+    let did = did_web::did_from_domain(&args.domain)?;
+    let path = args.output_dir.join(did_web::document_path(&did)?);
+    if path.exists() && !args.force {
+        bail!(
+            "DID document already exists at {}; use --force to overwrite it",
+            path.display()
+        );
+    }
+    let purposes: Vec<VerificationRelationship> = args.verification_relationships.into();
+    let keys = args
+        .key_path
+        .into_iter()
+        .map(|key_path| {
+            let jwk = read_json_file(&key_path)?;
+            Ok(WebKey {
+                jwk,
+                purposes: purposes.clone(),
+            })
+        })
+        .collect::<Result<Vec<WebKey>>>()
+        .context("Read key")?;
+    let document = did_web::build_document(&did, keys, args.service)?;
+    write_document(&path, &document)?;
+    eprintln!("Wrote DID document to {}", path.display());
+    println!("{did}");
+    Ok(())
+}
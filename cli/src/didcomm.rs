@@ -0,0 +1,153 @@
+use std::{
+    io::{stdin, stdout, BufReader, BufWriter, Read},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use didkit::{
+    didcomm::{self, ContentEncryption, Message, SenderKey},
+    JWK,
+};
+
+use crate::{opts::ResolverOptions, read_json_file};
+
+#[derive(Subcommand)]
+pub enum DidcommCmd {
+    /// Pack a DIDComm v2 message
+    ///
+    /// Reads the plaintext message from standard input. With --to, the message is encrypted
+    /// (authcrypt if a sender key is given, otherwise anoncrypt). With a signing key, it is
+    /// signed. Otherwise the plaintext message is output.
+    Pack(Box<DidcommPackArgs>),
+    /// Unpack a DIDComm v2 message
+    ///
+    /// Reads the packed message from standard input. Outputs the plaintext message and metadata
+    /// describing how it was protected.
+    Unpack(DidcommUnpackArgs),
+    /// Discover DIDComm v2 messaging service endpoints of a DID
+    Discover(DidcommDiscoverArgs),
+}
+
+#[derive(Args)]
+pub struct DidcommPackArgs {
+    /// Recipient DID, or DID URL of a recipient key agreement key
+    #[clap(short, long)]
+    to: Vec<String>,
+    /// DID URL of the sender's key agreement key, for authenticated encryption
+    #[clap(long, requires = "sender_key_path")]
+    sender_kid: Option<String>,
+    /// JWK file of the sender's key agreement key
+    #[clap(long, requires = "sender_kid")]
+    sender_key_path: Option<PathBuf>,
+    /// DID URL of the signing key, for a signed message
+    #[clap(long, requires = "sign_key_path")]
+    sign_kid: Option<String>,
+    /// JWK file of the signing key
+    #[clap(long, requires = "sign_kid")]
+    sign_key_path: Option<PathBuf>,
+    /// Content encryption algorithm
+    #[clap(long, default_value_t)]
+    enc: ContentEncryption,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct DidcommUnpackArgs {
+    /// JWK file of a recipient key agreement key
+    #[clap(short, long)]
+    key_path: Vec<PathBuf>,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct DidcommDiscoverArgs {
+    /// DID to discover messaging service endpoints of
+    did: String,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+pub async fn cli(cmd: DidcommCmd) -> Result<()> {
+    match cmd {
+        DidcommCmd::Pack(args) => pack(*args).await?,
+        DidcommCmd::Unpack(args) => unpack(args).await?,
+        DidcommCmd::Discover(args) => discover(args).await?,
+    };
+    Ok(())
+}
+
+pub async fn pack(args: DidcommPackArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let message_reader = BufReader::new(stdin());
+    let message: Message = serde_json::from_reader(message_reader).context("Read message")?;
+    let sender_key: Option<JWK> = match &args.sender_key_path {
+        Some(path) => Some(read_json_file(path).context("Read sender key")?),
+        None => None,
+    };
+    let sign_key: Option<JWK> = match &args.sign_key_path {
+        Some(path) => Some(read_json_file(path).context("Read signing key")?),
+        None => None,
+    };
+    let sender = match (&args.sender_kid, &sender_key) {
+        (Some(kid), Some(key)) => Some(SenderKey { kid, key }),
+        _ => None,
+    };
+    let signer = match (&args.sign_kid, &sign_key) {
+        (Some(kid), Some(key)) => Some(SenderKey { kid, key }),
+        _ => None,
+    };
+    let packed = if !args.to.is_empty() {
+        didcomm::pack_encrypted(
+            &message,
+            &args.to,
+            sender.as_ref(),
+            signer.as_ref(),
+            args.enc,
+            &resolver,
+        )
+        .await
+        .context("Encrypt message")?
+    } else if sender.is_some() {
+        bail!("Authenticated encryption requires at least one recipient (--to)");
+    } else if let Some(signer) = signer {
+        didcomm::pack_signed(&message, &signer).context("Sign message")?
+    } else {
+        didcomm::pack_plaintext(&message)?
+    };
+    println!("{packed}");
+    Ok(())
+}
+
+pub async fn unpack(args: DidcommUnpackArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let keys = args
+        .key_path
+        .iter()
+        .map(|path| read_json_file(path).context("Read recipient key"))
+        .collect::<Result<Vec<JWK>>>()?;
+    let mut packed = String::new();
+    stdin()
+        .read_to_string(&mut packed)
+        .context("Read message")?;
+    let unpacked = didcomm::unpack(&packed, &keys, &resolver)
+        .await
+        .context("Unpack message")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &unpacked).unwrap();
+    println!();
+    Ok(())
+}
+
+pub async fn discover(args: DidcommDiscoverArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let services = didcomm::discover(&args.did, &resolver)
+        .await
+        .context("Discover messaging services")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &services).unwrap();
+    println!();
+    Ok(())
+}
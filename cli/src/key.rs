@@ -1,10 +1,24 @@
-use std::io::{stdout, BufWriter};
+use std::{
+    fs::File,
+    io::{stdin, stdout, BufWriter, Read, Write},
+    path::PathBuf,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Args, Subcommand};
-use didkit::{get_verification_method, ssi::ssh, Error, Source, DID_METHODS, JWK};
+use didkit::{
+    get_verification_method,
+    key_derive::{self, Curve},
+    key_format,
+    ssi::jwk::Params,
+    ssi::ssh,
+    Error, Source, JWK,
+};
+use serde_json::json;
 use sshkeys::PublicKey;
 
+use crate::did_methods;
+
 #[derive(Subcommand)]
 pub enum KeyCmd {
     /// Generate and output a keypair in JWK format
@@ -16,6 +30,20 @@ pub enum KeyCmd {
     /// Get a key (e.g. JWK) from other formats (e.g. SSH public key)
     #[clap(subcommand)]
     From(Box<KeyFromCmd>),
+    /// Describe a key and list the DIDs and verification methods it maps to
+    ///
+    /// Outputs the key type, curve, algorithm, JWK thumbprint, whether the private key is present,
+    /// and, for each DID method that can derive a DID from the key, the DID and its verification
+    /// method.
+    Inspect(KeyInspectArgs),
+    /// Derive a keypair in JWK format from a BIP-39 mnemonic phrase or a seed
+    ///
+    /// The mnemonic phrase is read from standard input, unless a hex-encoded seed is given with
+    /// --seed. Ed25519 keys are derived using SLIP-0010 and secp256k1 keys using BIP-32.
+    ///
+    /// With --did, the DID of the derived key is output instead of the JWK, so that e.g. did:key,
+    /// did:tz or did:pkh:eip155 DIDs can be recovered from a backup phrase.
+    Derive(KeyDeriveArgs),
 }
 
 #[derive(Subcommand)]
@@ -34,12 +62,35 @@ pub enum KeyToCmd {
     Did(KeyToDIDArgs),
     /// Output a verificationMethod DID URL for a JWK and DID method name/pattern
     VerificationMethod(KeyToVMArgs),
+    /// Output a key in PEM format (PKCS#8 or SubjectPublicKeyInfo, or SEC1 with --sec1)
+    Pem(KeyToPemArgs),
+    /// Output a key in DER-encoded PKCS#8 (or SubjectPublicKeyInfo) format
+    Pkcs8(KeyToFormatArgs),
+    /// Output a key as a multibase-encoded multicodec key, as in publicKeyMultibase
+    Multibase(KeyToFormatArgs),
+    /// Output a key in OpenSSH format (public key line, or private key with --private)
+    Openssh(KeyToFormatArgs),
+    /// Output the public key of a JWK
+    JwkPublic(KeyToJWKArgs),
+    /// Output the JWK thumbprint (RFC 7638) of a key
+    Thumbprint(KeyToJWKArgs),
 }
 
 #[derive(Subcommand)]
 pub enum KeyFromCmd {
     /// Convert a SSH public key to a JWK
     Ssh(KeyFromSSHArgs),
+    /// Convert a PEM key to a JWK
+    ///
+    /// Accepts PKCS#8 and SubjectPublicKeyInfo, SEC1 EC private keys, PKCS#1 RSA keys and OpenSSH
+    /// private keys.
+    Pem(KeyFromFileArgs),
+    /// Convert a DER-encoded PKCS#8 private key (or SubjectPublicKeyInfo public key) to a JWK
+    Pkcs8(KeyFromFileArgs),
+    /// Convert a multibase-encoded multicodec key (e.g. publicKeyMultibase) to a JWK
+    Multibase(KeyFromMultibaseArgs),
+    /// Convert an unencrypted OpenSSH private key to a JWK
+    OpensshPrivate(KeyFromFileArgs),
 }
 
 #[derive(Subcommand)]
@@ -50,6 +101,49 @@ pub enum KeyGenerateCmd {
     Secp256k1,
     /// Generate and output a P-256 keypair in JWK format
     Secp256r1,
+    /// Generate and output a P-384 keypair in JWK format
+    Secp384r1,
+    /// Generate and output a RSA keypair in JWK format
+    Rsa(KeyGenerateRsaArgs),
+}
+
+#[derive(Args)]
+pub struct KeyGenerateRsaArgs {
+    /// Key size in bits
+    #[clap(long, default_value_t = 2048)]
+    bits: usize,
+}
+
+#[derive(Args)]
+pub struct KeyDeriveArgs {
+    /// Hex-encoded seed to derive the key from, instead of a mnemonic phrase
+    #[clap(env = "DIDKIT_SEED", long, hide_env_values = true)]
+    seed: Option<String>,
+    /// BIP-39 passphrase of the mnemonic phrase
+    #[clap(
+        env = "DIDKIT_MNEMONIC_PASSPHRASE",
+        long,
+        hide_env_values = true,
+        conflicts_with = "seed"
+    )]
+    passphrase: Option<String>,
+    /// Curve of the key to derive: ed25519 or secp256k1
+    #[clap(short, long, default_value_t = Curve::Ed25519)]
+    curve: Curve,
+    /// Derivation path, e.g. `m/44'/60'/0'/0/0`.
+    /// Default: `m/44'/1729'/0'/0'` for ed25519, `m/44'/60'/0'/0/0` for secp256k1
+    #[clap(short, long)]
+    path: Option<String>,
+    /// Output the DID of the derived key for this DID method name or pattern (e.g. `key`, `tz`,
+    /// or `pkh:eip155`) instead of the JWK
+    #[clap(long)]
+    did: Option<String>,
+}
+
+#[derive(Args)]
+pub struct KeyInspectArgs {
+    #[clap(flatten)]
+    key: crate::KeyArg,
 }
 
 #[derive(Args)]
@@ -69,6 +163,42 @@ pub struct KeyToVMArgs {
     pub key: crate::KeyArg,
 }
 
+#[derive(Args)]
+pub struct KeyToFormatArgs {
+    /// Output the private key. Default: output the public key
+    #[clap(long)]
+    pub private: bool,
+    #[clap(flatten)]
+    pub key: crate::KeyArg,
+}
+
+#[derive(Args)]
+pub struct KeyToPemArgs {
+    /// Encode EC private keys as SEC1 ("EC PRIVATE KEY") instead of PKCS#8
+    #[clap(long, requires = "private")]
+    pub sec1: bool,
+    #[clap(flatten)]
+    pub format: KeyToFormatArgs,
+}
+
+#[derive(Args)]
+pub struct KeyToJWKArgs {
+    #[clap(flatten)]
+    pub key: crate::KeyArg,
+}
+
+#[derive(Args)]
+pub struct KeyFromFileArgs {
+    /// Key file. Default: read from standard input
+    path: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct KeyFromMultibaseArgs {
+    /// Multibase-encoded key
+    multibase: String,
+}
+
 #[derive(Args)]
 pub struct KeyFromSSHArgs {
     /// SSH Public Key
@@ -80,6 +210,8 @@ pub async fn cli(cmd: KeyCmd) -> Result<()> {
         KeyCmd::Generate(cmd_generate) => generate(cmd_generate).await?,
         KeyCmd::To(cmd_to) => to(*cmd_to).await?,
         KeyCmd::From(cmd_from) => from(*cmd_from).await?,
+        KeyCmd::Inspect(cmd_inspect) => inspect(cmd_inspect).await?,
+        KeyCmd::Derive(cmd_derive) => derive(cmd_derive)?,
     };
     Ok(())
 }
@@ -88,6 +220,12 @@ pub async fn to(cmd: KeyToCmd) -> Result<()> {
     match cmd {
         KeyToCmd::Did(cmd_did) => to_did(cmd_did).await?,
         KeyToCmd::VerificationMethod(cmd_vm) => to_vm(cmd_vm).await?,
+        KeyToCmd::Pem(cmd_pem) => to_pem(cmd_pem)?,
+        KeyToCmd::Pkcs8(cmd_pkcs8) => to_pkcs8(cmd_pkcs8)?,
+        KeyToCmd::Multibase(cmd_multibase) => to_multibase(cmd_multibase)?,
+        KeyToCmd::Openssh(cmd_openssh) => to_openssh(cmd_openssh)?,
+        KeyToCmd::JwkPublic(cmd_jwk) => to_jwk_public(cmd_jwk)?,
+        KeyToCmd::Thumbprint(cmd_jwk) => to_thumbprint(cmd_jwk)?,
     };
     Ok(())
 }
@@ -95,6 +233,10 @@ pub async fn to(cmd: KeyToCmd) -> Result<()> {
 pub async fn from(cmd: KeyFromCmd) -> Result<()> {
     match cmd {
         KeyFromCmd::Ssh(cmd_ssh) => from_ssh(cmd_ssh).await?,
+        KeyFromCmd::Pem(cmd_pem) => from_pem(cmd_pem)?,
+        KeyFromCmd::Pkcs8(cmd_pkcs8) => from_pkcs8(cmd_pkcs8)?,
+        KeyFromCmd::Multibase(cmd_multibase) => from_multibase(cmd_multibase)?,
+        KeyFromCmd::OpensshPrivate(cmd_openssh) => from_openssh_private(cmd_openssh)?,
     };
     Ok(())
 }
@@ -104,15 +246,18 @@ pub async fn generate(cmd: KeyGenerateCmd) -> Result<()> {
         KeyGenerateCmd::Ed25519 => JWK::generate_ed25519().unwrap(),
         KeyGenerateCmd::Secp256k1 => JWK::generate_secp256k1().unwrap(),
         KeyGenerateCmd::Secp256r1 => JWK::generate_p256().unwrap(),
+        KeyGenerateCmd::Secp384r1 => JWK::generate_p384().unwrap(),
+        KeyGenerateCmd::Rsa(args) => {
+            key_format::generate_rsa(args.bits).context("Generate RSA key")?
+        }
     };
-    let jwk_str = serde_json::to_string(&jwk).unwrap();
-    println!("{jwk_str}");
+    print_jwk(&jwk);
     Ok(())
 }
 
 pub async fn to_did(args: KeyToDIDArgs) -> Result<()> {
-    let jwk = args.key.get_jwk();
-    let did = DID_METHODS
+    let jwk = args.key.get_jwk()?;
+    let did = did_methods()
         .generate(&Source::KeyAndPattern(&jwk, &args.method_pattern))
         .ok_or(Error::UnableToGenerateDID)
         .unwrap();
@@ -129,12 +274,12 @@ pub async fn to_vm(args: KeyToVMArgs) -> Result<()> {
             "key".to_string()
         }
     };
-    let jwk = args.key.get_jwk();
-    let did = DID_METHODS
+    let jwk = args.key.get_jwk()?;
+    let did = did_methods()
         .generate(&Source::KeyAndPattern(&jwk, &method_pattern))
         .ok_or(Error::UnableToGenerateDID)
         .unwrap();
-    let did_resolver = DID_METHODS.to_resolver();
+    let did_resolver = did_methods().to_resolver();
     let vm = get_verification_method(&did, did_resolver)
         .await
         .ok_or(Error::UnableToGetVerificationMethod)
@@ -151,3 +296,183 @@ pub async fn from_ssh(args: KeyFromSSHArgs) -> Result<()> {
     serde_json::to_writer_pretty(stdout_writer, &jwk).unwrap();
     Ok(())
 }
+
+pub fn derive(args: KeyDeriveArgs) -> Result<()> {
+    let path = args
+        .path
+        .as_deref()
+        .unwrap_or_else(|| args.curve.default_path());
+    let jwk = match &args.seed {
+        Some(seed) => key_derive::derive_from_seed_hex(seed, args.curve, path),
+        None => {
+            let mut mnemonic = String::new();
+            stdin()
+                .read_to_string(&mut mnemonic)
+                .context("Read mnemonic")?;
+            let passphrase = args.passphrase.as_deref().unwrap_or_default();
+            key_derive::derive_from_mnemonic(mnemonic.trim(), passphrase, args.curve, path)
+        }
+    }
+    .context("Derive key")?;
+    match &args.did {
+        Some(method_pattern) => {
+            let did = did_methods()
+                .generate(&Source::KeyAndPattern(&jwk, method_pattern))
+                .ok_or(Error::UnableToGenerateDID)?;
+            println!("{did}");
+        }
+        None => print_jwk(&jwk),
+    }
+    Ok(())
+}
+
+fn print_jwk(jwk: &JWK) {
+    let jwk_str = serde_json::to_string(jwk).unwrap();
+    println!("{jwk_str}");
+}
+
+fn read_key_input(path: &Option<PathBuf>) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    match path {
+        Some(path) => File::open(path)
+            .context("Open key file")?
+            .read_to_end(&mut data),
+        None => stdin().read_to_end(&mut data),
+    }
+    .context("Read key")?;
+    Ok(data)
+}
+
+pub fn from_pem(args: KeyFromFileArgs) -> Result<()> {
+    let pem = String::from_utf8(read_key_input(&args.path)?).context("PEM must be UTF-8")?;
+    let jwk = key_format::jwk_from_pem(&pem).context("Convert PEM key")?;
+    print_jwk(&jwk);
+    Ok(())
+}
+
+pub fn from_pkcs8(args: KeyFromFileArgs) -> Result<()> {
+    let der = read_key_input(&args.path)?;
+    let jwk = key_format::jwk_from_der(&der).context("Convert PKCS#8 key")?;
+    print_jwk(&jwk);
+    Ok(())
+}
+
+pub fn from_multibase(args: KeyFromMultibaseArgs) -> Result<()> {
+    let jwk = key_format::jwk_from_multibase(&args.multibase).context("Convert multibase key")?;
+    print_jwk(&jwk);
+    Ok(())
+}
+
+pub fn from_openssh_private(args: KeyFromFileArgs) -> Result<()> {
+    let openssh =
+        String::from_utf8(read_key_input(&args.path)?).context("OpenSSH key must be UTF-8")?;
+    let jwk =
+        key_format::jwk_from_openssh_private(&openssh).context("Convert OpenSSH private key")?;
+    print_jwk(&jwk);
+    Ok(())
+}
+
+pub fn to_pem(args: KeyToPemArgs) -> Result<()> {
+    let jwk = args.format.key.get_jwk()?;
+    let pem = key_format::jwk_to_pem(&jwk, args.format.private, args.sec1)
+        .context("Convert key to PEM")?;
+    print!("{pem}");
+    Ok(())
+}
+
+pub fn to_pkcs8(args: KeyToFormatArgs) -> Result<()> {
+    let jwk = args.key.get_jwk()?;
+    let der = key_format::jwk_to_der(&jwk, args.private).context("Convert key to PKCS#8")?;
+    stdout().write_all(&der).context("Write key")?;
+    Ok(())
+}
+
+pub fn to_multibase(args: KeyToFormatArgs) -> Result<()> {
+    let jwk = args.key.get_jwk()?;
+    let multibase =
+        key_format::jwk_to_multibase(&jwk, args.private).context("Convert key to multibase")?;
+    println!("{multibase}");
+    Ok(())
+}
+
+pub fn to_openssh(args: KeyToFormatArgs) -> Result<()> {
+    let jwk = args.key.get_jwk()?;
+    let openssh =
+        key_format::jwk_to_openssh(&jwk, args.private).context("Convert key to OpenSSH")?;
+    if args.private {
+        print!("{openssh}");
+    } else {
+        println!("{openssh}");
+    }
+    Ok(())
+}
+
+pub fn to_jwk_public(args: KeyToJWKArgs) -> Result<()> {
+    let jwk = args.key.get_jwk()?;
+    print_jwk(&jwk.to_public());
+    Ok(())
+}
+
+pub fn to_thumbprint(args: KeyToJWKArgs) -> Result<()> {
+    let jwk = args.key.get_jwk()?;
+    let thumbprint = jwk.thumbprint().context("Compute JWK thumbprint")?;
+    println!("{thumbprint}");
+    Ok(())
+}
+
+/// did:pkh patterns to try when inspecting a key, since did:pkh needs a blockchain namespace
+const PKH_PATTERNS: &[&str] = &[
+    "tz", "eth", "eip155", "sol", "solana", "btc", "doge", "bip122", "aleo",
+];
+
+pub async fn inspect(args: KeyInspectArgs) -> Result<()> {
+    let jwk = args.key.get_jwk()?;
+    let (kty, curve) = match &jwk.params {
+        Params::OKP(okp) => ("OKP", Some(okp.curve.clone())),
+        Params::EC(ec) => ("EC", ec.curve.clone()),
+        Params::RSA(_) => ("RSA", None),
+        Params::Symmetric(_) => ("oct", None),
+    };
+    let private = match &jwk.params {
+        Params::OKP(okp) => okp.private_key.is_some(),
+        Params::EC(ec) => ec.ecc_private_key.is_some(),
+        Params::RSA(rsa) => rsa.private_exponent.is_some(),
+        Params::Symmetric(_) => true,
+    };
+    let mut method_names: Vec<&str> = did_methods().methods.keys().copied().collect();
+    method_names.sort_unstable();
+    let mut patterns = Vec::new();
+    for name in method_names {
+        if name == "pkh" {
+            patterns.extend(PKH_PATTERNS.iter().map(|pattern| format!("pkh:{pattern}")));
+        } else {
+            patterns.push(name.to_string());
+        }
+    }
+    let resolver = did_methods().to_resolver();
+    let mut dids = Vec::new();
+    for pattern in patterns {
+        let did = match did_methods().generate(&Source::KeyAndPattern(&jwk, &pattern)) {
+            Some(did) => did,
+            None => continue,
+        };
+        let verification_method = get_verification_method(&did, resolver).await;
+        dids.push(json!({
+            "methodPattern": pattern,
+            "did": did,
+            "verificationMethod": verification_method,
+        }));
+    }
+    let info = json!({
+        "kty": kty,
+        "curve": curve,
+        "algorithm": jwk.get_algorithm(),
+        "thumbprint": jwk.thumbprint().ok(),
+        "private": private,
+        "dids": dids,
+    });
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &info).unwrap();
+    println!();
+    Ok(())
+}
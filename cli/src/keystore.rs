@@ -0,0 +1,397 @@
+//! Encrypted local keystore.
+//!
+//! Keys are stored as JWKs encrypted with XChaCha20-Poly1305 under a key derived from a
+//! passphrase with scrypt, one salt per key, with the alias as associated data. Public keys are
+//! stored in the clear so that keys can be listed without the passphrase.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File},
+    io::{stdin, stdout, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305,
+};
+use chrono::prelude::*;
+use clap::{Args, Subcommand};
+use didkit::JWK;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use zeroize::Zeroizing;
+
+/// Environment variable from which to read the keystore passphrase
+pub const PASSPHRASE_ENV: &str = "DIDKIT_KEYSTORE_PASSPHRASE";
+
+const KEYSTORE_VERSION: u32 = 1;
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// Passphrase read from `--passphrase-fd`, kept as the descriptor can only be read once
+static FD_PASSPHRASE: OnceLock<Zeroizing<String>> = OnceLock::new();
+
+#[derive(Args, Clone, Debug)]
+pub struct KeystoreOptions {
+    /// Keystore file. Default: ~/.didkit/keystore.json
+    #[clap(env = "DIDKIT_KEYSTORE", long)]
+    keystore: Option<PathBuf>,
+    /// Read the keystore passphrase from this file descriptor instead of the
+    /// DIDKIT_KEYSTORE_PASSPHRASE environment variable
+    #[clap(long)]
+    passphrase_fd: Option<i32>,
+}
+
+#[derive(Subcommand)]
+pub enum KeystoreCmd {
+    /// Add a key to the keystore
+    ///
+    /// Reads the JWK from the given file, or from standard input.
+    Add(KeystoreAddArgs),
+    /// List keys in the keystore
+    List(KeystoreListArgs),
+    /// Remove a key from the keystore
+    Remove(KeystoreRemoveArgs),
+    /// Export a key from the keystore as a JWK
+    Export(KeystoreExportArgs),
+}
+
+#[derive(Args)]
+pub struct KeystoreAddArgs {
+    /// Alias for the key
+    alias: String,
+    /// JWK file to add. Default: read from standard input
+    #[clap(short, long)]
+    key_path: Option<PathBuf>,
+    /// Replace an existing key with the same alias
+    #[clap(long)]
+    force: bool,
+    #[clap(flatten)]
+    options: KeystoreOptions,
+}
+
+#[derive(Args)]
+pub struct KeystoreListArgs {
+    #[clap(flatten)]
+    options: KeystoreOptions,
+}
+
+#[derive(Args)]
+pub struct KeystoreRemoveArgs {
+    /// Alias of the key
+    alias: String,
+    #[clap(flatten)]
+    options: KeystoreOptions,
+}
+
+#[derive(Args)]
+pub struct KeystoreExportArgs {
+    /// Alias of the key
+    alias: String,
+    /// Export only the public key (no passphrase needed)
+    #[clap(long)]
+    public: bool,
+    #[clap(flatten)]
+    options: KeystoreOptions,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "alg", rename_all = "lowercase")]
+enum Kdf {
+    Scrypt {
+        salt: String,
+        log_n: u8,
+        r: u32,
+        p: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct KeystoreEntry {
+    public_key: JWK,
+    created: DateTime<Utc>,
+    kdf: Kdf,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Keystore {
+    version: u32,
+    keys: BTreeMap<String, KeystoreEntry>,
+}
+
+impl Default for Keystore {
+    fn default() -> Self {
+        Self {
+            version: KEYSTORE_VERSION,
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl KeystoreOptions {
+    fn path(&self) -> Result<PathBuf> {
+        if let Some(path) = &self.keystore {
+            return Ok(path.clone());
+        }
+        let home = env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .ok_or_else(|| anyhow!("Unable to find home directory; use --keystore"))?;
+        Ok(Path::new(&home).join(".didkit").join("keystore.json"))
+    }
+
+    fn passphrase(&self) -> Result<Zeroizing<String>> {
+        let passphrase = match (self.passphrase_fd, FD_PASSPHRASE.get()) {
+            (Some(_), Some(passphrase)) => passphrase.clone(),
+            (Some(fd), None) => {
+                let mut passphrase = read_fd(fd).context("Read passphrase from file descriptor")?;
+                let len = passphrase.trim_end_matches(&['\r', '\n'][..]).len();
+                passphrase.truncate(len);
+                FD_PASSPHRASE.get_or_init(|| passphrase).clone()
+            }
+            (None, _) => Zeroizing::new(env::var(PASSPHRASE_ENV).map_err(|_| {
+                anyhow!("Passphrase required: set {PASSPHRASE_ENV} or use --passphrase-fd")
+            })?),
+        };
+        if passphrase.is_empty() {
+            bail!("Passphrase must not be empty");
+        }
+        Ok(passphrase)
+    }
+}
+
+#[cfg(unix)]
+fn read_fd(fd: i32) -> Result<Zeroizing<String>> {
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::FromRawFd;
+    if fd < 0 {
+        bail!("Invalid file descriptor: {fd}");
+    }
+    // Safety: the file descriptor is provided by the caller for this purpose. It is only read,
+    // and not closed, as the file is never dropped.
+    let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    let mut passphrase = Zeroizing::new(String::new());
+    file.read_to_string(&mut passphrase)?;
+    Ok(passphrase)
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32) -> Result<Zeroizing<String>> {
+    bail!("--passphrase-fd is only supported on Unix")
+}
+
+fn derive_key(passphrase: &str, kdf: &Kdf) -> Result<Zeroizing<[u8; 32]>> {
+    let Kdf::Scrypt { salt, log_n, r, p } = kdf;
+    let salt = base64::decode_config(salt, base64::URL_SAFE_NO_PAD).context("Decode salt")?;
+    let params = scrypt::Params::new(*log_n, *r, *p, 32).context("Scrypt parameters")?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(passphrase.as_bytes(), &salt, &params, &mut key[..])
+        .map_err(|e| anyhow!("Scrypt: {e}"))?;
+    Ok(key)
+}
+
+fn encrypt(alias: &str, jwk: &JWK, passphrase: &str) -> Result<KeystoreEntry> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = Kdf::Scrypt {
+        salt: base64::encode_config(salt, base64::URL_SAFE_NO_PAD),
+        log_n: SCRYPT_LOG_N,
+        r: SCRYPT_R,
+        p: SCRYPT_P,
+    };
+    let key = derive_key(passphrase, &kdf)?;
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+    let plaintext = Zeroizing::new(serde_json::to_vec(jwk)?);
+    let ciphertext = XChaCha20Poly1305::new((&*key).into())
+        .encrypt(
+            &nonce.into(),
+            Payload {
+                msg: &plaintext,
+                aad: alias.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow!("Unable to encrypt key"))?;
+    Ok(KeystoreEntry {
+        public_key: jwk.to_public(),
+        created: Utc::now(),
+        kdf,
+        nonce: base64::encode_config(nonce, base64::URL_SAFE_NO_PAD),
+        ciphertext: base64::encode_config(ciphertext, base64::URL_SAFE_NO_PAD),
+    })
+}
+
+fn decrypt(alias: &str, entry: &KeystoreEntry, passphrase: &str) -> Result<JWK> {
+    let key = derive_key(passphrase, &entry.kdf)?;
+    let nonce = base64::decode_config(&entry.nonce, base64::URL_SAFE_NO_PAD)?;
+    if nonce.len() != 24 {
+        bail!("Invalid nonce length");
+    }
+    let ciphertext = base64::decode_config(&entry.ciphertext, base64::URL_SAFE_NO_PAD)?;
+    let plaintext = Zeroizing::new(
+        XChaCha20Poly1305::new((&*key).into())
+            .decrypt(
+                nonce.as_slice().into(),
+                Payload {
+                    msg: &ciphertext,
+                    aad: alias.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("Unable to decrypt key '{alias}': wrong passphrase?"))?,
+    );
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+impl Keystore {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = File::open(path).context("Open keystore")?;
+        let keystore: Self =
+            serde_json::from_reader(BufReader::new(file)).context("Read keystore")?;
+        if keystore.version != KEYSTORE_VERSION {
+            bail!("Unsupported keystore version: {}", keystore.version);
+        }
+        Ok(keystore)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("Create keystore directory")?;
+        }
+        // Write to a temporary file and rename it, so that the keystore is never left truncated.
+        let tmp_path = path.with_extension("json.tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(&tmp_path).context("Create keystore file")?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self).context("Write keystore")?;
+        writer.flush()?;
+        fs::rename(&tmp_path, path).context("Replace keystore file")?;
+        Ok(())
+    }
+
+    fn entry(&self, alias: &str) -> Result<&KeystoreEntry> {
+        self.keys
+            .get(alias)
+            .ok_or_else(|| anyhow!("No key with alias '{alias}' in keystore"))
+    }
+}
+
+/// Load and decrypt a key from the keystore, for `--key-alias`.
+pub fn get_key(options: &KeystoreOptions, alias: &str) -> Result<JWK> {
+    let keystore = Keystore::load(&options.path()?)?;
+    let entry = keystore.entry(alias)?;
+    decrypt(alias, entry, &options.passphrase()?)
+}
+
+pub fn cli(cmd: KeystoreCmd) -> Result<()> {
+    match cmd {
+        KeystoreCmd::Add(args) => add(args)?,
+        KeystoreCmd::List(args) => list(args)?,
+        KeystoreCmd::Remove(args) => remove(args)?,
+        KeystoreCmd::Export(args) => export(args)?,
+    };
+    Ok(())
+}
+
+pub fn add(args: KeystoreAddArgs) -> Result<()> {
+    let path = args.options.path()?;
+    let mut keystore = Keystore::load(&path)?;
+    if keystore.keys.contains_key(&args.alias) && !args.force {
+        bail!(
+            "Key with alias '{}' already exists; use --force to replace it",
+            args.alias
+        );
+    }
+    let mut key_json = Zeroizing::new(String::new());
+    match &args.key_path {
+        Some(key_path) => File::open(key_path)
+            .context("Open key file")?
+            .read_to_string(&mut key_json),
+        None => stdin().read_to_string(&mut key_json),
+    }
+    .context("Read key")?;
+    let jwk: JWK = serde_json::from_str(&key_json).context("Parse JWK")?;
+    if jwk.to_public() == jwk {
+        bail!("JWK has no private key");
+    }
+    let entry = encrypt(&args.alias, &jwk, &args.options.passphrase()?)?;
+    keystore.keys.insert(args.alias, entry);
+    keystore.save(&path)
+}
+
+pub fn list(args: KeystoreListArgs) -> Result<()> {
+    let keystore = Keystore::load(&args.options.path()?)?;
+    let keys: Vec<_> = keystore
+        .keys
+        .iter()
+        .map(|(alias, entry)| {
+            json!({
+                "alias": alias,
+                "created": entry.created,
+                "algorithm": entry.public_key.get_algorithm(),
+                "thumbprint": entry.public_key.thumbprint().ok(),
+            })
+        })
+        .collect();
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &keys).unwrap();
+    println!();
+    Ok(())
+}
+
+pub fn remove(args: KeystoreRemoveArgs) -> Result<()> {
+    let path = args.options.path()?;
+    let mut keystore = Keystore::load(&path)?;
+    if keystore.keys.remove(&args.alias).is_none() {
+        bail!("No key with alias '{}' in keystore", args.alias);
+    }
+    keystore.save(&path)
+}
+
+pub fn export(args: KeystoreExportArgs) -> Result<()> {
+    let keystore = Keystore::load(&args.options.path()?)?;
+    let entry = keystore.entry(&args.alias)?;
+    let jwk = if args.public {
+        entry.public_key.clone()
+    } else {
+        decrypt(&args.alias, entry, &args.options.passphrase()?)?
+    };
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer(stdout_writer, &jwk).unwrap();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let entry = encrypt("alias", &jwk, "correct horse").unwrap();
+        assert_eq!(entry.public_key, jwk.to_public());
+        assert_eq!(decrypt("alias", &entry, "correct horse").unwrap(), jwk);
+        assert!(decrypt("alias", &entry, "wrong").is_err());
+        assert!(decrypt("other", &entry, "correct horse").is_err());
+    }
+
+    #[test]
+    fn invalid_fd() {
+        assert!(read_fd(-1).is_err());
+    }
+}
@@ -1,4 +1,12 @@
-use std::{convert::TryFrom, fs::File, io::BufReader, ops::Deref, path::PathBuf, str::FromStr};
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::BufReader,
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
 
 use anyhow::{anyhow, bail, Context, Error as AError, Result as AResult};
 use chrono::prelude::*;
@@ -6,21 +14,34 @@ use clap::{ArgGroup, Args, Parser, Subcommand};
 use credential::{CredentialIssueArgs, CredentialVerifyArgs};
 use didkit::ssi::ldp::ProofSuiteType;
 use didkit::{
-    ssi::did::ServiceEndpoint, DIDMethod, Error, LinkedDataProofOptions, Metadata, ProofFormat,
-    VerificationRelationship, DIDURL, DID_METHODS, JWK, URI,
+    config::Config,
+    ssi::did::{DIDMethods, ServiceEndpoint},
+    DIDMethod, Error, LinkedDataProofOptions, Metadata, ProofFormat, VerificationRelationship,
+    DIDURL, JWK, URI,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 
 mod credential;
 mod did;
+mod did_peer;
+mod did_web;
+mod didcomm;
 mod jsonld;
 mod key;
+mod keystore;
+mod oid4vp;
 mod opts;
 mod presentation;
+mod presentation_exchange;
+mod zcap;
 
 #[derive(Parser)]
 struct DIDKit {
+    /// Configuration file (didkit.toml), to enable or disable DID methods and set their endpoints.
+    /// The did:ethr `rpc-url` setting is not supported yet, as did:ethr DIDs are resolved offline
+    #[clap(env = "DIDKIT_CONFIG", long, global = true)]
+    config: Option<PathBuf>,
     #[command(subcommand)]
     command: DIDKitCmd,
 }
@@ -84,20 +105,33 @@ pub enum DIDKitCmd {
     Jsonld(jsonld::JsonldCmd),
     #[clap(hide = true)]
     ToRdfURDNA2015(jsonld::JsonldToRDFURDNAArgs),
+    /// Subcommand for OpenID for Verifiable Presentations (OID4VP)
+    #[clap(subcommand)]
+    Oid4vp(oid4vp::Oid4vpCmd),
+    /// Subcommand for the encrypted keystore
+    #[clap(subcommand)]
+    Keystore(keystore::KeystoreCmd),
+    /// Subcommand for DIDComm v2 messaging
+    #[clap(subcommand)]
+    Didcomm(didcomm::DidcommCmd),
+    /// Subcommand for authorization capabilities (ZCAP-LD)
+    #[clap(subcommand)]
+    Zcap(zcap::ZcapCmd),
     /*
     /// Revoke Credential
     VCRevokeCredential {},
     */
+}
 
-    /*
-    // DIDComm Functionality (???)
-    /// Discover a messaging endpoint from a DID which supports DIDComm.
-    DIDCommDiscover {},
-    /// Send a DIDComm message.
-    DIDCommSend {},
-    /// Receive a DIDComm message.
-    DIDCommReceive {},
-    */
+// DID methods are only required to be Sync, so they are kept by reference.
+static CONFIGURED_DID_METHODS: OnceLock<&'static DIDMethods<'static>> = OnceLock::new();
+
+/// DID methods from the configuration file, or the default DID methods
+pub fn did_methods() -> &'static DIDMethods<'static> {
+    CONFIGURED_DID_METHODS
+        .get()
+        .copied()
+        .unwrap_or(&didkit::DID_METHODS)
 }
 
 // An id and optionally a DID
@@ -121,7 +155,7 @@ pub struct IdAndDid {
 impl IdAndDid {
     pub fn parse<'a>(self) -> AResult<(&'a dyn DIDMethod, String, DIDURL)> {
         let Self { id, did } = self;
-        let method = DID_METHODS
+        let method = did_methods()
             .get_method(&id.did)
             .map_err(|e| anyhow!("Unable to get DID method: {}", e))?;
         Ok((method.deref(), did.unwrap_or_else(|| id.did.clone()), id))
@@ -238,6 +272,11 @@ pub struct KeyArg {
     /// Request signature using SSH Agent
     #[clap(short = 'S', long, group = "key_group")]
     ssh_agent: bool,
+    /// Alias of a key in the encrypted keystore
+    #[clap(env, long, group = "key_group", conflicts_with_all = ["key_path", "jwk"])]
+    key_alias: Option<String>,
+    #[clap(flatten)]
+    keystore_options: keystore::KeystoreOptions,
 }
 
 #[derive(Args, Debug)]
@@ -355,20 +394,28 @@ fn read_jwk_file_opt(pathbuf_opt: &Option<PathBuf>) -> AResult<Option<JWK>> {
     Ok(Some(jwk))
 }
 
+fn read_json_file<T: DeserializeOwned>(path: &Path) -> AResult<T> {
+    let file = File::open(path).context("Opening JSON file")?;
+    let reader = BufReader::new(file);
+    let value = serde_json::from_reader(reader).context("Reading JSON file")?;
+    Ok(value)
+}
+
 impl KeyArg {
-    fn get_jwk(&self) -> JWK {
-        self.get_jwk_opt()
-            .expect("Key path or JWK option is required")
+    fn get_jwk(&self) -> AResult<JWK> {
+        self.get_jwk_opt()?
+            .ok_or_else(|| anyhow!("Key path, JWK or key alias option is required"))
     }
-    fn get_jwk_opt(&self) -> Option<JWK> {
-        match &self.key_path {
-            Some(p) => {
-                let key_file = File::open(p).unwrap();
-                let key_reader = BufReader::new(key_file);
-                Some(serde_json::from_reader(key_reader).unwrap())
-            }
-            None => self.jwk.clone(),
+    fn get_jwk_opt(&self) -> AResult<Option<JWK>> {
+        if let Some(alias) = &self.key_alias {
+            let jwk = keystore::get_key(&self.keystore_options, alias)
+                .with_context(|| format!("Unable to get key '{alias}' from keystore"))?;
+            return Ok(Some(jwk));
+        }
+        if self.key_path.is_some() {
+            return read_jwk_file_opt(&self.key_path);
         }
+        Ok(self.jwk.clone())
     }
 }
 
@@ -499,9 +546,17 @@ async fn main() -> AResult<()> {
     tracing_subscriber::fmt::init();
 
     let opt = DIDKit::parse();
+    if let Some(path) = &opt.config {
+        let did_methods = Config::load(path)
+            .and_then(|config| config.did_methods())
+            .with_context(|| format!("Load configuration from {}", path.display()))?;
+        CONFIGURED_DID_METHODS
+            .set(Box::leak(Box::new(did_methods)))
+            .ok();
+    }
     match opt.command {
-        DIDKitCmd::GenerateEd25519Key => key::generate(key::KeyGenerateCmd::Ed25519).await.unwrap(),
-        DIDKitCmd::Key(cmd) => key::cli(cmd).await.unwrap(),
+        DIDKitCmd::GenerateEd25519Key => key::generate(key::KeyGenerateCmd::Ed25519).await?,
+        DIDKitCmd::Key(cmd) => key::cli(cmd).await?,
         DIDKitCmd::KeyToDIDKey(key) => {
             // Deprecated in favor of KeyToDID
             eprintln!("didkit: use key-to-did instead of key-to-did-key");
@@ -509,30 +564,33 @@ async fn main() -> AResult<()> {
                 method_pattern: "key".to_string(),
                 key,
             })
-            .await
-            .unwrap();
+            .await?;
         }
-        DIDKitCmd::KeyToDID(args) => key::to_did(args).await.unwrap(),
-        DIDKitCmd::KeyToVerificationMethod(args) => key::to_vm(args).await.unwrap(),
-        DIDKitCmd::SshPkToJwk(args) => key::from_ssh(args).await.unwrap(),
-        DIDKitCmd::VCIssueCredential(args) => credential::issue(args).await.unwrap(),
-        DIDKitCmd::VCVerifyCredential(args) => credential::verify(args).await.unwrap(),
-        DIDKitCmd::Credential(cmd) => credential::cli(cmd).await.unwrap(),
-        DIDKitCmd::VCIssuePresentation(args) => presentation::issue(args).await.unwrap(),
-        DIDKitCmd::VCVerifyPresentation(args) => presentation::verify(args).await.unwrap(),
-        DIDKitCmd::Presentation(cmd) => presentation::cli(cmd).await.unwrap(),
-        DIDKitCmd::Jsonld(cmd) => jsonld::cli(cmd).await.unwrap(),
-        DIDKitCmd::ToRdfURDNA2015(args) => jsonld::to_rdfurdna(args).await.unwrap(),
-        DIDKitCmd::Did(args) => did::cli(args).await.unwrap(),
-        DIDKitCmd::DIDCreate(args) => did::create(args).await.unwrap(),
-        DIDKitCmd::DIDFromTx => did::from_tx().await.unwrap(),
-        DIDKitCmd::DIDSubmitTx => did::submit_tx().await.unwrap(),
-        DIDKitCmd::DIDUpdate(args) => did::update(args).await.unwrap(),
-        DIDKitCmd::DIDRecover(args) => did::recover(args).await.unwrap(),
-        DIDKitCmd::DIDDeactivate(args) => did::deactivate(args).await.unwrap(),
-        DIDKitCmd::DIDResolve(args) => did::resolve(args).await.unwrap(),
-        DIDKitCmd::DIDDereference(args) => did::dereference(args).await.unwrap(),
-        DIDKitCmd::DIDAuth(args) => did::authenticate(args).await.unwrap(),
+        DIDKitCmd::KeyToDID(args) => key::to_did(args).await?,
+        DIDKitCmd::KeyToVerificationMethod(args) => key::to_vm(args).await?,
+        DIDKitCmd::SshPkToJwk(args) => key::from_ssh(args).await?,
+        DIDKitCmd::VCIssueCredential(args) => credential::issue(args).await?,
+        DIDKitCmd::VCVerifyCredential(args) => credential::verify(args).await?,
+        DIDKitCmd::Credential(cmd) => credential::cli(cmd).await?,
+        DIDKitCmd::VCIssuePresentation(args) => presentation::issue(args).await?,
+        DIDKitCmd::VCVerifyPresentation(args) => presentation::verify(args).await?,
+        DIDKitCmd::Presentation(cmd) => presentation::cli(cmd).await?,
+        DIDKitCmd::Jsonld(cmd) => jsonld::cli(cmd).await?,
+        DIDKitCmd::ToRdfURDNA2015(args) => jsonld::to_rdfurdna(args).await?,
+        DIDKitCmd::Oid4vp(cmd) => oid4vp::cli(cmd).await?,
+        DIDKitCmd::Didcomm(cmd) => didcomm::cli(cmd).await?,
+        DIDKitCmd::Zcap(cmd) => zcap::cli(cmd).await?,
+        DIDKitCmd::Keystore(cmd) => keystore::cli(cmd)?,
+        DIDKitCmd::Did(args) => did::cli(args).await?,
+        DIDKitCmd::DIDCreate(args) => did::create(args).await?,
+        DIDKitCmd::DIDFromTx => did::from_tx().await?,
+        DIDKitCmd::DIDSubmitTx => did::submit_tx().await?,
+        DIDKitCmd::DIDUpdate(args) => did::update(args).await?,
+        DIDKitCmd::DIDRecover(args) => did::recover(args).await?,
+        DIDKitCmd::DIDDeactivate(args) => did::deactivate(args).await?,
+        DIDKitCmd::DIDResolve(args) => did::resolve(args).await?,
+        DIDKitCmd::DIDDereference(args) => did::dereference(args).await?,
+        DIDKitCmd::DIDAuth(args) => did::authenticate(args).await?,
     }
     Ok(())
 }
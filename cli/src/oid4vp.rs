@@ -0,0 +1,217 @@
+use std::{
+    io::{stdin, stdout, BufReader, BufWriter},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use didkit::{
+    oid4vp::{
+        self, AuthorizationRequest, AuthorizationResponse, CLIENT_ID_SCHEME_DID,
+        DEFAULT_REQUEST_URI_BASE, RESPONSE_TYPE_VP_TOKEN,
+    },
+    ContextLoader, JWTOrLDPOptions, LinkedDataProofOptions, VerifiablePresentation,
+};
+use serde_json::Value;
+
+use crate::{get_ssh_agent_sock, opts::ResolverOptions, read_json_file, KeyArg, ProofOptions};
+
+#[derive(Subcommand)]
+pub enum Oid4vpCmd {
+    /// Create or parse authorization requests (verifier)
+    #[clap(subcommand)]
+    Request(Oid4vpRequestCmd),
+    /// Create or verify authorization responses (wallet and verifier)
+    #[clap(subcommand)]
+    Response(Oid4vpResponseCmd),
+}
+
+#[derive(Subcommand)]
+pub enum Oid4vpRequestCmd {
+    /// Create an authorization request URI with a request object signed by the verifier's DID
+    Create(Box<Oid4vpRequestCreateArgs>),
+    /// Parse an authorization request URI, verifying its request object
+    ///
+    /// Outputs the authorization request parameters as JSON.
+    Parse(Oid4vpRequestParseArgs),
+}
+
+#[derive(Subcommand)]
+pub enum Oid4vpResponseCmd {
+    /// Create an authorization response with a VP token
+    ///
+    /// Reads the unsigned presentation from standard input. Outputs the response parameters
+    /// (vp_token, presentation_submission, state) as JSON.
+    Create(Box<Oid4vpResponseCreateArgs>),
+    /// Verify an authorization response against its authorization request
+    ///
+    /// Reads the response parameters as JSON from standard input. Outputs a verification result.
+    Verify(Oid4vpResponseVerifyArgs),
+}
+
+#[derive(Args)]
+pub struct Oid4vpRequestCreateArgs {
+    /// DID of the verifier, used as client_id
+    #[clap(long)]
+    client_id: String,
+    /// Verification method (DID URL) of the verifier's DID for signing the request object
+    #[clap(short, long)]
+    verification_method: String,
+    /// URI to which the wallet should send the response
+    #[clap(long)]
+    response_uri: String,
+    /// Response mode
+    #[clap(long, default_value = "direct_post")]
+    response_mode: String,
+    /// Nonce that the presentation must be bound to
+    #[clap(short, long)]
+    nonce: String,
+    /// Opaque state value returned in the response
+    #[clap(short, long)]
+    state: Option<String>,
+    /// Presentation definition JSON file
+    #[clap(short = 'P', long)]
+    presentation_definition: Option<PathBuf>,
+    /// Base URI for the authorization request
+    #[clap(long, default_value = DEFAULT_REQUEST_URI_BASE)]
+    base: String,
+    #[clap(flatten)]
+    key: KeyArg,
+}
+
+#[derive(Args)]
+pub struct Oid4vpRequestParseArgs {
+    /// Authorization request URI
+    uri: String,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct Oid4vpResponseCreateArgs {
+    /// Authorization request parameters JSON file, e.g. from `oid4vp request parse`
+    #[clap(long)]
+    request: PathBuf,
+    /// Presentation submission JSON file
+    #[clap(long)]
+    presentation_submission: PathBuf,
+    #[clap(flatten)]
+    key: KeyArg,
+    #[clap(flatten)]
+    proof_options: ProofOptions,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct Oid4vpResponseVerifyArgs {
+    /// Authorization request parameters JSON file
+    #[clap(long)]
+    request: PathBuf,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+pub async fn cli(cmd: Oid4vpCmd) -> Result<()> {
+    match cmd {
+        Oid4vpCmd::Request(Oid4vpRequestCmd::Create(args)) => request_create(*args).await?,
+        Oid4vpCmd::Request(Oid4vpRequestCmd::Parse(args)) => request_parse(args).await?,
+        Oid4vpCmd::Response(Oid4vpResponseCmd::Create(args)) => response_create(*args).await?,
+        Oid4vpCmd::Response(Oid4vpResponseCmd::Verify(args)) => response_verify(args).await?,
+    };
+    Ok(())
+}
+
+pub async fn request_create(args: Oid4vpRequestCreateArgs) -> Result<()> {
+    let presentation_definition: Option<Value> = match args.presentation_definition {
+        Some(path) => Some(read_json_file(&path).context("Read presentation definition")?),
+        None => None,
+    };
+    let request = AuthorizationRequest {
+        response_type: RESPONSE_TYPE_VP_TOKEN.to_string(),
+        client_id: args.client_id,
+        client_id_scheme: Some(CLIENT_ID_SCHEME_DID.to_string()),
+        response_uri: Some(args.response_uri),
+        response_mode: Some(args.response_mode),
+        nonce: args.nonce,
+        state: args.state,
+        presentation_definition,
+        ..Default::default()
+    };
+    if args.key.ssh_agent {
+        bail!("ssh-agent signing of request objects is not supported");
+    }
+    let jwk = args.key.get_jwk()?;
+    let request_object = oid4vp::sign_request_object(&request, &jwk, &args.verification_method)
+        .context("Sign request object")?;
+    let uri = oid4vp::request_object_uri(&request.client_id, &request_object, &args.base)
+        .context("Encode authorization request URI")?;
+    println!("{uri}");
+    Ok(())
+}
+
+pub async fn request_parse(args: Oid4vpRequestParseArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let request = oid4vp::parse_request_uri(&args.uri, &resolver)
+        .await
+        .context("Parse authorization request")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &request).unwrap();
+    println!();
+    Ok(())
+}
+
+pub async fn response_create(args: Oid4vpResponseCreateArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let request: AuthorizationRequest =
+        read_json_file(&args.request).context("Read authorization request")?;
+    let presentation_submission: Value =
+        read_json_file(&args.presentation_submission).context("Read presentation submission")?;
+    let presentation_reader = BufReader::new(stdin());
+    let presentation: VerifiablePresentation =
+        serde_json::from_reader(presentation_reader).context("Read presentation")?;
+    let jwk_opt = args.key.get_jwk_opt()?;
+    let ssh_agent_sock_opt = if args.key.ssh_agent {
+        Some(get_ssh_agent_sock())
+    } else {
+        None
+    };
+    let mut options = JWTOrLDPOptions::default();
+    options.proof_format = Some(args.proof_options.proof_format.clone());
+    options.ldp_options = LinkedDataProofOptions::from(args.proof_options);
+    let response = oid4vp::create_response(
+        &request,
+        presentation,
+        presentation_submission,
+        jwk_opt.as_ref(),
+        options,
+        &resolver,
+        &mut context_loader,
+        ssh_agent_sock_opt.as_deref(),
+    )
+    .await
+    .context("Create authorization response")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer(stdout_writer, &response).unwrap();
+    Ok(())
+}
+
+pub async fn response_verify(args: Oid4vpResponseVerifyArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let request: AuthorizationRequest =
+        read_json_file(&args.request).context("Read authorization request")?;
+    let response_reader = BufReader::new(stdin());
+    let response: AuthorizationResponse =
+        serde_json::from_reader(response_reader).context("Read authorization response")?;
+    let result = oid4vp::verify_response(&response, &request, &resolver, &mut context_loader)
+        .await
+        .context("Verify authorization response")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer(stdout_writer, &result).unwrap();
+    if !result.errors.is_empty() {
+        std::process::exit(2);
+    }
+    Ok(())
+}
@@ -1,11 +1,19 @@
+use std::path::PathBuf;
+
 use clap::Args;
 
-use didkit::{HTTPDIDResolver, SeriesResolver, DID_METHODS};
+use didkit::{did_fixtures::FixtureResolver, HTTPDIDResolver, SeriesResolver};
+
+use crate::did_methods;
 
 fn parse(s: &str) -> Result<HTTPDIDResolver, anyhow::Error> {
     Ok(HTTPDIDResolver::new(s))
 }
 
+fn parse_fixtures(s: &str) -> Result<FixtureResolver, anyhow::Error> {
+    Ok(FixtureResolver::load(&PathBuf::from(s))?)
+}
+
 #[derive(Args, Debug, Clone, Default)]
 pub struct ResolverOptions {
     #[clap(env, short = 'r', long, value_parser(parse))]
@@ -14,17 +22,26 @@ pub struct ResolverOptions {
     #[clap(env, short = 'R', long, value_parser(parse))]
     /// Override DID Resolver HTTP(S) endpoint, for all DID methods.
     pub did_resolver_override: Option<HTTPDIDResolver>,
+    #[clap(env, long, value_parser(parse_fixtures))]
+    /// Directory of DID document fixtures, used before any other resolver.
+    ///
+    /// Fixtures are JSON files containing a DID document or a DID resolution result, keyed by the
+    /// document id and the versionId in its metadata. DIDs without fixtures are resolved as usual.
+    pub did_fixtures: Option<FixtureResolver>,
 }
 
 impl ResolverOptions {
     pub fn to_resolver(&self) -> SeriesResolver {
-        let mut resolvers = vec![DID_METHODS.to_resolver()];
+        let mut resolvers = vec![did_methods().to_resolver()];
         if let Some(http_did_resolver) = &self.did_resolver {
             resolvers.push(http_did_resolver);
         }
         if let Some(http_did_resolver) = &self.did_resolver_override {
             resolvers.insert(0, http_did_resolver);
         }
+        if let Some(fixture_resolver) = &self.did_fixtures {
+            resolvers.insert(0, fixture_resolver);
+        }
         SeriesResolver { resolvers }
     }
 }
@@ -7,7 +7,12 @@ use didkit::{
 };
 use tracing::warn;
 
-use crate::{get_ssh_agent_sock, opts::ResolverOptions, KeyArg, ProofOptions};
+use crate::{
+    get_ssh_agent_sock,
+    opts::ResolverOptions,
+    presentation_exchange::{self, PresentationExchangeCmd},
+    KeyArg, ProofOptions,
+};
 
 #[derive(Subcommand)]
 pub enum PresentationCmd {
@@ -15,6 +20,9 @@ pub enum PresentationCmd {
     Issue(Box<PresentationIssueArgs>),
     /// Verify Presentation
     Verify(PresentationVerifyArgs),
+    /// DIF Presentation Exchange
+    #[clap(subcommand)]
+    Exchange(PresentationExchangeCmd),
 }
 
 #[derive(Args)]
@@ -39,6 +47,7 @@ pub async fn cli(cmd: PresentationCmd) -> Result<()> {
     match cmd {
         PresentationCmd::Issue(cmd_issue) => issue(*cmd_issue).await?,
         PresentationCmd::Verify(cmd_verify) => verify(cmd_verify).await?,
+        PresentationCmd::Exchange(cmd_exchange) => presentation_exchange::cli(cmd_exchange)?,
     };
     Ok(())
 }
@@ -50,7 +59,7 @@ pub async fn issue(args: PresentationIssueArgs) -> Result<()> {
     let mut presentation: VerifiablePresentation =
         serde_json::from_reader(presentation_reader).unwrap();
 
-    let jwk_opt: Option<JWK> = args.key.get_jwk_opt();
+    let jwk_opt: Option<JWK> = args.key.get_jwk_opt()?;
     let ssh_agent_sock_opt = if args.key.ssh_agent {
         Some(get_ssh_agent_sock())
     } else {
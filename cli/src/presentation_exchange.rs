@@ -0,0 +1,152 @@
+use std::{
+    fs::{read_to_string, File},
+    io::{stdin, stdout, BufWriter, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use didkit::{
+    presentation_exchange::{self, PresentationDefinition, PresentationSubmission},
+    ProofFormat,
+};
+use serde_json::Value;
+
+use crate::read_json_file;
+
+#[derive(Subcommand)]
+pub enum PresentationExchangeCmd {
+    /// Evaluate a presentation definition against credentials
+    ///
+    /// Outputs the matching credentials (by index) for each input descriptor, and the input
+    /// descriptors selected for submission, or null if the definition cannot be satisfied.
+    Evaluate(PresentationExchangeEvaluateArgs),
+    /// Select credentials satisfying a presentation definition
+    ///
+    /// Outputs an unsigned presentation, ready for `presentation issue`, and writes the
+    /// corresponding presentation submission to a file.
+    Create(PresentationExchangeCreateArgs),
+    /// Check that a presentation satisfies a presentation definition
+    ///
+    /// Reads the presentation (JSON-LD or JWT) from standard input. Proofs are not verified; use
+    /// `presentation verify` for that.
+    Verify(PresentationExchangeVerifyArgs),
+}
+
+#[derive(Args)]
+pub struct PresentationExchangeEvaluateArgs {
+    /// Presentation definition JSON file
+    #[clap(short = 'D', long)]
+    definition: PathBuf,
+    /// Credential files (JSON-LD or JWT)
+    credentials: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct PresentationExchangeCreateArgs {
+    /// Presentation definition JSON file
+    #[clap(short = 'D', long)]
+    definition: PathBuf,
+    /// File to write the presentation submission to
+    #[clap(short = 's', long)]
+    submission: PathBuf,
+    /// DID of the holder of the presentation
+    #[clap(short = 'H', long)]
+    holder: Option<String>,
+    /// Proof format the presentation will be issued with
+    #[clap(default_value_t, short = 'f', long)]
+    proof_format: ProofFormat,
+    /// Credential files (JSON-LD or JWT)
+    credentials: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct PresentationExchangeVerifyArgs {
+    /// Presentation definition JSON file
+    #[clap(short = 'D', long)]
+    definition: PathBuf,
+    /// Presentation submission JSON file
+    #[clap(short = 's', long)]
+    submission: PathBuf,
+}
+
+pub fn cli(cmd: PresentationExchangeCmd) -> Result<()> {
+    match cmd {
+        PresentationExchangeCmd::Evaluate(args) => evaluate(args)?,
+        PresentationExchangeCmd::Create(args) => create(args)?,
+        PresentationExchangeCmd::Verify(args) => verify(args)?,
+    };
+    Ok(())
+}
+
+/// Parse a credential or presentation as JSON, or else as a JWT.
+fn parse_credential(data: &str) -> Value {
+    let data = data.trim();
+    match serde_json::from_str(data) {
+        Ok(value) => value,
+        Err(_) => Value::String(data.to_string()),
+    }
+}
+
+fn read_credentials(paths: &[PathBuf]) -> Result<Vec<Value>> {
+    paths
+        .iter()
+        .map(|path| {
+            let data = read_to_string(path)
+                .with_context(|| format!("Read credential {}", path.display()))?;
+            Ok(parse_credential(&data))
+        })
+        .collect()
+}
+
+fn read_definition(path: &Path) -> Result<PresentationDefinition> {
+    read_json_file(path).context("Read presentation definition")
+}
+
+pub fn evaluate(args: PresentationExchangeEvaluateArgs) -> Result<()> {
+    let definition = read_definition(&args.definition)?;
+    let credentials = read_credentials(&args.credentials)?;
+    let evaluation = presentation_exchange::evaluate(&definition, &credentials)
+        .context("Evaluate presentation definition")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &evaluation).unwrap();
+    println!();
+    Ok(())
+}
+
+pub fn create(args: PresentationExchangeCreateArgs) -> Result<()> {
+    let definition = read_definition(&args.definition)?;
+    let credentials = read_credentials(&args.credentials)?;
+    let (presentation, submission) = presentation_exchange::build_presentation(
+        &definition,
+        &credentials,
+        args.holder.as_deref(),
+        args.proof_format,
+    )
+    .context("Select credentials")?;
+    let submission_file = File::create(&args.submission).context("Create submission file")?;
+    serde_json::to_writer_pretty(BufWriter::new(submission_file), &submission)
+        .context("Write presentation submission")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer(stdout_writer, &presentation).unwrap();
+    Ok(())
+}
+
+pub fn verify(args: PresentationExchangeVerifyArgs) -> Result<()> {
+    let definition = read_definition(&args.definition)?;
+    let submission: PresentationSubmission =
+        read_json_file(&args.submission).context("Read presentation submission")?;
+    let mut data = String::new();
+    stdin()
+        .read_to_string(&mut data)
+        .context("Read presentation")?;
+    let presentation = parse_credential(&data);
+    let check = presentation_exchange::check_submission(&definition, &submission, &presentation)
+        .context("Check presentation submission")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer(stdout_writer, &check).unwrap();
+    if !check.errors.is_empty() {
+        std::process::exit(2);
+    }
+    Ok(())
+}
@@ -0,0 +1,398 @@
+use std::io::{stdin, stdout, BufReader, BufWriter};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand};
+use didkit::{
+    zcap::{self, CapabilityStore, Caveats, ChainReport, Revocations},
+    ContextLoader, DIDResolver, Delegation, Invocation, LinkedDataProofOptions, ProofFormat,
+    VerificationResult, URI,
+};
+use serde_json::Value;
+
+use crate::{get_ssh_agent_sock, opts::ResolverOptions, read_json_file, KeyArg, ProofOptions};
+
+#[derive(Subcommand)]
+pub enum ZcapCmd {
+    /// Delegate a capability
+    ///
+    /// Reads the capability delegation from standard input. Outputs it with a
+    /// capabilityDelegation proof.
+    Delegate(Box<ZcapDelegateArgs>),
+    /// Invoke a capability
+    ///
+    /// Reads the invocation from standard input. Outputs it with a capabilityInvocation proof.
+    Invoke(Box<ZcapInvokeArgs>),
+    /// Revoke capabilities
+    ///
+    /// Outputs a revocation list, signed as a JWS, for `didkit zcap verify-delegation` and
+    /// `didkit zcap verify-invocation`.
+    Revoke(ZcapRevokeArgs),
+    /// Verify a capability delegation
+    ///
+    /// Reads the delegation from standard input. Outputs a verification result, or a report for
+    /// each hop of the delegation chain if capabilities are given for chain verification.
+    VerifyDelegation(ZcapVerifyDelegationArgs),
+    /// Verify a capability invocation
+    ///
+    /// Reads the invocation from standard input. Outputs a verification result, or a report for
+    /// each hop of the delegation chain if capabilities are given for chain verification.
+    VerifyInvocation(ZcapVerifyInvocationArgs),
+}
+
+#[derive(Args)]
+pub struct ZcapDelegateArgs {
+    /// Parent capability JSON file, from which the capability chain is derived
+    #[clap(long, conflicts_with = "capability_chain")]
+    parent: Option<PathBuf>,
+    /// Capability chain entry (capability id), starting from the root capability
+    ///
+    /// May be repeated. Defaults to the parentCapability of the delegation if there is no
+    /// --parent option.
+    #[clap(long)]
+    capability_chain: Vec<String>,
+    /// Time (RFC 3339) after which the capability may not be used
+    #[clap(long)]
+    expires: Option<DateTime<Utc>>,
+    /// Action that the capability may be invoked for. May be repeated.
+    #[clap(long)]
+    allowed_action: Vec<String>,
+    /// URI of the target that the capability may be invoked on, including targets under it
+    #[clap(long)]
+    invocation_target: Option<String>,
+    #[clap(flatten)]
+    key: KeyArg,
+    #[clap(flatten)]
+    proof_options: ProofOptions,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct ZcapInvokeArgs {
+    /// Id of the capability being invoked
+    #[clap(long)]
+    target: String,
+    /// Action being invoked, checked against the capability's allowed actions
+    #[clap(long)]
+    action: Option<String>,
+    /// Target URI of the invocation, checked against the capability's invocation target
+    #[clap(long)]
+    invocation_target: Option<String>,
+    #[clap(flatten)]
+    key: KeyArg,
+    #[clap(flatten)]
+    proof_options: ProofOptions,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct ZcapRevokeArgs {
+    /// Ids of the capabilities to revoke
+    #[clap(required = true)]
+    ids: Vec<String>,
+    /// Verification method of the revoking DID, to sign the revocation list with
+    #[clap(short, long)]
+    verification_method: String,
+    #[clap(flatten)]
+    key: KeyArg,
+}
+
+/// Revocation lists to check capabilities against
+#[derive(Args)]
+pub struct RevocationOptions {
+    /// Revocation list file, as output by `didkit zcap revoke`. May be repeated.
+    #[clap(long)]
+    revocation_list: Vec<PathBuf>,
+}
+
+impl RevocationOptions {
+    async fn revocations(&self, resolver: &dyn DIDResolver) -> Result<Revocations> {
+        let mut lists = Vec::new();
+        for path in &self.revocation_list {
+            let jws = std::fs::read_to_string(path).context("Unable to read revocation list")?;
+            lists.push(jws.trim().to_string());
+        }
+        Revocations::verify(&lists, resolver)
+            .await
+            .context("Unable to verify revocation list")
+    }
+}
+
+/// Capabilities for verifying delegation chains
+#[derive(Args)]
+pub struct ChainOptions {
+    /// Directory of capability JSON files, to look up parent capabilities in
+    #[clap(long)]
+    capability_store: Option<PathBuf>,
+    /// Capability JSON file, to look up parent capabilities in. May be repeated.
+    #[clap(long)]
+    capability: Vec<PathBuf>,
+    /// Id of the trusted root capability, at which the delegation chain must end
+    #[clap(long)]
+    root: Option<String>,
+}
+
+impl ChainOptions {
+    /// Get the capability store and trusted root capability id, if chain verification was
+    /// requested
+    fn store(&self) -> Result<Option<(CapabilityStore, &str)>> {
+        if self.capability_store.is_none() && self.capability.is_empty() {
+            return Ok(None);
+        }
+        let root = match &self.root {
+            Some(root) => root,
+            None => bail!("Delegation chain verification requires the trusted root (--root)"),
+        };
+        let mut store = match &self.capability_store {
+            Some(dir) => CapabilityStore::load(dir)?,
+            None => CapabilityStore::new(),
+        };
+        for path in &self.capability {
+            store.insert(read_json_file(path).context("Unable to read capability")?);
+        }
+        Ok(Some((store, root)))
+    }
+}
+
+#[derive(Args)]
+pub struct ZcapVerifyDelegationArgs {
+    #[clap(flatten)]
+    chain_options: ChainOptions,
+    #[clap(flatten)]
+    revocation_options: RevocationOptions,
+    #[clap(flatten)]
+    proof_options: ProofOptions,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+#[derive(Args)]
+pub struct ZcapVerifyInvocationArgs {
+    /// Capability delegation JSON file for the invoked capability
+    ///
+    /// Without this option, only the invocation's signature is verified.
+    #[clap(long)]
+    delegation: Option<PathBuf>,
+    #[clap(flatten)]
+    chain_options: ChainOptions,
+    #[clap(flatten)]
+    revocation_options: RevocationOptions,
+    #[clap(flatten)]
+    proof_options: ProofOptions,
+    #[clap(flatten)]
+    resolver_options: ResolverOptions,
+}
+
+pub async fn cli(cmd: ZcapCmd) -> Result<()> {
+    match cmd {
+        ZcapCmd::Delegate(args) => delegate(*args).await?,
+        ZcapCmd::Invoke(args) => invoke(*args).await?,
+        ZcapCmd::Revoke(args) => revoke(args)?,
+        ZcapCmd::VerifyDelegation(args) => verify_delegation(args).await?,
+        ZcapCmd::VerifyInvocation(args) => verify_invocation(args).await?,
+    };
+    Ok(())
+}
+
+fn ldp_options(proof_options: ProofOptions) -> Result<LinkedDataProofOptions> {
+    if proof_options.proof_format != ProofFormat::LDP {
+        bail!("Capabilities only support Linked Data Proofs");
+    }
+    Ok(LinkedDataProofOptions::from(proof_options))
+}
+
+fn print_result(result: &VerificationResult) {
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, result).unwrap();
+    println!();
+    if !result.errors.is_empty() {
+        std::process::exit(2);
+    }
+}
+
+fn print_report(report: &ChainReport) {
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, report).unwrap();
+    println!();
+    if !report.valid {
+        std::process::exit(2);
+    }
+}
+
+pub async fn delegate(args: ZcapDelegateArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let mut delegation: Delegation<Value, Value> =
+        serde_json::from_reader(BufReader::new(stdin())).context("Unable to parse delegation")?;
+    let caveats = Caveats {
+        expires: args.expires,
+        allowed_action: if args.allowed_action.is_empty() {
+            None
+        } else {
+            Some(args.allowed_action)
+        },
+        invocation_target: args.invocation_target,
+    };
+    caveats.apply(&mut delegation);
+    let capability_chain = if let Some(parent_path) = &args.parent {
+        let parent: Delegation<Value, Value> =
+            read_json_file(parent_path).context("Unable to read parent capability")?;
+        zcap::capability_chain(&parent)?
+    } else if !args.capability_chain.is_empty() {
+        args.capability_chain
+    } else {
+        match &delegation.parent_capability {
+            URI::String(parent) => vec![parent.clone()],
+        }
+    };
+    let jwk_opt = args.key.get_jwk_opt()?;
+    let ssh_agent_sock_opt = if args.key.ssh_agent {
+        Some(get_ssh_agent_sock())
+    } else {
+        None
+    };
+    let options = ldp_options(args.proof_options)?;
+    let proof = zcap::delegate(
+        &delegation,
+        &capability_chain,
+        jwk_opt.as_ref(),
+        options,
+        &resolver,
+        &mut context_loader,
+        ssh_agent_sock_opt.as_deref(),
+    )
+    .await
+    .context("Unable to delegate capability")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &delegation.set_proof(proof)).unwrap();
+    println!();
+    Ok(())
+}
+
+pub async fn invoke(args: ZcapInvokeArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let invocation: Invocation<Value> =
+        serde_json::from_reader(BufReader::new(stdin())).context("Unable to parse invocation")?;
+    let jwk_opt = args.key.get_jwk_opt()?;
+    let ssh_agent_sock_opt = if args.key.ssh_agent {
+        Some(get_ssh_agent_sock())
+    } else {
+        None
+    };
+    let options = ldp_options(args.proof_options)?;
+    let proof = zcap::invoke(
+        &invocation,
+        &URI::String(args.target),
+        args.action.as_deref(),
+        args.invocation_target.as_deref(),
+        jwk_opt.as_ref(),
+        options,
+        &resolver,
+        &mut context_loader,
+        ssh_agent_sock_opt.as_deref(),
+    )
+    .await
+    .context("Unable to invoke capability")?;
+    let stdout_writer = BufWriter::new(stdout());
+    serde_json::to_writer_pretty(stdout_writer, &invocation.set_proof(proof)).unwrap();
+    println!();
+    Ok(())
+}
+
+pub fn revoke(args: ZcapRevokeArgs) -> Result<()> {
+    let jwk = match args.key.get_jwk_opt()? {
+        Some(jwk) => jwk,
+        None => bail!("Revocation lists cannot be signed with SSH Agent"),
+    };
+    let jws = zcap::sign_revocation_list(args.ids, &args.verification_method, &jwk, Utc::now())
+        .context("Unable to sign revocation list")?;
+    println!("{jws}");
+    Ok(())
+}
+
+pub async fn verify_delegation(args: ZcapVerifyDelegationArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let delegation: Delegation<Value, Value> =
+        serde_json::from_reader(BufReader::new(stdin())).context("Unable to parse delegation")?;
+    let revocations = args.revocation_options.revocations(&resolver).await?;
+    if let Some((store, root)) = args.chain_options.store()? {
+        let report = zcap::verify_delegation_chain(
+            &delegation,
+            &store,
+            root,
+            &revocations,
+            Utc::now(),
+            &resolver,
+            &mut context_loader,
+        )
+        .await;
+        print_report(&report);
+        return Ok(());
+    }
+    let options = ldp_options(args.proof_options)?;
+    let result = zcap::verify_delegation(
+        &delegation,
+        Some(options),
+        &revocations,
+        Utc::now(),
+        &resolver,
+        &mut context_loader,
+    )
+    .await;
+    print_result(&result);
+    Ok(())
+}
+
+pub async fn verify_invocation(args: ZcapVerifyInvocationArgs) -> Result<()> {
+    let resolver = args.resolver_options.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let invocation: Invocation<Value> =
+        serde_json::from_reader(BufReader::new(stdin())).context("Unable to parse invocation")?;
+    let revocations = args.revocation_options.revocations(&resolver).await?;
+    if let Some((mut store, root)) = args.chain_options.store()? {
+        if let Some(delegation_path) = &args.delegation {
+            store.insert(read_json_file(delegation_path).context("Unable to read delegation")?);
+        }
+        let report = zcap::verify_invocation_chain(
+            &invocation,
+            &store,
+            root,
+            &revocations,
+            Utc::now(),
+            &resolver,
+            &mut context_loader,
+        )
+        .await;
+        print_report(&report);
+        return Ok(());
+    }
+    let options = ldp_options(args.proof_options)?;
+    let result = match &args.delegation {
+        Some(delegation_path) => {
+            let delegation: Delegation<Value, Value> =
+                read_json_file(delegation_path).context("Unable to read delegation")?;
+            zcap::verify_invocation(
+                &invocation,
+                &delegation,
+                Some(options),
+                &revocations,
+                Utc::now(),
+                &resolver,
+                &mut context_loader,
+            )
+            .await
+        }
+        None => {
+            invocation
+                .verify_signature(Some(options), &resolver, &mut context_loader)
+                .await
+        }
+    };
+    print_result(&result);
+    Ok(())
+}
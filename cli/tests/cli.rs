@@ -1,9 +1,11 @@
 use serde_json::Value;
 use std::io::Write;
-use std::process::{Command, Stdio};
+use std::process::{Command, Output, Stdio};
 
 use didkit::ssi;
 
+mod sidetree;
+
 static BIN: &str = env!("CARGO_BIN_EXE_didkit");
 
 const DID_KEY_K256: &str = "did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme";
@@ -255,6 +257,121 @@ _:c14n1 <example:cat> "AAA" .
     assert_eq!(rdf, rdf_expected);
 }
 
+#[test]
+fn zcap_cli() {
+    fn didkit(args: &[&str], stdin: &[u8]) -> std::process::Output {
+        let mut child = Command::new(BIN)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .unwrap();
+        child.stdin.as_mut().unwrap().write_all(stdin).unwrap();
+        child.wait_with_output().unwrap()
+    }
+    let did_output = didkit(&["key-to-did", "key", "-k", "tests/ed25519-key.jwk"], b"");
+    assert!(did_output.status.success());
+    let did = String::from_utf8(did_output.stdout)
+        .unwrap()
+        .trim()
+        .to_string();
+    let vm_output = didkit(
+        &[
+            "key-to-verification-method",
+            "key",
+            "-k",
+            "tests/ed25519-key.jwk",
+        ],
+        b"",
+    );
+    assert!(vm_output.status.success());
+    let verification_method = String::from_utf8(vm_output.stdout)
+        .unwrap()
+        .trim()
+        .to_string();
+    let context = serde_json::json!([
+        "https://w3id.org/security/v2",
+        { "@vocab": "https://example.org/vocab#" }
+    ]);
+
+    // Delegate a capability to the same DID
+    let delegation = serde_json::json!({
+        "@context": context,
+        "id": "urn:uuid:a6e1f1c2-2d2c-4bd3-b5d2-0a4c6bb2b0c9",
+        "parentCapability": "https://example.org/resource",
+        "invoker": did,
+    });
+    let delegate_output = didkit(
+        &[
+            "zcap",
+            "delegate",
+            "-k",
+            "tests/ed25519-key.jwk",
+            "-v",
+            &verification_method,
+        ],
+        delegation.to_string().as_bytes(),
+    );
+    assert!(delegate_output.status.success());
+    let delegation: Value = serde_json::from_slice(&delegate_output.stdout).unwrap();
+    assert_eq!(
+        delegation["proof"]["capabilityChain"],
+        serde_json::json!(["https://example.org/resource"])
+    );
+    let verify_output = didkit(
+        &["zcap", "verify-delegation"],
+        delegation.to_string().as_bytes(),
+    );
+    assert!(verify_output.status.success());
+    let delegation_path = std::env::temp_dir().join(format!(
+        "didkit-zcap-delegation-{}.json",
+        std::process::id()
+    ));
+    std::fs::write(&delegation_path, delegation.to_string()).unwrap();
+
+    // Invoke the delegated capability
+    let invocation = serde_json::json!({
+        "@context": context,
+        "id": "urn:uuid:c4b7e4a8-1f5e-4a4e-9d8f-6c1e4e8f4b1a",
+    });
+    let invoke_output = didkit(
+        &[
+            "zcap",
+            "invoke",
+            "--target",
+            delegation["id"].as_str().unwrap(),
+            "-k",
+            "tests/ed25519-key.jwk",
+            "-v",
+            &verification_method,
+        ],
+        invocation.to_string().as_bytes(),
+    );
+    assert!(invoke_output.status.success());
+    let invocation = invoke_output.stdout;
+    let verify_output = didkit(
+        &[
+            "zcap",
+            "verify-invocation",
+            "--delegation",
+            delegation_path.to_str().unwrap(),
+        ],
+        &invocation,
+    );
+    std::fs::remove_file(&delegation_path).ok();
+    assert!(verify_output.status.success());
+
+    // Tampered invocation fails verification
+    let mut tampered: Value = serde_json::from_slice(&invocation).unwrap();
+    tampered["id"] = "urn:uuid:00000000-0000-4000-8000-000000000000".into();
+    let verify_output = didkit(
+        &["zcap", "verify-invocation"],
+        tampered.to_string().as_bytes(),
+    );
+    assert!(!verify_output.status.success());
+}
+
 #[tokio::test]
 async fn resolver_option() {
     use serde_json::json;
@@ -418,3 +535,176 @@ async fn resolver_option() {
 
     shutdown().ok();
 }
+
+/// Run didkit with the given arguments and standard input, in a directory, using an ION node
+async fn didkit_ion(dir: &std::path::Path, api_url: &str, args: &[&str], stdin: &[u8]) -> Output {
+    use tokio::io::AsyncWriteExt;
+    let mut child = tokio::process::Command::new(BIN)
+        .args(args)
+        .current_dir(dir)
+        .env("DID_ION_API_URL", api_url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .unwrap();
+    let mut child_stdin = child.stdin.take().unwrap();
+    child_stdin.write_all(stdin).await.unwrap();
+    drop(child_stdin);
+    child.wait_with_output().await.unwrap()
+}
+
+#[tokio::test]
+async fn ion_mock_operations() {
+    use serde_json::json;
+    let node = sidetree::SidetreeNode::start().unwrap();
+    let api_url = node.url().to_string();
+    let dir = std::env::temp_dir().join(format!("didkit-ion-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let didkit = |args: Vec<String>, stdin: Vec<u8>| {
+        let dir = dir.clone();
+        let api_url = api_url.clone();
+        async move {
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            didkit_ion(&dir, &api_url, &args, &stdin).await
+        }
+    };
+    macro_rules! run {
+        ($stdin:expr, $($arg:expr),+) => {{
+            let output = didkit(vec![$($arg.to_string()),+], $stdin.to_vec()).await;
+            assert!(output.status.success(), "didkit failed");
+            output.stdout
+        }};
+    }
+    let resolve = |did: String, metadata: bool| {
+        let didkit = &didkit;
+        async move {
+            let mut args = vec!["did".to_string(), "resolve".to_string(), did];
+            if metadata {
+                args.push("-m".to_string());
+            }
+            let output = didkit(args, Vec::new()).await;
+            if !output.status.success() {
+                return None;
+            }
+            Some(serde_json::from_slice::<Value>(&output.stdout).unwrap())
+        }
+    };
+    for name in ["u1", "r1", "u2", "u3", "r2", "u4", "v"] {
+        let jwk = run!(b"", "key", "generate", "secp256k1");
+        std::fs::write(dir.join(format!("{name}.jwk")), jwk).unwrap();
+    }
+    // Public keys only, for signing offline
+    for name in ["u2", "u3"] {
+        let mut jwk: Value =
+            serde_json::from_slice(&std::fs::read(dir.join(format!("{name}.jwk"))).unwrap())
+                .unwrap();
+        jwk.as_object_mut().unwrap().remove("d");
+        std::fs::write(dir.join(format!("{name}.pub.jwk")), jwk.to_string()).unwrap();
+    }
+
+    // Create operation and long-form DID
+    let create = run!(b"", "did", "create", "ion", "-r", "r1.jwk", "-u", "u1.jwk");
+    let long_did = String::from_utf8(run!(create, "did", "from-tx"))
+        .unwrap()
+        .trim()
+        .to_string();
+    let short_did = long_did.rsplit_once(':').unwrap().0.to_string();
+    assert!(long_did.starts_with("did:ion:"));
+
+    // Unpublished: only the long-form DID resolves
+    let result = resolve(long_did.clone(), true).await.unwrap();
+    assert_eq!(result["didDocument"]["id"], json!(long_did));
+    assert_eq!(
+        result["didDocumentMetadata"]["method"]["published"],
+        json!(false)
+    );
+    assert!(resolve(short_did.clone(), false).await.is_none());
+
+    // Publish
+    run!(create, "did", "submit-tx");
+    let document = resolve(short_did.clone(), false).await.unwrap();
+    assert_eq!(document["id"], json!(short_did));
+    let result = resolve(long_did.clone(), true).await.unwrap();
+    assert_eq!(
+        result["didDocumentMetadata"]["method"]["published"],
+        json!(true)
+    );
+    assert_eq!(
+        result["didDocumentMetadata"]["canonicalId"],
+        json!(short_did)
+    );
+
+    // Update: add a verification method
+    let vm = format!("{short_did}#key-1");
+    let update = run!(
+        b"",
+        "did",
+        "update",
+        "-U",
+        "u1.jwk",
+        "-u",
+        "u2.jwk",
+        "set",
+        "verification-method",
+        &vm,
+        "-t",
+        "JsonWebKey2020",
+        "-k",
+        "v.jwk",
+        "--authentication"
+    );
+    run!(update, "did", "submit-tx");
+    let document = resolve(short_did.clone(), false).await.unwrap();
+    assert_eq!(document["verificationMethod"][0]["id"], json!(vm));
+    assert_eq!(document["authentication"], json!([vm]));
+
+    // Replaying the update fails, as its update key is no longer committed to
+    let output = didkit(
+        vec!["did".to_string(), "submit-tx".to_string()],
+        update.clone(),
+    )
+    .await;
+    assert!(!output.status.success());
+
+    // Offline update: build with public keys, then sign
+    let unsigned = run!(
+        b"",
+        "did",
+        "update",
+        "--unsigned",
+        "-U",
+        "u2.pub.jwk",
+        "-u",
+        "u3.pub.jwk",
+        "remove",
+        "verification-method",
+        &vm
+    );
+    let signed = run!(unsigned, "did", "sign-tx", "-k", "u2.jwk");
+    run!(signed, "did", "submit-tx");
+    let document = resolve(short_did.clone(), false).await.unwrap();
+    assert!(document.get("verificationMethod").is_none());
+
+    // Recover, with new keys
+    let recover = run!(
+        b"", "did", "recover", &short_did, "-R", "r1.jwk", "-r", "r2.jwk", "-u", "u4.jwk", "-v",
+        "v.jwk"
+    );
+    run!(recover, "did", "submit-tx");
+    let document = resolve(short_did.clone(), false).await.unwrap();
+    assert_eq!(document["verificationMethod"].as_array().unwrap().len(), 1);
+
+    // Deactivate requires the current recovery key
+    let deactivate = run!(b"", "did", "deactivate", &short_did, "-k", "r1.jwk");
+    let output = didkit(vec!["did".to_string(), "submit-tx".to_string()], deactivate).await;
+    assert!(!output.status.success());
+    let deactivate = run!(b"", "did", "deactivate", &short_did, "-k", "r2.jwk");
+    run!(deactivate, "did", "submit-tx");
+    let result = resolve(short_did.clone(), true).await.unwrap();
+    assert_eq!(result["didDocumentMetadata"]["deactivated"], json!(true));
+    assert!(result["didDocument"].get("verificationMethod").is_none());
+
+    std::fs::remove_dir_all(&dir).ok();
+    node.shutdown();
+}
@@ -0,0 +1,390 @@
+//! In-process stand-in for an ION/Sidetree node, for testing DID operations without a network.
+//!
+//! Accepts create, update, recover and deactivate operations at `POST /operations`, and resolves
+//! DIDs at `GET /identifiers/{did}`, like the ION node API used by `DIDION`. Operations are
+//! applied immediately, without anchoring. Long-form DIDs resolve from their initial state until
+//! published, and short-form DIDs are not found until published. Hashes and commitments are
+//! checked with the Sidetree functions of `did-ion`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use did_ion::sidetree::{PublicKeyJwk, Sidetree};
+use did_ion::ION;
+use didkit::ssi;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Map, Value};
+use ssi::did_resolve::TYPE_DID_RESOLUTION;
+use ssi::jsonld::DID_RESOLUTION_V1_CONTEXT;
+use ssi::jwk::JWK;
+
+const METHOD_PREFIX: &str = "did:ion:";
+
+/// State of a published DID
+#[derive(Debug, Clone)]
+struct DIDState {
+    public_keys: Vec<Value>,
+    services: Vec<Value>,
+    update_commitment: String,
+    recovery_commitment: String,
+    deactivated: bool,
+}
+
+type Error = (StatusCode, String);
+
+fn bad_request(message: impl Into<String>) -> Error {
+    (StatusCode::BAD_REQUEST, message.into())
+}
+
+fn canonical_hash(value: &Value) -> Result<String, Error> {
+    let canonical =
+        ION::json_canonicalization_scheme(value).map_err(|e| bad_request(e.to_string()))?;
+    Ok(ION::hash(canonical.as_bytes()))
+}
+
+fn commitment(public_key: &Value) -> Result<String, Error> {
+    let public_key: PublicKeyJwk =
+        serde_json::from_value(public_key.clone()).map_err(|e| bad_request(e.to_string()))?;
+    ION::commitment_scheme(&public_key).map_err(|e| bad_request(e.to_string()))
+}
+
+fn get<'a>(value: &'a Value, name: &str) -> Result<&'a Value, Error> {
+    value
+        .get(name)
+        .ok_or_else(|| bad_request(format!("Missing {name}")))
+}
+
+fn get_str<'a>(value: &'a Value, name: &str) -> Result<&'a str, Error> {
+    get(value, name)?
+        .as_str()
+        .ok_or_else(|| bad_request(format!("{name} must be a string")))
+}
+
+/// Check a reveal value and signed data against a commitment, returning the signed claims
+fn verify_signed_data(
+    operation: &Value,
+    key_claim: &str,
+    commitment_value: &str,
+) -> Result<Value, Error> {
+    let jws = get_str(operation, "signedData")?;
+    let (_, payload) = ssi::jws::decode_unverified(jws).map_err(|e| bad_request(e.to_string()))?;
+    let claims: Value = serde_json::from_slice(&payload).map_err(|e| bad_request(e.to_string()))?;
+    let public_key = get(&claims, key_claim)?;
+    if commitment(public_key)? != commitment_value {
+        return Err(bad_request("Key does not match commitment"));
+    }
+    if canonical_hash(public_key)? != get_str(operation, "revealValue")? {
+        return Err(bad_request("Invalid reveal value"));
+    }
+    let jwk: JWK =
+        serde_json::from_value(public_key.clone()).map_err(|e| bad_request(e.to_string()))?;
+    ssi::jws::decode_verify(jws, &jwk).map_err(|e| bad_request(e.to_string()))?;
+    Ok(claims)
+}
+
+fn check_delta(delta: &Value, delta_hash: &str) -> Result<(), Error> {
+    if canonical_hash(delta)? != delta_hash {
+        return Err(bad_request("Delta hash mismatch"));
+    }
+    Ok(())
+}
+
+fn remove_ids(entries: &mut Vec<Value>, ids: &Value) {
+    let ids: Vec<&str> = ids
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    entries.retain(|entry| !ids.contains(&entry["id"].as_str().unwrap_or_default()));
+}
+
+fn add_entries(entries: &mut Vec<Value>, new_entries: &Value) {
+    for entry in new_entries.as_array().into_iter().flatten() {
+        entries.retain(|existing| existing["id"] != entry["id"]);
+        entries.push(entry.clone());
+    }
+}
+
+fn array(value: Option<&Value>) -> Vec<Value> {
+    value.and_then(Value::as_array).cloned().unwrap_or_default()
+}
+
+impl DIDState {
+    fn apply_delta(&mut self, delta: &Value) -> Result<(), Error> {
+        for patch in array(delta.get("patches")) {
+            match get_str(&patch, "action")? {
+                "replace" => {
+                    let document = get(&patch, "document")?;
+                    self.public_keys = array(document.get("publicKeys"));
+                    self.services = array(document.get("services"));
+                }
+                "add-public-keys" => add_entries(&mut self.public_keys, get(&patch, "publicKeys")?),
+                "remove-public-keys" => remove_ids(&mut self.public_keys, get(&patch, "ids")?),
+                "add-services" => add_entries(&mut self.services, get(&patch, "services")?),
+                "remove-services" => remove_ids(&mut self.services, get(&patch, "ids")?),
+                action => return Err(bad_request(format!("Unsupported patch action: {action}"))),
+            }
+        }
+        self.update_commitment = get_str(delta, "updateCommitment")?.to_string();
+        Ok(())
+    }
+
+    /// Initial state from the suffix data and delta of a create operation, with the DID suffix
+    fn create(suffix_data: &Value, delta: &Value) -> Result<(String, Self), Error> {
+        check_delta(delta, get_str(suffix_data, "deltaHash")?)?;
+        let mut state = Self {
+            public_keys: Vec::new(),
+            services: Vec::new(),
+            update_commitment: String::new(),
+            recovery_commitment: get_str(suffix_data, "recoveryCommitment")?.to_string(),
+            deactivated: false,
+        };
+        state.apply_delta(delta)?;
+        Ok((canonical_hash(suffix_data)?, state))
+    }
+
+    fn document(&self, did: &str) -> Value {
+        let mut document = Map::new();
+        document.insert(
+            "@context".to_string(),
+            json!(["https://www.w3.org/ns/did/v1"]),
+        );
+        document.insert("id".to_string(), json!(did));
+        if self.deactivated {
+            return Value::Object(document);
+        }
+        let mut verification_methods = Vec::new();
+        for public_key in &self.public_keys {
+            let id = format!("{did}#{}", public_key["id"].as_str().unwrap_or_default());
+            verification_methods.push(json!({
+                "id": id,
+                "controller": did,
+                "type": public_key["type"],
+                "publicKeyJwk": public_key["publicKeyJwk"],
+            }));
+            for purpose in array(public_key.get("purposes")) {
+                if let Some(purpose) = purpose.as_str() {
+                    match document.get_mut(purpose) {
+                        Some(Value::Array(ids)) => ids.push(json!(id)),
+                        _ => {
+                            document.insert(purpose.to_string(), json!([id]));
+                        }
+                    }
+                }
+            }
+        }
+        if !verification_methods.is_empty() {
+            document.insert(
+                "verificationMethod".to_string(),
+                json!(verification_methods),
+            );
+        }
+        if !self.services.is_empty() {
+            let services: Vec<Value> = self
+                .services
+                .iter()
+                .map(|service| {
+                    let mut service = service.clone();
+                    let id = format!("{did}#{}", service["id"].as_str().unwrap_or_default());
+                    service["id"] = json!(id);
+                    service
+                })
+                .collect();
+            document.insert("service".to_string(), json!(services));
+        }
+        Value::Object(document)
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    dids: HashMap<String, DIDState>,
+}
+
+impl Node {
+    fn submit(&mut self, operation: &Value) -> Result<Value, Error> {
+        match get_str(operation, "type")? {
+            "create" => {
+                let (suffix, state) =
+                    DIDState::create(get(operation, "suffixData")?, get(operation, "delta")?)?;
+                if self.dids.contains_key(&suffix) {
+                    return Err(bad_request("DID already exists"));
+                }
+                self.dids.insert(suffix.clone(), state);
+                self.resolve(&format!("{METHOD_PREFIX}{suffix}"))
+            }
+            "update" => {
+                let state = self.published_state(operation)?;
+                let claims = verify_signed_data(operation, "updateKey", &state.update_commitment)?;
+                let delta = get(operation, "delta")?;
+                check_delta(delta, get_str(&claims, "deltaHash")?)?;
+                state.apply_delta(delta)?;
+                Ok(json!({}))
+            }
+            "recover" => {
+                let state = self.published_state(operation)?;
+                let claims =
+                    verify_signed_data(operation, "recoveryKey", &state.recovery_commitment)?;
+                let delta = get(operation, "delta")?;
+                check_delta(delta, get_str(&claims, "deltaHash")?)?;
+                state.public_keys.clear();
+                state.services.clear();
+                state.apply_delta(delta)?;
+                state.recovery_commitment = get_str(&claims, "recoveryCommitment")?.to_string();
+                Ok(json!({}))
+            }
+            "deactivate" => {
+                let suffix = get_str(operation, "didSuffix")?.to_string();
+                let state = self.published_state(operation)?;
+                let claims =
+                    verify_signed_data(operation, "recoveryKey", &state.recovery_commitment)?;
+                if get_str(&claims, "didSuffix")? != suffix {
+                    return Err(bad_request("DID suffix mismatch"));
+                }
+                state.deactivated = true;
+                Ok(json!({}))
+            }
+            type_ => Err(bad_request(format!("Unsupported operation type: {type_}"))),
+        }
+    }
+
+    fn published_state(&mut self, operation: &Value) -> Result<&mut DIDState, Error> {
+        let suffix = get_str(operation, "didSuffix")?;
+        let state = self
+            .dids
+            .get_mut(suffix)
+            .ok_or_else(|| bad_request("DID not found"))?;
+        if state.deactivated {
+            return Err(bad_request("DID is deactivated"));
+        }
+        Ok(state)
+    }
+
+    /// Resolve a short-form or long-form DID to a DID resolution result
+    fn resolve(&self, did: &str) -> Result<Value, Error> {
+        let not_found = || (StatusCode::NOT_FOUND, "DID not found".to_string());
+        let id = did.strip_prefix(METHOD_PREFIX).ok_or_else(not_found)?;
+        let (suffix, long_form) = match id.split_once(':') {
+            Some((suffix, initial_state)) => (suffix, Some(initial_state)),
+            None => (id, None),
+        };
+        let short_did = format!("{METHOD_PREFIX}{suffix}");
+        let mut metadata = json!({ "equivalentId": [short_did] });
+        let state = match (self.dids.get(suffix), long_form) {
+            (Some(state), _) => {
+                metadata["canonicalId"] = json!(short_did);
+                metadata["method"] = json!({ "published": true });
+                state.clone()
+            }
+            (None, Some(initial_state)) => {
+                let data = base64::decode_config(initial_state, base64::URL_SAFE_NO_PAD)
+                    .map_err(|e| bad_request(e.to_string()))?;
+                let initial_state: Value =
+                    serde_json::from_slice(&data).map_err(|e| bad_request(e.to_string()))?;
+                let (initial_suffix, state) = DIDState::create(
+                    get(&initial_state, "suffixData")?,
+                    get(&initial_state, "delta")?,
+                )?;
+                if initial_suffix != suffix {
+                    return Err(bad_request("Long-form DID suffix mismatch"));
+                }
+                metadata["method"] = json!({ "published": false });
+                state
+            }
+            (None, None) => return Err(not_found()),
+        };
+        metadata["method"]["updateCommitment"] = json!(state.update_commitment);
+        metadata["method"]["recoveryCommitment"] = json!(state.recovery_commitment);
+        if state.deactivated {
+            metadata["deactivated"] = json!(true);
+        }
+        Ok(json!({
+            "@context": DID_RESOLUTION_V1_CONTEXT,
+            "didDocument": state.document(did),
+            "didDocumentMetadata": metadata,
+            "didResolutionMetadata": {
+                "contentType": "application/did+ld+json"
+            }
+        }))
+    }
+}
+
+async fn handle(node: Arc<Mutex<Node>>, req: Request<Body>) -> Result<Value, Error> {
+    let path = req.uri().path().to_string();
+    match (req.method(), path.trim_end_matches('/')) {
+        (&Method::POST, "/operations") => {
+            let body = hyper::body::to_bytes(req.into_body())
+                .await
+                .map_err(|e| bad_request(e.to_string()))?;
+            let operation: Value =
+                serde_json::from_slice(&body).map_err(|e| bad_request(e.to_string()))?;
+            node.lock().unwrap().submit(&operation)
+        }
+        (&Method::GET, _) if path.starts_with("/identifiers/") => {
+            let did = percent_encoding::percent_decode(path["/identifiers/".len()..].as_bytes())
+                .decode_utf8()
+                .map_err(|e| bad_request(e.to_string()))?
+                .to_string();
+            node.lock().unwrap().resolve(&did)
+        }
+        _ => Err((StatusCode::NOT_FOUND, "Not found".to_string())),
+    }
+}
+
+/// Running mock Sidetree node
+pub struct SidetreeNode {
+    url: String,
+    shutdown: futures::channel::oneshot::Sender<()>,
+}
+
+impl SidetreeNode {
+    /// Start the node on a local port. Must be called from within a Tokio runtime.
+    pub fn start() -> Result<Self, hyper::Error> {
+        let node = Arc::new(Mutex::new(Node::default()));
+        let addr = ([127, 0, 0, 1], 0).into();
+        let make_svc = make_service_fn(move |_| {
+            let node = node.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |req| {
+                    let node = node.clone();
+                    async move {
+                        let (status, body, content_type) = match handle(node, req).await {
+                            Ok(result) => (StatusCode::OK, result, TYPE_DID_RESOLUTION),
+                            Err((status, message)) => (
+                                status,
+                                json!({ "error": { "code": status.as_u16(), "message": message } }),
+                                "application/json",
+                            ),
+                        };
+                        let response = Response::builder()
+                            .status(status)
+                            .header(CONTENT_TYPE, content_type)
+                            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                            .unwrap();
+                        Ok::<_, hyper::Error>(response)
+                    }
+                }))
+            }
+        });
+        let server = Server::try_bind(&addr)?.serve(make_svc);
+        let url = format!("http://{}/", server.local_addr());
+        let (shutdown, shutdown_rx) = futures::channel::oneshot::channel();
+        let graceful = server.with_graceful_shutdown(async {
+            shutdown_rx.await.ok();
+        });
+        tokio::task::spawn(async move {
+            graceful.await.ok();
+        });
+        Ok(Self { url, shutdown })
+    }
+
+    /// Base URL of the node API, for `DID_ION_API_URL`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn shutdown(self) {
+        self.shutdown.send(()).ok();
+    }
+}
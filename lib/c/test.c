@@ -163,6 +163,86 @@ int main() {
     didkit_free_string(res);
     didkit_free_string(vp_jwt);
 
+    // Generate secp256k1 and P-256 keys
+    const char *key_secp256k1 = didkit_vc_generate_secp256k1_key();
+    if (key_secp256k1 == NULL) errx(1, "generate secp256k1 key: %s", didkit_error_message());
+    const char *did_secp256k1 = didkit_key_to_did("key", key_secp256k1);
+    if (did_secp256k1 == NULL) errx(1, "secp256k1 key to did: %s", didkit_error_message());
+    didkit_free_string(did_secp256k1);
+    didkit_free_string(key_secp256k1);
+    const char *key_p256 = didkit_vc_generate_p256_key();
+    if (key_p256 == NULL) errx(1, "generate P-256 key: %s", didkit_error_message());
+    const char *did_p256 = didkit_key_to_did("key", key_p256);
+    if (did_p256 == NULL) errx(1, "P-256 key to did: %s", didkit_error_message());
+    didkit_free_string(did_p256);
+    didkit_free_string(key_p256);
+
+    // Prepare and complete credential with an invalid signature
+    snprintf(vc_options, sizeof vc_options, "{"
+            "  \"proofPurpose\": \"assertionMethod\","
+            "  \"verificationMethod\": \"%s\""
+            "}", verification_method);
+    const char *preparation = didkit_vc_prepare_issue_credential(credential, vc_options, key);
+    if (preparation == NULL) errx(1, "prepare credential: %s", didkit_error_message());
+    if (strstr(preparation, "\"signingInput\"") == NULL) errx(1, "proof preparation: %s", preparation);
+    vc_ldp = didkit_vc_complete_issue_credential(credential, preparation, "invalid");
+    assert(vc_ldp == NULL);
+    didkit_free_string(preparation);
+
+    // Delegate capability
+    char delegation[0x1000];
+    snprintf(delegation, sizeof delegation, "{"
+        "   \"@context\": [\"https://w3id.org/security/v2\","
+        "       {\"@vocab\": \"https://example.org/vocab#\"}],"
+        "   \"id\": \"urn:uuid:e1c0b9f2-8ad4-4cf4-a4fb-7fdbb2d2f3c8\","
+        "   \"parentCapability\": \"https://example.org/resource\","
+        "   \"invoker\": \"%s\""
+        "}", did);
+    char zcap_options[0x1000];
+    snprintf(zcap_options, sizeof zcap_options, "{"
+            "  \"verificationMethod\": \"%s\""
+            "}", verification_method);
+    const char *delegated = didkit_zcap_delegate(delegation, zcap_options,
+            "[\"https://example.org/resource\"]", key);
+    if (delegated == NULL) errx(1, "delegate capability: %s", didkit_error_message());
+
+    // Verify delegation
+    res = didkit_zcap_verify_delegation(delegated, NULL);
+    if (res == NULL) errx(1, "verify delegation: %s", didkit_error_message());
+    if (strstr(res, "\"errors\":[]") == NULL) errx(1, "verify delegation result: %s", res);
+    didkit_free_string(res);
+
+    // Invoke capability
+    const char *invocation = "{"
+        "   \"@context\": [\"https://w3id.org/security/v2\","
+        "       {\"@vocab\": \"https://example.org/vocab#\"}],"
+        "   \"id\": \"urn:uuid:0f2e8a5e-1c4b-4d0b-9f5b-0b0b1d3f5e2a\""
+        "}";
+    const char *invoked = didkit_zcap_invoke(invocation,
+            "urn:uuid:e1c0b9f2-8ad4-4cf4-a4fb-7fdbb2d2f3c8", "read",
+            "https://example.org/resource/a", zcap_options, key);
+    if (invoked == NULL) errx(1, "invoke capability: %s", didkit_error_message());
+
+    // Verify invocation
+    res = didkit_zcap_verify_invocation(invoked, delegated, NULL);
+    if (res == NULL) errx(1, "verify invocation: %s", didkit_error_message());
+    if (strstr(res, "\"errors\":[]") == NULL) errx(1, "verify invocation result: %s", res);
+    didkit_free_string(res);
+
+    // Revoke capability
+    const char *revocation_list = didkit_zcap_revoke(
+            "[\"urn:uuid:e1c0b9f2-8ad4-4cf4-a4fb-7fdbb2d2f3c8\"]", verification_method, key);
+    if (revocation_list == NULL) errx(1, "revoke capability: %s", didkit_error_message());
+    char revocation_lists[0x1000];
+    snprintf(revocation_lists, sizeof revocation_lists, "[\"%s\"]", revocation_list);
+    res = didkit_zcap_verify_invocation(invoked, delegated, revocation_lists);
+    if (res == NULL) errx(1, "verify revoked invocation: %s", didkit_error_message());
+    if (strstr(res, "\"errors\":[]") != NULL) errx(1, "verify revoked invocation result: %s", res);
+    didkit_free_string(res);
+    didkit_free_string(revocation_list);
+    didkit_free_string(invoked);
+    didkit_free_string(delegated);
+
     didkit_free_string(verification_method);
     didkit_free_string(did);
     didkit_free_string(key);
@@ -1,17 +1,52 @@
 package com.spruceid;
 
+import java.util.concurrent.CompletableFuture;
+
 public class DIDKit {
     public static native String getVersion();
     public static native String generateEd25519Key() throws DIDKitException;
     public static native String keyToDID(String methodPattern, String jwk) throws DIDKitException;
     public static native String keyToVerificationMethod(String methodPattern, String jwk) throws DIDKitException;
+    public static native String keyDeriveFromMnemonic(String mnemonic, String passphrase, String curve, String path) throws DIDKitException;
+    public static native String keyDeriveFromSeed(String seed, String curve, String path) throws DIDKitException;
     public static native String issueCredential(String credential, String linkedDataProofOptions, String key) throws DIDKitException;
     public static native String verifyCredential(String verifiableCredential, String linkedDataProofOptions);
     public static native String issuePresentation(String presentation, String linkedDataProofOptions, String key) throws DIDKitException;
     public static native String DIDAuth(String holder, String linkedDataProofOptions, String key) throws DIDKitException;
     public static native String verifyPresentation(String verifiablePresentation, String linkedDataProofOptions);
     public static native String resolveDID(String did, String inputMetadata);
+    public static native String resolveDIDWithConfig(String did, String inputMetadata, String config) throws DIDKitException;
     public static native String dereferenceDIDURL(String didUrl, String inputMetadata);
+    public static native String dereferenceDIDURLWithConfig(String didUrl, String inputMetadata, String config) throws DIDKitException;
+    public static native String oid4vpCreateRequest(String request, String key, String verificationMethod) throws DIDKitException;
+    public static native String oid4vpParseRequest(String requestUri) throws DIDKitException;
+    public static native String oid4vpCreateResponse(String request, String presentation, String presentationSubmission, String linkedDataProofOptions, String key) throws DIDKitException;
+    public static native String oid4vpVerifyResponse(String response, String request) throws DIDKitException;
+    public static native String presentationExchangeEvaluate(String definition, String credentials) throws DIDKitException;
+    public static native String presentationExchangeCreate(String definition, String credentials, String holder, String proofFormat) throws DIDKitException;
+    public static native String presentationExchangeVerify(String definition, String submission, String presentation) throws DIDKitException;
+
+    // Asynchronous variants. The returned future is completed from a native worker thread, with
+    // the result string or exceptionally with a DIDKitException.
+    public static native CompletableFuture<String> keyToVerificationMethodAsync(String methodPattern, String jwk) throws DIDKitException;
+    public static native CompletableFuture<String> issueCredentialAsync(String credential, String linkedDataProofOptions, String key) throws DIDKitException;
+    public static native CompletableFuture<String> verifyCredentialAsync(String verifiableCredential, String linkedDataProofOptions) throws DIDKitException;
+    public static native CompletableFuture<String> issuePresentationAsync(String presentation, String linkedDataProofOptions, String key) throws DIDKitException;
+    public static native CompletableFuture<String> DIDAuthAsync(String holder, String linkedDataProofOptions, String key) throws DIDKitException;
+    public static native CompletableFuture<String> verifyPresentationAsync(String verifiablePresentation, String linkedDataProofOptions) throws DIDKitException;
+    public static native CompletableFuture<String> resolveDIDAsync(String did, String inputMetadata) throws DIDKitException;
+    public static native CompletableFuture<String> resolveDIDWithConfigAsync(String did, String inputMetadata, String config) throws DIDKitException;
+    public static native CompletableFuture<String> dereferenceDIDURLAsync(String didUrl, String inputMetadata) throws DIDKitException;
+    public static native CompletableFuture<String> dereferenceDIDURLWithConfigAsync(String didUrl, String inputMetadata, String config) throws DIDKitException;
+
+    // Variants using a DIDKitContext instead of the default configuration
+    public static native String issueCredentialWithContext(DIDKitContext context, String credential, String linkedDataProofOptions, String key) throws DIDKitException;
+    public static native String verifyCredentialWithContext(DIDKitContext context, String verifiableCredential, String linkedDataProofOptions) throws DIDKitException;
+    public static native String issuePresentationWithContext(DIDKitContext context, String presentation, String linkedDataProofOptions, String key) throws DIDKitException;
+    public static native String DIDAuthWithContext(DIDKitContext context, String holder, String linkedDataProofOptions, String key) throws DIDKitException;
+    public static native String verifyPresentationWithContext(DIDKitContext context, String verifiablePresentation, String linkedDataProofOptions) throws DIDKitException;
+    public static native String resolveDIDWithContext(DIDKitContext context, String did, String inputMetadata) throws DIDKitException;
+    public static native String dereferenceDIDURLWithContext(DIDKitContext context, String didUrl, String inputMetadata) throws DIDKitException;
 
     static {
         System.loadLibrary("didkit");
@@ -0,0 +1,38 @@
+package com.spruceid;
+
+/**
+ * Configuration for the DIDKit methods with a WithContext suffix: the DID resolver, JSON-LD
+ * contexts and caches that they use, and the native runtime on which they run.
+ *
+ * Options are given as a JSON object, with optional properties "config" (DID methods
+ * configuration as didkit.toml), "didResolver" (URL of a fallback HTTP DID resolver),
+ * "contexts" (JSON-LD context documents by URL) and "cacheResolutions" (boolean).
+ *
+ * A context may be used from several threads at once. Closing it while a call is using it does
+ * not interrupt the call; the native context is freed once the call returns. Calls made after
+ * closing throw a DIDKitException.
+ */
+public class DIDKitContext implements AutoCloseable {
+    private long handle;
+
+    public DIDKitContext(String options) throws DIDKitException {
+        handle = create(options);
+    }
+
+    public DIDKitContext() throws DIDKitException {
+        this(null);
+    }
+
+    @Override
+    public synchronized void close() {
+        free(handle);
+        handle = 0;
+    }
+
+    private static native long create(String options) throws DIDKitException;
+    private static native void free(long handle);
+
+    static {
+        System.loadLibrary("didkit");
+    }
+}
@@ -1,6 +1,7 @@
 package com.spruceid;
 
 import com.spruceid.DIDKit;
+import java.util.concurrent.CompletionException;
 
 class DIDKitTest {
     public static void main(String[] args) throws DIDKitException {
@@ -141,5 +142,42 @@ class DIDKitTest {
             + "}";
         vpResult = DIDKit.verifyPresentation(vpJwt, vpVerifyOptions);
         assert vpResult.contains("\"errors\":[]");
+
+        // Resolve DID asynchronously
+        resolutionResult = DIDKit.resolveDIDAsync(did, "{}").join();
+        assert resolutionResult.contains("\"didDocument\":{");
+
+        // Verify Credential asynchronously
+        vcVerifyOptions = "{"
+            + "  \"proofPurpose\": \"assertionMethod\""
+            + "}";
+        vcResult = DIDKit.verifyCredentialAsync(vc, vcVerifyOptions).join();
+        assert vcResult.contains("\"errors\":[]");
+
+        // Complete exceptionally
+        threw = false;
+        try {
+            DIDKit.issueCredentialAsync(credential, vcOptions, "{}").join();
+        } catch (CompletionException e) {
+            threw = e.getCause() instanceof DIDKitException;
+        }
+        assert threw;
+
+        // Resolve DID and verify Credential with a context
+        try (DIDKitContext context = new DIDKitContext("{\"cacheResolutions\": true}")) {
+            resolutionResult = DIDKit.resolveDIDWithContext(context, did, "{}");
+            assert resolutionResult.contains("\"didDocument\":{");
+            vcResult = DIDKit.verifyCredentialWithContext(context, vc, vcVerifyOptions);
+            assert vcResult.contains("\"errors\":[]");
+        }
+
+        // Invalid context options
+        threw = false;
+        try {
+            new DIDKitContext("{\"foo\": 1}");
+        } catch (DIDKitException e) {
+            threw = true;
+        }
+        assert threw;
     }
 }
@@ -0,0 +1,1993 @@
+//! Node.js bindings for DIDKit, using [N-API](https://nodejs.org/api/n-api.html).
+//!
+//! Functions that resolve DIDs or create or verify proofs return Promises, run on the Tokio
+//! runtime of the addon rather than on the Node.js main thread.
+//!
+//! JS callbacks given to the `WithResolver` and `WithSigner` functions are called on the main
+//! thread through thread-safe functions, and return Promises that the runtime awaits.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use iref::IriBuf;
+use json_ld::JsonLdProcessor;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction};
+use napi_derive::napi;
+use serde_json::{json, Map, Value};
+
+use didkit::context::{Context, ContextOptions};
+use didkit::error::Error;
+use didkit::key_derive::{self, Curve};
+use didkit::presentation_exchange::{self, PresentationDefinition, PresentationSubmission};
+use didkit::ssi::did_resolve::TYPE_DID_LD_JSON;
+use didkit::ssi::jsonld::{parse_ld_context, StaticLoader};
+use didkit::ssi::ldp::SigningInput;
+use didkit::ssi::rdf;
+use didkit::ssi::vc::Proof;
+use didkit::ssi::{self, ldp::ProofSuite};
+use didkit::zcap::{self, CapabilityStore, Revocations};
+use didkit::{dereference, DereferencingInputMetadata};
+use didkit::{generate_proof, get_verification_method};
+use didkit::{ContextLoader, DIDResolver, Document};
+use didkit::{DIDCreate, DIDDeactivate, DIDDocumentOperation, DIDRecover, DIDUpdate};
+use didkit::{Delegation, Invocation};
+use didkit::{DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata};
+use didkit::{JWTOrLDPOptions, ProofFormat, URI};
+use didkit::{LinkedDataProofOptions, ProofPreparation, Source};
+use didkit::{VerifiableCredential, VerifiablePresentation};
+use didkit::{DID_METHODS, JWK};
+
+pub static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn map_error<E: std::fmt::Display>(err: E) -> napi::Error {
+    napi::Error::from_reason(err.to_string())
+}
+
+#[napi(js_name = "getVersion")]
+pub fn get_version() -> String {
+    VERSION.into()
+}
+
+// Keys
+
+#[napi(js_name = "generateEd25519Key")]
+pub fn generate_ed25519_key() -> Result<String> {
+    let jwk = JWK::generate_ed25519().map_err(map_error)?;
+    serde_json::to_string(&jwk).map_err(map_error)
+}
+
+#[napi(js_name = "generateSecp256k1Key")]
+pub fn generate_secp256k1_key() -> Result<String> {
+    let jwk = JWK::generate_secp256k1().map_err(map_error)?;
+    serde_json::to_string(&jwk).map_err(map_error)
+}
+
+#[napi(js_name = "generateP256Key")]
+pub fn generate_p256_key() -> Result<String> {
+    let jwk = JWK::generate_p256().map_err(map_error)?;
+    serde_json::to_string(&jwk).map_err(map_error)
+}
+
+fn key_to_did_(method_pattern: String, jwk: String) -> std::result::Result<String, Error> {
+    let key: JWK = serde_json::from_str(&jwk)?;
+    let did = DID_METHODS
+        .generate(&Source::KeyAndPattern(&key, &method_pattern))
+        .ok_or(Error::UnableToGenerateDID)?;
+    Ok(did)
+}
+
+#[napi(js_name = "keyToDID")]
+pub fn key_to_did(method_pattern: String, jwk: String) -> Result<String> {
+    key_to_did_(method_pattern, jwk).map_err(map_error)
+}
+
+async fn key_to_verification_method_(
+    method_pattern: String,
+    jwk: String,
+) -> std::result::Result<String, Error> {
+    let key: JWK = serde_json::from_str(&jwk)?;
+    let did = DID_METHODS
+        .generate(&Source::KeyAndPattern(&key, &method_pattern))
+        .ok_or(Error::UnableToGenerateDID)?;
+    let did_resolver = DID_METHODS.to_resolver();
+    let vm = get_verification_method(&did, did_resolver)
+        .await
+        .ok_or(Error::UnableToGetVerificationMethod)?;
+    Ok(vm)
+}
+
+#[napi(js_name = "keyToVerificationMethod")]
+pub async fn key_to_verification_method(method_pattern: String, jwk: String) -> Result<String> {
+    key_to_verification_method_(method_pattern, jwk)
+        .await
+        .map_err(map_error)
+}
+
+fn key_derive_from_mnemonic_(
+    mnemonic: String,
+    passphrase: Option<String>,
+    curve: String,
+    path: Option<String>,
+) -> std::result::Result<String, Error> {
+    let curve: Curve = curve.parse()?;
+    let path = path.unwrap_or_else(|| curve.default_path().to_string());
+    let jwk = key_derive::derive_from_mnemonic(
+        &mnemonic,
+        passphrase.as_deref().unwrap_or_default(),
+        curve,
+        &path,
+    )?;
+    Ok(serde_json::to_string(&jwk)?)
+}
+
+#[napi(js_name = "keyDeriveFromMnemonic")]
+pub fn key_derive_from_mnemonic(
+    mnemonic: String,
+    passphrase: Option<String>,
+    curve: String,
+    path: Option<String>,
+) -> Result<String> {
+    key_derive_from_mnemonic_(mnemonic, passphrase, curve, path).map_err(map_error)
+}
+
+fn key_derive_from_seed_(
+    seed: String,
+    curve: String,
+    path: Option<String>,
+) -> std::result::Result<String, Error> {
+    let curve: Curve = curve.parse()?;
+    let path = path.unwrap_or_else(|| curve.default_path().to_string());
+    let jwk = key_derive::derive_from_seed_hex(&seed, curve, &path)?;
+    Ok(serde_json::to_string(&jwk)?)
+}
+
+#[napi(js_name = "keyDeriveFromSeed")]
+pub fn key_derive_from_seed(seed: String, curve: String, path: Option<String>) -> Result<String> {
+    key_derive_from_seed_(seed, curve, path).map_err(map_error)
+}
+
+#[napi(js_name = "JWKFromTezos")]
+pub fn jwk_from_tezos(tz: String) -> Result<String> {
+    let jwk = ssi::tzkey::jwk_from_tezos_key(&tz).map_err(map_error)?;
+    serde_json::to_string(&jwk).map_err(map_error)
+}
+
+// DID resolution and DID methods
+
+async fn resolve_did_(
+    context: Arc<Context>,
+    did: String,
+    input_metadata: String,
+) -> std::result::Result<String, String> {
+    resolve_did_with_(context.resolver(), did, input_metadata).await
+}
+
+async fn resolve_did_with_(
+    resolver: &dyn DIDResolver,
+    did: String,
+    input_metadata: String,
+) -> std::result::Result<String, String> {
+    let (res_meta, doc, _) = resolver
+        .resolve(
+            &did,
+            &serde_json::from_str(&input_metadata).map_err(|e| e.to_string())?,
+        )
+        .await;
+    if let Some(error) = res_meta.error {
+        return Err(error);
+    }
+    match doc {
+        Some(doc) => serde_json::to_string(&doc).map_err(|e| e.to_string()),
+        None => Err("No document resolved.".to_string()),
+    }
+}
+
+#[napi(js_name = "resolveDID")]
+pub async fn resolve_did(did: String, input_metadata: String) -> Result<String> {
+    resolve_did_(Context::shared_default(), did, input_metadata)
+        .await
+        .map_err(map_error)
+}
+
+/// Resolve a DID using DID methods configured by a `didkit.toml` configuration, given as a string
+#[napi(js_name = "resolveDIDWithConfig")]
+pub async fn resolve_did_with_config(
+    did: String,
+    input_metadata: String,
+    config: String,
+) -> Result<String> {
+    let options = ContextOptions {
+        config: Some(config),
+        ..Default::default()
+    };
+    let context = Context::new(options).map_err(map_error)?;
+    resolve_did_(Arc::new(context), did, input_metadata)
+        .await
+        .map_err(map_error)
+}
+
+async fn dereference_did_url_(
+    context: Arc<Context>,
+    did_url: String,
+    input_metadata: String,
+) -> std::result::Result<String, Error> {
+    let input_metadata: DereferencingInputMetadata = serde_json::from_str(&input_metadata)?;
+    let deref_result = dereference(context.resolver(), &did_url, &input_metadata).await;
+    Ok(serde_json::to_string(&json!(deref_result))?)
+}
+
+/// Dereference a DID URL, resolving to a JSON array of the dereferencing metadata, the content
+/// and the content metadata
+#[napi(js_name = "dereferenceDIDURL")]
+pub async fn dereference_did_url(did_url: String, input_metadata: String) -> Result<String> {
+    dereference_did_url_(Context::shared_default(), did_url, input_metadata)
+        .await
+        .map_err(map_error)
+}
+
+fn jwk_opt(key: Option<String>) -> std::result::Result<Option<JWK>, Error> {
+    match key {
+        Some(key) => Ok(Some(serde_json::from_str(&key)?)),
+        None => Ok(None),
+    }
+}
+
+fn did_method_options(options: Option<String>) -> std::result::Result<Map<String, Value>, Error> {
+    match options {
+        Some(options) => Ok(serde_json::from_str(&options)?),
+        None => Ok(Map::new()),
+    }
+}
+
+fn create_did_(
+    method_name: String,
+    update_key: Option<String>,
+    recovery_key: Option<String>,
+    verification_key: Option<String>,
+    options: Option<String>,
+) -> std::result::Result<String, Error> {
+    let method = DID_METHODS
+        .get(&method_name)
+        .ok_or(Error::UnknownDIDMethod)?;
+    let tx = method.create(DIDCreate {
+        update_key: jwk_opt(update_key)?,
+        recovery_key: jwk_opt(recovery_key)?,
+        verification_key: jwk_opt(verification_key)?,
+        options: did_method_options(options)?,
+    })?;
+    Ok(serde_json::to_string(&tx)?)
+}
+
+/// Build a DID Create transaction for a DID method (e.g. `ion`)
+#[napi(js_name = "createDID")]
+pub fn create_did(
+    method: String,
+    update_key: Option<String>,
+    recovery_key: Option<String>,
+    verification_key: Option<String>,
+    options: Option<String>,
+) -> Result<String> {
+    create_did_(method, update_key, recovery_key, verification_key, options).map_err(map_error)
+}
+
+fn update_did_(
+    did: String,
+    update_key: Option<String>,
+    new_update_key: Option<String>,
+    operation: String,
+    options: Option<String>,
+) -> std::result::Result<String, Error> {
+    let operation: DIDDocumentOperation = serde_json::from_str(&operation)?;
+    let method = DID_METHODS
+        .get_method(&did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.update(DIDUpdate {
+        did,
+        update_key: jwk_opt(update_key)?,
+        new_update_key: jwk_opt(new_update_key)?,
+        operation,
+        options: did_method_options(options)?,
+    })?;
+    Ok(serde_json::to_string(&tx)?)
+}
+
+/// Build a DID Update transaction
+#[napi(js_name = "updateDID")]
+pub fn update_did(
+    did: String,
+    update_key: Option<String>,
+    new_update_key: Option<String>,
+    operation: String,
+    options: Option<String>,
+) -> Result<String> {
+    update_did_(did, update_key, new_update_key, operation, options).map_err(map_error)
+}
+
+fn recover_did_(
+    did: String,
+    recovery_key: Option<String>,
+    new_update_key: Option<String>,
+    new_recovery_key: Option<String>,
+    new_verification_key: Option<String>,
+    options: Option<String>,
+) -> std::result::Result<String, Error> {
+    let method = DID_METHODS
+        .get_method(&did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.recover(DIDRecover {
+        did,
+        recovery_key: jwk_opt(recovery_key)?,
+        new_update_key: jwk_opt(new_update_key)?,
+        new_recovery_key: jwk_opt(new_recovery_key)?,
+        new_verification_key: jwk_opt(new_verification_key)?,
+        options: did_method_options(options)?,
+    })?;
+    Ok(serde_json::to_string(&tx)?)
+}
+
+/// Build a DID Recover transaction
+#[napi(js_name = "recoverDID")]
+pub fn recover_did(
+    did: String,
+    recovery_key: Option<String>,
+    new_update_key: Option<String>,
+    new_recovery_key: Option<String>,
+    new_verification_key: Option<String>,
+    options: Option<String>,
+) -> Result<String> {
+    recover_did_(
+        did,
+        recovery_key,
+        new_update_key,
+        new_recovery_key,
+        new_verification_key,
+        options,
+    )
+    .map_err(map_error)
+}
+
+fn deactivate_did_(
+    did: String,
+    key: Option<String>,
+    options: Option<String>,
+) -> std::result::Result<String, Error> {
+    let method = DID_METHODS
+        .get_method(&did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.deactivate(DIDDeactivate {
+        did,
+        key: jwk_opt(key)?,
+        options: did_method_options(options)?,
+    })?;
+    Ok(serde_json::to_string(&tx)?)
+}
+
+/// Build a DID Deactivate transaction
+#[napi(js_name = "deactivateDID")]
+pub fn deactivate_did(did: String, key: Option<String>, options: Option<String>) -> Result<String> {
+    deactivate_did_(did, key, options).map_err(map_error)
+}
+
+// Credentials and presentations
+
+async fn issue_credential_(
+    context: Arc<Context>,
+    credential: String,
+    proof_options: String,
+    key: String,
+) -> std::result::Result<String, Error> {
+    let mut credential = VerifiableCredential::from_json_unsigned(&credential)?;
+    let key: JWK = serde_json::from_str(&key)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
+    let vc_string = match proof_format {
+        ProofFormat::JWT => {
+            credential
+                .generate_jwt(Some(&key), &options.ldp_options, resolver)
+                .await?
+        }
+        ProofFormat::LDP => {
+            let proof = credential
+                .generate_proof(&key, &options.ldp_options, resolver, &mut context_loader)
+                .await?;
+            credential.add_proof(proof);
+            serde_json::to_string(&credential)?
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(vc_string)
+}
+
+#[napi(js_name = "issueCredential")]
+pub async fn issue_credential(
+    credential: String,
+    proof_options: String,
+    key: String,
+) -> Result<String> {
+    issue_credential_(Context::shared_default(), credential, proof_options, key)
+        .await
+        .map_err(map_error)
+}
+
+async fn prepare_issue_credential_(
+    credential: String,
+    linked_data_proof_options: String,
+    public_key: String,
+) -> std::result::Result<String, Error> {
+    let public_key: JWK = serde_json::from_str(&public_key)?;
+    let credential = VerifiableCredential::from_json_unsigned(&credential)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&linked_data_proof_options)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let preparation = credential
+        .prepare_proof(&public_key, &options, resolver, &mut context_loader)
+        .await?;
+    Ok(serde_json::to_string(&preparation)?)
+}
+
+#[napi(js_name = "prepareIssueCredential")]
+pub async fn prepare_issue_credential(
+    credential: String,
+    linked_data_proof_options: String,
+    public_key: String,
+) -> Result<String> {
+    prepare_issue_credential_(credential, linked_data_proof_options, public_key)
+        .await
+        .map_err(map_error)
+}
+
+async fn complete_issue_credential_(
+    credential: String,
+    preparation: String,
+    signature: String,
+) -> std::result::Result<String, Error> {
+    let mut credential = VerifiableCredential::from_json_unsigned(&credential)?;
+    let preparation: ProofPreparation = serde_json::from_str(&preparation)?;
+    let proof = preparation
+        .proof
+        .type_
+        .complete(&preparation, &signature)
+        .await?;
+    credential.add_proof(proof);
+    Ok(serde_json::to_string(&credential)?)
+}
+
+#[napi(js_name = "completeIssueCredential")]
+pub async fn complete_issue_credential(
+    credential: String,
+    preparation: String,
+    signature: String,
+) -> Result<String> {
+    complete_issue_credential_(credential, preparation, signature)
+        .await
+        .map_err(map_error)
+}
+
+async fn verify_credential_(
+    context: Arc<Context>,
+    vc_string: String,
+    proof_options: String,
+) -> std::result::Result<String, Error> {
+    verify_credential_with_(
+        context.resolver(),
+        context.context_loader(),
+        vc_string,
+        proof_options,
+    )
+    .await
+}
+
+async fn verify_credential_with_(
+    resolver: &dyn DIDResolver,
+    mut context_loader: ContextLoader,
+    vc_string: String,
+    proof_options: String,
+) -> std::result::Result<String, Error> {
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let result = match proof_format {
+        ProofFormat::JWT => {
+            VerifiableCredential::verify_jwt(
+                &vc_string,
+                Some(options.ldp_options),
+                resolver,
+                &mut context_loader,
+            )
+            .await
+        }
+        ProofFormat::LDP => {
+            let vc = VerifiableCredential::from_json_unsigned(&vc_string)?;
+            vc.verify(Some(options.ldp_options), resolver, &mut context_loader)
+                .await
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(serde_json::to_string(&result)?)
+}
+
+#[napi(js_name = "verifyCredential")]
+pub async fn verify_credential(vc: String, proof_options: String) -> Result<String> {
+    verify_credential_(Context::shared_default(), vc, proof_options)
+        .await
+        .map_err(map_error)
+}
+
+async fn issue_presentation_(
+    context: Arc<Context>,
+    presentation: String,
+    proof_options: String,
+    key: String,
+) -> std::result::Result<String, Error> {
+    let mut presentation = VerifiablePresentation::from_json_unsigned(&presentation)?;
+    let key: JWK = serde_json::from_str(&key)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
+    let vp_string = match proof_format {
+        ProofFormat::JWT => {
+            presentation
+                .generate_jwt(Some(&key), &options.ldp_options, resolver)
+                .await?
+        }
+        ProofFormat::LDP => {
+            let proof = presentation
+                .generate_proof(&key, &options.ldp_options, resolver, &mut context_loader)
+                .await?;
+            presentation.add_proof(proof);
+            serde_json::to_string(&presentation)?
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(vp_string)
+}
+
+#[napi(js_name = "issuePresentation")]
+pub async fn issue_presentation(
+    presentation: String,
+    proof_options: String,
+    key: String,
+) -> Result<String> {
+    issue_presentation_(Context::shared_default(), presentation, proof_options, key)
+        .await
+        .map_err(map_error)
+}
+
+async fn prepare_issue_presentation_(
+    presentation: String,
+    linked_data_proof_options: String,
+    public_key: String,
+) -> std::result::Result<String, Error> {
+    let public_key: JWK = serde_json::from_str(&public_key)?;
+    let presentation = VerifiablePresentation::from_json_unsigned(&presentation)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&linked_data_proof_options)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let preparation = presentation
+        .prepare_proof(&public_key, &options, resolver, &mut context_loader)
+        .await?;
+    Ok(serde_json::to_string(&preparation)?)
+}
+
+#[napi(js_name = "prepareIssuePresentation")]
+pub async fn prepare_issue_presentation(
+    presentation: String,
+    linked_data_proof_options: String,
+    public_key: String,
+) -> Result<String> {
+    prepare_issue_presentation_(presentation, linked_data_proof_options, public_key)
+        .await
+        .map_err(map_error)
+}
+
+async fn complete_issue_presentation_(
+    presentation: String,
+    preparation: String,
+    signature: String,
+) -> std::result::Result<String, Error> {
+    let mut presentation = VerifiablePresentation::from_json_unsigned(&presentation)?;
+    let preparation: ProofPreparation = serde_json::from_str(&preparation)?;
+    let proof = preparation
+        .proof
+        .type_
+        .complete(&preparation, &signature)
+        .await?;
+    presentation.add_proof(proof);
+    Ok(serde_json::to_string(&presentation)?)
+}
+
+#[napi(js_name = "completeIssuePresentation")]
+pub async fn complete_issue_presentation(
+    presentation: String,
+    preparation: String,
+    signature: String,
+) -> Result<String> {
+    complete_issue_presentation_(presentation, preparation, signature)
+        .await
+        .map_err(map_error)
+}
+
+async fn verify_presentation_(
+    context: Arc<Context>,
+    vp_string: String,
+    proof_options: String,
+) -> std::result::Result<String, Error> {
+    verify_presentation_with_(
+        context.resolver(),
+        context.context_loader(),
+        vp_string,
+        proof_options,
+    )
+    .await
+}
+
+async fn verify_presentation_with_(
+    resolver: &dyn DIDResolver,
+    mut context_loader: ContextLoader,
+    vp_string: String,
+    proof_options: String,
+) -> std::result::Result<String, Error> {
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let result = match proof_format {
+        ProofFormat::JWT => {
+            VerifiablePresentation::verify_jwt(
+                &vp_string,
+                Some(options.ldp_options),
+                resolver,
+                &mut context_loader,
+            )
+            .await
+        }
+        ProofFormat::LDP => {
+            let vp = VerifiablePresentation::from_json_unsigned(&vp_string)?;
+            vp.verify(Some(options.ldp_options), resolver, &mut context_loader)
+                .await
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(serde_json::to_string(&result)?)
+}
+
+#[napi(js_name = "verifyPresentation")]
+pub async fn verify_presentation(vp: String, proof_options: String) -> Result<String> {
+    verify_presentation_(Context::shared_default(), vp, proof_options)
+        .await
+        .map_err(map_error)
+}
+
+async fn did_auth_(
+    context: Arc<Context>,
+    holder: String,
+    proof_options: String,
+    key: String,
+) -> std::result::Result<String, Error> {
+    let mut presentation = VerifiablePresentation::default();
+    presentation.holder = Some(URI::String(holder));
+    let key: JWK = serde_json::from_str(&key)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
+    let vp_string = match proof_format {
+        ProofFormat::JWT => {
+            presentation
+                .generate_jwt(Some(&key), &options.ldp_options, resolver)
+                .await?
+        }
+        ProofFormat::LDP => {
+            let proof = presentation
+                .generate_proof(&key, &options.ldp_options, resolver, &mut context_loader)
+                .await?;
+            presentation.add_proof(proof);
+            serde_json::to_string(&presentation)?
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(vp_string)
+}
+
+#[napi(js_name = "DIDAuth")]
+pub async fn did_auth(
+    holder: String,
+    linked_data_proof_options: String,
+    key: String,
+) -> Result<String> {
+    did_auth_(
+        Context::shared_default(),
+        holder,
+        linked_data_proof_options,
+        key,
+    )
+    .await
+    .map_err(map_error)
+}
+
+// Signing with ssh-agent
+
+fn ssh_agent_sock(socket_path: Option<String>) -> Result<String> {
+    match socket_path {
+        Some(socket_path) => Ok(socket_path),
+        None => std::env::var("SSH_AUTH_SOCK").map_err(|_| {
+            napi::Error::from_reason(
+                "Missing SSH_AUTH_SOCK environment variable for ssh-agent. Start ssh-agent or \
+                 pass the path of its socket."
+                    .to_string(),
+            )
+        }),
+    }
+}
+
+async fn issue_credential_with_ssh_agent_(
+    credential: String,
+    linked_data_proof_options: String,
+    public_key: Option<String>,
+    socket_path: String,
+) -> Result<String> {
+    let mut credential =
+        VerifiableCredential::from_json_unsigned(&credential).map_err(map_error)?;
+    let public_key = jwk_opt(public_key).map_err(map_error)?;
+    let options: LinkedDataProofOptions =
+        serde_json::from_str(&linked_data_proof_options).map_err(map_error)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let proof = generate_proof(
+        &credential,
+        public_key.as_ref(),
+        options,
+        resolver,
+        &mut context_loader,
+        Some(&socket_path),
+    )
+    .await
+    .map_err(map_error)?;
+    credential.add_proof(proof);
+    serde_json::to_string(&credential).map_err(map_error)
+}
+
+/// Issue a credential with a linked data proof, signing with a key held by ssh-agent. The public
+/// key (JWK) selects the key, if the agent has several. The agent's socket path defaults to
+/// `SSH_AUTH_SOCK`.
+#[napi(js_name = "issueCredentialWithSSHAgent")]
+pub async fn issue_credential_with_ssh_agent(
+    credential: String,
+    linked_data_proof_options: String,
+    public_key: Option<String>,
+    socket_path: Option<String>,
+) -> Result<String> {
+    let socket_path = ssh_agent_sock(socket_path)?;
+    issue_credential_with_ssh_agent_(
+        credential,
+        linked_data_proof_options,
+        public_key,
+        socket_path,
+    )
+    .await
+}
+
+async fn issue_presentation_with_ssh_agent_(
+    presentation: String,
+    linked_data_proof_options: String,
+    public_key: Option<String>,
+    socket_path: String,
+) -> Result<String> {
+    let mut presentation =
+        VerifiablePresentation::from_json_unsigned(&presentation).map_err(map_error)?;
+    let public_key = jwk_opt(public_key).map_err(map_error)?;
+    let options: LinkedDataProofOptions =
+        serde_json::from_str(&linked_data_proof_options).map_err(map_error)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let proof = generate_proof(
+        &presentation,
+        public_key.as_ref(),
+        options,
+        resolver,
+        &mut context_loader,
+        Some(&socket_path),
+    )
+    .await
+    .map_err(map_error)?;
+    presentation.add_proof(proof);
+    serde_json::to_string(&presentation).map_err(map_error)
+}
+
+/// Issue a presentation with a linked data proof, signing with a key held by ssh-agent, as for
+/// `issueCredentialWithSSHAgent`
+#[napi(js_name = "issuePresentationWithSSHAgent")]
+pub async fn issue_presentation_with_ssh_agent(
+    presentation: String,
+    linked_data_proof_options: String,
+    public_key: Option<String>,
+    socket_path: Option<String>,
+) -> Result<String> {
+    let socket_path = ssh_agent_sock(socket_path)?;
+    issue_presentation_with_ssh_agent_(
+        presentation,
+        linked_data_proof_options,
+        public_key,
+        socket_path,
+    )
+    .await
+}
+
+/// Create a DIDAuth presentation for a holder, signing with a key held by ssh-agent, as for
+/// `issueCredentialWithSSHAgent`
+#[napi(js_name = "DIDAuthWithSSHAgent")]
+pub async fn did_auth_with_ssh_agent(
+    holder: String,
+    linked_data_proof_options: String,
+    public_key: Option<String>,
+    socket_path: Option<String>,
+) -> Result<String> {
+    let socket_path = ssh_agent_sock(socket_path)?;
+    let mut presentation = VerifiablePresentation::default();
+    presentation.holder = Some(URI::String(holder));
+    let presentation = serde_json::to_string(&presentation).map_err(map_error)?;
+    issue_presentation_with_ssh_agent_(
+        presentation,
+        linked_data_proof_options,
+        public_key,
+        socket_path,
+    )
+    .await
+}
+
+// Resolver and signer callbacks
+
+/// JS function `resolve(did, inputMetadata)`, called on the Node.js main thread
+type ResolveFunction = ThreadsafeFunction<(String, Value), ErrorStrategy::Fatal>;
+
+/// JS function `sign(bytes, alg)`, called on the Node.js main thread
+type SignFunction = ThreadsafeFunction<(Uint8Array, String), ErrorStrategy::Fatal>;
+
+/// DID resolver calling a JS function `resolve(did, inputMetadata)`, with the DID resolution input
+/// metadata (e.g. `versionId` or `versionTime`) as an object, returning a Promise of a DID
+/// document, as an object or JSON string, or of `null`/`undefined` to fall back to the built-in DID
+/// methods
+struct JsResolver {
+    resolve: Option<ResolveFunction>,
+    fallback: Arc<Context>,
+}
+
+impl JsResolver {
+    fn new(resolve: Option<ResolveFunction>) -> Self {
+        Self {
+            resolve,
+            fallback: Context::shared_default(),
+        }
+    }
+
+    async fn resolve_js(
+        &self,
+        resolve: &ResolveFunction,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> Result<Option<Document>> {
+        let input_metadata = serde_json::to_value(input_metadata).map_err(map_error)?;
+        let value = resolve
+            .call_async::<Promise<Value>>((did.to_string(), input_metadata))
+            .await?
+            .await?;
+        let document = match value {
+            Value::Null => return Ok(None),
+            Value::String(document_json) => serde_json::from_str(&document_json),
+            value => serde_json::from_value(value),
+        }
+        .map_err(map_error)?;
+        Ok(Some(document))
+    }
+}
+
+#[async_trait]
+impl DIDResolver for JsResolver {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let resolve = match &self.resolve {
+            Some(resolve) => resolve,
+            None => return self.fallback.resolve(did, input_metadata).await,
+        };
+        match self.resolve_js(resolve, did, input_metadata).await {
+            Ok(Some(document)) => (
+                ResolutionMetadata {
+                    content_type: Some(TYPE_DID_LD_JSON.to_string()),
+                    ..Default::default()
+                },
+                Some(document),
+                Some(DocumentMetadata::default()),
+            ),
+            Ok(None) => self.fallback.resolve(did, input_metadata).await,
+            Err(err) => (ResolutionMetadata::from_error(&err.reason), None, None),
+        }
+    }
+}
+
+/// Signer calling a JS function `sign(bytes, alg)`, with the signing input as a `Uint8Array` and
+/// the JOSE algorithm name, returning a Promise of the signature as a `Uint8Array` (or `Buffer`),
+/// or as a string in the encoding expected by the proof type
+struct JsSigner {
+    sign: SignFunction,
+}
+
+impl JsSigner {
+    fn new(sign: SignFunction) -> Self {
+        Self { sign }
+    }
+
+    /// Sign a prepared proof, completing it
+    async fn sign_proof(&self, preparation: &ProofPreparation, public_key: &JWK) -> Result<Proof> {
+        let bytes = match &preparation.signing_input {
+            SigningInput::Bytes(bytes) => bytes.0.clone(),
+            _ => {
+                return Err(napi::Error::from_reason(
+                    "Unsupported signing input for external signing".to_string(),
+                ))
+            }
+        };
+        let missing_algorithm =
+            || napi::Error::from_reason("Unable to determine signature algorithm".to_string());
+        let algorithm = preparation
+            .jws_header
+            .as_ref()
+            .map(|header| header.algorithm)
+            .or_else(|| public_key.get_algorithm())
+            .ok_or_else(missing_algorithm)?;
+        let alg = match serde_json::to_value(algorithm).map_err(map_error)? {
+            Value::String(alg) => alg,
+            _ => return Err(missing_algorithm()),
+        };
+        let signature = match self
+            .sign
+            .call_async::<Promise<Either<String, Uint8Array>>>((Uint8Array::new(bytes), alg))
+            .await?
+            .await?
+        {
+            Either::A(signature) => signature,
+            Either::B(signature) => {
+                base64::encode_config(signature.to_vec(), base64::URL_SAFE_NO_PAD)
+            }
+        };
+        preparation
+            .proof
+            .type_
+            .complete(preparation, &signature)
+            .await
+            .map_err(map_error)
+    }
+}
+
+/// Resolve a DID using a JS `resolve(did, inputMetadata)` function, falling back to the built-in
+/// DID methods if its Promise resolves to `null` or `undefined`
+#[napi(js_name = "resolveDIDWithResolver")]
+pub async fn resolve_did_with_resolver(
+    did: String,
+    input_metadata: String,
+    #[napi(
+        ts_arg_type = "(did: string, inputMetadata: object) => Promise<object | string | null>"
+    )]
+    resolve: ResolveFunction,
+) -> Result<String> {
+    let resolver = JsResolver::new(Some(resolve));
+    resolve_did_with_(&resolver, did, input_metadata)
+        .await
+        .map_err(map_error)
+}
+
+/// Verify a credential, resolving DIDs using a JS `resolve(did, inputMetadata)` function
+#[napi(js_name = "verifyCredentialWithResolver")]
+pub async fn verify_credential_with_resolver(
+    vc: String,
+    proof_options: String,
+    #[napi(
+        ts_arg_type = "(did: string, inputMetadata: object) => Promise<object | string | null>"
+    )]
+    resolve: ResolveFunction,
+) -> Result<String> {
+    let resolver = JsResolver::new(Some(resolve));
+    verify_credential_with_(&resolver, ContextLoader::default(), vc, proof_options)
+        .await
+        .map_err(map_error)
+}
+
+/// Verify a presentation, resolving DIDs using a JS `resolve(did, inputMetadata)` function
+#[napi(js_name = "verifyPresentationWithResolver")]
+pub async fn verify_presentation_with_resolver(
+    vp: String,
+    proof_options: String,
+    #[napi(
+        ts_arg_type = "(did: string, inputMetadata: object) => Promise<object | string | null>"
+    )]
+    resolve: ResolveFunction,
+) -> Result<String> {
+    let resolver = JsResolver::new(Some(resolve));
+    verify_presentation_with_(&resolver, ContextLoader::default(), vp, proof_options)
+        .await
+        .map_err(map_error)
+}
+
+async fn issue_credential_with_signer_(
+    credential: String,
+    linked_data_proof_options: String,
+    public_key: String,
+    sign: SignFunction,
+    resolve: Option<ResolveFunction>,
+) -> Result<String> {
+    let public_key: JWK = serde_json::from_str(&public_key).map_err(map_error)?;
+    let mut credential =
+        VerifiableCredential::from_json_unsigned(&credential).map_err(map_error)?;
+    let options: LinkedDataProofOptions =
+        serde_json::from_str(&linked_data_proof_options).map_err(map_error)?;
+    let resolver = JsResolver::new(resolve);
+    let mut context_loader = ContextLoader::default();
+    let preparation = credential
+        .prepare_proof(&public_key, &options, &resolver, &mut context_loader)
+        .await
+        .map_err(map_error)?;
+    let proof = JsSigner::new(sign)
+        .sign_proof(&preparation, &public_key)
+        .await?;
+    credential.add_proof(proof);
+    serde_json::to_string(&credential).map_err(map_error)
+}
+
+/// Issue a credential with a linked data proof, signing with a JS `sign(bytes, alg)` function
+/// instead of a private key. The public key is given as a JWK. DIDs are resolved using an optional
+/// JS `resolve(did, inputMetadata)` function.
+#[napi(js_name = "issueCredentialWithSigner")]
+pub async fn issue_credential_with_signer(
+    credential: String,
+    linked_data_proof_options: String,
+    public_key: String,
+    #[napi(ts_arg_type = "(bytes: Uint8Array, alg: string) => Promise<Uint8Array | string>")]
+    sign: SignFunction,
+    #[napi(
+        ts_arg_type = "(did: string, inputMetadata: object) => Promise<object | string | null>"
+    )]
+    resolve: Option<ResolveFunction>,
+) -> Result<String> {
+    issue_credential_with_signer_(
+        credential,
+        linked_data_proof_options,
+        public_key,
+        sign,
+        resolve,
+    )
+    .await
+}
+
+async fn issue_presentation_with_signer_(
+    presentation: String,
+    linked_data_proof_options: String,
+    public_key: String,
+    sign: SignFunction,
+    resolve: Option<ResolveFunction>,
+) -> Result<String> {
+    let public_key: JWK = serde_json::from_str(&public_key).map_err(map_error)?;
+    let mut presentation =
+        VerifiablePresentation::from_json_unsigned(&presentation).map_err(map_error)?;
+    let options: LinkedDataProofOptions =
+        serde_json::from_str(&linked_data_proof_options).map_err(map_error)?;
+    let resolver = JsResolver::new(resolve);
+    let mut context_loader = ContextLoader::default();
+    let preparation = presentation
+        .prepare_proof(&public_key, &options, &resolver, &mut context_loader)
+        .await
+        .map_err(map_error)?;
+    let proof = JsSigner::new(sign)
+        .sign_proof(&preparation, &public_key)
+        .await?;
+    presentation.add_proof(proof);
+    serde_json::to_string(&presentation).map_err(map_error)
+}
+
+/// Issue a presentation with a linked data proof, signing with a JS `sign(bytes, alg)` function
+/// instead of a private key, as for `issueCredentialWithSigner`
+#[napi(js_name = "issuePresentationWithSigner")]
+pub async fn issue_presentation_with_signer(
+    presentation: String,
+    linked_data_proof_options: String,
+    public_key: String,
+    #[napi(ts_arg_type = "(bytes: Uint8Array, alg: string) => Promise<Uint8Array | string>")]
+    sign: SignFunction,
+    #[napi(
+        ts_arg_type = "(did: string, inputMetadata: object) => Promise<object | string | null>"
+    )]
+    resolve: Option<ResolveFunction>,
+) -> Result<String> {
+    issue_presentation_with_signer_(
+        presentation,
+        linked_data_proof_options,
+        public_key,
+        sign,
+        resolve,
+    )
+    .await
+}
+
+// Contexts
+
+/// Configuration for a series of calls, as its methods or the functions with a `WithContext`
+/// suffix: the DID resolver, JSON-LD contexts and caches that they use. Created from options given
+/// as a JSON object with optional properties `config` (DID methods configuration as `didkit.toml`),
+/// `didResolver` (URL of a fallback HTTP DID resolver), `contexts` (JSON-LD context documents by
+/// URL) and `cacheResolutions` (boolean).
+#[napi]
+pub struct DIDKitContext {
+    context: Arc<Context>,
+}
+
+#[napi]
+impl DIDKitContext {
+    #[napi(constructor)]
+    pub fn new(options: Option<String>) -> Result<Self> {
+        let context = match options {
+            Some(options) => Context::from_json(&options).map_err(map_error)?,
+            None => Context::default(),
+        };
+        Ok(Self {
+            context: Arc::new(context),
+        })
+    }
+
+    #[napi(js_name = "resolveDID", ts_return_type = "Promise<string>")]
+    pub fn resolve_did(&self, env: Env, did: String, input_metadata: String) -> Result<JsObject> {
+        let context = self.context.clone();
+        env.execute_tokio_future(
+            async move {
+                resolve_did_(context, did, input_metadata)
+                    .await
+                    .map_err(map_error)
+            },
+            |_, out| Ok(out),
+        )
+    }
+
+    #[napi(js_name = "dereferenceDIDURL", ts_return_type = "Promise<string>")]
+    pub fn dereference_did_url(
+        &self,
+        env: Env,
+        did_url: String,
+        input_metadata: String,
+    ) -> Result<JsObject> {
+        let context = self.context.clone();
+        env.execute_tokio_future(
+            async move {
+                dereference_did_url_(context, did_url, input_metadata)
+                    .await
+                    .map_err(map_error)
+            },
+            |_, out| Ok(out),
+        )
+    }
+
+    #[napi(js_name = "issueCredential", ts_return_type = "Promise<string>")]
+    pub fn issue_credential(
+        &self,
+        env: Env,
+        credential: String,
+        proof_options: String,
+        key: String,
+    ) -> Result<JsObject> {
+        let context = self.context.clone();
+        env.execute_tokio_future(
+            async move {
+                issue_credential_(context, credential, proof_options, key)
+                    .await
+                    .map_err(map_error)
+            },
+            |_, out| Ok(out),
+        )
+    }
+
+    #[napi(js_name = "verifyCredential", ts_return_type = "Promise<string>")]
+    pub fn verify_credential(
+        &self,
+        env: Env,
+        vc: String,
+        proof_options: String,
+    ) -> Result<JsObject> {
+        let context = self.context.clone();
+        env.execute_tokio_future(
+            async move {
+                verify_credential_(context, vc, proof_options)
+                    .await
+                    .map_err(map_error)
+            },
+            |_, out| Ok(out),
+        )
+    }
+
+    #[napi(js_name = "issuePresentation", ts_return_type = "Promise<string>")]
+    pub fn issue_presentation(
+        &self,
+        env: Env,
+        presentation: String,
+        proof_options: String,
+        key: String,
+    ) -> Result<JsObject> {
+        let context = self.context.clone();
+        env.execute_tokio_future(
+            async move {
+                issue_presentation_(context, presentation, proof_options, key)
+                    .await
+                    .map_err(map_error)
+            },
+            |_, out| Ok(out),
+        )
+    }
+
+    #[napi(js_name = "verifyPresentation", ts_return_type = "Promise<string>")]
+    pub fn verify_presentation(
+        &self,
+        env: Env,
+        vp: String,
+        proof_options: String,
+    ) -> Result<JsObject> {
+        let context = self.context.clone();
+        env.execute_tokio_future(
+            async move {
+                verify_presentation_(context, vp, proof_options)
+                    .await
+                    .map_err(map_error)
+            },
+            |_, out| Ok(out),
+        )
+    }
+
+    #[napi(js_name = "DIDAuth", ts_return_type = "Promise<string>")]
+    pub fn did_auth(
+        &self,
+        env: Env,
+        holder: String,
+        linked_data_proof_options: String,
+        key: String,
+    ) -> Result<JsObject> {
+        let context = self.context.clone();
+        env.execute_tokio_future(
+            async move {
+                did_auth_(context, holder, linked_data_proof_options, key)
+                    .await
+                    .map_err(map_error)
+            },
+            |_, out| Ok(out),
+        )
+    }
+}
+
+/// Resolve a DID using a `DIDKitContext`
+#[napi(js_name = "resolveDIDWithContext", ts_return_type = "Promise<string>")]
+pub fn resolve_did_with_context(
+    env: Env,
+    context: &DIDKitContext,
+    did: String,
+    input_metadata: String,
+) -> Result<JsObject> {
+    let context = context.context.clone();
+    env.execute_tokio_future(
+        async move {
+            resolve_did_(context, did, input_metadata)
+                .await
+                .map_err(map_error)
+        },
+        |_, out| Ok(out),
+    )
+}
+
+/// Dereference a DID URL using a `DIDKitContext`
+#[napi(
+    js_name = "dereferenceDIDURLWithContext",
+    ts_return_type = "Promise<string>"
+)]
+pub fn dereference_did_url_with_context(
+    env: Env,
+    context: &DIDKitContext,
+    did_url: String,
+    input_metadata: String,
+) -> Result<JsObject> {
+    let context = context.context.clone();
+    env.execute_tokio_future(
+        async move {
+            dereference_did_url_(context, did_url, input_metadata)
+                .await
+                .map_err(map_error)
+        },
+        |_, out| Ok(out),
+    )
+}
+
+/// Issue a credential using a `DIDKitContext`
+#[napi(
+    js_name = "issueCredentialWithContext",
+    ts_return_type = "Promise<string>"
+)]
+pub fn issue_credential_with_context(
+    env: Env,
+    context: &DIDKitContext,
+    credential: String,
+    proof_options: String,
+    key: String,
+) -> Result<JsObject> {
+    let context = context.context.clone();
+    env.execute_tokio_future(
+        async move {
+            issue_credential_(context, credential, proof_options, key)
+                .await
+                .map_err(map_error)
+        },
+        |_, out| Ok(out),
+    )
+}
+
+/// Verify a credential using a `DIDKitContext`
+#[napi(
+    js_name = "verifyCredentialWithContext",
+    ts_return_type = "Promise<string>"
+)]
+pub fn verify_credential_with_context(
+    env: Env,
+    context: &DIDKitContext,
+    vc: String,
+    proof_options: String,
+) -> Result<JsObject> {
+    let context = context.context.clone();
+    env.execute_tokio_future(
+        async move {
+            verify_credential_(context, vc, proof_options)
+                .await
+                .map_err(map_error)
+        },
+        |_, out| Ok(out),
+    )
+}
+
+/// Issue a presentation using a `DIDKitContext`
+#[napi(
+    js_name = "issuePresentationWithContext",
+    ts_return_type = "Promise<string>"
+)]
+pub fn issue_presentation_with_context(
+    env: Env,
+    context: &DIDKitContext,
+    presentation: String,
+    proof_options: String,
+    key: String,
+) -> Result<JsObject> {
+    let context = context.context.clone();
+    env.execute_tokio_future(
+        async move {
+            issue_presentation_(context, presentation, proof_options, key)
+                .await
+                .map_err(map_error)
+        },
+        |_, out| Ok(out),
+    )
+}
+
+/// Verify a presentation using a `DIDKitContext`
+#[napi(
+    js_name = "verifyPresentationWithContext",
+    ts_return_type = "Promise<string>"
+)]
+pub fn verify_presentation_with_context(
+    env: Env,
+    context: &DIDKitContext,
+    vp: String,
+    proof_options: String,
+) -> Result<JsObject> {
+    let context = context.context.clone();
+    env.execute_tokio_future(
+        async move {
+            verify_presentation_(context, vp, proof_options)
+                .await
+                .map_err(map_error)
+        },
+        |_, out| Ok(out),
+    )
+}
+
+/// Create a DIDAuth presentation using a `DIDKitContext`
+#[napi(js_name = "DIDAuthWithContext", ts_return_type = "Promise<string>")]
+pub fn did_auth_with_context(
+    env: Env,
+    context: &DIDKitContext,
+    holder: String,
+    linked_data_proof_options: String,
+    key: String,
+) -> Result<JsObject> {
+    let context = context.context.clone();
+    env.execute_tokio_future(
+        async move {
+            did_auth_(context, holder, linked_data_proof_options, key)
+                .await
+                .map_err(map_error)
+        },
+        |_, out| Ok(out),
+    )
+}
+
+// Authorization capabilities
+
+async fn revocations(
+    revocation_lists: Option<String>,
+    resolver: &dyn DIDResolver,
+) -> std::result::Result<Revocations, Error> {
+    let lists: Vec<String> = match revocation_lists {
+        Some(lists) => serde_json::from_str(&lists)?,
+        None => Vec::new(),
+    };
+    Ok(Revocations::verify(&lists, resolver).await?)
+}
+
+async fn delegate_capability_(
+    capability: String,
+    linked_data_proof_options: String,
+    parent_caps: String,
+    key: String,
+) -> std::result::Result<String, Error> {
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&capability)?;
+    let key: JWK = serde_json::from_str(&key)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&linked_data_proof_options)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let parents: Vec<String> = serde_json::from_str(&parent_caps)?;
+    let proof = delegation
+        .generate_proof(
+            &key,
+            &options,
+            resolver,
+            &mut context_loader,
+            &parents.iter().map(|p| p.as_ref()).collect::<Vec<&str>>(),
+        )
+        .await?;
+    Ok(serde_json::to_string(&delegation.set_proof(proof))?)
+}
+
+#[napi(js_name = "delegateCapability")]
+pub async fn delegate_capability(
+    capability: String,
+    linked_data_proof_options: String,
+    parents: String,
+    key: String,
+) -> Result<String> {
+    delegate_capability_(capability, linked_data_proof_options, parents, key)
+        .await
+        .map_err(map_error)
+}
+
+async fn prepare_delegate_capability_(
+    capability: String,
+    linked_data_proof_options: String,
+    parent_caps: String,
+    public_key: String,
+) -> std::result::Result<String, Error> {
+    let public_key: JWK = serde_json::from_str(&public_key)?;
+    let capability: Delegation<Value, Value> = serde_json::from_str(&capability)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&linked_data_proof_options)?;
+    let parents: Vec<String> = serde_json::from_str(&parent_caps)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let preparation = capability
+        .prepare_proof(
+            &public_key,
+            &options,
+            resolver,
+            &mut context_loader,
+            &parents.iter().map(|p| p.as_ref()).collect::<Vec<&str>>(),
+        )
+        .await?;
+    Ok(serde_json::to_string(&preparation)?)
+}
+
+#[napi(js_name = "prepareDelegateCapability")]
+pub async fn prepare_delegate_capability(
+    capability: String,
+    linked_data_proof_options: String,
+    parents: String,
+    public_key: String,
+) -> Result<String> {
+    prepare_delegate_capability_(capability, linked_data_proof_options, parents, public_key)
+        .await
+        .map_err(map_error)
+}
+
+async fn complete_delegate_capability_(
+    capability: String,
+    preparation: String,
+    signature: String,
+) -> std::result::Result<String, Error> {
+    let capability: Delegation<Value, Value> = serde_json::from_str(&capability)?;
+    let preparation: ProofPreparation = serde_json::from_str(&preparation)?;
+    let proof = preparation
+        .proof
+        .type_
+        .complete(&preparation, &signature)
+        .await?;
+    Ok(serde_json::to_string(&capability.set_proof(proof))?)
+}
+
+#[napi(js_name = "completeDelegateCapability")]
+pub async fn complete_delegate_capability(
+    capability: String,
+    preparation: String,
+    signature: String,
+) -> Result<String> {
+    complete_delegate_capability_(capability, preparation, signature)
+        .await
+        .map_err(map_error)
+}
+
+async fn verify_delegation_(
+    delegation: String,
+    revocation_lists: Option<String>,
+) -> std::result::Result<String, Error> {
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let revocations = revocations(revocation_lists, resolver).await?;
+    let result = zcap::verify_delegation(
+        &delegation,
+        None,
+        &revocations,
+        chrono::Utc::now(),
+        resolver,
+        &mut context_loader,
+    )
+    .await;
+    Ok(serde_json::to_string(&result)?)
+}
+
+/// Verify a capability delegation, including its caveats. `revocationLists` is an optional JSON
+/// array of revocation lists, as returned by `revokeCapabilities`.
+#[napi(js_name = "verifyDelegation")]
+pub async fn verify_delegation(
+    delegation: String,
+    revocation_lists: Option<String>,
+) -> Result<String> {
+    verify_delegation_(delegation, revocation_lists)
+        .await
+        .map_err(map_error)
+}
+
+fn revoke_capabilities_(
+    ids: String,
+    verification_method: String,
+    key: String,
+) -> std::result::Result<String, Error> {
+    let ids: Vec<String> = serde_json::from_str(&ids)?;
+    let key: JWK = serde_json::from_str(&key)?;
+    let jws = zcap::sign_revocation_list(ids, &verification_method, &key, chrono::Utc::now())?;
+    Ok(jws)
+}
+
+/// Sign a revocation list for the capabilities with the given ids (a JSON array), using a
+/// verification method of the revoking DID
+#[napi(js_name = "revokeCapabilities")]
+pub fn revoke_capabilities(
+    ids: String,
+    verification_method: String,
+    key: String,
+) -> Result<String> {
+    revoke_capabilities_(ids, verification_method, key).map_err(map_error)
+}
+
+async fn invoke_capability_(
+    invocation: String,
+    target_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
+    linked_data_proof_options: String,
+    key: String,
+) -> std::result::Result<String, Error> {
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation)?;
+    let key: JWK = serde_json::from_str(&key)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&linked_data_proof_options)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let proof = zcap::invoke(
+        &invocation,
+        &URI::String(target_id),
+        action.as_deref(),
+        invocation_target.as_deref(),
+        Some(&key),
+        options,
+        resolver,
+        &mut context_loader,
+        None,
+    )
+    .await?;
+    Ok(serde_json::to_string(&invocation.set_proof(proof))?)
+}
+
+/// Invoke the capability with id `targetId`. The optional `action` and `invocationTarget` are
+/// included in the proof as `capabilityAction` and `invocationTarget`, to be checked against the
+/// capability's caveats.
+#[napi(js_name = "invokeCapability")]
+pub async fn invoke_capability(
+    invocation: String,
+    target_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
+    linked_data_proof_options: String,
+    key: String,
+) -> Result<String> {
+    invoke_capability_(
+        invocation,
+        target_id,
+        action,
+        invocation_target,
+        linked_data_proof_options,
+        key,
+    )
+    .await
+    .map_err(map_error)
+}
+
+async fn prepare_invoke_capability_(
+    invocation: String,
+    target_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
+    linked_data_proof_options: String,
+    public_key: String,
+) -> std::result::Result<String, Error> {
+    let public_key: JWK = serde_json::from_str(&public_key)?;
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&linked_data_proof_options)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let preparation = zcap::prepare_invoke(
+        &invocation,
+        &URI::String(target_id),
+        action.as_deref(),
+        invocation_target.as_deref(),
+        &public_key,
+        options,
+        resolver,
+        &mut context_loader,
+    )
+    .await?;
+    Ok(serde_json::to_string(&preparation)?)
+}
+
+/// Prepare a capability invocation proof, to be signed externally. Arguments are as for
+/// `invokeCapability`, with the public key of the signer instead of the private key.
+#[napi(js_name = "prepareInvokeCapability")]
+pub async fn prepare_invoke_capability(
+    invocation: String,
+    target_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
+    linked_data_proof_options: String,
+    public_key: String,
+) -> Result<String> {
+    prepare_invoke_capability_(
+        invocation,
+        target_id,
+        action,
+        invocation_target,
+        linked_data_proof_options,
+        public_key,
+    )
+    .await
+    .map_err(map_error)
+}
+
+async fn complete_invoke_capability_(
+    invocation: String,
+    preparation: String,
+    signature: String,
+) -> std::result::Result<String, Error> {
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation)?;
+    let preparation: ProofPreparation = serde_json::from_str(&preparation)?;
+    let proof = preparation
+        .proof
+        .type_
+        .complete(&preparation, &signature)
+        .await?;
+    Ok(serde_json::to_string(&invocation.set_proof(proof))?)
+}
+
+#[napi(js_name = "completeInvokeCapability")]
+pub async fn complete_invoke_capability(
+    invocation: String,
+    preparation: String,
+    signature: String,
+) -> Result<String> {
+    complete_invoke_capability_(invocation, preparation, signature)
+        .await
+        .map_err(map_error)
+}
+
+async fn verify_invocation_signature_(invocation: String) -> std::result::Result<String, Error> {
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let result = invocation
+        .verify_signature(None, resolver, &mut context_loader)
+        .await;
+    Ok(serde_json::to_string(&result)?)
+}
+
+#[napi(js_name = "verifyInvocationSignature")]
+pub async fn verify_invocation_signature(invocation: String) -> Result<String> {
+    verify_invocation_signature_(invocation)
+        .await
+        .map_err(map_error)
+}
+
+async fn verify_invocation_(
+    invocation: String,
+    delegation: String,
+    revocation_lists: Option<String>,
+) -> std::result::Result<String, Error> {
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation)?;
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let revocations = revocations(revocation_lists, resolver).await?;
+    let result = zcap::verify_invocation(
+        &invocation,
+        &delegation,
+        None,
+        &revocations,
+        chrono::Utc::now(),
+        resolver,
+        &mut context_loader,
+    )
+    .await;
+    Ok(serde_json::to_string(&result)?)
+}
+
+/// Verify a capability invocation against the invoked capability's delegation, including the
+/// capability's caveats. `revocationLists` is as for `verifyDelegation`.
+#[napi(js_name = "verifyInvocation")]
+pub async fn verify_invocation(
+    invocation: String,
+    delegation: String,
+    revocation_lists: Option<String>,
+) -> Result<String> {
+    verify_invocation_(invocation, delegation, revocation_lists)
+        .await
+        .map_err(map_error)
+}
+
+async fn verify_delegation_chain_(
+    delegation: String,
+    capabilities: String,
+    root: String,
+    revocation_lists: Option<String>,
+) -> std::result::Result<String, Error> {
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation)?;
+    let store = CapabilityStore::from_value(serde_json::from_str(&capabilities)?)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let revocations = revocations(revocation_lists, resolver).await?;
+    let report = zcap::verify_delegation_chain(
+        &delegation,
+        &store,
+        &root,
+        &revocations,
+        chrono::Utc::now(),
+        resolver,
+        &mut context_loader,
+    )
+    .await;
+    Ok(serde_json::to_string(&report)?)
+}
+
+/// Verify a capability delegation and its delegation chain, looking up parent capabilities in
+/// `capabilities` (a JSON array, or an object mapping capability ids to capabilities). The chain
+/// must end at the capability with id `root`, the root capability trusted by the verifier.
+#[napi(js_name = "verifyDelegationChain")]
+pub async fn verify_delegation_chain(
+    delegation: String,
+    capabilities: String,
+    root: String,
+    revocation_lists: Option<String>,
+) -> Result<String> {
+    verify_delegation_chain_(delegation, capabilities, root, revocation_lists)
+        .await
+        .map_err(map_error)
+}
+
+async fn verify_invocation_chain_(
+    invocation: String,
+    capabilities: String,
+    root: String,
+    revocation_lists: Option<String>,
+) -> std::result::Result<String, Error> {
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation)?;
+    let store = CapabilityStore::from_value(serde_json::from_str(&capabilities)?)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let revocations = revocations(revocation_lists, resolver).await?;
+    let report = zcap::verify_invocation_chain(
+        &invocation,
+        &store,
+        &root,
+        &revocations,
+        chrono::Utc::now(),
+        resolver,
+        &mut context_loader,
+    )
+    .await;
+    Ok(serde_json::to_string(&report)?)
+}
+
+/// Verify a capability invocation and the delegation chain of the invoked capability.
+/// `capabilities` and `root` are as for `verifyDelegationChain`. `capabilities` must include the
+/// invoked capability, which may be the root capability.
+#[napi(js_name = "verifyInvocationChain")]
+pub async fn verify_invocation_chain(
+    invocation: String,
+    capabilities: String,
+    root: String,
+    revocation_lists: Option<String>,
+) -> Result<String> {
+    verify_invocation_chain_(invocation, capabilities, root, revocation_lists)
+        .await
+        .map_err(map_error)
+}
+
+// Presentation Exchange
+
+fn presentation_exchange_evaluate_(
+    definition: String,
+    credentials: String,
+) -> std::result::Result<String, Error> {
+    let definition: PresentationDefinition = serde_json::from_str(&definition)?;
+    let credentials: Vec<Value> = serde_json::from_str(&credentials)?;
+    let evaluation = presentation_exchange::evaluate(&definition, &credentials)?;
+    Ok(serde_json::to_string(&evaluation)?)
+}
+
+#[napi(js_name = "presentationExchangeEvaluate")]
+pub fn presentation_exchange_evaluate(definition: String, credentials: String) -> Result<String> {
+    presentation_exchange_evaluate_(definition, credentials).map_err(map_error)
+}
+
+fn presentation_exchange_create_(
+    definition: String,
+    credentials: String,
+    holder: Option<String>,
+    proof_format: Option<String>,
+) -> std::result::Result<String, Error> {
+    let definition: PresentationDefinition = serde_json::from_str(&definition)?;
+    let credentials: Vec<Value> = serde_json::from_str(&credentials)?;
+    let proof_format = match proof_format {
+        Some(proof_format) => proof_format
+            .parse()
+            .map_err(|_| Error::UnknownProofFormat(proof_format))?,
+        None => ProofFormat::default(),
+    };
+    let (presentation, presentation_submission) = presentation_exchange::build_presentation(
+        &definition,
+        &credentials,
+        holder.as_deref(),
+        proof_format,
+    )?;
+    Ok(serde_json::to_string(&json!({
+        "presentation": presentation,
+        "presentation_submission": presentation_submission,
+    }))?)
+}
+
+#[napi(js_name = "presentationExchangeCreate")]
+pub fn presentation_exchange_create(
+    definition: String,
+    credentials: String,
+    holder: Option<String>,
+    proof_format: Option<String>,
+) -> Result<String> {
+    presentation_exchange_create_(definition, credentials, holder, proof_format).map_err(map_error)
+}
+
+fn presentation_exchange_verify_(
+    definition: String,
+    submission: String,
+    presentation: String,
+) -> std::result::Result<String, Error> {
+    let definition: PresentationDefinition = serde_json::from_str(&definition)?;
+    let submission: PresentationSubmission = serde_json::from_str(&submission)?;
+    let presentation = match serde_json::from_str(&presentation) {
+        Ok(presentation) => presentation,
+        Err(_) => Value::String(presentation),
+    };
+    let check = presentation_exchange::check_submission(&definition, &submission, &presentation)?;
+    Ok(serde_json::to_string(&check)?)
+}
+
+#[napi(js_name = "presentationExchangeVerify")]
+pub fn presentation_exchange_verify(
+    definition: String,
+    submission: String,
+    presentation: String,
+) -> Result<String> {
+    presentation_exchange_verify_(definition, submission, presentation).map_err(map_error)
+}
+
+// JSON-LD
+
+fn debug_error<E: std::fmt::Debug>(err: E) -> String {
+    format!("{:?}", err)
+}
+
+// As the `didkit jsonld to rdfurdna` command, using only the built-in JSON-LD contexts
+async fn to_rdf_urdna2015_(
+    json: String,
+    base: Option<String>,
+    expand_context: Option<String>,
+    more_context_json: Option<String>,
+) -> std::result::Result<String, String> {
+    let mut loader = StaticLoader;
+    let expand_context = match (more_context_json, expand_context) {
+        (Some(more_context_json), Some(expand_context)) => {
+            let more_context: Value =
+                serde_json::from_str(&more_context_json).map_err(|e| e.to_string())?;
+            Some(json!([expand_context, more_context]).to_string())
+        }
+        (Some(more_context_json), None) => Some(more_context_json),
+        (None, expand_context) => expand_context,
+    };
+    let json = ssi::jsonld::syntax::to_value_with(
+        serde_json::from_str::<Value>(&json).map_err(|e| e.to_string())?,
+        Default::default,
+    )
+    .map_err(debug_error)?;
+    let expand_context = match expand_context {
+        Some(context) => Some(parse_ld_context(&context).map_err(debug_error)?),
+        None => None,
+    };
+    let base = match base {
+        Some(base) => Some(IriBuf::from_string(base).map_err(debug_error)?),
+        None => None,
+    };
+    let options = ssi::jsonld::Options {
+        base,
+        expand_context,
+        ..Default::default()
+    };
+    let doc = ssi::jsonld::RemoteDocument::new(None, None, json);
+    let mut generator =
+        rdf_types::generator::Blank::new_with_prefix("b".to_string()).with_default_metadata();
+    let mut to_rdf = doc
+        .to_rdf_using(&mut generator, &mut loader, options)
+        .await
+        .map_err(debug_error)?;
+    let dataset: rdf::DataSet = to_rdf
+        .cloned_quads()
+        .map(|rdf_types::Quad(subject, predicate, object, graph)| {
+            let predicate = predicate
+                .into_iri()
+                .ok_or_else(|| "Blank node predicates are not supported".to_string())?;
+            Ok(rdf_types::Quad(subject, predicate, object, graph))
+        })
+        .collect::<std::result::Result<_, String>>()?;
+    let dataset_normalized = ssi::urdna2015::normalize(dataset.quads().map(Into::into));
+    Ok(dataset_normalized.into_nquads())
+}
+
+/// Convert a JSON-LD document to URDNA2015-canonicalized RDF N-Quads. Only the built-in JSON-LD
+/// contexts are loaded. Optional arguments are the base IRI, an IRI for the `expandContext` option,
+/// and additional values for the `@context` property, as JSON.
+#[napi(js_name = "toRDFURDNA2015")]
+pub async fn to_rdf_urdna2015(
+    json: String,
+    base: Option<String>,
+    expand_context: Option<String>,
+    more_context_json: Option<String>,
+) -> Result<String> {
+    to_rdf_urdna2015_(json, base, expand_context, more_context_json)
+        .await
+        .map_err(map_error)
+}
@@ -0,0 +1,238 @@
+const crypto = require("crypto");
+const {
+  getVersion,
+  generateEd25519Key,
+  keyToDID,
+  keyToVerificationMethod,
+  issueCredential,
+  verifyCredential,
+  DIDAuth,
+  verifyPresentation,
+  resolveDID,
+  recoverDID,
+  issueCredentialWithSSHAgent,
+  DIDKitContext,
+  resolveDIDWithContext,
+  resolveDIDWithResolver,
+  verifyCredentialWithResolver,
+  issueCredentialWithSigner,
+  toRDFURDNA2015,
+} = require("..");
+
+let failed = false;
+
+const test = async (title, fn) => {
+  try {
+    await fn();
+    console.log(`[    ok: ${title}]`);
+  } catch (e) {
+    failed = true;
+    console.error(`[fail: ${title}]`, e);
+  }
+};
+
+const emptyObj = JSON.stringify({});
+
+(async () => {
+  await test("should get library version", () => {
+    if (typeof getVersion() !== "string") throw "version is not an string";
+  });
+
+  await test("should generate ed25519 key", () => {
+    const k = JSON.parse(generateEd25519Key());
+    if (k.kty !== "OKP") throw "expected 'OKP' in 'kty'";
+    if (k.crv !== "Ed25519") throw "expected 'Ed25519' in 'crv'";
+    if (!("d" in k)) throw "missing 'd' prop";
+  });
+
+  const keyStr = JSON.stringify({
+    kty: "OKP",
+    crv: "Ed25519",
+    x: "PBcY2yJ4h_cLUnQNcYhplu9KQQBNpGxP4sYcMPdlu6I",
+    d: "n5WUFIghmRYZi0rEYo2lz-Zg2B9B1KW4MYfJXwOXfyI",
+  });
+  const did = "did:key:z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK";
+  const verificationMethod = `${did}#z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK`;
+
+  await test("should produce did", () => {
+    if (keyToDID("key", keyStr) !== did) throw `expected '${did}'`;
+  });
+
+  await test("should produce verificationMethod", async () => {
+    const vm = await keyToVerificationMethod("key", keyStr);
+    if (vm !== verificationMethod) throw `expected '${verificationMethod}'`;
+  });
+
+  await test("should resolve did:key", async () => {
+    const doc = JSON.parse(await resolveDID(did, emptyObj));
+    if (doc.id !== did) throw `expected '${did}'`;
+  });
+
+  await test("should not build a did:key recover transaction", () => {
+    try {
+      recoverDID(did, keyStr, null, null, null);
+    } catch (e) {
+      return;
+    }
+    throw "did not fail";
+  });
+
+  await test("should reject the promise on invalid parameters", async () => {
+    let rejected = false;
+    await issueCredential(emptyObj, emptyObj, emptyObj).catch(() => {
+      rejected = true;
+    });
+    if (!rejected) throw "did not fail";
+  });
+
+  const credential = JSON.stringify({
+    "@context": "https://www.w3.org/2018/credentials/v1",
+    id: "http://example.org/credentials/3731",
+    type: ["VerifiableCredential"],
+    issuer: did,
+    issuanceDate: "2020-08-19T21:41:50Z",
+    credentialSubject: {
+      id: "did:example:d23dd687a7dc6787646f2eb98d0",
+    },
+  });
+
+  for (const proofFormat of ["ldp", "jwt"]) {
+    await test(`should verify issued credential (${proofFormat})`, async () => {
+      const vc = await issueCredential(
+        credential,
+        JSON.stringify({
+          proofPurpose: "assertionMethod",
+          proofFormat,
+          verificationMethod,
+        }),
+        keyStr
+      );
+      const verify = JSON.parse(
+        await verifyCredential(
+          vc,
+          JSON.stringify({ proofPurpose: "assertionMethod", proofFormat })
+        )
+      );
+      if (verify.errors.length > 0) throw verify.errors;
+    });
+  }
+
+  await test("should verify DIDAuth presentation", async () => {
+    const vp = await DIDAuth(
+      did,
+      JSON.stringify({ proofPurpose: "authentication", verificationMethod }),
+      keyStr
+    );
+    const verify = JSON.parse(
+      await verifyPresentation(
+        vp,
+        JSON.stringify({ proofPurpose: "authentication" })
+      )
+    );
+    if (verify.errors.length > 0) throw verify.errors;
+  });
+
+  await test("should fail without ssh-agent socket", async () => {
+    let rejected = false;
+    await issueCredentialWithSSHAgent(
+      credential,
+      JSON.stringify({ proofPurpose: "assertionMethod", verificationMethod }),
+      null,
+      "/nonexistent/ssh-agent.sock"
+    ).catch(() => {
+      rejected = true;
+    });
+    if (!rejected) throw "did not fail";
+  });
+
+  await test("should issue and verify with a context", async () => {
+    const context = new DIDKitContext(
+      JSON.stringify({
+        config: '[did-methods]\ndisable = ["onion"]',
+        cacheResolutions: true,
+      })
+    );
+    const doc = JSON.parse(await context.resolveDID(did, emptyObj));
+    if (doc.id !== did) throw `expected '${did}'`;
+    const vc = await context.issueCredential(
+      credential,
+      JSON.stringify({ proofPurpose: "assertionMethod", verificationMethod }),
+      keyStr
+    );
+    const verify = JSON.parse(
+      await context.verifyCredential(
+        vc,
+        JSON.stringify({ proofPurpose: "assertionMethod" })
+      )
+    );
+    if (verify.errors.length > 0) throw verify.errors;
+  });
+
+  await test("should resolve with a context as argument", async () => {
+    const context = new DIDKitContext();
+    const doc = JSON.parse(await resolveDIDWithContext(context, did, emptyObj));
+    if (doc.id !== did) throw `expected '${did}'`;
+  });
+
+  await test("should resolve with a resolver callback", async () => {
+    const exampleDID = "did:example:foo";
+    const resolve = async (d) =>
+      d === exampleDID
+        ? { "@context": "https://www.w3.org/ns/did/v1", id: d }
+        : null;
+    const doc = JSON.parse(
+      await resolveDIDWithResolver(exampleDID, emptyObj, resolve)
+    );
+    if (doc.id !== exampleDID) throw `expected '${exampleDID}'`;
+    const fallback = JSON.parse(
+      await resolveDIDWithResolver(did, emptyObj, resolve)
+    );
+    if (fallback.id !== did) throw `expected '${did}'`;
+  });
+
+  await test("should issue with a signer callback", async () => {
+    const { d, ...publicKey } = JSON.parse(keyStr);
+    const privateKey = crypto.createPrivateKey({
+      key: JSON.parse(keyStr),
+      format: "jwk",
+    });
+    const sign = async (bytes) => crypto.sign(null, bytes, privateKey);
+    const vc = await issueCredentialWithSigner(
+      credential,
+      JSON.stringify({ proofPurpose: "assertionMethod", verificationMethod }),
+      JSON.stringify(publicKey),
+      sign
+    );
+    const verify = JSON.parse(
+      await verifyCredentialWithResolver(
+        vc,
+        JSON.stringify({ proofPurpose: "assertionMethod" }),
+        async () => null
+      )
+    );
+    if (verify.errors.length > 0) throw verify.errors;
+  });
+
+  await test("should convert JSON-LD to URDNA2015 N-Quads", async () => {
+    const nquads = await toRDFURDNA2015(
+      JSON.stringify({
+        "@context": { name: "http://schema.org/name" },
+        "@id": "http://example.org/a",
+        name: "A",
+      })
+    );
+    const expected = '<http://example.org/a> <http://schema.org/name> "A" .\n';
+    if (nquads !== expected) throw `expected '${expected}'`;
+  });
+
+  await test("should reject invalid context options", () => {
+    try {
+      new DIDKitContext(JSON.stringify({ foo: 1 }));
+    } catch (e) {
+      return;
+    }
+    throw "did not fail";
+  });
+
+  if (failed) process.exit(1);
+})();
@@ -0,0 +1,49 @@
+"""Type stubs for the DIDKit Python module.
+
+Arguments and results that are JSON documents (keys, credentials, presentations,
+options, DID documents...) are passed as JSON strings.
+"""
+
+from typing import Optional
+
+__version__: str
+
+class DIDKitException(Exception): ...
+class VCError(DIDKitException): ...
+class JWKError(DIDKitException): ...
+class DIDError(DIDKitException): ...
+class ResolutionError(DIDKitException): ...
+class ZcapError(DIDKitException): ...
+class ConfigError(DIDKitException): ...
+class ProofFormatError(DIDKitException): ...
+
+def get_version() -> str: ...
+def generate_ed25519_key() -> str: ...
+def generate_secp256k1_key() -> str: ...
+def generate_p256_key() -> str: ...
+def key_to_did(method_pattern: str, jwk: str) -> str: ...
+async def key_to_verification_method(method_pattern: str, jwk: str) -> str: ...
+async def resolve_did(did: str, input_metadata: str) -> str: ...
+async def dereference_did_url(did_url: str, input_metadata: str) -> str: ...
+def create_did(
+    method: str,
+    update_key: Optional[str] = None,
+    recovery_key: Optional[str] = None,
+    verification_key: Optional[str] = None,
+    options: Optional[str] = None,
+) -> str: ...
+def update_did(
+    did: str,
+    update_key: Optional[str],
+    new_update_key: Optional[str],
+    operation: str,
+    options: Optional[str] = None,
+) -> str: ...
+def deactivate_did(
+    did: str, key: Optional[str] = None, options: Optional[str] = None
+) -> str: ...
+async def issue_credential(credential: str, proof_options: str, key: str) -> str: ...
+async def verify_credential(vc: str, proof_options: str) -> str: ...
+async def issue_presentation(presentation: str, proof_options: str, key: str) -> str: ...
+async def verify_presentation(vp: str, proof_options: str) -> str: ...
+async def did_auth(holder: str, proof_options: str, key: str) -> str: ...
@@ -0,0 +1,504 @@
+//! Python bindings for DIDKit, using [PyO3](https://pyo3.rs/).
+//!
+//! Functions that resolve DIDs or create or verify proofs are coroutine functions for asyncio,
+//! run on the [shared runtime](didkit::runtime::shared) of DIDKit. Errors are raised as
+//! subclasses of `DIDKitException`, by kind of [`Error`].
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use serde_json::{Map, Value};
+
+use didkit::error::Error;
+use didkit::{dereference, get_verification_method, runtime};
+use didkit::{ContextLoader, DIDResolver, DereferencingInputMetadata, ResolutionInputMetadata};
+use didkit::{DIDCreate, DIDDeactivate, DIDDocumentOperation, DIDUpdate};
+use didkit::{JWTOrLDPOptions, ProofFormat, Source, URI};
+use didkit::{VerifiableCredential, VerifiablePresentation};
+use didkit::{DID_METHODS, JWK};
+
+pub static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+create_exception!(
+    didkit,
+    DIDKitException,
+    PyException,
+    "Error raised by DIDKit"
+);
+create_exception!(
+    didkit,
+    VCError,
+    DIDKitException,
+    "Invalid credential, presentation or options (including invalid JSON), or failure to \
+     issue or verify them"
+);
+create_exception!(
+    didkit,
+    JWKError,
+    DIDKitException,
+    "Invalid or unsupported key"
+);
+create_exception!(
+    didkit,
+    DIDError,
+    DIDKitException,
+    "Unknown DID method, or failure to generate a DID or build a DID operation"
+);
+create_exception!(
+    didkit,
+    ResolutionError,
+    DIDKitException,
+    "DID resolution error, such as `notFound` or `invalidDid`"
+);
+create_exception!(
+    didkit,
+    ZcapError,
+    DIDKitException,
+    "Invalid authorization capability or capability proof"
+);
+create_exception!(
+    didkit,
+    ConfigError,
+    DIDKitException,
+    "Invalid DID methods configuration"
+);
+create_exception!(
+    didkit,
+    ProofFormatError,
+    DIDKitException,
+    "Unknown proof format"
+);
+
+fn to_py_err(err: Error) -> PyErr {
+    let message = err.to_string();
+    match err {
+        Error::VC(_) => VCError::new_err(message),
+        Error::JWK(_) | Error::KeyDerivation(_) | Error::KeyFormat(_) => JWKError::new_err(message),
+        Error::DIDMethod(_)
+        | Error::DIDDocument(_)
+        | Error::DIDPeer(_)
+        | Error::DIDWeb(_)
+        | Error::UnableToGenerateDID
+        | Error::UnknownDIDMethod
+        | Error::UnableToGetVerificationMethod => DIDError::new_err(message),
+        Error::Zcap(_) | Error::Capability(_) => ZcapError::new_err(message),
+        Error::Config(_) | Error::Context(_) => ConfigError::new_err(message),
+        Error::UnknownProofFormat(_) => ProofFormatError::new_err(message),
+        _ => DIDKitException::new_err(message),
+    }
+}
+
+/// Get the version of DIDKit
+#[pyfunction]
+fn get_version() -> &'static str {
+    VERSION
+}
+
+// Keys
+
+/// Generate an Ed25519 key, as a JWK
+#[pyfunction]
+fn generate_ed25519_key() -> PyResult<String> {
+    let jwk = JWK::generate_ed25519().map_err(|e| to_py_err(e.into()))?;
+    serde_json::to_string(&jwk).map_err(|e| to_py_err(e.into()))
+}
+
+/// Generate a secp256k1 key, as a JWK
+#[pyfunction]
+fn generate_secp256k1_key() -> PyResult<String> {
+    let jwk = JWK::generate_secp256k1().map_err(|e| to_py_err(e.into()))?;
+    serde_json::to_string(&jwk).map_err(|e| to_py_err(e.into()))
+}
+
+/// Generate a P-256 key, as a JWK
+#[pyfunction]
+fn generate_p256_key() -> PyResult<String> {
+    let jwk = JWK::generate_p256().map_err(|e| to_py_err(e.into()))?;
+    serde_json::to_string(&jwk).map_err(|e| to_py_err(e.into()))
+}
+
+fn key_to_did_(method_pattern: &str, jwk: &str) -> Result<String, Error> {
+    let key: JWK = serde_json::from_str(jwk)?;
+    let did = DID_METHODS
+        .generate(&Source::KeyAndPattern(&key, method_pattern))
+        .ok_or(Error::UnableToGenerateDID)?;
+    Ok(did)
+}
+
+/// Get the DID of a key (JWK) for a DID method pattern, e.g. `key` or `tz`
+#[pyfunction]
+fn key_to_did(method_pattern: &str, jwk: &str) -> PyResult<String> {
+    key_to_did_(method_pattern, jwk).map_err(to_py_err)
+}
+
+async fn key_to_verification_method_(method_pattern: String, jwk: String) -> Result<String, Error> {
+    let did = key_to_did_(&method_pattern, &jwk)?;
+    let vm = get_verification_method(&did, DID_METHODS.to_resolver())
+        .await
+        .ok_or(Error::UnableToGetVerificationMethod)?;
+    Ok(vm)
+}
+
+/// Get the verification method id of a key (JWK) for a DID method pattern
+#[pyfunction]
+fn key_to_verification_method(py: Python, method_pattern: String, jwk: String) -> PyResult<&PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        key_to_verification_method_(method_pattern, jwk)
+            .await
+            .map_err(to_py_err)
+    })
+}
+
+// DID resolution
+
+/// Resolve a DID to a DID document. Raises `ResolutionError` with the DID resolution error code,
+/// e.g. `notFound`, if the DID cannot be resolved.
+#[pyfunction]
+fn resolve_did(py: Python, did: String, input_metadata: String) -> PyResult<&PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let input_metadata: ResolutionInputMetadata =
+            serde_json::from_str(&input_metadata).map_err(|e| to_py_err(e.into()))?;
+        let (res_meta, doc, _) = DID_METHODS.resolve(&did, &input_metadata).await;
+        if let Some(error) = res_meta.error {
+            return Err(ResolutionError::new_err(error));
+        }
+        match doc {
+            Some(doc) => serde_json::to_string(&doc).map_err(|e| to_py_err(e.into())),
+            None => Err(ResolutionError::new_err("No document resolved.")),
+        }
+    })
+}
+
+/// Dereference a DID URL, to a JSON array of the dereferencing metadata, the content and the
+/// content metadata
+#[pyfunction]
+fn dereference_did_url(py: Python, did_url: String, input_metadata: String) -> PyResult<&PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let input_metadata: DereferencingInputMetadata =
+            serde_json::from_str(&input_metadata).map_err(|e| to_py_err(e.into()))?;
+        let deref_result = dereference(DID_METHODS.to_resolver(), &did_url, &input_metadata).await;
+        if let Some(error) = deref_result.0.error {
+            return Err(ResolutionError::new_err(error));
+        }
+        serde_json::to_string(&deref_result).map_err(|e| to_py_err(e.into()))
+    })
+}
+
+// DID operations
+
+fn jwk_opt(key: Option<&str>) -> Result<Option<JWK>, Error> {
+    match key {
+        Some(key) => Ok(Some(serde_json::from_str(key)?)),
+        None => Ok(None),
+    }
+}
+
+fn did_method_options(options: Option<&str>) -> Result<Map<String, Value>, Error> {
+    match options {
+        Some(options) => Ok(serde_json::from_str(options)?),
+        None => Ok(Map::new()),
+    }
+}
+
+fn create_did_(
+    method_name: &str,
+    update_key: Option<&str>,
+    recovery_key: Option<&str>,
+    verification_key: Option<&str>,
+    options: Option<&str>,
+) -> Result<String, Error> {
+    let method = DID_METHODS
+        .get(method_name)
+        .ok_or(Error::UnknownDIDMethod)?;
+    let tx = method.create(DIDCreate {
+        update_key: jwk_opt(update_key)?,
+        recovery_key: jwk_opt(recovery_key)?,
+        verification_key: jwk_opt(verification_key)?,
+        options: did_method_options(options)?,
+    })?;
+    Ok(serde_json::to_string(&tx)?)
+}
+
+/// Build a DID Create transaction for a DID method, e.g. `ion`
+#[pyfunction]
+#[args(
+    update_key = "None",
+    recovery_key = "None",
+    verification_key = "None",
+    options = "None"
+)]
+fn create_did(
+    method: &str,
+    update_key: Option<&str>,
+    recovery_key: Option<&str>,
+    verification_key: Option<&str>,
+    options: Option<&str>,
+) -> PyResult<String> {
+    create_did_(method, update_key, recovery_key, verification_key, options).map_err(to_py_err)
+}
+
+fn update_did_(
+    did: &str,
+    update_key: Option<&str>,
+    new_update_key: Option<&str>,
+    operation: &str,
+    options: Option<&str>,
+) -> Result<String, Error> {
+    let operation: DIDDocumentOperation = serde_json::from_str(operation)?;
+    let method = DID_METHODS
+        .get_method(did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.update(DIDUpdate {
+        did: did.to_string(),
+        update_key: jwk_opt(update_key)?,
+        new_update_key: jwk_opt(new_update_key)?,
+        operation,
+        options: did_method_options(options)?,
+    })?;
+    Ok(serde_json::to_string(&tx)?)
+}
+
+/// Build a DID Update transaction
+#[pyfunction]
+#[args(options = "None")]
+fn update_did(
+    did: &str,
+    update_key: Option<&str>,
+    new_update_key: Option<&str>,
+    operation: &str,
+    options: Option<&str>,
+) -> PyResult<String> {
+    update_did_(did, update_key, new_update_key, operation, options).map_err(to_py_err)
+}
+
+fn deactivate_did_(did: &str, key: Option<&str>, options: Option<&str>) -> Result<String, Error> {
+    let method = DID_METHODS
+        .get_method(did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.deactivate(DIDDeactivate {
+        did: did.to_string(),
+        key: jwk_opt(key)?,
+        options: did_method_options(options)?,
+    })?;
+    Ok(serde_json::to_string(&tx)?)
+}
+
+/// Build a DID Deactivate transaction
+#[pyfunction]
+#[args(key = "None", options = "None")]
+fn deactivate_did(did: &str, key: Option<&str>, options: Option<&str>) -> PyResult<String> {
+    deactivate_did_(did, key, options).map_err(to_py_err)
+}
+
+// Credentials and presentations
+
+async fn issue_credential_(
+    credential: String,
+    proof_options: String,
+    key: String,
+) -> Result<String, Error> {
+    let mut credential = VerifiableCredential::from_json_unsigned(&credential)?;
+    let key: JWK = serde_json::from_str(&key)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let vc_string = match proof_format {
+        ProofFormat::JWT => {
+            credential
+                .generate_jwt(Some(&key), &options.ldp_options, resolver)
+                .await?
+        }
+        ProofFormat::LDP => {
+            let proof = credential
+                .generate_proof(&key, &options.ldp_options, resolver, &mut context_loader)
+                .await?;
+            credential.add_proof(proof);
+            serde_json::to_string(&credential)?
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(vc_string)
+}
+
+/// Issue a credential, as a JWT or with a linked data proof according to the `proofFormat` of
+/// the options
+#[pyfunction]
+fn issue_credential(
+    py: Python,
+    credential: String,
+    proof_options: String,
+    key: String,
+) -> PyResult<&PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        issue_credential_(credential, proof_options, key)
+            .await
+            .map_err(to_py_err)
+    })
+}
+
+async fn verify_credential_(vc_string: String, proof_options: String) -> Result<String, Error> {
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let result = match proof_format {
+        ProofFormat::JWT => {
+            VerifiableCredential::verify_jwt(
+                &vc_string,
+                Some(options.ldp_options),
+                resolver,
+                &mut context_loader,
+            )
+            .await
+        }
+        ProofFormat::LDP => {
+            let vc = VerifiableCredential::from_json_unsigned(&vc_string)?;
+            vc.verify(Some(options.ldp_options), resolver, &mut context_loader)
+                .await
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(serde_json::to_string(&result)?)
+}
+
+/// Verify a credential, returning the verification result (with `checks`, `warnings` and
+/// `errors`)
+#[pyfunction]
+fn verify_credential(py: Python, vc: String, proof_options: String) -> PyResult<&PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        verify_credential_(vc, proof_options)
+            .await
+            .map_err(to_py_err)
+    })
+}
+
+async fn issue_presentation_(
+    mut presentation: VerifiablePresentation,
+    proof_options: String,
+    key: String,
+) -> Result<String, Error> {
+    let key: JWK = serde_json::from_str(&key)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let vp_string = match proof_format {
+        ProofFormat::JWT => {
+            presentation
+                .generate_jwt(Some(&key), &options.ldp_options, resolver)
+                .await?
+        }
+        ProofFormat::LDP => {
+            let proof = presentation
+                .generate_proof(&key, &options.ldp_options, resolver, &mut context_loader)
+                .await?;
+            presentation.add_proof(proof);
+            serde_json::to_string(&presentation)?
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(vp_string)
+}
+
+/// Issue a presentation, as a JWT or with a linked data proof according to the `proofFormat` of
+/// the options
+#[pyfunction]
+fn issue_presentation(
+    py: Python,
+    presentation: String,
+    proof_options: String,
+    key: String,
+) -> PyResult<&PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let presentation = VerifiablePresentation::from_json_unsigned(&presentation)
+            .map_err(|e| to_py_err(e.into()))?;
+        issue_presentation_(presentation, proof_options, key)
+            .await
+            .map_err(to_py_err)
+    })
+}
+
+async fn verify_presentation_(vp_string: String, proof_options: String) -> Result<String, Error> {
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ContextLoader::default();
+    let result = match proof_format {
+        ProofFormat::JWT => {
+            VerifiablePresentation::verify_jwt(
+                &vp_string,
+                Some(options.ldp_options),
+                resolver,
+                &mut context_loader,
+            )
+            .await
+        }
+        ProofFormat::LDP => {
+            let vp = VerifiablePresentation::from_json_unsigned(&vp_string)?;
+            vp.verify(Some(options.ldp_options), resolver, &mut context_loader)
+                .await
+        }
+        _ => Err(Error::UnknownProofFormat(proof_format.to_string()))?,
+    };
+    Ok(serde_json::to_string(&result)?)
+}
+
+/// Verify a presentation, returning the verification result
+#[pyfunction]
+fn verify_presentation(py: Python, vp: String, proof_options: String) -> PyResult<&PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        verify_presentation_(vp, proof_options)
+            .await
+            .map_err(to_py_err)
+    })
+}
+
+/// Create a DIDAuth presentation for a holder
+#[pyfunction]
+fn did_auth(py: Python, holder: String, proof_options: String, key: String) -> PyResult<&PyAny> {
+    pyo3_asyncio::tokio::future_into_py(py, async move {
+        let mut presentation = VerifiablePresentation::default();
+        presentation.holder = Some(URI::String(holder));
+        issue_presentation_(presentation, proof_options, key)
+            .await
+            .map_err(to_py_err)
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "didkit")]
+fn didkit_python(py: Python, m: &PyModule) -> PyResult<()> {
+    // Run coroutines on the runtime shared with the other bindings. This fails only if the
+    // module is initialized again, with the runtime already set.
+    let runtime = runtime::shared().map_err(to_py_err)?;
+    let _ = pyo3_asyncio::tokio::init_with_runtime(runtime);
+
+    m.add("__version__", VERSION)?;
+    m.add("DIDKitException", py.get_type::<DIDKitException>())?;
+    m.add("VCError", py.get_type::<VCError>())?;
+    m.add("JWKError", py.get_type::<JWKError>())?;
+    m.add("DIDError", py.get_type::<DIDError>())?;
+    m.add("ResolutionError", py.get_type::<ResolutionError>())?;
+    m.add("ZcapError", py.get_type::<ZcapError>())?;
+    m.add("ConfigError", py.get_type::<ConfigError>())?;
+    m.add("ProofFormatError", py.get_type::<ProofFormatError>())?;
+
+    m.add_function(wrap_pyfunction!(get_version, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_ed25519_key, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_secp256k1_key, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_p256_key, m)?)?;
+    m.add_function(wrap_pyfunction!(key_to_did, m)?)?;
+    m.add_function(wrap_pyfunction!(key_to_verification_method, m)?)?;
+    m.add_function(wrap_pyfunction!(resolve_did, m)?)?;
+    m.add_function(wrap_pyfunction!(dereference_did_url, m)?)?;
+    m.add_function(wrap_pyfunction!(create_did, m)?)?;
+    m.add_function(wrap_pyfunction!(update_did, m)?)?;
+    m.add_function(wrap_pyfunction!(deactivate_did, m)?)?;
+    m.add_function(wrap_pyfunction!(issue_credential, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_credential, m)?)?;
+    m.add_function(wrap_pyfunction!(issue_presentation, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_presentation, m)?)?;
+    m.add_function(wrap_pyfunction!(did_auth, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+import asyncio
+import json
+
+import pytest
+
+import didkit
+
+KEY = json.dumps(
+    {
+        "kty": "OKP",
+        "crv": "Ed25519",
+        "x": "PBcY2yJ4h_cLUnQNcYhplu9KQQBNpGxP4sYcMPdlu6I",
+        "d": "n5WUFIghmRYZi0rEYo2lz-Zg2B9B1KW4MYfJXwOXfyI",
+    }
+)
+DID = "did:key:z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK"
+VERIFICATION_METHOD = DID + "#z6MkiVpwA241guqtKWAkohHpcAry7S94QQb6ukW3GcCsugbK"
+
+CREDENTIAL = json.dumps(
+    {
+        "@context": "https://www.w3.org/2018/credentials/v1",
+        "id": "http://example.org/credentials/3731",
+        "type": ["VerifiableCredential"],
+        "issuer": DID,
+        "issuanceDate": "2020-08-19T21:41:50Z",
+        "credentialSubject": {"id": "did:example:d23dd687a7dc6787646f2eb98d0"},
+    }
+)
+
+
+def test_version():
+    assert didkit.get_version() == didkit.__version__
+
+
+def test_generate_key():
+    key = json.loads(didkit.generate_ed25519_key())
+    assert key["kty"] == "OKP"
+    assert key["crv"] == "Ed25519"
+    assert "d" in key
+
+
+def test_key_to_did():
+    assert didkit.key_to_did("key", KEY) == DID
+    with pytest.raises(didkit.VCError):
+        didkit.key_to_did("key", "{}")
+
+
+def test_key_to_verification_method():
+    vm = asyncio.run(didkit.key_to_verification_method("key", KEY))
+    assert vm == VERIFICATION_METHOD
+
+
+def test_resolve_did():
+    doc = json.loads(asyncio.run(didkit.resolve_did(DID, "{}")))
+    assert doc["id"] == DID
+    with pytest.raises(didkit.ResolutionError):
+        asyncio.run(didkit.resolve_did("did:key:foo", "{}"))
+
+
+@pytest.mark.parametrize("proof_format", ["ldp", "jwt"])
+def test_issue_verify_credential(proof_format):
+    async def issue_verify():
+        vc = await didkit.issue_credential(
+            CREDENTIAL,
+            json.dumps(
+                {
+                    "proofPurpose": "assertionMethod",
+                    "proofFormat": proof_format,
+                    "verificationMethod": VERIFICATION_METHOD,
+                }
+            ),
+            KEY,
+        )
+        return await didkit.verify_credential(
+            vc,
+            json.dumps({"proofPurpose": "assertionMethod", "proofFormat": proof_format}),
+        )
+
+    result = json.loads(asyncio.run(issue_verify()))
+    assert result["errors"] == []
+
+
+def test_did_auth():
+    async def did_auth():
+        vp = await didkit.did_auth(
+            DID,
+            json.dumps(
+                {
+                    "proofPurpose": "authentication",
+                    "verificationMethod": VERIFICATION_METHOD,
+                }
+            ),
+            KEY,
+        )
+        return await didkit.verify_presentation(
+            vp, json.dumps({"proofPurpose": "authentication"})
+        )
+
+    result = json.loads(asyncio.run(did_auth()))
+    assert result["errors"] == []
+
+
+def test_concurrent_verification():
+    async def issue_verify_many():
+        vc = await didkit.issue_credential(
+            CREDENTIAL,
+            json.dumps(
+                {
+                    "proofPurpose": "assertionMethod",
+                    "verificationMethod": VERIFICATION_METHOD,
+                }
+            ),
+            KEY,
+        )
+        return await asyncio.gather(
+            *(didkit.verify_credential(vc, "{}") for _ in range(8))
+        )
+
+    for result in asyncio.run(issue_verify_many()):
+        assert json.loads(result)["errors"] == []
+
+
+def test_typed_exceptions():
+    with pytest.raises(didkit.VCError):
+        asyncio.run(didkit.issue_credential(CREDENTIAL, "{}", "{}"))
+    with pytest.raises(didkit.DIDError):
+        didkit.create_did("foo")
+    assert issubclass(didkit.DIDError, didkit.DIDKitException)
@@ -1,20 +1,37 @@
 use std::ffi::CStr;
 use std::ffi::CString;
-use std::os::raw::c_char;
+use std::future::Future;
+use std::os::raw::{c_char, c_int, c_void};
 use std::ptr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
 
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+use crate::context::{Context, ContextOptions};
 use crate::error::Error;
 #[cfg(doc)]
 use crate::error::{didkit_error_code, didkit_error_message};
 use crate::get_verification_method;
+use crate::key_derive::{self, Curve};
+use crate::offline_tx::{self, UnsignedTransaction};
+use crate::oid4vp::{self, AuthorizationRequest, AuthorizationResponse};
+use crate::presentation_exchange::{self, PresentationDefinition, PresentationSubmission};
 use crate::runtime;
+use crate::zcap::{self, Revocations};
+use crate::LinkedDataProofOptions;
+use crate::ProofPreparation;
 use crate::Source;
 use crate::VerifiableCredential;
 use crate::VerifiablePresentation;
 use crate::DID_METHODS;
 use crate::JWK;
 use crate::{dereference, DereferencingInputMetadata, ResolutionInputMetadata, ResolutionResult};
+use crate::{DIDCreate, DIDDeactivate, DIDDocumentOperation, DIDUpdate};
+use crate::{Delegation, Invocation, URI};
 use crate::{JWTOrLDPOptions, ProofFormat};
+use serde_json::{Map, Value};
 
 /// The version of the DIDKit library, as a NULL-terminated string
 pub static VERSION_C: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
@@ -38,6 +55,249 @@ fn ccchar_or_error(result: Result<*const c_char, Error>) -> *const c_char {
     }
 }
 
+// Copy a C string argument
+fn string(ptr: *const c_char) -> Result<String, Error> {
+    Ok(unsafe { CStr::from_ptr(ptr) }.to_str()?.to_string())
+}
+
+// Copy a C string argument that may be NULL
+fn string_opt(ptr: *const c_char) -> Result<Option<String>, Error> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    string(ptr).map(Some)
+}
+
+// Run a future on a runtime for the current thread, blocking until it completes. The future is
+// made by a closure, so that reading its arguments can fail.
+fn block_on<F, Fut>(make_future: F) -> Result<*const c_char, Error>
+where
+    F: FnOnce() -> Result<Fut, Error>,
+    Fut: Future<Output = Result<String, Error>>,
+{
+    let future = make_future()?;
+    let rt = runtime::get()?;
+    let out = rt.block_on(future)?;
+    Ok(CString::new(out)?.into_raw())
+}
+
+/// Completion callback for the asynchronous (`_async`) DIDKit C functions.
+///
+/// The callback is called once, with the `user_data` pointer given to the asynchronous function.
+/// On success, `result` is the C string that the corresponding synchronous function would return,
+/// and `error_code` is 0 and `error_message` is `NULL`. On failure, `result` is `NULL`, and
+/// `error_code` and `error_message` are as would be returned by [`didkit_error_code`] and
+/// [`didkit_error_message`]. The strings are only valid for the duration of the callback, and
+/// should not be mutated or freed; copy them to keep them. Passing a `NULL` callback to an
+/// asynchronous function is an error.
+///
+/// # Threading
+///
+/// Asynchronous functions may be called from any thread. They return immediately, and run on a
+/// multi-threaded runtime shared by all asynchronous calls, which is started on first use. The
+/// callback is called on one of the runtime's worker threads, not on the calling thread: it should
+/// dispatch the result to the application's own thread or queue (e.g. the UI thread) rather than
+/// block, and `user_data` must be safe to use from another thread. No lock is held while the
+/// callback runs, so it may call [`didkit_task_cancel`] or wait on a thread that does. Errors of
+/// asynchronous calls are only passed to the callback; they are not stored for
+/// [`didkit_error_message`], which is per-thread.
+pub type DIDKitCallback = Option<
+    extern "C" fn(
+        user_data: *mut c_void,
+        result: *const c_char,
+        error_code: c_int,
+        error_message: *const c_char,
+    ),
+>;
+
+// States of a task: running, calling back, or cancelled before calling back
+const TASK_PENDING: u8 = 0;
+const TASK_COMPLETING: u8 = 1;
+const TASK_CANCELLED: u8 = 2;
+
+/// Handle for a call to an asynchronous DIDKit C function, used to cancel the call.
+///
+/// The handle must be freed with [`didkit_task_free`], whether or not the call has completed or
+/// been cancelled.
+pub struct DIDKitTask {
+    state: Arc<AtomicU8>,
+    handle: JoinHandle<()>,
+}
+
+// Pointer passed through to a callback, on another thread
+struct UserData(*mut c_void);
+unsafe impl Send for UserData {}
+
+// Spawn a future on the shared runtime, calling the callback with its result unless the task is
+// cancelled first. The future is made by a closure, so that reading its arguments can fail.
+fn spawn<F, Fut>(
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+    make_future: F,
+) -> Result<*mut DIDKitTask, Error>
+where
+    F: FnOnce() -> Result<Fut, Error>,
+    Fut: Future<Output = Result<String, Error>> + Send + 'static,
+{
+    let callback = callback.ok_or(Error::NullCallback)?;
+    let future = make_future()?;
+    let rt = runtime::shared()?;
+    let state = Arc::new(AtomicU8::new(TASK_PENDING));
+    let task_state = state.clone();
+    let user_data = UserData(user_data);
+    let handle = rt.spawn(async move {
+        let result = future.await;
+        // Claim the callback, unless the task was cancelled. The callback is then called without
+        // holding a lock, so that it may cancel the task itself.
+        if task_state
+            .compare_exchange(
+                TASK_PENDING,
+                TASK_COMPLETING,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            return;
+        }
+        let result: Result<CString, Error> = result.and_then(|out| Ok(CString::new(out)?));
+        match result {
+            Ok(out) => callback(user_data.0, out.as_ptr(), 0, ptr::null()),
+            Err(err) => {
+                let code = err.get_code();
+                let message = CString::new(err.to_string()).unwrap_or_default();
+                callback(user_data.0, ptr::null(), code, message.as_ptr())
+            }
+        }
+    });
+    Ok(Box::into_raw(Box::new(DIDKitTask { state, handle })))
+}
+
+fn task_or_error(result: Result<*mut DIDKitTask, Error>) -> *mut DIDKitTask {
+    // On success, pass through the task. On error, save the error for retrieval using
+    // didkit_error_message, and return NULL. The callback is then not called.
+    match result {
+        Ok(task) => task,
+        Err(error) => {
+            error.stash();
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Cancel an asynchronous call. Returns 1 if the call was cancelled, in which case its callback
+/// will not be called, or 0 if the callback has already been called or is being called. This
+/// does not wait for a running callback to return, so it may be called from the callback. The
+/// task must still be freed with [`didkit_task_free`].
+pub extern "C" fn didkit_task_cancel(task: *mut DIDKitTask) -> c_int {
+    if task.is_null() {
+        return 0;
+    }
+    let task = unsafe { &*task };
+    let cancelled = task
+        .state
+        .compare_exchange(
+            TASK_PENDING,
+            TASK_CANCELLED,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .is_ok();
+    if cancelled {
+        task.handle.abort();
+    }
+    cancelled as c_int
+}
+
+#[no_mangle]
+/// Free a task handle returned by an asynchronous DIDKit C function. Freeing a task does not
+/// cancel it: the callback is still called when the call completes.
+pub extern "C" fn didkit_task_free(task: *mut DIDKitTask) {
+    if task.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(task));
+    }
+}
+
+/// Configuration for the DIDKit C functions with a `_with_context` suffix: the DID resolver,
+/// JSON-LD context loader and caches that they use, and a runtime on which they run.
+///
+/// A context is created with [`didkit_context_new`] and freed with [`didkit_context_free`]. It
+/// may be used by calls on several threads at once, but must not be freed while in use.
+pub struct DIDKitContext {
+    pub(crate) context: Arc<Context>,
+    pub(crate) runtime: Runtime,
+}
+
+fn context_new(options_json: *const c_char) -> Result<*mut DIDKitContext, Error> {
+    let context = match string_opt(options_json)? {
+        Some(options_json) => Context::from_json(&options_json)?,
+        None => Context::default(),
+    };
+    let runtime = runtime::new_multi_thread()?;
+    Ok(Box::into_raw(Box::new(DIDKitContext {
+        context: Arc::new(context),
+        runtime,
+    })))
+}
+
+#[no_mangle]
+/// Create a context for the `_with_context` functions. The argument is a C string containing a
+/// JSON object of context options, or `NULL` for the default options:
+///
+/// - `config`: string containing a `didkit.toml` configuration of the DID methods to use
+/// - `didResolver`: URL of an HTTP(S) DID resolver for DIDs of methods that are not enabled
+/// - `contexts`: object of JSON-LD context documents by URL, added to the built-in contexts
+/// - `cacheResolutions`: boolean, whether to keep successful DID resolution results for the
+///   lifetime of the context
+///
+/// The return value is a context, to be freed with [`didkit_context_free`], or `NULL` on error, in
+/// which case the error can be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_context_new(options_json: *const c_char) -> *mut DIDKitContext {
+    match context_new(options_json) {
+        Ok(context) => context,
+        Err(error) => {
+            error.stash();
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Free a context created by [`didkit_context_new`]. The context must not be in use by a call on
+/// another thread.
+pub extern "C" fn didkit_context_free(context: *mut DIDKitContext) {
+    if context.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(context));
+    }
+}
+
+// Run a future on a context's runtime, blocking until it completes. The future is made by a
+// closure given the context's configuration. If the context is NULL, the default context is used,
+// on a runtime for the current thread.
+fn block_on_context<F, Fut>(
+    context: *const DIDKitContext,
+    make_future: F,
+) -> Result<*const c_char, Error>
+where
+    F: FnOnce(Arc<Context>) -> Result<Fut, Error>,
+    Fut: Future<Output = Result<String, Error>>,
+{
+    if context.is_null() {
+        return block_on(|| make_future(Context::shared_default()));
+    }
+    let context = unsafe { &*context };
+    let future = make_future(context.context.clone())?;
+    let out = context.runtime.block_on(future)?;
+    Ok(CString::new(out)?.into_raw())
+}
+
 // TODO: instead of having two of each function, make a procedural macro to wrap each function.  Or
 // implement std::ops::Try (nightly).
 
@@ -54,6 +314,34 @@ pub extern "C" fn didkit_vc_generate_ed25519_key() -> *const c_char {
     ccchar_or_error(generate_ed25519_key())
 }
 
+// Generate secp256k1 key
+#[cfg(feature = "secp256k1")]
+fn generate_secp256k1_key() -> Result<*const c_char, Error> {
+    let jwk = JWK::generate_secp256k1()?;
+    Ok(CString::new(serde_json::to_string(&jwk)?)?.into_raw())
+}
+/// Generate a new secp256k1 keypair in JWK format. Return value and errors are as for
+/// [`didkit_vc_generate_ed25519_key`].
+#[no_mangle]
+#[cfg(feature = "secp256k1")]
+pub extern "C" fn didkit_vc_generate_secp256k1_key() -> *const c_char {
+    ccchar_or_error(generate_secp256k1_key())
+}
+
+// Generate P-256 key
+#[cfg(feature = "secp256r1")]
+fn generate_p256_key() -> Result<*const c_char, Error> {
+    let jwk = JWK::generate_p256()?;
+    Ok(CString::new(serde_json::to_string(&jwk)?)?.into_raw())
+}
+/// Generate a new P-256 (secp256r1) keypair in JWK format. Return value and errors are as for
+/// [`didkit_vc_generate_ed25519_key`].
+#[no_mangle]
+#[cfg(feature = "secp256r1")]
+pub extern "C" fn didkit_vc_generate_p256_key() -> *const c_char {
+    ccchar_or_error(generate_p256_key())
+}
+
 // Convert JWK to did:key DID
 fn key_to_did(
     method_pattern_ptr: *const c_char,
@@ -82,13 +370,11 @@ pub extern "C" fn didkit_key_to_did(
 }
 
 // Convert JWK to did:key DID URI for verificationMethod
-fn key_to_verification_method(
-    method_pattern_ptr: *const c_char,
-    key_json_ptr: *const c_char,
-) -> Result<*const c_char, Error> {
-    let method_pattern = unsafe { CStr::from_ptr(method_pattern_ptr) }.to_str()?;
-    let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
-    let key: JWK = serde_json::from_str(key_json)?;
+pub(crate) async fn key_to_verification_method(
+    method_pattern: String,
+    key_json: String,
+) -> Result<String, Error> {
+    let key: JWK = serde_json::from_str(&key_json)?;
     let did_method = DID_METHODS
         .get(&method_pattern)
         .ok_or(Error::UnknownDIDMethod)?;
@@ -96,11 +382,10 @@ fn key_to_verification_method(
         .generate(&Source::Key(&key))
         .ok_or(Error::UnableToGenerateDID)?;
     let did_resolver = did_method.to_resolver();
-    let rt = runtime::get()?;
-    let vm = rt
-        .block_on(get_verification_method(&did, did_resolver))
+    let vm = get_verification_method(&did, did_resolver)
+        .await
         .ok_or(Error::UnableToGetVerificationMethod)?;
-    Ok(CString::new(vm)?.into_raw())
+    Ok(vm)
 }
 /// Convert a key to a `did:key` DID URI for use in the `verificationMethod` property of a linked data
 /// proof. Input should be a C string containing the key as a JWK. The JWK should contain public
@@ -111,41 +396,128 @@ pub extern "C" fn didkit_key_to_verification_method(
     method_pattern: *const c_char,
     jwk: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(key_to_verification_method(method_pattern, jwk))
+    ccchar_or_error(block_on(|| {
+        Ok(key_to_verification_method(
+            string(method_pattern)?,
+            string(jwk)?,
+        ))
+    }))
 }
 
-// Issue Credential
-fn issue_credential(
-    credential_json_ptr: *const c_char,
-    proof_options_json_ptr: *const c_char,
-    key_json_ptr: *const c_char,
+#[no_mangle]
+/// Asynchronous version of [`didkit_key_to_verification_method`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_key_to_verification_method_async(
+    method_pattern: *const c_char,
+    jwk: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(key_to_verification_method(
+            string(method_pattern)?,
+            string(jwk)?,
+        ))
+    }))
+}
+
+// Derive key from BIP-39 mnemonic
+fn key_derive_from_mnemonic(
+    mnemonic_ptr: *const c_char,
+    passphrase_ptr: *const c_char,
+    curve_ptr: *const c_char,
+    path_ptr: *const c_char,
 ) -> Result<*const c_char, Error> {
-    let resolver = DID_METHODS.to_resolver();
-    let mut context_loader = ssi::jsonld::ContextLoader::default();
-    let credential_json = unsafe { CStr::from_ptr(credential_json_ptr) }.to_str()?;
-    let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
-    let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
-    let mut credential = VerifiableCredential::from_json_unsigned(credential_json)?;
-    let key: JWK = serde_json::from_str(key_json)?;
-    let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
+    let mnemonic = unsafe { CStr::from_ptr(mnemonic_ptr) }.to_str()?;
+    let passphrase = if passphrase_ptr.is_null() {
+        ""
+    } else {
+        unsafe { CStr::from_ptr(passphrase_ptr) }.to_str()?
+    };
+    let curve: Curve = unsafe { CStr::from_ptr(curve_ptr) }.to_str()?.parse()?;
+    let path = if path_ptr.is_null() {
+        curve.default_path()
+    } else {
+        unsafe { CStr::from_ptr(path_ptr) }.to_str()?
+    };
+    let jwk = key_derive::derive_from_mnemonic(mnemonic, passphrase, curve, path)?;
+    Ok(CString::new(serde_json::to_string(&jwk)?)?.into_raw())
+}
+#[no_mangle]
+/// Derive a keypair in JWK format from a BIP-39 mnemonic phrase. `passphrase` is the optional
+/// BIP-39 passphrase and may be `NULL`. `curve` is `ed25519` (SLIP-0010) or `secp256k1` (BIP-32).
+/// `path` is a derivation path such as `m/44'/60'/0'/0/0`; if `NULL`, a default path for the curve
+/// is used. On success, returns a newly-allocated C string containing the JWK, which must be freed
+/// with [`didkit_free_string`]. On failure, returns `NULL`; the error message can be retrieved
+/// with [`didkit_error_message`].
+pub extern "C" fn didkit_key_derive_from_mnemonic(
+    mnemonic: *const c_char,
+    passphrase: *const c_char,
+    curve: *const c_char,
+    path: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(key_derive_from_mnemonic(mnemonic, passphrase, curve, path))
+}
+
+// Derive key from hex-encoded seed
+fn key_derive_from_seed(
+    seed_ptr: *const c_char,
+    curve_ptr: *const c_char,
+    path_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let seed = unsafe { CStr::from_ptr(seed_ptr) }.to_str()?;
+    let curve: Curve = unsafe { CStr::from_ptr(curve_ptr) }.to_str()?.parse()?;
+    let path = if path_ptr.is_null() {
+        curve.default_path()
+    } else {
+        unsafe { CStr::from_ptr(path_ptr) }.to_str()?
+    };
+    let jwk = key_derive::derive_from_seed_hex(seed, curve, path)?;
+    Ok(CString::new(serde_json::to_string(&jwk)?)?.into_raw())
+}
+#[no_mangle]
+/// Derive a keypair in JWK format from a hex-encoded seed. `curve` and `path` are as in
+/// [`didkit_key_derive_from_mnemonic`]. On success, returns a newly-allocated C string containing
+/// the JWK, which must be freed with [`didkit_free_string`]. On failure, returns `NULL`; the error
+/// message can be retrieved with [`didkit_error_message`].
+pub extern "C" fn didkit_key_derive_from_seed(
+    seed: *const c_char,
+    curve: *const c_char,
+    path: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(key_derive_from_seed(seed, curve, path))
+}
+
+// Issue Credential
+pub(crate) async fn issue_credential(
+    context: Arc<Context>,
+    credential_json: String,
+    proof_options_json: String,
+    key_json: String,
+) -> Result<String, Error> {
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
+    let mut credential = VerifiableCredential::from_json_unsigned(&credential_json)?;
+    let key: JWK = serde_json::from_str(&key_json)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let rt = runtime::get()?;
     let out = match proof_format {
         ProofFormat::JWT => {
-            rt.block_on(credential.generate_jwt(Some(&key), &options.ldp_options, resolver))?
+            credential
+                .generate_jwt(Some(&key), &options.ldp_options, resolver)
+                .await?
         }
         ProofFormat::LDP => {
-            let proof = rt.block_on(credential.generate_proof(
-                &key,
-                &options.ldp_options,
-                resolver,
-                &mut context_loader,
-            ))?;
+            let proof = credential
+                .generate_proof(&key, &options.ldp_options, resolver, &mut context_loader)
+                .await?;
             credential.add_proof(proof);
             serde_json::to_string(&credential)?
         }
     };
-    Ok(CString::new(out)?.into_raw())
+    Ok(out)
 }
 #[no_mangle]
 /// Issue a Verifiable Credential. Input parameters are JSON C strings for the unsigned credential
@@ -158,38 +530,180 @@ pub extern "C" fn didkit_vc_issue_credential(
     proof_options_json: *const c_char,
     key_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(issue_credential(
-        credential_json,
-        proof_options_json,
-        key_json,
-    ))
+    ccchar_or_error(block_on(|| {
+        Ok(issue_credential(
+            Context::shared_default(),
+            string(credential_json)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
 }
 
-// Verify Credential
-fn verify_credential(
-    credential_ptr: *const c_char,
-    proof_options_json_ptr: *const c_char,
-) -> Result<*const c_char, Error> {
-    let vc_str = unsafe { CStr::from_ptr(credential_ptr) }.to_str()?;
-    let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
-    let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
-    let proof_format = options.proof_format.unwrap_or_default();
-    let rt = runtime::get()?;
+#[no_mangle]
+/// Asynchronous version of [`didkit_vc_issue_credential`], with a callback and a user data pointer
+/// for the callback as additional arguments. Returns a task handle, or `NULL` if the arguments
+/// could not be read. See [`DIDKitCallback`] for how the result is passed to the callback.
+pub extern "C" fn didkit_vc_issue_credential_async(
+    credential_json: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(issue_credential(
+            Context::shared_default(),
+            string(credential_json)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Version of [`didkit_vc_issue_credential`] using a context created by [`didkit_context_new`], given as the first
+/// argument. If the context is `NULL`, the default context is used.
+pub extern "C" fn didkit_vc_issue_credential_with_context(
+    context: *const DIDKitContext,
+    credential_json: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on_context(context, |context| {
+        Ok(issue_credential(
+            context,
+            string(credential_json)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+// Prepare Credential proof for external signing
+async fn prepare_issue_credential(
+    credential_json: String,
+    proof_options_json: String,
+    public_key_json: String,
+) -> Result<String, Error> {
     let resolver = DID_METHODS.to_resolver();
     let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let credential = VerifiableCredential::from_json_unsigned(&credential_json)?;
+    let public_key: JWK = serde_json::from_str(&public_key_json)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&proof_options_json)?;
+    let preparation = credential
+        .prepare_proof(&public_key, &options, resolver, &mut context_loader)
+        .await?;
+    Ok(serde_json::to_string(&preparation)?)
+}
+#[no_mangle]
+/// Prepare a linked data proof for a Verifiable Credential, to be signed externally, e.g. by a key
+/// in a hardware keystore. Input parameters are JSON C strings for the unsigned credential, the
+/// linked data proof options, and the public key (JWK) of the signer. On success, returns a
+/// newly-allocated C string containing the proof preparation as JSON; its `signingInput` is to be
+/// signed, and the signature passed to [`didkit_vc_complete_issue_credential`]. The returned
+/// string should be freed using [`didkit_free_string`]. On failure, `NULL` is returned, and the
+/// error message can be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_vc_prepare_issue_credential(
+    credential_json: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(prepare_issue_credential(
+            string(credential_json)?,
+            string(proof_options_json)?,
+            string(public_key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_vc_prepare_issue_credential`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_vc_prepare_issue_credential_async(
+    credential_json: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(prepare_issue_credential(
+            string(credential_json)?,
+            string(proof_options_json)?,
+            string(public_key_json)?,
+        ))
+    }))
+}
+
+// Complete Credential proof from an external signature
+async fn complete_issue_credential(
+    credential_json: String,
+    preparation_json: String,
+    signature: String,
+) -> Result<String, Error> {
+    let mut credential = VerifiableCredential::from_json_unsigned(&credential_json)?;
+    let preparation: ProofPreparation = serde_json::from_str(&preparation_json)?;
+    let proof = preparation
+        .proof
+        .type_
+        .complete(&preparation, &signature)
+        .await?;
+    credential.add_proof(proof);
+    Ok(serde_json::to_string(&credential)?)
+}
+#[no_mangle]
+/// Complete issuing a Verifiable Credential with an external signature. Input parameters are C
+/// strings for the unsigned credential (JSON), the proof preparation (JSON) returned by
+/// [`didkit_vc_prepare_issue_credential`], and the signature of the preparation's signing input,
+/// in the encoding expected by the proof type (e.g. base64url for JWS-based proofs). On success,
+/// returns the verifiable credential as a newly-allocated C string, which should be freed using
+/// [`didkit_free_string`]. On failure, `NULL` is returned, and the error message can be retrieved
+/// using [`didkit_error_message`].
+pub extern "C" fn didkit_vc_complete_issue_credential(
+    credential_json: *const c_char,
+    preparation_json: *const c_char,
+    signature: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(complete_issue_credential(
+            string(credential_json)?,
+            string(preparation_json)?,
+            string(signature)?,
+        ))
+    }))
+}
+
+// Verify Credential
+pub(crate) async fn verify_credential(
+    context: Arc<Context>,
+    vc_str: String,
+    proof_options_json: String,
+) -> Result<String, Error> {
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
+    let proof_format = options.proof_format.unwrap_or_default();
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
     let result = match proof_format {
-        ProofFormat::JWT => rt.block_on(VerifiableCredential::verify_jwt(
-            &vc_str,
-            Some(options.ldp_options),
-            resolver,
-            &mut context_loader,
-        )),
+        ProofFormat::JWT => {
+            VerifiableCredential::verify_jwt(
+                &vc_str,
+                Some(options.ldp_options),
+                resolver,
+                &mut context_loader,
+            )
+            .await
+        }
         ProofFormat::LDP => {
-            let vc = VerifiableCredential::from_json_unsigned(vc_str)?;
-            rt.block_on(vc.verify(Some(options.ldp_options), resolver, &mut context_loader))
+            let vc = VerifiableCredential::from_json_unsigned(&vc_str)?;
+            vc.verify(Some(options.ldp_options), resolver, &mut context_loader)
+                .await
         }
     };
-    Ok(CString::new(serde_json::to_string(&result)?)?.into_raw())
+    Ok(serde_json::to_string(&result)?)
 }
 #[no_mangle]
 /// Verify a Verifiable Credential. Arguments are a C string containing the Verifiable Credential
@@ -205,41 +719,79 @@ pub extern "C" fn didkit_vc_verify_credential(
     credential: *const c_char,
     proof_options_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(verify_credential(credential, proof_options_json))
+    ccchar_or_error(block_on(|| {
+        Ok(verify_credential(
+            Context::shared_default(),
+            string(credential)?,
+            string(proof_options_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_vc_verify_credential`], with a callback and a user data pointer
+/// for the callback as additional arguments. Returns a task handle, or `NULL` if the arguments
+/// could not be read. See [`DIDKitCallback`] for how the result is passed to the callback.
+pub extern "C" fn didkit_vc_verify_credential_async(
+    credential: *const c_char,
+    proof_options_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(verify_credential(
+            Context::shared_default(),
+            string(credential)?,
+            string(proof_options_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Version of [`didkit_vc_verify_credential`] using a context created by [`didkit_context_new`], given as the first
+/// argument. If the context is `NULL`, the default context is used.
+pub extern "C" fn didkit_vc_verify_credential_with_context(
+    context: *const DIDKitContext,
+    credential: *const c_char,
+    proof_options_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on_context(context, |context| {
+        Ok(verify_credential(
+            context,
+            string(credential)?,
+            string(proof_options_json)?,
+        ))
+    }))
 }
 
 // Issue Presentation
-fn issue_presentation(
-    presentation_json_ptr: *const c_char,
-    proof_options_json_ptr: *const c_char,
-    key_json_ptr: *const c_char,
-) -> Result<*const c_char, Error> {
-    let resolver = DID_METHODS.to_resolver();
-    let mut context_loader = ssi::jsonld::ContextLoader::default();
-    let presentation_json = unsafe { CStr::from_ptr(presentation_json_ptr) }.to_str()?;
-    let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
-    let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
-    let mut presentation = VerifiablePresentation::from_json_unsigned(presentation_json)?;
-    let key: JWK = serde_json::from_str(key_json)?;
-    let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
+pub(crate) async fn issue_presentation(
+    context: Arc<Context>,
+    presentation_json: String,
+    proof_options_json: String,
+    key_json: String,
+) -> Result<String, Error> {
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
+    let mut presentation = VerifiablePresentation::from_json_unsigned(&presentation_json)?;
+    let key: JWK = serde_json::from_str(&key_json)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let rt = runtime::get()?;
     let out = match proof_format {
         ProofFormat::JWT => {
-            rt.block_on(presentation.generate_jwt(Some(&key), &options.ldp_options, resolver))?
+            presentation
+                .generate_jwt(Some(&key), &options.ldp_options, resolver)
+                .await?
         }
         ProofFormat::LDP => {
-            let proof = rt.block_on(presentation.generate_proof(
-                &key,
-                &options.ldp_options,
-                resolver,
-                &mut context_loader,
-            ))?;
+            let proof = presentation
+                .generate_proof(&key, &options.ldp_options, resolver, &mut context_loader)
+                .await?;
             presentation.add_proof(proof);
             serde_json::to_string(&presentation)?
         }
     };
-    Ok(CString::new(out)?.into_raw())
+    Ok(out)
 }
 #[no_mangle]
 /// Issue a Verifiable Presentation. Input parameters are JSON C strings for the unsigned
@@ -252,46 +804,175 @@ pub extern "C" fn didkit_vc_issue_presentation(
     proof_options_json: *const c_char,
     key_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(issue_presentation(
-        presentation_json,
-        proof_options_json,
-        key_json,
-    ))
+    ccchar_or_error(block_on(|| {
+        Ok(issue_presentation(
+            Context::shared_default(),
+            string(presentation_json)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
 }
 
-// Issue Presentation (DIDAuth)
-fn did_auth(
-    holder_ptr: *const c_char,
-    proof_options_json_ptr: *const c_char,
-    key_json_ptr: *const c_char,
-) -> Result<*const c_char, Error> {
+#[no_mangle]
+/// Asynchronous version of [`didkit_vc_issue_presentation`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_vc_issue_presentation_async(
+    presentation_json: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(issue_presentation(
+            Context::shared_default(),
+            string(presentation_json)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Version of [`didkit_vc_issue_presentation`] using a context created by [`didkit_context_new`], given as the first
+/// argument. If the context is `NULL`, the default context is used.
+pub extern "C" fn didkit_vc_issue_presentation_with_context(
+    context: *const DIDKitContext,
+    presentation_json: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on_context(context, |context| {
+        Ok(issue_presentation(
+            context,
+            string(presentation_json)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+// Prepare Presentation proof for external signing
+async fn prepare_issue_presentation(
+    presentation_json: String,
+    proof_options_json: String,
+    public_key_json: String,
+) -> Result<String, Error> {
     let resolver = DID_METHODS.to_resolver();
     let mut context_loader = ssi::jsonld::ContextLoader::default();
-    let holder = unsafe { CStr::from_ptr(holder_ptr) }.to_str()?;
-    let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
-    let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
+    let presentation = VerifiablePresentation::from_json_unsigned(&presentation_json)?;
+    let public_key: JWK = serde_json::from_str(&public_key_json)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&proof_options_json)?;
+    let preparation = presentation
+        .prepare_proof(&public_key, &options, resolver, &mut context_loader)
+        .await?;
+    Ok(serde_json::to_string(&preparation)?)
+}
+#[no_mangle]
+/// Prepare a linked data proof for a Verifiable Presentation, to be signed externally. Arguments
+/// and return value are as for [`didkit_vc_prepare_issue_credential`], with an unsigned
+/// presentation instead of a credential.
+pub extern "C" fn didkit_vc_prepare_issue_presentation(
+    presentation_json: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(prepare_issue_presentation(
+            string(presentation_json)?,
+            string(proof_options_json)?,
+            string(public_key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_vc_prepare_issue_presentation`], with a callback and a user
+/// data pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_vc_prepare_issue_presentation_async(
+    presentation_json: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(prepare_issue_presentation(
+            string(presentation_json)?,
+            string(proof_options_json)?,
+            string(public_key_json)?,
+        ))
+    }))
+}
+
+// Complete Presentation proof from an external signature
+async fn complete_issue_presentation(
+    presentation_json: String,
+    preparation_json: String,
+    signature: String,
+) -> Result<String, Error> {
+    let mut presentation = VerifiablePresentation::from_json_unsigned(&presentation_json)?;
+    let preparation: ProofPreparation = serde_json::from_str(&preparation_json)?;
+    let proof = preparation
+        .proof
+        .type_
+        .complete(&preparation, &signature)
+        .await?;
+    presentation.add_proof(proof);
+    Ok(serde_json::to_string(&presentation)?)
+}
+#[no_mangle]
+/// Complete issuing a Verifiable Presentation with an external signature. Arguments and return
+/// value are as for [`didkit_vc_complete_issue_credential`], with an unsigned presentation and a
+/// preparation from [`didkit_vc_prepare_issue_presentation`].
+pub extern "C" fn didkit_vc_complete_issue_presentation(
+    presentation_json: *const c_char,
+    preparation_json: *const c_char,
+    signature: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(complete_issue_presentation(
+            string(presentation_json)?,
+            string(preparation_json)?,
+            string(signature)?,
+        ))
+    }))
+}
+
+// Issue Presentation (DIDAuth)
+pub(crate) async fn did_auth(
+    context: Arc<Context>,
+    holder: String,
+    proof_options_json: String,
+    key_json: String,
+) -> Result<String, Error> {
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
     let mut presentation = VerifiablePresentation::default();
-    presentation.holder = Some(ssi::vc::URI::String(holder.to_string()));
-    let key: JWK = serde_json::from_str(key_json)?;
-    let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
+    presentation.holder = Some(ssi::vc::URI::String(holder));
+    let key: JWK = serde_json::from_str(&key_json)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let rt = runtime::get()?;
     let out = match proof_format {
         ProofFormat::JWT => {
-            rt.block_on(presentation.generate_jwt(Some(&key), &options.ldp_options, resolver))?
+            presentation
+                .generate_jwt(Some(&key), &options.ldp_options, resolver)
+                .await?
         }
         ProofFormat::LDP => {
-            let proof = rt.block_on(presentation.generate_proof(
-                &key,
-                &options.ldp_options,
-                resolver,
-                &mut context_loader,
-            ))?;
+            let proof = presentation
+                .generate_proof(&key, &options.ldp_options, resolver, &mut context_loader)
+                .await?;
             presentation.add_proof(proof);
             serde_json::to_string(&presentation)?
         }
     };
-    Ok(CString::new(out)?.into_raw())
+    Ok(out)
 }
 #[no_mangle]
 /// Issue a Verifiable Presentation for [DIDAuth](https://w3c-ccg.github.io/vp-request-spec/#did-authentication-request). Input parameters are the holder URI as a C string, and JSON C strings for the linked data proof options and the JWK for signing. On success,
@@ -303,39 +984,88 @@ pub extern "C" fn didkit_did_auth(
     proof_options_json: *const c_char,
     key_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(did_auth(holder, proof_options_json, key_json))
+    ccchar_or_error(block_on(|| {
+        Ok(did_auth(
+            Context::shared_default(),
+            string(holder)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_did_auth`], with a callback and a user data pointer for the
+/// callback as additional arguments. Returns a task handle, or `NULL` if the arguments could not be
+/// read. See [`DIDKitCallback`] for how the result is passed to the callback.
+pub extern "C" fn didkit_did_auth_async(
+    holder: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(did_auth(
+            Context::shared_default(),
+            string(holder)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Version of [`didkit_did_auth`] using a context created by [`didkit_context_new`], given as the first
+/// argument. If the context is `NULL`, the default context is used.
+pub extern "C" fn didkit_did_auth_with_context(
+    context: *const DIDKitContext,
+    holder: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on_context(context, |context| {
+        Ok(did_auth(
+            context,
+            string(holder)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
 }
 
 // Verify Presentation
-fn verify_presentation(
-    presentation_ptr: *const c_char,
-    proof_options_json_ptr: *const c_char,
-) -> Result<*const c_char, Error> {
-    let vp_str = unsafe { CStr::from_ptr(presentation_ptr) }.to_str()?;
-    let proof_options_json = unsafe { CStr::from_ptr(proof_options_json_ptr) }.to_str()?;
+pub(crate) async fn verify_presentation(
+    context: Arc<Context>,
+    vp_str: String,
+    proof_options_json: String,
+) -> Result<String, Error> {
     // TODO
-    let options: JWTOrLDPOptions = serde_json::from_str(proof_options_json)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let mut context_loader = ssi::jsonld::ContextLoader::default();
-    let rt = runtime::get()?;
-    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = context.context_loader();
+    let resolver = context.resolver();
     let result = match proof_format {
-        ProofFormat::JWT => rt.block_on(VerifiablePresentation::verify_jwt(
-            &vp_str,
-            Some(options.ldp_options),
-            resolver,
-            &mut context_loader,
-        )),
+        ProofFormat::JWT => {
+            VerifiablePresentation::verify_jwt(
+                &vp_str,
+                Some(options.ldp_options),
+                resolver,
+                &mut context_loader,
+            )
+            .await
+        }
         ProofFormat::LDP => {
-            let vp = VerifiablePresentation::from_json_unsigned(vp_str)?;
-            rt.block_on(vp.verify(
+            let vp = VerifiablePresentation::from_json_unsigned(&vp_str)?;
+            vp.verify(
                 Some(options.ldp_options),
-                DID_METHODS.to_resolver(),
+                context.resolver(),
                 &mut context_loader,
-            ))
+            )
+            .await
         }
     };
-    Ok(CString::new(serde_json::to_string(&result)?)?.into_raw())
+    Ok(serde_json::to_string(&result)?)
 }
 #[no_mangle]
 /// Verify a Verifiable Presentation. Arguments are a C string containing the Verifiable
@@ -351,31 +1081,90 @@ pub extern "C" fn didkit_vc_verify_presentation(
     presentation: *const c_char,
     proof_options_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(verify_presentation(presentation, proof_options_json))
+    ccchar_or_error(block_on(|| {
+        Ok(verify_presentation(
+            Context::shared_default(),
+            string(presentation)?,
+            string(proof_options_json)?,
+        ))
+    }))
 }
 
-// Resolve DID
-fn resolve_did(
-    did_ptr: *const c_char,
-    input_metadata_json_ptr: *const c_char,
-) -> Result<*const c_char, Error> {
-    let did = unsafe { CStr::from_ptr(did_ptr) }.to_str()?;
-    let input_metadata_json = if input_metadata_json_ptr.is_null() {
-        "{}"
-    } else {
-        unsafe { CStr::from_ptr(input_metadata_json_ptr) }.to_str()?
-    };
-    let input_metadata: ResolutionInputMetadata = serde_json::from_str(input_metadata_json)?;
-    let resolver = DID_METHODS.to_resolver();
-    let rt = runtime::get()?;
-    let (res_meta, doc_opt, doc_meta_opt) = rt.block_on(resolver.resolve(did, &input_metadata));
+#[no_mangle]
+/// Asynchronous version of [`didkit_vc_verify_presentation`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_vc_verify_presentation_async(
+    presentation: *const c_char,
+    proof_options_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(verify_presentation(
+            Context::shared_default(),
+            string(presentation)?,
+            string(proof_options_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Version of [`didkit_vc_verify_presentation`] using a context created by [`didkit_context_new`], given as the first
+/// argument. If the context is `NULL`, the default context is used.
+pub extern "C" fn didkit_vc_verify_presentation_with_context(
+    context: *const DIDKitContext,
+    presentation: *const c_char,
+    proof_options_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on_context(context, |context| {
+        Ok(verify_presentation(
+            context,
+            string(presentation)?,
+            string(proof_options_json)?,
+        ))
+    }))
+}
+
+// Get a context with DID methods configured as in a didkit.toml configuration, if one is given,
+// or the default context
+fn context_from_config(config_toml: Option<String>) -> Result<Arc<Context>, Error> {
+    match config_toml {
+        Some(config_toml) => Ok(Arc::new(Context::new(ContextOptions {
+            config: Some(config_toml),
+            ..Default::default()
+        })?)),
+        None => Ok(Context::shared_default()),
+    }
+}
+
+// Resolve DID
+pub(crate) async fn resolve_did(
+    did: String,
+    input_metadata_json: Option<String>,
+    config_toml: Option<String>,
+) -> Result<String, Error> {
+    let context = context_from_config(config_toml)?;
+    resolve_did_with_context(context, did, input_metadata_json).await
+}
+
+pub(crate) async fn resolve_did_with_context(
+    context: Arc<Context>,
+    did: String,
+    input_metadata_json: Option<String>,
+) -> Result<String, Error> {
+    let input_metadata: ResolutionInputMetadata =
+        serde_json::from_str(input_metadata_json.as_deref().unwrap_or("{}"))?;
+    let resolver = context.resolver();
+    let (res_meta, doc_opt, doc_meta_opt) = resolver.resolve(&did, &input_metadata).await;
     let result = ResolutionResult {
         did_document: doc_opt,
         did_resolution_metadata: Some(res_meta),
         did_document_metadata: doc_meta_opt,
         ..Default::default()
     };
-    Ok(CString::new(serde_json::to_string(&result)?)?.into_raw())
+    Ok(serde_json::to_string(&result)?)
 }
 
 #[no_mangle]
@@ -388,27 +1177,113 @@ pub extern "C" fn didkit_did_resolve(
     did: *const c_char,
     input_metadata_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(resolve_did(did, input_metadata_json))
+    ccchar_or_error(block_on(|| {
+        Ok(resolve_did(
+            string(did)?,
+            string_opt(input_metadata_json)?,
+            None,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_did_resolve`], with a callback and a user data pointer for the
+/// callback as additional arguments. Returns a task handle, or `NULL` if the arguments could not be
+/// read. See [`DIDKitCallback`] for how the result is passed to the callback.
+pub extern "C" fn didkit_did_resolve_async(
+    did: *const c_char,
+    input_metadata_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(resolve_did(
+            string(did)?,
+            string_opt(input_metadata_json)?,
+            None,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Resolve a DID to a DID Document, using DID methods configured as in a `didkit.toml`
+/// configuration file instead of the default DID methods. Arguments are as for
+/// [`didkit_did_resolve`], followed by a C string containing the TOML configuration. If the
+/// configuration is `NULL`, the default DID methods are used.
+pub extern "C" fn didkit_did_resolve_with_config(
+    did: *const c_char,
+    input_metadata_json: *const c_char,
+    config_toml: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(resolve_did(
+            string(did)?,
+            string_opt(input_metadata_json)?,
+            string_opt(config_toml)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_did_resolve_with_config`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_did_resolve_with_config_async(
+    did: *const c_char,
+    input_metadata_json: *const c_char,
+    config_toml: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(resolve_did(
+            string(did)?,
+            string_opt(input_metadata_json)?,
+            string_opt(config_toml)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Version of [`didkit_did_resolve`] using a context created by [`didkit_context_new`], given as the first
+/// argument. If the context is `NULL`, the default context is used.
+pub extern "C" fn didkit_did_resolve_with_context(
+    context: *const DIDKitContext,
+    did: *const c_char,
+    input_metadata_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on_context(context, |context| {
+        Ok(resolve_did_with_context(
+            context,
+            string(did)?,
+            string_opt(input_metadata_json)?,
+        ))
+    }))
 }
 
 // Dereference DID URL
-fn dereference_did_url(
-    did_url_ptr: *const c_char,
-    input_metadata_json_ptr: *const c_char,
-) -> Result<*const c_char, Error> {
-    let did_url = unsafe { CStr::from_ptr(did_url_ptr) }.to_str()?;
-    let input_metadata_json = if input_metadata_json_ptr.is_null() {
-        "{}"
-    } else {
-        unsafe { CStr::from_ptr(input_metadata_json_ptr) }.to_str()?
-    };
-    let input_metadata: DereferencingInputMetadata = serde_json::from_str(input_metadata_json)?;
-    let resolver = DID_METHODS.to_resolver();
-    let rt = runtime::get()?;
-    let deref_result = rt.block_on(dereference(resolver, did_url, &input_metadata));
+pub(crate) async fn dereference_did_url(
+    did_url: String,
+    input_metadata_json: Option<String>,
+    config_toml: Option<String>,
+) -> Result<String, Error> {
+    let context = context_from_config(config_toml)?;
+    dereference_did_url_with_context(context, did_url, input_metadata_json).await
+}
+
+pub(crate) async fn dereference_did_url_with_context(
+    context: Arc<Context>,
+    did_url: String,
+    input_metadata_json: Option<String>,
+) -> Result<String, Error> {
+    let input_metadata: DereferencingInputMetadata =
+        serde_json::from_str(input_metadata_json.as_deref().unwrap_or("{}"))?;
+    let resolver = context.resolver();
+    let deref_result = dereference(resolver, &did_url, &input_metadata).await;
     use serde_json::json;
     let result = json!(deref_result);
-    Ok(CString::new(serde_json::to_string(&result)?)?.into_raw())
+    Ok(serde_json::to_string(&result)?)
 }
 
 #[no_mangle]
@@ -421,7 +1296,1254 @@ pub extern "C" fn didkit_did_url_dereference(
     did_url: *const c_char,
     input_metadata_json: *const c_char,
 ) -> *const c_char {
-    ccchar_or_error(dereference_did_url(did_url, input_metadata_json))
+    ccchar_or_error(block_on(|| {
+        Ok(dereference_did_url(
+            string(did_url)?,
+            string_opt(input_metadata_json)?,
+            None,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_did_url_dereference`], with a callback and a user data pointer
+/// for the callback as additional arguments. Returns a task handle, or `NULL` if the arguments
+/// could not be read. See [`DIDKitCallback`] for how the result is passed to the callback.
+pub extern "C" fn didkit_did_url_dereference_async(
+    did_url: *const c_char,
+    input_metadata_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(dereference_did_url(
+            string(did_url)?,
+            string_opt(input_metadata_json)?,
+            None,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Dereference a DID URL, using DID methods configured as in a `didkit.toml` configuration file
+/// instead of the default DID methods. Arguments are as for [`didkit_did_url_dereference`],
+/// followed by a C string containing the TOML configuration. If the configuration is `NULL`, the
+/// default DID methods are used.
+pub extern "C" fn didkit_did_url_dereference_with_config(
+    did_url: *const c_char,
+    input_metadata_json: *const c_char,
+    config_toml: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(dereference_did_url(
+            string(did_url)?,
+            string_opt(input_metadata_json)?,
+            string_opt(config_toml)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_did_url_dereference_with_config`], with a callback and a user
+/// data pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_did_url_dereference_with_config_async(
+    did_url: *const c_char,
+    input_metadata_json: *const c_char,
+    config_toml: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(dereference_did_url(
+            string(did_url)?,
+            string_opt(input_metadata_json)?,
+            string_opt(config_toml)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Version of [`didkit_did_url_dereference`] using a context created by [`didkit_context_new`], given as the first
+/// argument. If the context is `NULL`, the default context is used.
+pub extern "C" fn didkit_did_url_dereference_with_context(
+    context: *const DIDKitContext,
+    did_url: *const c_char,
+    input_metadata_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on_context(context, |context| {
+        Ok(dereference_did_url_with_context(
+            context,
+            string(did_url)?,
+            string_opt(input_metadata_json)?,
+        ))
+    }))
+}
+
+// Parse an optional JWK
+fn jwk_opt(key_json_ptr: *const c_char) -> Result<Option<JWK>, Error> {
+    if key_json_ptr.is_null() {
+        return Ok(None);
+    }
+    let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
+    Ok(Some(serde_json::from_str(key_json)?))
+}
+
+// Parse optional DID method operation options
+fn did_method_options(options_json_ptr: *const c_char) -> Result<Map<String, Value>, Error> {
+    if options_json_ptr.is_null() {
+        return Ok(Map::new());
+    }
+    let options_json = unsafe { CStr::from_ptr(options_json_ptr) }.to_str()?;
+    Ok(serde_json::from_str(options_json)?)
+}
+
+// Create DID
+fn did_create(
+    method_ptr: *const c_char,
+    update_key_json_ptr: *const c_char,
+    recovery_key_json_ptr: *const c_char,
+    verification_key_json_ptr: *const c_char,
+    options_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let method_name = unsafe { CStr::from_ptr(method_ptr) }.to_str()?;
+    let method = DID_METHODS
+        .get(method_name)
+        .ok_or(Error::UnknownDIDMethod)?;
+    let tx = method.create(DIDCreate {
+        update_key: jwk_opt(update_key_json_ptr)?,
+        recovery_key: jwk_opt(recovery_key_json_ptr)?,
+        verification_key: jwk_opt(verification_key_json_ptr)?,
+        options: did_method_options(options_json_ptr)?,
+    })?;
+    Ok(CString::new(serde_json::to_string(&tx)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Build a DID Create transaction for a DID method. Arguments are C strings for the DID method name
+/// (e.g. `ion`), the update key, recovery key and verification key (JWKs, each of which may be
+/// `NULL` if the method does not need it), and DID method-specific options as a JSON object (may
+/// be `NULL`). On success, returns a newly-allocated C string containing the DID method
+/// transaction as JSON, which should be freed using [`didkit_free_string`]. On failure, `NULL` is
+/// returned, and the error message can be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_did_create(
+    method: *const c_char,
+    update_key_json: *const c_char,
+    recovery_key_json: *const c_char,
+    verification_key_json: *const c_char,
+    options_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(did_create(
+        method,
+        update_key_json,
+        recovery_key_json,
+        verification_key_json,
+        options_json,
+    ))
+}
+
+// Update DID
+fn did_update(
+    did_ptr: *const c_char,
+    update_key_json_ptr: *const c_char,
+    new_update_key_json_ptr: *const c_char,
+    operation_json_ptr: *const c_char,
+    options_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let did = unsafe { CStr::from_ptr(did_ptr) }.to_str()?;
+    let operation_json = unsafe { CStr::from_ptr(operation_json_ptr) }.to_str()?;
+    let operation: DIDDocumentOperation = serde_json::from_str(operation_json)?;
+    let method = DID_METHODS
+        .get_method(did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.update(DIDUpdate {
+        did: did.to_string(),
+        update_key: jwk_opt(update_key_json_ptr)?,
+        new_update_key: jwk_opt(new_update_key_json_ptr)?,
+        operation,
+        options: did_method_options(options_json_ptr)?,
+    })?;
+    Ok(CString::new(serde_json::to_string(&tx)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Build a DID Update transaction. Arguments are C strings for the DID, the current update key and
+/// the new update key (JWKs, may be `NULL` if the method does not need them), the DID document
+/// operation (JSON, e.g. `{"didDocumentOperation": "setDidDocument", "didDocument": {...}}`), and
+/// DID method-specific options as a JSON object (may be `NULL`). Return value and errors are as
+/// for [`didkit_did_create`].
+pub extern "C" fn didkit_did_update(
+    did: *const c_char,
+    update_key_json: *const c_char,
+    new_update_key_json: *const c_char,
+    operation_json: *const c_char,
+    options_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(did_update(
+        did,
+        update_key_json,
+        new_update_key_json,
+        operation_json,
+        options_json,
+    ))
+}
+
+// Deactivate DID
+fn did_deactivate(
+    did_ptr: *const c_char,
+    key_json_ptr: *const c_char,
+    options_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let did = unsafe { CStr::from_ptr(did_ptr) }.to_str()?;
+    let method = DID_METHODS
+        .get_method(did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.deactivate(DIDDeactivate {
+        did: did.to_string(),
+        key: jwk_opt(key_json_ptr)?,
+        options: did_method_options(options_json_ptr)?,
+    })?;
+    Ok(CString::new(serde_json::to_string(&tx)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Build a DID Deactivate transaction. Arguments are C strings for the DID, the key to perform the
+/// operation with (JWK, may be `NULL` if the method does not need it), and DID method-specific
+/// options as a JSON object (may be `NULL`). Return value and errors are as for
+/// [`didkit_did_create`].
+pub extern "C" fn didkit_did_deactivate(
+    did: *const c_char,
+    key_json: *const c_char,
+    options_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(did_deactivate(did, key_json, options_json))
+}
+
+// Prepare unsigned DID Update transaction
+fn did_prepare_update(
+    did_ptr: *const c_char,
+    update_public_key_json_ptr: *const c_char,
+    new_update_public_key_json_ptr: *const c_char,
+    operation_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let did = unsafe { CStr::from_ptr(did_ptr) }.to_str()?;
+    let update_public_key_json = unsafe { CStr::from_ptr(update_public_key_json_ptr) }.to_str()?;
+    let new_update_public_key_json =
+        unsafe { CStr::from_ptr(new_update_public_key_json_ptr) }.to_str()?;
+    let operation_json = unsafe { CStr::from_ptr(operation_json_ptr) }.to_str()?;
+    let update_public_key: JWK = serde_json::from_str(update_public_key_json)?;
+    let new_update_public_key: JWK = serde_json::from_str(new_update_public_key_json)?;
+    let operation: DIDDocumentOperation = serde_json::from_str(operation_json)?;
+    let unsigned = offline_tx::update(
+        did,
+        &update_public_key,
+        &new_update_public_key,
+        vec![operation],
+    )?;
+    Ok(CString::new(serde_json::to_string(&unsigned)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Build an unsigned DID Update transaction from public keys only, to be signed externally.
+/// Arguments are C strings for the DID, the current and new update public keys (JWKs), and the
+/// DID document operation (JSON). Supported for did:ion. On success, returns a newly-allocated C
+/// string containing the unsigned transaction as JSON; its `signingInput` is to be signed with the
+/// update key, and the signature passed to [`didkit_did_complete_tx`]. The returned string should
+/// be freed using [`didkit_free_string`]. On failure, `NULL` is returned, and the error message can
+/// be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_did_prepare_update(
+    did: *const c_char,
+    update_public_key_json: *const c_char,
+    new_update_public_key_json: *const c_char,
+    operation_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(did_prepare_update(
+        did,
+        update_public_key_json,
+        new_update_public_key_json,
+        operation_json,
+    ))
+}
+
+// Prepare unsigned DID Recover transaction
+fn did_prepare_recover(
+    did_ptr: *const c_char,
+    recovery_public_key_json_ptr: *const c_char,
+    new_recovery_public_key_json_ptr: *const c_char,
+    new_update_public_key_json_ptr: *const c_char,
+    new_verification_public_key_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let did = unsafe { CStr::from_ptr(did_ptr) }.to_str()?;
+    let recovery_public_key_json =
+        unsafe { CStr::from_ptr(recovery_public_key_json_ptr) }.to_str()?;
+    let new_recovery_public_key_json =
+        unsafe { CStr::from_ptr(new_recovery_public_key_json_ptr) }.to_str()?;
+    let new_update_public_key_json =
+        unsafe { CStr::from_ptr(new_update_public_key_json_ptr) }.to_str()?;
+    let recovery_public_key: JWK = serde_json::from_str(recovery_public_key_json)?;
+    let new_recovery_public_key: JWK = serde_json::from_str(new_recovery_public_key_json)?;
+    let new_update_public_key: JWK = serde_json::from_str(new_update_public_key_json)?;
+    let new_verification_public_key = jwk_opt(new_verification_public_key_json_ptr)?;
+    let unsigned = offline_tx::recover(
+        did,
+        &recovery_public_key,
+        &new_recovery_public_key,
+        &new_update_public_key,
+        new_verification_public_key.as_ref(),
+    )?;
+    Ok(CString::new(serde_json::to_string(&unsigned)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Build an unsigned DID Recover transaction from public keys only, to be signed externally with
+/// the recovery key. Arguments are C strings for the DID, the current and new recovery public
+/// keys, the new update public key, and the new verification public key (JWKs; the verification
+/// key may be `NULL`). The DID document is replaced with one containing only the new verification
+/// key, if any. Return value and errors are as for [`didkit_did_prepare_update`].
+pub extern "C" fn didkit_did_prepare_recover(
+    did: *const c_char,
+    recovery_public_key_json: *const c_char,
+    new_recovery_public_key_json: *const c_char,
+    new_update_public_key_json: *const c_char,
+    new_verification_public_key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(did_prepare_recover(
+        did,
+        recovery_public_key_json,
+        new_recovery_public_key_json,
+        new_update_public_key_json,
+        new_verification_public_key_json,
+    ))
+}
+
+// Prepare unsigned DID Deactivate transaction
+fn did_prepare_deactivate(
+    did_ptr: *const c_char,
+    recovery_public_key_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let did = unsafe { CStr::from_ptr(did_ptr) }.to_str()?;
+    let recovery_public_key_json =
+        unsafe { CStr::from_ptr(recovery_public_key_json_ptr) }.to_str()?;
+    let recovery_public_key: JWK = serde_json::from_str(recovery_public_key_json)?;
+    let unsigned = offline_tx::deactivate(did, &recovery_public_key)?;
+    Ok(CString::new(serde_json::to_string(&unsigned)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Build an unsigned DID Deactivate transaction from the recovery public key (JWK), to be signed
+/// externally with the recovery key. Return value and errors are as for
+/// [`didkit_did_prepare_update`].
+pub extern "C" fn didkit_did_prepare_deactivate(
+    did: *const c_char,
+    recovery_public_key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(did_prepare_deactivate(did, recovery_public_key_json))
+}
+
+// Complete unsigned DID method transaction
+fn did_complete_tx(
+    unsigned_tx_json_ptr: *const c_char,
+    signature_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let unsigned_tx_json = unsafe { CStr::from_ptr(unsigned_tx_json_ptr) }.to_str()?;
+    let signature = unsafe { CStr::from_ptr(signature_ptr) }.to_str()?;
+    let unsigned: UnsignedTransaction = serde_json::from_str(unsigned_tx_json)?;
+    let signature = base64::decode_config(signature.trim(), base64::URL_SAFE_NO_PAD)?;
+    let tx = unsigned.complete(&signature)?;
+    Ok(CString::new(serde_json::to_string(&tx)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Complete an unsigned DID method transaction with an external signature. Arguments are C strings
+/// for the unsigned transaction (JSON) returned by [`didkit_did_prepare_update`],
+/// [`didkit_did_prepare_recover`] or [`didkit_did_prepare_deactivate`], and the base64url-encoded
+/// signature of its signing input.
+/// The signature is verified against the transaction's public key. Return value and errors are as
+/// for [`didkit_did_create`].
+pub extern "C" fn didkit_did_complete_tx(
+    unsigned_tx_json: *const c_char,
+    signature: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(did_complete_tx(unsigned_tx_json, signature))
+}
+
+// Create OID4VP Authorization Request URI
+fn oid4vp_create_request(
+    request_json_ptr: *const c_char,
+    key_json_ptr: *const c_char,
+    verification_method_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let request_json = unsafe { CStr::from_ptr(request_json_ptr) }.to_str()?;
+    let request: AuthorizationRequest = serde_json::from_str(request_json)?;
+    let uri = if key_json_ptr.is_null() {
+        request.to_uri(oid4vp::DEFAULT_REQUEST_URI_BASE)?
+    } else {
+        let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
+        let verification_method = unsafe { CStr::from_ptr(verification_method_ptr) }.to_str()?;
+        let key: JWK = serde_json::from_str(key_json)?;
+        let request_object = oid4vp::sign_request_object(&request, &key, verification_method)?;
+        oid4vp::request_object_uri(
+            &request.client_id,
+            &request_object,
+            oid4vp::DEFAULT_REQUEST_URI_BASE,
+        )?
+    };
+    Ok(CString::new(uri)?.into_raw())
+}
+
+#[no_mangle]
+/// Create an [OID4VP](https://openid.net/specs/openid-4-verifiable-presentations-1_0.html)
+/// authorization request URI. Arguments are a JSON C string for the authorization request
+/// parameters, and optionally a JWK C string and a verification method DID URL C string. If a key
+/// is given, the request is signed as a request object and passed by value (`client_id_scheme`
+/// must be `did`). On success, returns a newly-allocated C string containing the URI, which should
+/// be freed using [`didkit_free_string`]. On failure, `NULL` is returned, and the error message can
+/// be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_oid4vp_create_request(
+    request_json: *const c_char,
+    key_json: *const c_char,
+    verification_method: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(oid4vp_create_request(
+        request_json,
+        key_json,
+        verification_method,
+    ))
+}
+
+// Parse OID4VP Authorization Request URI
+async fn oid4vp_parse_request(request_uri: String) -> Result<String, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let request = oid4vp::parse_request_uri(&request_uri, resolver).await?;
+    Ok(serde_json::to_string(&request)?)
+}
+
+#[no_mangle]
+/// Parse an OID4VP authorization request URI, verifying its request object if there is one. On
+/// success, returns a newly-allocated C string containing the authorization request parameters as
+/// JSON, which should be freed using [`didkit_free_string`]. On failure, `NULL` is returned, and
+/// the error message can be retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_oid4vp_parse_request(request_uri: *const c_char) -> *const c_char {
+    ccchar_or_error(block_on(|| Ok(oid4vp_parse_request(string(request_uri)?))))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_oid4vp_parse_request`], with a callback and a user data pointer
+/// for the callback as additional arguments. Returns a task handle, or `NULL` if the arguments
+/// could not be read. See [`DIDKitCallback`] for how the result is passed to the callback.
+pub extern "C" fn didkit_oid4vp_parse_request_async(
+    request_uri: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(oid4vp_parse_request(string(request_uri)?))
+    }))
+}
+
+// Create OID4VP Authorization Response
+async fn oid4vp_create_response(
+    request_json: String,
+    presentation_json: String,
+    presentation_submission_json: String,
+    proof_options_json: String,
+    key_json: String,
+) -> Result<String, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let request: AuthorizationRequest = serde_json::from_str(&request_json)?;
+    let presentation = VerifiablePresentation::from_json_unsigned(&presentation_json)?;
+    let presentation_submission = serde_json::from_str(&presentation_submission_json)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
+    let key: JWK = serde_json::from_str(&key_json)?;
+    let response = oid4vp::create_response(
+        &request,
+        presentation,
+        presentation_submission,
+        Some(&key),
+        options,
+        resolver,
+        &mut context_loader,
+        None,
+    )
+    .await?;
+    Ok(serde_json::to_string(&response)?)
+}
+
+#[no_mangle]
+/// Create an OID4VP authorization response as a wallet. Input parameters are JSON C strings for
+/// the authorization request parameters, the unsigned presentation, the presentation submission,
+/// the proof options, and the JWK for signing. The presentation proof is bound to the request's
+/// `nonce` and `client_id`. On success, returns a newly-allocated C string containing the response
+/// (`vp_token`, `presentation_submission` and `state`) as JSON, which should be freed using
+/// [`didkit_free_string`]. On failure, `NULL` is returned, and the error message can be retrieved
+/// using [`didkit_error_message`].
+pub extern "C" fn didkit_oid4vp_create_response(
+    request_json: *const c_char,
+    presentation_json: *const c_char,
+    presentation_submission_json: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(oid4vp_create_response(
+            string(request_json)?,
+            string(presentation_json)?,
+            string(presentation_submission_json)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_oid4vp_create_response`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_oid4vp_create_response_async(
+    request_json: *const c_char,
+    presentation_json: *const c_char,
+    presentation_submission_json: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(oid4vp_create_response(
+            string(request_json)?,
+            string(presentation_json)?,
+            string(presentation_submission_json)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+// Verify OID4VP Authorization Response
+async fn oid4vp_verify_response(
+    response_json: String,
+    request_json: String,
+) -> Result<String, Error> {
+    let response: AuthorizationResponse = serde_json::from_str(&response_json)?;
+    let request: AuthorizationRequest = serde_json::from_str(&request_json)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let result =
+        oid4vp::verify_response(&response, &request, resolver, &mut context_loader).await?;
+    Ok(serde_json::to_string(&result)?)
+}
+
+#[no_mangle]
+/// Verify an OID4VP authorization response against the authorization request it answers.
+/// Arguments are JSON C strings for the response and the request parameters. The return value is
+/// a newly-allocated C string containing a JSON object for the verification result, or `NULL` if
+/// the response does not match the request (state or presentation definition) or cannot be
+/// parsed; the error can then be retrieved using [`didkit_error_message`]. The verification result
+/// contains an empty "errors" array if the presentation is valid and bound to the request's nonce
+/// and client id. A string returned from this function should be freed using
+/// [`didkit_free_string`].
+pub extern "C" fn didkit_oid4vp_verify_response(
+    response_json: *const c_char,
+    request_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(oid4vp_verify_response(
+            string(response_json)?,
+            string(request_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_oid4vp_verify_response`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_oid4vp_verify_response_async(
+    response_json: *const c_char,
+    request_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(oid4vp_verify_response(
+            string(response_json)?,
+            string(request_json)?,
+        ))
+    }))
+}
+
+// Evaluate Presentation Definition
+fn presentation_exchange_evaluate(
+    definition_json_ptr: *const c_char,
+    credentials_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let definition_json = unsafe { CStr::from_ptr(definition_json_ptr) }.to_str()?;
+    let credentials_json = unsafe { CStr::from_ptr(credentials_json_ptr) }.to_str()?;
+    let definition: PresentationDefinition = serde_json::from_str(definition_json)?;
+    let credentials: Vec<Value> = serde_json::from_str(credentials_json)?;
+    let evaluation = presentation_exchange::evaluate(&definition, &credentials)?;
+    Ok(CString::new(serde_json::to_string(&evaluation)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Evaluate a DIF Presentation Exchange presentation definition against credentials. Arguments
+/// are JSON C strings for the presentation definition and an array of credentials (JSON-LD
+/// objects or JWT strings). The return value is a newly-allocated C string containing a JSON
+/// object listing the matching credentials for each input descriptor and the input descriptors
+/// selected for submission (`null` if the definition cannot be satisfied), or `NULL` on error. A
+/// string returned from this function should be freed using [`didkit_free_string`].
+pub extern "C" fn didkit_presentation_exchange_evaluate(
+    definition_json: *const c_char,
+    credentials_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(presentation_exchange_evaluate(
+        definition_json,
+        credentials_json,
+    ))
+}
+
+// Build Presentation for Presentation Definition
+fn presentation_exchange_create(
+    definition_json_ptr: *const c_char,
+    credentials_json_ptr: *const c_char,
+    holder_ptr: *const c_char,
+    proof_format_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let definition_json = unsafe { CStr::from_ptr(definition_json_ptr) }.to_str()?;
+    let credentials_json = unsafe { CStr::from_ptr(credentials_json_ptr) }.to_str()?;
+    let holder = if holder_ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(holder_ptr) }.to_str()?)
+    };
+    let proof_format = if proof_format_ptr.is_null() {
+        ProofFormat::default()
+    } else {
+        let proof_format = unsafe { CStr::from_ptr(proof_format_ptr) }.to_str()?;
+        proof_format
+            .parse()
+            .map_err(|_| Error::UnknownProofFormat(proof_format.to_string()))?
+    };
+    let definition: PresentationDefinition = serde_json::from_str(definition_json)?;
+    let credentials: Vec<Value> = serde_json::from_str(credentials_json)?;
+    let (presentation, presentation_submission) =
+        presentation_exchange::build_presentation(&definition, &credentials, holder, proof_format)?;
+    let result = serde_json::json!({
+        "presentation": presentation,
+        "presentation_submission": presentation_submission,
+    });
+    Ok(CString::new(serde_json::to_string(&result)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Select credentials satisfying a DIF Presentation Exchange presentation definition. Arguments
+/// are JSON C strings for the presentation definition and an array of credentials, an optional
+/// holder DID, and an optional proof format ("ldp" or "jwt", default "ldp") of the presentation
+/// to be issued. The return value is a newly-allocated C string containing a JSON object with the
+/// unsigned presentation (`presentation`) and its `presentation_submission`, or `NULL` on error,
+/// including if the credentials do not satisfy the definition. A string returned from this
+/// function should be freed using [`didkit_free_string`].
+pub extern "C" fn didkit_presentation_exchange_create(
+    definition_json: *const c_char,
+    credentials_json: *const c_char,
+    holder: *const c_char,
+    proof_format: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(presentation_exchange_create(
+        definition_json,
+        credentials_json,
+        holder,
+        proof_format,
+    ))
+}
+
+// Check Presentation against Presentation Definition
+fn presentation_exchange_verify(
+    definition_json_ptr: *const c_char,
+    submission_json_ptr: *const c_char,
+    presentation_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let definition_json = unsafe { CStr::from_ptr(definition_json_ptr) }.to_str()?;
+    let submission_json = unsafe { CStr::from_ptr(submission_json_ptr) }.to_str()?;
+    let presentation_str = unsafe { CStr::from_ptr(presentation_ptr) }.to_str()?;
+    let definition: PresentationDefinition = serde_json::from_str(definition_json)?;
+    let submission: PresentationSubmission = serde_json::from_str(submission_json)?;
+    let presentation = match serde_json::from_str(presentation_str) {
+        Ok(presentation) => presentation,
+        // Not JSON: a JWT VP
+        Err(_) => Value::String(presentation_str.to_string()),
+    };
+    let check = presentation_exchange::check_submission(&definition, &submission, &presentation)?;
+    Ok(CString::new(serde_json::to_string(&check)?)?.into_raw())
+}
+
+#[no_mangle]
+/// Check that a presentation satisfies a DIF Presentation Exchange presentation definition.
+/// Arguments are C strings for the presentation definition (JSON), the presentation submission
+/// (JSON) and the presentation (JSON-LD object or JWT). Proofs are not verified; use
+/// [`didkit_vc_verify_presentation`] for that. The return value is a newly-allocated C string
+/// containing a JSON object with an "errors" array, which is empty if the presentation satisfies
+/// the definition, or `NULL` on error. A string returned from this function should be freed
+/// using [`didkit_free_string`].
+pub extern "C" fn didkit_presentation_exchange_verify(
+    definition_json: *const c_char,
+    submission_json: *const c_char,
+    presentation: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(presentation_exchange_verify(
+        definition_json,
+        submission_json,
+        presentation,
+    ))
+}
+
+// Verify optional revocation lists
+async fn revocations(revocation_lists_json: Option<String>) -> Result<Revocations, Error> {
+    let lists: Vec<String> = match revocation_lists_json {
+        Some(revocation_lists_json) => serde_json::from_str(&revocation_lists_json)?,
+        None => Vec::new(),
+    };
+    Ok(Revocations::verify(&lists, DID_METHODS.to_resolver()).await?)
+}
+
+// Delegate Capability
+async fn zcap_delegate(
+    delegation_json: String,
+    proof_options_json: String,
+    capability_chain_json: String,
+    key_json: String,
+) -> Result<String, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation_json)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&proof_options_json)?;
+    let capability_chain: Vec<String> = serde_json::from_str(&capability_chain_json)?;
+    let key: JWK = serde_json::from_str(&key_json)?;
+    let proof = zcap::delegate(
+        &delegation,
+        &capability_chain,
+        Some(&key),
+        options,
+        resolver,
+        &mut context_loader,
+        None,
+    )
+    .await?;
+    Ok(serde_json::to_string(&delegation.set_proof(proof))?)
+}
+
+#[no_mangle]
+/// Delegate an authorization capability. Input parameters are JSON C strings for the capability
+/// delegation, the linked data proof options, the capability chain (an array of capability ids,
+/// starting from the root capability), and the JWK for signing. On success, returns the delegation
+/// with a `capabilityDelegation` proof as a newly-allocated C string, which should be freed using
+/// [`didkit_free_string`]. On failure, `NULL` is returned, and the error message can be retrieved
+/// using [`didkit_error_message`].
+pub extern "C" fn didkit_zcap_delegate(
+    delegation_json: *const c_char,
+    proof_options_json: *const c_char,
+    capability_chain_json: *const c_char,
+    key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(zcap_delegate(
+            string(delegation_json)?,
+            string(proof_options_json)?,
+            string(capability_chain_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_zcap_delegate`], with a callback and a user data pointer for
+/// the callback as additional arguments. Returns a task handle, or `NULL` if the arguments could
+/// not be read. See [`DIDKitCallback`] for how the result is passed to the callback.
+pub extern "C" fn didkit_zcap_delegate_async(
+    delegation_json: *const c_char,
+    proof_options_json: *const c_char,
+    capability_chain_json: *const c_char,
+    key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(zcap_delegate(
+            string(delegation_json)?,
+            string(proof_options_json)?,
+            string(capability_chain_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+// Prepare Capability Delegation proof for external signing
+async fn zcap_prepare_delegate(
+    delegation_json: String,
+    proof_options_json: String,
+    capability_chain_json: String,
+    public_key_json: String,
+) -> Result<String, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation_json)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&proof_options_json)?;
+    let capability_chain: Vec<String> = serde_json::from_str(&capability_chain_json)?;
+    let public_key: JWK = serde_json::from_str(&public_key_json)?;
+    let preparation = delegation
+        .prepare_proof(
+            &public_key,
+            &options,
+            resolver,
+            &mut context_loader,
+            &capability_chain
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<&str>>(),
+        )
+        .await?;
+    Ok(serde_json::to_string(&preparation)?)
+}
+
+#[no_mangle]
+/// Prepare a `capabilityDelegation` proof, to be signed externally. Arguments are as for
+/// [`didkit_zcap_delegate`], with the public key (JWK) of the signer instead of the private key.
+/// Return value is as for [`didkit_vc_prepare_issue_credential`]; the signature is passed to
+/// [`didkit_zcap_complete_delegate`].
+pub extern "C" fn didkit_zcap_prepare_delegate(
+    delegation_json: *const c_char,
+    proof_options_json: *const c_char,
+    capability_chain_json: *const c_char,
+    public_key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(zcap_prepare_delegate(
+            string(delegation_json)?,
+            string(proof_options_json)?,
+            string(capability_chain_json)?,
+            string(public_key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_zcap_prepare_delegate`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_zcap_prepare_delegate_async(
+    delegation_json: *const c_char,
+    proof_options_json: *const c_char,
+    capability_chain_json: *const c_char,
+    public_key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(zcap_prepare_delegate(
+            string(delegation_json)?,
+            string(proof_options_json)?,
+            string(capability_chain_json)?,
+            string(public_key_json)?,
+        ))
+    }))
+}
+
+// Complete Capability Delegation proof from an external signature
+async fn zcap_complete_delegate(
+    delegation_json: String,
+    preparation_json: String,
+    signature: String,
+) -> Result<String, Error> {
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation_json)?;
+    let preparation: ProofPreparation = serde_json::from_str(&preparation_json)?;
+    let proof = preparation
+        .proof
+        .type_
+        .complete(&preparation, &signature)
+        .await?;
+    Ok(serde_json::to_string(&delegation.set_proof(proof))?)
+}
+
+#[no_mangle]
+/// Complete a capability delegation with an external signature. Arguments and return value are as
+/// for [`didkit_vc_complete_issue_credential`], with the capability delegation and a preparation
+/// from [`didkit_zcap_prepare_delegate`].
+pub extern "C" fn didkit_zcap_complete_delegate(
+    delegation_json: *const c_char,
+    preparation_json: *const c_char,
+    signature: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(zcap_complete_delegate(
+            string(delegation_json)?,
+            string(preparation_json)?,
+            string(signature)?,
+        ))
+    }))
+}
+
+// Verify Capability Delegation
+async fn zcap_verify_delegation(
+    delegation_json: String,
+    revocation_lists_json: Option<String>,
+) -> Result<String, Error> {
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation_json)?;
+    let revocations = revocations(revocation_lists_json).await?;
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let result = zcap::verify_delegation(
+        &delegation,
+        None,
+        &revocations,
+        chrono::Utc::now(),
+        DID_METHODS.to_resolver(),
+        &mut context_loader,
+    )
+    .await;
+    Ok(serde_json::to_string(&result)?)
+}
+
+#[no_mangle]
+/// Verify a capability delegation, including its caveats. Arguments are C strings for the
+/// delegation (JSON) and an optional JSON array of revocation lists (JWSs, as returned by
+/// [`didkit_zcap_revoke`]), which may be `NULL`. The return value is a newly-allocated C string
+/// containing a JSON object for the verification result, or `NULL` on error, as for
+/// [`didkit_vc_verify_credential`].
+pub extern "C" fn didkit_zcap_verify_delegation(
+    delegation_json: *const c_char,
+    revocation_lists_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(zcap_verify_delegation(
+            string(delegation_json)?,
+            string_opt(revocation_lists_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_zcap_verify_delegation`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_zcap_verify_delegation_async(
+    delegation_json: *const c_char,
+    revocation_lists_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(zcap_verify_delegation(
+            string(delegation_json)?,
+            string_opt(revocation_lists_json)?,
+        ))
+    }))
+}
+
+// Invoke Capability
+async fn zcap_invoke(
+    invocation_json: String,
+    capability_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
+    proof_options_json: String,
+    key_json: String,
+) -> Result<String, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation_json)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&proof_options_json)?;
+    let key: JWK = serde_json::from_str(&key_json)?;
+    let proof = zcap::invoke(
+        &invocation,
+        &URI::String(capability_id),
+        action.as_deref(),
+        invocation_target.as_deref(),
+        Some(&key),
+        options,
+        resolver,
+        &mut context_loader,
+        None,
+    )
+    .await?;
+    Ok(serde_json::to_string(&invocation.set_proof(proof))?)
+}
+
+#[no_mangle]
+/// Invoke an authorization capability. Input parameters are C strings for the invocation (JSON),
+/// the id of the capability being invoked, the action and the target URI of the invocation (each
+/// may be `NULL`), the linked data proof options (JSON), and the JWK for signing. The action and
+/// target are included in the proof as `capabilityAction` and `invocationTarget`, to be checked
+/// against the capability's caveats. On success, returns the invocation with a
+/// `capabilityInvocation` proof as a newly-allocated C string, which should be freed using
+/// [`didkit_free_string`]. On failure, `NULL` is returned, and the error message can be retrieved
+/// using [`didkit_error_message`].
+pub extern "C" fn didkit_zcap_invoke(
+    invocation_json: *const c_char,
+    capability_id: *const c_char,
+    action: *const c_char,
+    invocation_target: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(zcap_invoke(
+            string(invocation_json)?,
+            string(capability_id)?,
+            string_opt(action)?,
+            string_opt(invocation_target)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_zcap_invoke`], with a callback and a user data pointer for the
+/// callback as additional arguments. Returns a task handle, or `NULL` if the arguments could not be
+/// read. See [`DIDKitCallback`] for how the result is passed to the callback.
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn didkit_zcap_invoke_async(
+    invocation_json: *const c_char,
+    capability_id: *const c_char,
+    action: *const c_char,
+    invocation_target: *const c_char,
+    proof_options_json: *const c_char,
+    key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(zcap_invoke(
+            string(invocation_json)?,
+            string(capability_id)?,
+            string_opt(action)?,
+            string_opt(invocation_target)?,
+            string(proof_options_json)?,
+            string(key_json)?,
+        ))
+    }))
+}
+
+// Prepare Capability Invocation proof for external signing
+async fn zcap_prepare_invoke(
+    invocation_json: String,
+    capability_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
+    proof_options_json: String,
+    public_key_json: String,
+) -> Result<String, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation_json)?;
+    let options: LinkedDataProofOptions = serde_json::from_str(&proof_options_json)?;
+    let public_key: JWK = serde_json::from_str(&public_key_json)?;
+    let preparation = zcap::prepare_invoke(
+        &invocation,
+        &URI::String(capability_id),
+        action.as_deref(),
+        invocation_target.as_deref(),
+        &public_key,
+        options,
+        resolver,
+        &mut context_loader,
+    )
+    .await?;
+    Ok(serde_json::to_string(&preparation)?)
+}
+
+#[no_mangle]
+/// Prepare a `capabilityInvocation` proof, to be signed externally. Arguments are as for
+/// [`didkit_zcap_invoke`], with the public key (JWK) of the signer instead of the private key.
+/// Return value is as for [`didkit_vc_prepare_issue_credential`]; the signature is passed to
+/// [`didkit_zcap_complete_invoke`].
+pub extern "C" fn didkit_zcap_prepare_invoke(
+    invocation_json: *const c_char,
+    capability_id: *const c_char,
+    action: *const c_char,
+    invocation_target: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(zcap_prepare_invoke(
+            string(invocation_json)?,
+            string(capability_id)?,
+            string_opt(action)?,
+            string_opt(invocation_target)?,
+            string(proof_options_json)?,
+            string(public_key_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_zcap_prepare_invoke`], with a callback and a user data pointer
+/// for the callback as additional arguments. Returns a task handle, or `NULL` if the arguments
+/// could not be read. See [`DIDKitCallback`] for how the result is passed to the callback.
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn didkit_zcap_prepare_invoke_async(
+    invocation_json: *const c_char,
+    capability_id: *const c_char,
+    action: *const c_char,
+    invocation_target: *const c_char,
+    proof_options_json: *const c_char,
+    public_key_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(zcap_prepare_invoke(
+            string(invocation_json)?,
+            string(capability_id)?,
+            string_opt(action)?,
+            string_opt(invocation_target)?,
+            string(proof_options_json)?,
+            string(public_key_json)?,
+        ))
+    }))
+}
+
+// Complete Capability Invocation proof from an external signature
+async fn zcap_complete_invoke(
+    invocation_json: String,
+    preparation_json: String,
+    signature: String,
+) -> Result<String, Error> {
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation_json)?;
+    let preparation: ProofPreparation = serde_json::from_str(&preparation_json)?;
+    let proof = preparation
+        .proof
+        .type_
+        .complete(&preparation, &signature)
+        .await?;
+    Ok(serde_json::to_string(&invocation.set_proof(proof))?)
+}
+
+#[no_mangle]
+/// Complete a capability invocation with an external signature. Arguments and return value are as
+/// for [`didkit_vc_complete_issue_credential`], with the invocation and a preparation from
+/// [`didkit_zcap_prepare_invoke`].
+pub extern "C" fn didkit_zcap_complete_invoke(
+    invocation_json: *const c_char,
+    preparation_json: *const c_char,
+    signature: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(zcap_complete_invoke(
+            string(invocation_json)?,
+            string(preparation_json)?,
+            string(signature)?,
+        ))
+    }))
+}
+
+// Verify Capability Invocation
+async fn zcap_verify_invocation(
+    invocation_json: String,
+    delegation_json: Option<String>,
+    revocation_lists_json: Option<String>,
+) -> Result<String, Error> {
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation_json)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let result = match delegation_json {
+        None => {
+            invocation
+                .verify_signature(None, resolver, &mut context_loader)
+                .await
+        }
+        Some(delegation_json) => {
+            let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation_json)?;
+            let revocations = revocations(revocation_lists_json).await?;
+            zcap::verify_invocation(
+                &invocation,
+                &delegation,
+                None,
+                &revocations,
+                chrono::Utc::now(),
+                resolver,
+                &mut context_loader,
+            )
+            .await
+        }
+    };
+    Ok(serde_json::to_string(&result)?)
+}
+
+#[no_mangle]
+/// Verify a capability invocation. Arguments are C strings for the invocation (JSON), the
+/// delegation of the invoked capability (JSON), and an optional JSON array of revocation lists as
+/// for [`didkit_zcap_verify_delegation`]. If the delegation is `NULL`, only the invocation's
+/// signature is verified; otherwise the invocation is checked against the capability's invokers
+/// and caveats. The return value is as for [`didkit_zcap_verify_delegation`].
+pub extern "C" fn didkit_zcap_verify_invocation(
+    invocation_json: *const c_char,
+    delegation_json: *const c_char,
+    revocation_lists_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(block_on(|| {
+        Ok(zcap_verify_invocation(
+            string(invocation_json)?,
+            string_opt(delegation_json)?,
+            string_opt(revocation_lists_json)?,
+        ))
+    }))
+}
+
+#[no_mangle]
+/// Asynchronous version of [`didkit_zcap_verify_invocation`], with a callback and a user data
+/// pointer for the callback as additional arguments. Returns a task handle, or `NULL` if the
+/// arguments could not be read. See [`DIDKitCallback`] for how the result is passed to the
+/// callback.
+pub extern "C" fn didkit_zcap_verify_invocation_async(
+    invocation_json: *const c_char,
+    delegation_json: *const c_char,
+    revocation_lists_json: *const c_char,
+    callback: DIDKitCallback,
+    user_data: *mut c_void,
+) -> *mut DIDKitTask {
+    task_or_error(spawn(callback, user_data, || {
+        Ok(zcap_verify_invocation(
+            string(invocation_json)?,
+            string_opt(delegation_json)?,
+            string_opt(revocation_lists_json)?,
+        ))
+    }))
+}
+
+// Revoke Capabilities
+fn zcap_revoke(
+    ids_json_ptr: *const c_char,
+    verification_method_ptr: *const c_char,
+    key_json_ptr: *const c_char,
+) -> Result<*const c_char, Error> {
+    let ids_json = unsafe { CStr::from_ptr(ids_json_ptr) }.to_str()?;
+    let verification_method = unsafe { CStr::from_ptr(verification_method_ptr) }.to_str()?;
+    let key_json = unsafe { CStr::from_ptr(key_json_ptr) }.to_str()?;
+    let ids: Vec<String> = serde_json::from_str(ids_json)?;
+    let key: JWK = serde_json::from_str(key_json)?;
+    let jws = zcap::sign_revocation_list(ids, verification_method, &key, chrono::Utc::now())?;
+    Ok(CString::new(jws)?.into_raw())
+}
+
+#[no_mangle]
+/// Revoke capabilities. Arguments are C strings for a JSON array of the ids of the capabilities to
+/// revoke, a verification method of the revoking DID, and the JWK for signing. On success, returns
+/// the signed revocation list (a compact JWS) as a newly-allocated C string, which should be freed
+/// using [`didkit_free_string`]. On failure, `NULL` is returned, and the error message can be
+/// retrieved using [`didkit_error_message`].
+pub extern "C" fn didkit_zcap_revoke(
+    ids_json: *const c_char,
+    verification_method: *const c_char,
+    key_json: *const c_char,
+) -> *const c_char {
+    ccchar_or_error(zcap_revoke(ids_json, verification_method, key_json))
 }
 
 #[no_mangle]
@@ -439,6 +2561,8 @@ pub extern "C" fn didkit_free_string(string: *const c_char) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::AtomicPtr;
+    use std::sync::{mpsc, Mutex};
 
     #[test]
     fn get_version() {
@@ -447,4 +2571,126 @@ mod tests {
         let version_str = version_cstr.to_str().unwrap();
         assert!(version_str.len() > 0);
     }
+
+    extern "C" fn send_result(
+        user_data: *mut c_void,
+        result: *const c_char,
+        _error_code: c_int,
+        error_message: *const c_char,
+    ) {
+        let sender = unsafe { &*(user_data as *const Mutex<mpsc::Sender<Result<String, String>>>) };
+        let result = if result.is_null() {
+            Err(unsafe { CStr::from_ptr(error_message) }
+                .to_str()
+                .unwrap()
+                .to_string())
+        } else {
+            Ok(unsafe { CStr::from_ptr(result) }
+                .to_str()
+                .unwrap()
+                .to_string())
+        };
+        sender.lock().unwrap().send(result).unwrap();
+    }
+
+    #[test]
+    fn resolve_async() {
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let user_data = &sender as *const _ as *mut c_void;
+        let did = CString::new("did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp").unwrap();
+        let task =
+            didkit_did_resolve_async(did.as_ptr(), ptr::null(), Some(send_result), user_data);
+        assert!(!task.is_null());
+        let result = receiver.recv().unwrap().unwrap();
+        assert!(result.contains("\"didDocument\":{"));
+        didkit_task_free(task);
+
+        let did = CString::new("did:example:nope").unwrap();
+        let task =
+            didkit_did_resolve_async(did.as_ptr(), ptr::null(), Some(send_result), user_data);
+        let result = receiver.recv().unwrap().unwrap();
+        assert!(result.contains("\"error\""));
+        didkit_task_cancel(task);
+        didkit_task_free(task);
+
+        let config = CString::new("not toml").unwrap();
+        let task = didkit_did_resolve_with_config_async(
+            did.as_ptr(),
+            ptr::null(),
+            config.as_ptr(),
+            Some(send_result),
+            user_data,
+        );
+        assert!(receiver.recv().unwrap().is_err());
+        didkit_task_free(task);
+
+        let task = didkit_did_resolve_async(did.as_ptr(), ptr::null(), None, user_data);
+        assert!(task.is_null());
+    }
+
+    struct CancelInCallback {
+        task: AtomicPtr<DIDKitTask>,
+        sender: Mutex<mpsc::Sender<c_int>>,
+    }
+
+    extern "C" fn cancel_in_callback(
+        user_data: *mut c_void,
+        _result: *const c_char,
+        _error_code: c_int,
+        _error_message: *const c_char,
+    ) {
+        let data = unsafe { &*(user_data as *const CancelInCallback) };
+        let task = loop {
+            let task = data.task.load(Ordering::Acquire);
+            if !task.is_null() {
+                break task;
+            }
+            std::thread::yield_now();
+        };
+        // The callback is already running, so this neither cancels nor waits for it.
+        let cancelled = didkit_task_cancel(task);
+        data.sender.lock().unwrap().send(cancelled).unwrap();
+    }
+
+    #[test]
+    fn cancel_from_callback() {
+        let (sender, receiver) = mpsc::channel();
+        let data = CancelInCallback {
+            task: AtomicPtr::new(ptr::null_mut()),
+            sender: Mutex::new(sender),
+        };
+        let user_data = &data as *const _ as *mut c_void;
+        let did = CString::new("did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp").unwrap();
+        let task = didkit_did_resolve_async(
+            did.as_ptr(),
+            ptr::null(),
+            Some(cancel_in_callback),
+            user_data,
+        );
+        assert!(!task.is_null());
+        data.task.store(task, Ordering::Release);
+        assert_eq!(receiver.recv().unwrap(), 0);
+        assert_eq!(didkit_task_cancel(task), 0);
+        didkit_task_free(task);
+    }
+
+    #[test]
+    fn resolve_with_context() {
+        let options = CString::new("{\"cacheResolutions\": true}").unwrap();
+        let context = didkit_context_new(options.as_ptr());
+        assert!(!context.is_null());
+        let did = CString::new("did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp").unwrap();
+        for _ in 0..2 {
+            let result = didkit_did_resolve_with_context(context, did.as_ptr(), ptr::null());
+            assert!(!result.is_null());
+            let result_str = unsafe { CStr::from_ptr(result) }.to_str().unwrap();
+            assert!(result_str.contains("\"didDocument\":{"));
+            didkit_free_string(result);
+        }
+        didkit_context_free(context);
+
+        let options = CString::new("{\"foo\": 1}").unwrap();
+        assert!(didkit_context_new(options.as_ptr()).is_null());
+    }
 }
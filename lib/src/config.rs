@@ -0,0 +1,129 @@
+//! DIDKit configuration file (`didkit.toml`).
+//!
+//! ```toml
+//! [did-methods]
+//! disable = ["onion"]
+//!
+//! [did-methods.ion]
+//! api-url = "https://ion.example.org/"
+//!
+//! [did-methods.tz]
+//! indexer-url = "https://api.tzkt.io"
+//! ```
+//!
+//! `[did-methods.ethr] rpc-url` is reserved for an Ethereum JSON-RPC endpoint. did:ethr DIDs are
+//! currently resolved without querying the ERC-1056 registry, so setting it is an error while
+//! did:ethr is enabled.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use ssi::did::DIDMethods;
+use thiserror::Error;
+
+use crate::did_methods::{DIDMethodRegistry, DIDMethodRegistryError, DIDMethodsConfig};
+
+/// Conventional file name of the configuration file
+pub const CONFIG_FILE_NAME: &str = "didkit.toml";
+
+/// Maximum number of distinct configurations whose DID methods are kept by
+/// [`configured_did_methods`]
+pub const MAX_CONFIGURED_DID_METHODS: usize = 16;
+
+lazy_static! {
+    static ref CONFIGURED_DID_METHODS: Mutex<HashMap<String, &'static DIDMethods<'static>>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Unable to read configuration file: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Invalid configuration: {0}")]
+    TOML(#[from] toml::de::Error),
+    #[error(transparent)]
+    DIDMethodRegistry(#[from] DIDMethodRegistryError),
+    #[error("Too many distinct DID method configurations (at most {0})")]
+    TooManyConfigurations(usize),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub did_methods: DIDMethodsConfig,
+}
+
+impl Config {
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Build the DID methods enabled by this configuration
+    pub fn did_methods(&self) -> Result<DIDMethods<'static>, ConfigError> {
+        Ok(DIDMethodRegistry::from_config(self.did_methods.clone()).build()?)
+    }
+}
+
+/// Get the DID methods enabled by a configuration, given as TOML. The DID methods are built on
+/// first use of each distinct configuration, and kept for the rest of the process, so that they
+/// can be shared by calls on other threads. At most [`MAX_CONFIGURED_DID_METHODS`] distinct
+/// configurations are kept; configurations differing only in formatting count once.
+pub fn configured_did_methods(
+    config_toml: &str,
+) -> Result<&'static DIDMethods<'static>, ConfigError> {
+    let config = Config::from_toml(config_toml)?;
+    let key = format!("{:?}", config);
+    let mut configured = CONFIGURED_DID_METHODS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+    if let Some(did_methods) = configured.get(&key) {
+        return Ok(did_methods);
+    }
+    if configured.len() >= MAX_CONFIGURED_DID_METHODS {
+        return Err(ConfigError::TooManyConfigurations(
+            MAX_CONFIGURED_DID_METHODS,
+        ));
+    }
+    let did_methods: &'static DIDMethods<'static> = Box::leak(Box::new(config.did_methods()?));
+    configured.insert(key, did_methods);
+    Ok(did_methods)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let config = Config::from_toml(
+            r#"
+            [did-methods]
+            disable = ["onion"]
+
+            [did-methods.ion]
+            api-url = "https://ion.example.org/"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.did_methods.disable, ["onion"]);
+        assert_eq!(
+            config.did_methods.ion.api_url.as_deref(),
+            Some("https://ion.example.org/")
+        );
+        let methods = config.did_methods().unwrap();
+        assert!(methods.get("onion").is_none());
+        assert!(Config::from_toml("[did-methods]\nfoo = 1").is_err());
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+        let methods = configured_did_methods("[did-methods]\ndisable = [\"onion\"]").unwrap();
+        assert!(methods.get("onion").is_none());
+        let shared = configured_did_methods("[did-methods]\ndisable = [ \"onion\" ]").unwrap();
+        assert!(std::ptr::eq(methods, shared));
+    }
+}
@@ -0,0 +1,256 @@
+//! Configuration shared by a series of calls: DID resolution, JSON-LD context loading and caches.
+//!
+//! The functions of the FFI bindings use the [default context](Context::shared_default), with the
+//! DID methods of [`DID_METHODS`](crate::DID_METHODS) and the built-in JSON-LD contexts. Their
+//! `with_context` variants take a context created from [`ContextOptions`], given as JSON:
+//!
+//! ```json
+//! {
+//!   "config": "[did-methods]\ndisable = [\"onion\"]",
+//!   "didResolver": "https://resolver.example.org/1.0/identifiers/",
+//!   "contexts": {
+//!     "https://example.org/vocab/v1": {"@context": {"@vocab": "https://example.org/vocab#"}}
+//!   },
+//!   "cacheResolutions": true
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ssi::did::{DIDMethods, Document, PrimaryDIDURL};
+use ssi::did_resolve::{
+    Content, ContentMetadata, DIDResolver, DereferencingInputMetadata, DereferencingMetadata,
+    DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata,
+};
+#[cfg(feature = "http-did")]
+use ssi::did_resolve::{HTTPDIDResolver, ERROR_METHOD_NOT_SUPPORTED};
+use ssi::jsonld::{ContextLoader, FromContextMapError};
+use thiserror::Error;
+
+use crate::config::{configured_did_methods, ConfigError};
+use crate::DID_METHODS;
+
+lazy_static! {
+    static ref DEFAULT_CONTEXT: Arc<Context> = Arc::new(Context::default());
+}
+
+#[derive(Error, Debug)]
+pub enum ContextError {
+    #[error("Invalid context options: {0}")]
+    Options(#[from] serde_json::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("Invalid JSON-LD context: {0}")]
+    Contexts(#[from] FromContextMapError),
+    #[error("HTTP DID resolution is not supported by this build")]
+    HTTPDIDResolverUnsupported,
+    #[error("Context has been closed")]
+    Closed,
+}
+
+/// Options for creating a [`Context`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ContextOptions {
+    /// DID methods configuration, as the TOML of a [`didkit.toml`](crate::config) file. The DID
+    /// methods of each distinct configuration are kept for the rest of the process, see
+    /// [`configured_did_methods`].
+    pub config: Option<String>,
+    /// URL of an HTTP(S) DID resolver, used for DIDs of methods that are not enabled
+    pub did_resolver: Option<String>,
+    /// JSON-LD context documents to load by URL, in addition to the built-in contexts
+    #[serde(default)]
+    pub contexts: HashMap<String, Value>,
+    /// Keep successful DID resolution results for the lifetime of the context
+    #[serde(default)]
+    pub cache_resolutions: bool,
+}
+
+type Resolution = (
+    ResolutionMetadata,
+    Option<Document>,
+    Option<DocumentMetadata>,
+);
+
+/// DID resolver and JSON-LD context loader configuration, shared by a series of calls
+pub struct Context {
+    /// DID methods of the context's configuration, if any, instead of the default DID methods
+    did_methods: Option<&'static DIDMethods<'static>>,
+    #[cfg(feature = "http-did")]
+    did_resolver: Option<HTTPDIDResolver>,
+    context_loader: ContextLoader,
+    resolutions: Option<Mutex<HashMap<String, Resolution>>>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            did_methods: None,
+            #[cfg(feature = "http-did")]
+            did_resolver: None,
+            context_loader: ContextLoader::default(),
+            resolutions: None,
+        }
+    }
+}
+
+impl Context {
+    pub fn new(options: ContextOptions) -> Result<Self, ContextError> {
+        let did_methods = match options.config {
+            Some(config_toml) => Some(configured_did_methods(&config_toml)?),
+            None => None,
+        };
+        #[cfg(feature = "http-did")]
+        let did_resolver = options.did_resolver.as_deref().map(HTTPDIDResolver::new);
+        #[cfg(not(feature = "http-did"))]
+        if options.did_resolver.is_some() {
+            return Err(ContextError::HTTPDIDResolverUnsupported);
+        }
+        let mut context_loader = ContextLoader::default();
+        if !options.contexts.is_empty() {
+            let contexts = options
+                .contexts
+                .into_iter()
+                .map(|(url, context)| Ok((url, serde_json::to_string(&context)?)))
+                .collect::<Result<HashMap<String, String>, serde_json::Error>>()?;
+            context_loader = context_loader.with_context_map_from(contexts)?;
+        }
+        Ok(Self {
+            did_methods,
+            #[cfg(feature = "http-did")]
+            did_resolver,
+            context_loader,
+            resolutions: if options.cache_resolutions {
+                Some(Mutex::new(HashMap::new()))
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Create a context from [`ContextOptions`] given as JSON
+    pub fn from_json(options_json: &str) -> Result<Self, ContextError> {
+        Self::new(serde_json::from_str(options_json)?)
+    }
+
+    /// Context with the default DID methods and JSON-LD contexts, and no caches
+    pub fn shared_default() -> Arc<Self> {
+        DEFAULT_CONTEXT.clone()
+    }
+
+    fn did_methods(&self) -> &DIDMethods<'static> {
+        match self.did_methods {
+            Some(did_methods) => did_methods,
+            None => &*DID_METHODS,
+        }
+    }
+
+    /// Get the DID resolver of this context
+    pub fn resolver(&self) -> &dyn DIDResolver {
+        self
+    }
+
+    /// Get a JSON-LD context loader for a call, sharing the contexts of this context
+    pub fn context_loader(&self) -> ContextLoader {
+        self.context_loader.clone()
+    }
+
+    async fn resolve_uncached(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> Resolution {
+        let resolution = self.did_methods().resolve(did, input_metadata).await;
+        #[cfg(feature = "http-did")]
+        if let Some(did_resolver) = &self.did_resolver {
+            if resolution.0.error.as_deref() == Some(ERROR_METHOD_NOT_SUPPORTED) {
+                return did_resolver.resolve(did, input_metadata).await;
+            }
+        }
+        resolution
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for Context {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let resolutions = match &self.resolutions {
+            Some(resolutions) => resolutions,
+            None => return self.resolve_uncached(did, input_metadata).await,
+        };
+        let key = format!(
+            "{} {}",
+            did,
+            serde_json::to_string(input_metadata).unwrap_or_default()
+        );
+        if let Some(resolution) = resolutions
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(&key)
+        {
+            return resolution.clone();
+        }
+        let resolution = self.resolve_uncached(did, input_metadata).await;
+        if resolution.0.error.is_none() {
+            resolutions
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .insert(key, resolution.clone());
+        }
+        resolution
+    }
+
+    async fn dereference(
+        &self,
+        primary_did_url: &PrimaryDIDURL,
+        input_metadata: &DereferencingInputMetadata,
+    ) -> Option<(DereferencingMetadata, Content, ContentMetadata)> {
+        self.did_methods()
+            .dereference(primary_did_url, input_metadata)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn options() {
+        let context = Context::from_json(
+            &json!({
+                "config": "[did-methods]\ndisable = [\"onion\"]",
+                "contexts": {
+                    "https://example.org/vocab/v1": {
+                        "@context": {"@vocab": "https://example.org/vocab#"}
+                    }
+                },
+                "cacheResolutions": true
+            })
+            .to_string(),
+        )
+        .unwrap();
+        assert!(context.did_methods().get("onion").is_none());
+        assert!(context.resolutions.is_some());
+        assert!(Context::from_json("{\"foo\": 1}").is_err());
+        assert!(Context::from_json("{\"config\": \"[did-methods]\\nfoo = 1\"}").is_err());
+        assert!(Arc::ptr_eq(
+            &Context::shared_default(),
+            &Context::shared_default()
+        ));
+    }
+}
@@ -0,0 +1,623 @@
+//! Local editing of DID documents.
+//!
+//! Applies [DID document operations](DIDDocumentOperation) directly to a DID document, for DID
+//! methods such as did:web where the document is published as a file rather than through DID
+//! method transactions. Also compares DID documents, and plans the operations that update a DID
+//! document to a target DID document.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ssi::did::{Service, VerificationMethodMap, DIDURL};
+use thiserror::Error;
+
+use crate::{DIDDocumentOperation, Document, VerificationRelationship};
+
+/// Verification relationship properties of a DID document
+pub const VERIFICATION_RELATIONSHIPS: [&str; 5] = [
+    "authentication",
+    "assertionMethod",
+    "keyAgreement",
+    "capabilityInvocation",
+    "capabilityDelegation",
+];
+
+#[derive(Error, Debug)]
+pub enum DIDDocumentError {
+    #[error("Operation is for {0} but the DID document is for {1}")]
+    DIDMismatch(String, String),
+    #[error("DID document must be a JSON object")]
+    NotAnObject,
+    #[error("Unable to remove missing {0}: {1}")]
+    NotFound(&'static str, String),
+    #[error("Cannot change the DID document id")]
+    IdChange,
+    #[error("Unsupported verification relationship: {0:?}")]
+    UnsupportedRelationship(VerificationRelationship),
+    #[error("Unable to plan a reference to a verification method not in the DID document: {0}")]
+    ExternalReference(String),
+    #[error("Invalid DID URL: {0}")]
+    InvalidDIDURL(String),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+pub(crate) fn relationship_property(
+    relationship: &VerificationRelationship,
+) -> Result<&'static str, DIDDocumentError> {
+    Ok(match relationship {
+        VerificationRelationship::Authentication => "authentication",
+        VerificationRelationship::AssertionMethod => "assertionMethod",
+        VerificationRelationship::KeyAgreement => "keyAgreement",
+        VerificationRelationship::CapabilityInvocation => "capabilityInvocation",
+        VerificationRelationship::CapabilityDelegation => "capabilityDelegation",
+        _ => {
+            return Err(DIDDocumentError::UnsupportedRelationship(
+                relationship.clone(),
+            ))
+        }
+    })
+}
+
+fn property_relationship(property: &str) -> Option<VerificationRelationship> {
+    Some(match property {
+        "authentication" => VerificationRelationship::Authentication,
+        "assertionMethod" => VerificationRelationship::AssertionMethod,
+        "keyAgreement" => VerificationRelationship::KeyAgreement,
+        "capabilityInvocation" => VerificationRelationship::CapabilityInvocation,
+        "capabilityDelegation" => VerificationRelationship::CapabilityDelegation,
+        _ => return None,
+    })
+}
+
+/// Resolve a possibly relative DID URL (e.g. `#key-1`) against a DID
+pub fn absolute_id(did: &str, id: &str) -> String {
+    if id.starts_with('#') || id.starts_with('?') || id.starts_with('/') {
+        format!("{did}{id}")
+    } else {
+        id.to_string()
+    }
+}
+
+/// Get the id of an entry in a verification method, verification relationship or service array
+pub(crate) fn entry_id(entry: &Value) -> Option<&str> {
+    match entry {
+        Value::String(id) => Some(id),
+        Value::Object(object) => object.get("id").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+/// Remove entries with the given id from an array property, returning whether any were removed.
+/// The property is removed if it becomes empty.
+fn remove_entries(object: &mut Map<String, Value>, property: &str, did: &str, id: &str) -> bool {
+    let mut removed = false;
+    if let Some(Value::Array(entries)) = object.get_mut(property) {
+        let len = entries.len();
+        entries.retain(|entry| {
+            entry_id(entry)
+                .map(|entry_id| absolute_id(did, entry_id) != id)
+                .unwrap_or(true)
+        });
+        removed = entries.len() != len;
+        if entries.is_empty() {
+            object.remove(property);
+        }
+    }
+    removed
+}
+
+fn push_entry(object: &mut Map<String, Value>, property: &str, entry: Value) {
+    match object.get_mut(property) {
+        Some(Value::Array(entries)) => entries.push(entry),
+        Some(value) => {
+            let existing = value.take();
+            *value = Value::Array(vec![existing, entry]);
+        }
+        None => {
+            object.insert(property.to_string(), Value::Array(vec![entry]));
+        }
+    }
+}
+
+/// Apply a DID document operation to a DID document
+///
+/// Setting a verification method or service replaces any existing one with the same id. Setting a
+/// verification method also replaces its verification relationships with the given purposes.
+pub fn apply_operation(
+    document: &Document,
+    operation: DIDDocumentOperation,
+) -> Result<Document, DIDDocumentError> {
+    let did = document.id.clone();
+    let mut value = serde_json::to_value(document)?;
+    let object = value.as_object_mut().ok_or(DIDDocumentError::NotAnObject)?;
+    match operation {
+        DIDDocumentOperation::SetDidDocument(new_document) => {
+            if new_document.id != did {
+                return Err(DIDDocumentError::DIDMismatch(new_document.id, did));
+            }
+            return Ok(new_document);
+        }
+        DIDDocumentOperation::AddToDidDocument(properties) => {
+            for (name, property) in properties {
+                if name == "id" {
+                    return Err(DIDDocumentError::IdChange);
+                }
+                object.insert(name, property);
+            }
+        }
+        DIDDocumentOperation::RemoveFromDidDocument(names) => {
+            for name in names {
+                if name == "id" {
+                    return Err(DIDDocumentError::IdChange);
+                }
+                object.remove(&name);
+            }
+        }
+        DIDDocumentOperation::SetVerificationMethod { vmm, purposes } => {
+            let id = absolute_id(&did, &vmm.id);
+            remove_entries(object, "verificationMethod", &did, &id);
+            for property in VERIFICATION_RELATIONSHIPS {
+                remove_entries(object, property, &did, &id);
+            }
+            push_entry(object, "verificationMethod", serde_json::to_value(vmm)?);
+            for purpose in purposes {
+                let property = relationship_property(&purpose)?;
+                push_entry(object, property, Value::String(id.clone()));
+            }
+        }
+        DIDDocumentOperation::SetService(service) => {
+            let id = absolute_id(&did, &service.id);
+            remove_entries(object, "service", &did, &id);
+            push_entry(object, "service", serde_json::to_value(service)?);
+        }
+        DIDDocumentOperation::RemoveVerificationMethod(did_url) => {
+            let id = did_url.to_string();
+            let mut removed = remove_entries(object, "verificationMethod", &did, &id);
+            for property in VERIFICATION_RELATIONSHIPS {
+                removed |= remove_entries(object, property, &did, &id);
+            }
+            if !removed {
+                return Err(DIDDocumentError::NotFound("verification method", id));
+            }
+        }
+        DIDDocumentOperation::RemoveService(did_url) => {
+            let id = did_url.to_string();
+            if !remove_entries(object, "service", &did, &id) {
+                return Err(DIDDocumentError::NotFound("service", id));
+            }
+        }
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// A difference between two JSON values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub op: ChangeOp,
+    /// JSON Pointer to the changed value, in the new document for additions and in the old
+    /// document otherwise
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOp {
+    Add,
+    Remove,
+    Replace,
+}
+
+fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn diff_value(old: &Value, new: &Value, path: &str, did: &str, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old_object), Value::Object(new_object)) => {
+            for (name, old_value) in old_object {
+                let path = format!("{path}/{}", escape_pointer(name));
+                match new_object.get(name) {
+                    Some(new_value) => diff_value(old_value, new_value, &path, did, changes),
+                    None => changes.push(Change {
+                        op: ChangeOp::Remove,
+                        path,
+                        old: Some(old_value.clone()),
+                        new: None,
+                    }),
+                }
+            }
+            for (name, new_value) in new_object {
+                if !old_object.contains_key(name) {
+                    changes.push(Change {
+                        op: ChangeOp::Add,
+                        path: format!("{path}/{}", escape_pointer(name)),
+                        old: None,
+                        new: Some(new_value.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(old_entries), Value::Array(new_entries))
+            if old_entries
+                .iter()
+                .chain(new_entries)
+                .all(|entry| entry_id(entry).is_some()) =>
+        {
+            // Verification methods, verification relationships and services are matched by id,
+            // so that reordering or inserting entries shows only the entries that changed.
+            let id = |entry: &Value| absolute_id(did, entry_id(entry).unwrap_or_default());
+            for (i, old_entry) in old_entries.iter().enumerate() {
+                let path = format!("{path}/{i}");
+                match new_entries.iter().find(|entry| id(entry) == id(old_entry)) {
+                    Some(new_entry) => diff_value(old_entry, new_entry, &path, did, changes),
+                    None => changes.push(Change {
+                        op: ChangeOp::Remove,
+                        path,
+                        old: Some(old_entry.clone()),
+                        new: None,
+                    }),
+                }
+            }
+            for (i, new_entry) in new_entries.iter().enumerate() {
+                if !old_entries.iter().any(|entry| id(entry) == id(new_entry)) {
+                    changes.push(Change {
+                        op: ChangeOp::Add,
+                        path: format!("{path}/{i}"),
+                        old: None,
+                        new: Some(new_entry.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(old_entries), Value::Array(new_entries)) => {
+            for (i, (old_entry, new_entry)) in old_entries.iter().zip(new_entries).enumerate() {
+                diff_value(old_entry, new_entry, &format!("{path}/{i}"), did, changes);
+            }
+            for (i, old_entry) in old_entries.iter().enumerate().skip(new_entries.len()) {
+                changes.push(Change {
+                    op: ChangeOp::Remove,
+                    path: format!("{path}/{i}"),
+                    old: Some(old_entry.clone()),
+                    new: None,
+                });
+            }
+            for (i, new_entry) in new_entries.iter().enumerate().skip(old_entries.len()) {
+                changes.push(Change {
+                    op: ChangeOp::Add,
+                    path: format!("{path}/{i}"),
+                    old: None,
+                    new: Some(new_entry.clone()),
+                });
+            }
+        }
+        _ => {
+            if old != new {
+                changes.push(Change {
+                    op: ChangeOp::Replace,
+                    path: path.to_string(),
+                    old: Some(old.clone()),
+                    new: Some(new.clone()),
+                });
+            }
+        }
+    }
+}
+
+/// Compare two DID documents
+///
+/// Entries of arrays whose entries all have ids (such as verification methods and services) are
+/// matched by id, with relative ids resolved against the DID of the old document. Other arrays
+/// are compared by position.
+pub fn diff(old: &Document, new: &Document) -> Result<Vec<Change>, DIDDocumentError> {
+    let old_value = serde_json::to_value(old)?;
+    let new_value = serde_json::to_value(new)?;
+    let mut changes = Vec::new();
+    diff_value(&old_value, &new_value, "", &old.id, &mut changes);
+    Ok(changes)
+}
+
+/// Planned update of a DID document, as output by `didkit did plan`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePlan {
+    pub did: String,
+    pub operations: Vec<DIDDocumentOperation>,
+}
+
+/// Verification methods of a DID document by absolute id, with their verification relationship
+/// properties
+fn verification_methods(
+    did: &str,
+    object: &Map<String, Value>,
+) -> Result<BTreeMap<String, (Value, BTreeSet<&'static str>)>, DIDDocumentError> {
+    let mut vms = BTreeMap::new();
+    if let Some(Value::Array(entries)) = object.get("verificationMethod") {
+        for vm in entries {
+            if let Some(id) = entry_id(vm) {
+                vms.insert(absolute_id(did, id), (vm.clone(), BTreeSet::new()));
+            }
+        }
+    }
+    for property in VERIFICATION_RELATIONSHIPS {
+        let entries: Vec<&Value> = match object.get(property) {
+            Some(Value::Array(entries)) => entries.iter().collect(),
+            Some(entry) => vec![entry],
+            None => continue,
+        };
+        for entry in entries {
+            let id = match entry_id(entry) {
+                Some(id) => absolute_id(did, id),
+                None => continue,
+            };
+            if entry.is_object() {
+                // Embedded verification method
+                vms.entry(id.clone())
+                    .or_insert_with(|| (entry.clone(), BTreeSet::new()));
+            }
+            match vms.get_mut(&id) {
+                Some((_, properties)) => {
+                    properties.insert(property);
+                }
+                None => return Err(DIDDocumentError::ExternalReference(id)),
+            }
+        }
+    }
+    Ok(vms)
+}
+
+fn services(did: &str, object: &Map<String, Value>) -> BTreeMap<String, Value> {
+    let mut services = BTreeMap::new();
+    if let Some(Value::Array(entries)) = object.get("service") {
+        for service in entries {
+            if let Some(id) = entry_id(service) {
+                services.insert(absolute_id(did, id), service.clone());
+            }
+        }
+    }
+    services
+}
+
+/// Compare entries with their ids made absolute, so that relative and absolute ids are equal
+fn same_entry(did: &str, a: &Value, b: &Value) -> bool {
+    let normalize = |entry: &Value| {
+        let mut entry = entry.clone();
+        if let Some(id) = entry_id(&entry).map(|id| absolute_id(did, id)) {
+            entry["id"] = Value::String(id);
+        }
+        entry
+    };
+    normalize(a) == normalize(b)
+}
+
+fn did_url(id: String) -> Result<DIDURL, DIDDocumentError> {
+    DIDURL::try_from(id.clone()).map_err(|_| DIDDocumentError::InvalidDIDURL(id))
+}
+
+/// Plan the DID document operations that update a DID document to a target DID document
+///
+/// Removals come first, then verification methods and services to set, then other properties to
+/// set. Verification methods are planned together with their verification relationships, so
+/// verification methods embedded in a verification relationship are set as verification methods
+/// referenced from that relationship. Applying the plan with [`apply_operation`] results in a DID
+/// document equivalent to the target.
+pub fn plan(
+    current: &Document,
+    target: &Document,
+) -> Result<Vec<DIDDocumentOperation>, DIDDocumentError> {
+    let did = current.id.clone();
+    if target.id != did {
+        return Err(DIDDocumentError::DIDMismatch(target.id.clone(), did));
+    }
+    let current_value = serde_json::to_value(current)?;
+    let target_value = serde_json::to_value(target)?;
+    let current_object = current_value
+        .as_object()
+        .ok_or(DIDDocumentError::NotAnObject)?;
+    let target_object = target_value
+        .as_object()
+        .ok_or(DIDDocumentError::NotAnObject)?;
+    let current_vms = verification_methods(&did, current_object)?;
+    let target_vms = verification_methods(&did, target_object)?;
+    let current_services = services(&did, current_object);
+    let target_services = services(&did, target_object);
+
+    let mut removals = Vec::new();
+    let mut sets = Vec::new();
+    for id in current_services.keys() {
+        if !target_services.contains_key(id) {
+            removals.push(DIDDocumentOperation::RemoveService(did_url(id.clone())?));
+        }
+    }
+    for id in current_vms.keys() {
+        if !target_vms.contains_key(id) {
+            removals.push(DIDDocumentOperation::RemoveVerificationMethod(did_url(
+                id.clone(),
+            )?));
+        }
+    }
+    for (id, (vm, properties)) in &target_vms {
+        let unchanged = match current_vms.get(id) {
+            Some((current_vm, current_properties)) => {
+                same_entry(&did, current_vm, vm) && current_properties == properties
+            }
+            None => false,
+        };
+        if !unchanged {
+            let vmm: VerificationMethodMap = serde_json::from_value(vm.clone())?;
+            let purposes = properties
+                .iter()
+                .filter_map(|property| property_relationship(property))
+                .collect();
+            sets.push(DIDDocumentOperation::SetVerificationMethod { vmm, purposes });
+        }
+    }
+    for (id, service) in &target_services {
+        let unchanged = match current_services.get(id) {
+            Some(current_service) => same_entry(&did, current_service, service),
+            None => false,
+        };
+        if !unchanged {
+            let service: Service = serde_json::from_value(service.clone())?;
+            sets.push(DIDDocumentOperation::SetService(service));
+        }
+    }
+
+    // Other properties are set and removed as a whole.
+    let managed = |name: &str| {
+        name == "id"
+            || name == "verificationMethod"
+            || name == "service"
+            || VERIFICATION_RELATIONSHIPS.contains(&name)
+    };
+    let removed_properties: Vec<String> = current_object
+        .keys()
+        .filter(|name| !managed(name) && !target_object.contains_key(*name))
+        .cloned()
+        .collect();
+    if !removed_properties.is_empty() {
+        removals.push(DIDDocumentOperation::RemoveFromDidDocument(
+            removed_properties,
+        ));
+    }
+    let set_properties: Map<String, Value> = target_object
+        .iter()
+        .filter(|(name, value)| !managed(name) && current_object.get(*name) != Some(*value))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    if !set_properties.is_empty() {
+        sets.push(DIDDocumentOperation::AddToDidDocument(
+            set_properties.into_iter().collect(),
+        ));
+    }
+    removals.append(&mut sets);
+    Ok(removals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_remove() {
+        let document: Document = serde_json::from_value(serde_json::json!({
+            "@context": "https://www.w3.org/ns/did/v1",
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "#key-1",
+                "type": "JsonWebKey2020",
+                "controller": "did:web:example.com",
+                "publicKeyJwk": {"kty": "OKP", "crv": "Ed25519", "x": "G80iskrv_nE69qbGLSpeOHJgmV4MKIzsy5l5iT6pCww"}
+            }],
+            "assertionMethod": ["#key-1"]
+        }))
+        .unwrap();
+        let vmm = VerificationMethodMap {
+            id: "did:web:example.com#key-2".to_string(),
+            type_: "JsonWebKey2020".to_string(),
+            controller: "did:web:example.com".to_string(),
+            ..Default::default()
+        };
+        let document = apply_operation(
+            &document,
+            DIDDocumentOperation::SetVerificationMethod {
+                vmm,
+                purposes: vec![VerificationRelationship::Authentication],
+            },
+        )
+        .unwrap();
+        let service = Service {
+            id: "#hub".to_string(),
+            type_: ssi::vc::OneOrMany::One("Hub".to_string()),
+            service_endpoint: None,
+            property_set: None,
+        };
+        let document =
+            apply_operation(&document, DIDDocumentOperation::SetService(service)).unwrap();
+        let key_1 = DIDURL::try_from("did:web:example.com#key-1".to_string()).unwrap();
+        let document = apply_operation(
+            &document,
+            DIDDocumentOperation::RemoveVerificationMethod(key_1.clone()),
+        )
+        .unwrap();
+        let value = serde_json::to_value(&document).unwrap();
+        assert_eq!(value["verificationMethod"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            value["authentication"],
+            serde_json::json!(["did:web:example.com#key-2"])
+        );
+        assert!(value.get("assertionMethod").is_none());
+        assert_eq!(value["service"][0]["id"], "#hub");
+        assert!(apply_operation(
+            &document,
+            DIDDocumentOperation::RemoveVerificationMethod(key_1)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn plan_and_diff() {
+        let current: Document = serde_json::from_value(serde_json::json!({
+            "@context": "https://www.w3.org/ns/did/v1",
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "#key-1",
+                "type": "JsonWebKey2020",
+                "controller": "did:web:example.com",
+                "publicKeyJwk": {"kty": "OKP", "crv": "Ed25519", "x": "G80iskrv_nE69qbGLSpeOHJgmV4MKIzsy5l5iT6pCww"}
+            }],
+            "assertionMethod": ["#key-1"],
+            "service": [{
+                "id": "#hub",
+                "type": "Hub",
+                "serviceEndpoint": "https://hub.example.com/"
+            }]
+        }))
+        .unwrap();
+        let target: Document = serde_json::from_value(serde_json::json!({
+            "@context": "https://www.w3.org/ns/did/v1",
+            "id": "did:web:example.com",
+            "alsoKnownAs": ["https://example.com/"],
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-1",
+                "type": "JsonWebKey2020",
+                "controller": "did:web:example.com",
+                "publicKeyJwk": {"kty": "OKP", "crv": "Ed25519", "x": "G80iskrv_nE69qbGLSpeOHJgmV4MKIzsy5l5iT6pCww"}
+            }],
+            "assertionMethod": ["did:web:example.com#key-1"],
+            "authentication": ["did:web:example.com#key-1"]
+        }))
+        .unwrap();
+        let operations = plan(&current, &target).unwrap();
+        assert_eq!(operations.len(), 3);
+        assert!(matches!(
+            operations[0],
+            DIDDocumentOperation::RemoveService(_)
+        ));
+        let mut document = current.clone();
+        for operation in operations {
+            document = apply_operation(&document, operation).unwrap();
+        }
+        assert!(plan(&document, &target).unwrap().is_empty());
+
+        let changes = diff(&current, &target).unwrap();
+        let paths: Vec<&str> = changes.iter().map(|change| change.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/assertionMethod/0",
+                "/service",
+                "/verificationMethod/0/id",
+                "/alsoKnownAs",
+                "/authentication"
+            ]
+        );
+        assert!(diff(&current, &current).unwrap().is_empty());
+    }
+}
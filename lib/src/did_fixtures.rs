@@ -0,0 +1,279 @@
+//! Fixture-backed DID resolution.
+//!
+//! Serves DID documents from local files instead of their DID methods' registries or networks, so
+//! that tests can verify credentials against pinned DID document versions without a network.
+//!
+//! Each fixture is a JSON file containing either a DID document or a DID resolution result (with
+//! `didDocument` and `didDocumentMetadata` properties). Fixtures are keyed by the DID document id
+//! and by the `versionId` in the document metadata. The document metadata `updated` (or `created`)
+//! time is used to select a version for a `versionTime` resolution option, and to find the latest
+//! version when no version is requested.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use serde_json::Value;
+use ssi::did::Document;
+use ssi::did_resolve::{
+    DIDResolver, DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata,
+    ERROR_METHOD_NOT_SUPPORTED, ERROR_NOT_FOUND, TYPE_DID_LD_JSON,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FixtureError {
+    #[error("Unable to read fixture {0}: {1}")]
+    IO(PathBuf, std::io::Error),
+    #[error("Unable to parse fixture {0}: {1}")]
+    Json(PathBuf, serde_json::Error),
+    #[error("Fixture is missing a DID document")]
+    MissingDocument,
+    #[error("Invalid {0} in fixture: {1}")]
+    InvalidTime(&'static str, chrono::ParseError),
+    #[error("Duplicate fixture for {0} version {1}")]
+    Duplicate(String, String),
+    #[error(transparent)]
+    Document(#[from] serde_json::Error),
+}
+
+/// A version of a DID document
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub document: Document,
+    pub document_metadata: DocumentMetadata,
+    pub version_id: Option<String>,
+    pub time: Option<DateTime<FixedOffset>>,
+}
+
+impl Fixture {
+    /// Parse a fixture from a DID document or DID resolution result
+    pub fn from_value(mut value: Value) -> Result<Self, FixtureError> {
+        let (document, metadata) = match value.get_mut("didDocument") {
+            Some(document) => {
+                let document = document.take();
+                let metadata = value
+                    .get_mut("didDocumentMetadata")
+                    .map(Value::take)
+                    .unwrap_or_default();
+                (document, metadata)
+            }
+            None => (value, Value::Null),
+        };
+        if document.is_null() {
+            return Err(FixtureError::MissingDocument);
+        }
+        let version_id = match metadata.get("versionId") {
+            Some(Value::String(version_id)) => Some(version_id.clone()),
+            Some(Value::Number(version_id)) => Some(version_id.to_string()),
+            _ => None,
+        };
+        let mut time = None;
+        for property in ["created", "updated"] {
+            if let Some(value) = metadata.get(property).and_then(Value::as_str) {
+                let value = DateTime::parse_from_rfc3339(value)
+                    .map_err(|err| FixtureError::InvalidTime(property, err))?;
+                time = Some(value);
+            }
+        }
+        let document_metadata = if metadata.is_null() {
+            DocumentMetadata::default()
+        } else {
+            serde_json::from_value(metadata)?
+        };
+        Ok(Self {
+            document: serde_json::from_value(document)?,
+            document_metadata,
+            version_id,
+            time,
+        })
+    }
+}
+
+/// DID resolver serving DID documents from fixtures
+///
+/// DIDs without fixtures are reported as not supported, so that a
+/// [`SeriesResolver`](ssi::did_resolve::SeriesResolver) can fall back to other resolvers for them.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureResolver {
+    fixtures: HashMap<String, Vec<Fixture>>,
+}
+
+impl FixtureResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load fixtures from the `.json` files in a directory and its subdirectories
+    pub fn load(dir: &Path) -> Result<Self, FixtureError> {
+        let mut resolver = Self::new();
+        resolver.load_dir(dir)?;
+        Ok(resolver)
+    }
+
+    fn load_dir(&mut self, dir: &Path) -> Result<(), FixtureError> {
+        let read_dir = std::fs::read_dir(dir).map_err(|e| FixtureError::IO(dir.to_owned(), e))?;
+        let mut paths = Vec::new();
+        for entry in read_dir {
+            let entry = entry.map_err(|e| FixtureError::IO(dir.to_owned(), e))?;
+            paths.push(entry.path());
+        }
+        paths.sort();
+        for path in paths {
+            if path.is_dir() {
+                self.load_dir(&path)?;
+            } else if path.extension().map_or(false, |ext| ext == "json") {
+                let data = std::fs::read(&path).map_err(|e| FixtureError::IO(path.clone(), e))?;
+                let value: Value = serde_json::from_slice(&data)
+                    .map_err(|e| FixtureError::Json(path.clone(), e))?;
+                let fixture = match Fixture::from_value(value) {
+                    Ok(fixture) => fixture,
+                    Err(FixtureError::Document(err)) => return Err(FixtureError::Json(path, err)),
+                    Err(err) => return Err(err),
+                };
+                self.insert(fixture)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a DID document version
+    pub fn insert(&mut self, fixture: Fixture) -> Result<(), FixtureError> {
+        let versions = self
+            .fixtures
+            .entry(fixture.document.id.clone())
+            .or_default();
+        if let Some(version_id) = &fixture.version_id {
+            if versions
+                .iter()
+                .any(|version| version.version_id.as_ref() == Some(version_id))
+            {
+                return Err(FixtureError::Duplicate(
+                    fixture.document.id,
+                    version_id.to_string(),
+                ));
+            }
+        }
+        versions.push(fixture);
+        // Stable sort: versions without a time stay first, in insertion order.
+        versions.sort_by_key(|version| version.time);
+        Ok(())
+    }
+
+    /// Select the DID document version for the given resolution options
+    ///
+    /// Returns the error code for a DID resolution result if there is no such version.
+    pub fn select(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> Result<&Fixture, String> {
+        let versions = self
+            .fixtures
+            .get(did)
+            .ok_or_else(|| ERROR_METHOD_NOT_SUPPORTED.to_string())?;
+        let mut candidates: Vec<&Fixture> = versions.iter().collect();
+        if let Some(version_id) = &input_metadata.version_id {
+            candidates.retain(|version| version.version_id.as_ref() == Some(version_id));
+        }
+        if let Some(version_time) = &input_metadata.version_time {
+            let version_time = DateTime::parse_from_rfc3339(version_time)
+                .map_err(|err| format!("Invalid versionTime: {err}"))?;
+            candidates.retain(|version| match version.time {
+                Some(time) => time <= version_time,
+                None => false,
+            });
+        }
+        candidates
+            .last()
+            .copied()
+            .ok_or_else(|| ERROR_NOT_FOUND.to_string())
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for FixtureResolver {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        match self.select(did, input_metadata) {
+            Ok(fixture) => (
+                ResolutionMetadata {
+                    content_type: Some(TYPE_DID_LD_JSON.to_string()),
+                    ..Default::default()
+                },
+                Some(fixture.document.clone()),
+                Some(fixture.document_metadata.clone()),
+            ),
+            Err(err) => (ResolutionMetadata::from_error(&err), None, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn version(version_id: &str, updated: &str, key: &str) -> Fixture {
+        Fixture::from_value(json!({
+            "didDocument": {
+                "@context": "https://www.w3.org/ns/did/v1",
+                "id": "did:example:123",
+                "assertionMethod": [key]
+            },
+            "didDocumentMetadata": {
+                "versionId": version_id,
+                "updated": updated
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn select_version() {
+        let mut resolver = FixtureResolver::new();
+        resolver
+            .insert(version("2", "2022-01-01T00:00:00Z", "#key-2"))
+            .unwrap();
+        resolver
+            .insert(version("1", "2021-01-01T00:00:00Z", "#key-1"))
+            .unwrap();
+        assert!(resolver
+            .insert(version("1", "2021-06-01T00:00:00Z", "#key-3"))
+            .is_err());
+        let select = |version_id: Option<&str>, version_time: Option<&str>| {
+            let input_metadata = ResolutionInputMetadata {
+                version_id: version_id.map(String::from),
+                version_time: version_time.map(String::from),
+                ..Default::default()
+            };
+            resolver
+                .select("did:example:123", &input_metadata)
+                .map(|fixture| fixture.version_id.clone().unwrap())
+        };
+        assert_eq!(select(None, None).unwrap(), "2");
+        assert_eq!(select(Some("1"), None).unwrap(), "1");
+        assert_eq!(select(None, Some("2021-07-01T00:00:00Z")).unwrap(), "1");
+        assert_eq!(
+            select(None, Some("2020-01-01T00:00:00Z")),
+            Err(ERROR_NOT_FOUND.to_string())
+        );
+        assert_eq!(select(Some("3"), None), Err(ERROR_NOT_FOUND.to_string()));
+        assert!(select(None, Some("yesterday")).is_err());
+        assert_eq!(
+            resolver
+                .select("did:example:456", &ResolutionInputMetadata::default())
+                .unwrap_err(),
+            ERROR_METHOD_NOT_SUPPORTED
+        );
+    }
+}
@@ -0,0 +1,690 @@
+//! DID document linting.
+//!
+//! Checks a DID document for conformance with [DID Core](https://www.w3.org/TR/did-core/) and for
+//! common mistakes in verification methods and services. The document is checked as JSON, so that
+//! documents which cannot be parsed as a [`Document`] can still be linted.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use ssi::did::{Document, DEFAULT_CONTEXT};
+use ssi::jwk::JWK;
+
+use crate::did_document::{absolute_id, VERIFICATION_RELATIONSHIPS};
+
+lazy_static! {
+    static ref DID_REGEX: Regex =
+        Regex::new(r"^did:[a-z0-9]+:(?:(?:[A-Za-z0-9._\-]|%[0-9A-Fa-f]{2})*:)*(?:[A-Za-z0-9._\-]|%[0-9A-Fa-f]{2})+$")
+            .unwrap();
+}
+
+/// Public key properties of verification methods
+const PUBLIC_KEY_PROPERTIES: [&str; 6] = [
+    "publicKeyJwk",
+    "publicKeyMultibase",
+    "publicKeyBase58",
+    "publicKeyHex",
+    "blockchainAccountId",
+    "ethereumAddress",
+];
+
+/// Public key properties expected for known verification method types
+const VERIFICATION_METHOD_TYPES: [(&str, &[&str]); 10] = [
+    ("JsonWebKey2020", &["publicKeyJwk"]),
+    ("Multikey", &["publicKeyMultibase"]),
+    (
+        "Ed25519VerificationKey2018",
+        &["publicKeyBase58", "publicKeyJwk"],
+    ),
+    ("Ed25519VerificationKey2020", &["publicKeyMultibase"]),
+    ("X25519KeyAgreementKey2019", &["publicKeyBase58"]),
+    ("X25519KeyAgreementKey2020", &["publicKeyMultibase"]),
+    (
+        "EcdsaSecp256k1VerificationKey2019",
+        &["publicKeyJwk", "publicKeyHex", "publicKeyBase58"],
+    ),
+    (
+        "EcdsaSecp256k1RecoveryMethod2020",
+        &[
+            "blockchainAccountId",
+            "ethereumAddress",
+            "publicKeyJwk",
+            "publicKeyHex",
+        ],
+    ),
+    ("Bls12381G2Key2020", &["publicKeyBase58"]),
+    ("P256Key2021", &["publicKeyJwk", "publicKeyMultibase"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A lint finding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Short identifier of the check, e.g. `duplicate-id`
+    pub code: &'static str,
+    pub message: String,
+    /// JSON Pointer to the offending value in the DID document
+    pub path: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] {}: {}",
+            self.severity, self.code, self.path, self.message
+        )
+    }
+}
+
+/// Whether a string is a syntactically valid DID (without path, query or fragment)
+pub fn is_valid_did(did: &str) -> bool {
+    DID_REGEX.is_match(did)
+}
+
+/// Whether a string is a syntactically valid DID URL, with a valid DID before any path, query or
+/// fragment
+pub fn is_valid_did_url(did_url: &str) -> bool {
+    let end = did_url.find(|c| c == '/' || c == '?' || c == '#');
+    let did = &did_url[..end.unwrap_or(did_url.len())];
+    is_valid_did(did)
+}
+
+struct Linter {
+    did: String,
+    findings: Vec<Finding>,
+    /// Absolute ids of verification methods and services, with the path where each is defined
+    ids: HashMap<String, String>,
+    /// Absolute ids of verification methods, which verification relationships may reference
+    verification_method_ids: HashSet<String>,
+}
+
+impl Linter {
+    fn push(&mut self, severity: Severity, code: &'static str, path: &str, message: String) {
+        self.findings.push(Finding {
+            severity,
+            code,
+            message,
+            path: path.to_string(),
+        });
+    }
+
+    fn check_context(&mut self, object: &Map<String, Value>) {
+        let first = match object.get("@context") {
+            None => {
+                return self.push(
+                    Severity::Warning,
+                    "missing-context",
+                    "",
+                    "Missing @context, required for the JSON-LD representation".to_string(),
+                )
+            }
+            Some(Value::Array(contexts)) => contexts.first(),
+            Some(context) => Some(context),
+        };
+        if first.and_then(Value::as_str) != Some(DEFAULT_CONTEXT) {
+            self.push(
+                Severity::Error,
+                "invalid-context",
+                "/@context",
+                format!("The first @context must be {DEFAULT_CONTEXT}"),
+            );
+        }
+    }
+
+    fn check_did(&mut self, value: &Value, path: &str, property: &str) {
+        match value.as_str() {
+            Some(did) if is_valid_did(did) => {}
+            Some(did) => self.push(
+                Severity::Error,
+                "invalid-did",
+                path,
+                format!("Invalid DID for {property}: {did}"),
+            ),
+            None => self.push(
+                Severity::Error,
+                "invalid-did",
+                path,
+                format!("{property} must be a DID string"),
+            ),
+        }
+    }
+
+    fn check_controller(&mut self, value: &Value, path: &str) {
+        match value {
+            Value::Array(controllers) => {
+                for (i, controller) in controllers.iter().enumerate() {
+                    self.check_did(controller, &format!("{path}/{i}"), "controller");
+                }
+            }
+            _ => self.check_did(value, path, "controller"),
+        }
+    }
+
+    /// Check an id property, returning the absolute id
+    fn check_id(&mut self, object: &Map<String, Value>, path: &str) -> Option<String> {
+        let id_path = format!("{path}/id");
+        let id = match object.get("id") {
+            Some(Value::String(id)) => id,
+            Some(_) => {
+                self.push(
+                    Severity::Error,
+                    "invalid-id",
+                    &id_path,
+                    "id must be a string".to_string(),
+                );
+                return None;
+            }
+            None => {
+                self.push(
+                    Severity::Error,
+                    "missing-id",
+                    path,
+                    "Missing id".to_string(),
+                );
+                return None;
+            }
+        };
+        let absolute = absolute_id(&self.did, id);
+        if &absolute != id {
+            self.push(
+                Severity::Info,
+                "relative-id",
+                &id_path,
+                format!("Relative id {id} is resolved against the DID"),
+            );
+        }
+        if let Some(other_path) = self.ids.get(&absolute) {
+            let message = format!("Duplicate id {absolute}, also defined at {other_path}");
+            self.push(Severity::Error, "duplicate-id", &id_path, message);
+        } else {
+            self.ids.insert(absolute.clone(), path.to_string());
+        }
+        Some(absolute)
+    }
+
+    fn check_verification_method(&mut self, value: &Value, path: &str) {
+        let object = match value {
+            Value::Object(object) => object,
+            _ => {
+                return self.push(
+                    Severity::Error,
+                    "invalid-verification-method",
+                    path,
+                    "Verification method must be an object".to_string(),
+                )
+            }
+        };
+        if let Some(id) = self.check_id(object, path) {
+            self.verification_method_ids.insert(id.clone());
+            if !is_valid_did_url(&id) {
+                self.push(
+                    Severity::Error,
+                    "invalid-id",
+                    &format!("{path}/id"),
+                    format!("Verification method id must be a DID URL: {id}"),
+                );
+            }
+        }
+        match object.get("controller") {
+            Some(controller) => {
+                self.check_did(controller, &format!("{path}/controller"), "controller")
+            }
+            None => self.push(
+                Severity::Error,
+                "missing-controller",
+                path,
+                "Verification method is missing a controller".to_string(),
+            ),
+        }
+        let key_properties: Vec<&str> = PUBLIC_KEY_PROPERTIES
+            .iter()
+            .copied()
+            .filter(|property| object.contains_key(*property))
+            .collect();
+        if key_properties.is_empty() {
+            self.push(
+                Severity::Error,
+                "missing-public-key",
+                path,
+                "Verification method has no public key property".to_string(),
+            );
+        } else if key_properties.len() > 1 {
+            self.push(
+                Severity::Warning,
+                "multiple-public-keys",
+                path,
+                format!(
+                    "Verification method has multiple public key properties: {}",
+                    key_properties.join(", ")
+                ),
+            );
+        }
+        match object.get("type").and_then(Value::as_str) {
+            None => self.push(
+                Severity::Error,
+                "missing-type",
+                path,
+                "Verification method is missing a type".to_string(),
+            ),
+            Some(type_) => match VERIFICATION_METHOD_TYPES
+                .iter()
+                .find(|(name, _)| *name == type_)
+            {
+                Some((_, expected)) => {
+                    for property in &key_properties {
+                        if !expected.contains(property) {
+                            self.push(
+                                Severity::Error,
+                                "key-type-mismatch",
+                                &format!("{path}/{property}"),
+                                format!(
+                                    "{type_} expects {}, not {property}",
+                                    expected.join(" or ")
+                                ),
+                            );
+                        }
+                    }
+                }
+                None => self.push(
+                    Severity::Info,
+                    "unknown-type",
+                    &format!("{path}/type"),
+                    format!("Unknown verification method type {type_}"),
+                ),
+            },
+        }
+        if let Some(jwk) = object.get("publicKeyJwk") {
+            self.check_jwk(jwk, &format!("{path}/publicKeyJwk"));
+        }
+        if let Some(multibase) = object.get("publicKeyMultibase") {
+            let valid = multibase
+                .as_str()
+                .map_or(false, |multibase| multibase::decode(multibase).is_ok());
+            if !valid {
+                self.push(
+                    Severity::Error,
+                    "invalid-public-key",
+                    &format!("{path}/publicKeyMultibase"),
+                    "publicKeyMultibase must be a multibase string".to_string(),
+                );
+            }
+        }
+        if let Some(base58) = object.get("publicKeyBase58") {
+            let valid = base58
+                .as_str()
+                .map_or(false, |base58| bs58::decode(base58).into_vec().is_ok());
+            if !valid {
+                self.push(
+                    Severity::Error,
+                    "invalid-public-key",
+                    &format!("{path}/publicKeyBase58"),
+                    "publicKeyBase58 must be a base58 string".to_string(),
+                );
+            }
+        }
+    }
+
+    fn check_jwk(&mut self, value: &Value, path: &str) {
+        if let Err(err) = serde_json::from_value::<JWK>(value.clone()) {
+            return self.push(
+                Severity::Error,
+                "invalid-public-key",
+                path,
+                format!("Invalid JWK: {err}"),
+            );
+        }
+        if value.get("d").is_some() || value.get("k").is_some() {
+            self.push(
+                Severity::Error,
+                "private-key",
+                path,
+                "publicKeyJwk contains private key material".to_string(),
+            );
+        }
+    }
+
+    fn check_relationship(&mut self, property: &str, value: &Value) {
+        let path = format!("/{property}");
+        let entries = match value {
+            Value::Array(entries) => entries,
+            _ => {
+                return self.push(
+                    Severity::Error,
+                    "invalid-verification-relationship",
+                    &path,
+                    format!("{property} must be an array"),
+                )
+            }
+        };
+        for (i, entry) in entries.iter().enumerate() {
+            let entry_path = format!("{path}/{i}");
+            match entry {
+                Value::String(_) | Value::Object(_) => {}
+                _ => self.push(
+                    Severity::Error,
+                    "invalid-verification-relationship",
+                    &entry_path,
+                    "Verification relationship entry must be a DID URL or a verification method"
+                        .to_string(),
+                ),
+            }
+            if entry.is_object() {
+                self.check_verification_method(entry, &entry_path);
+            }
+        }
+    }
+
+    /// Check that verification relationship references resolve. Done after all embedded
+    /// verification methods are known.
+    fn check_references(&mut self, property: &str, value: &Value) {
+        let entries = match value.as_array() {
+            Some(entries) => entries,
+            None => return,
+        };
+        for (i, entry) in entries.iter().enumerate() {
+            let reference = match entry.as_str() {
+                Some(reference) => reference,
+                None => continue,
+            };
+            let path = format!("/{property}/{i}");
+            let id = absolute_id(&self.did, reference);
+            if self.verification_method_ids.contains(&id) {
+                continue;
+            }
+            if !id.starts_with(&self.did) || !id[self.did.len()..].starts_with(['#', '/', '?']) {
+                if is_valid_did_url(&id) {
+                    self.push(
+                        Severity::Info,
+                        "external-reference",
+                        &path,
+                        format!("Reference to another DID document is not checked: {id}"),
+                    );
+                } else {
+                    self.push(
+                        Severity::Error,
+                        "invalid-reference",
+                        &path,
+                        format!("Reference is not a DID URL: {reference}"),
+                    );
+                }
+                continue;
+            }
+            let message = if self.ids.contains_key(&id) {
+                format!("{id} is not a verification method")
+            } else {
+                format!("No verification method with id {id}")
+            };
+            self.push(Severity::Error, "unresolved-reference", &path, message);
+        }
+    }
+
+    fn check_service(&mut self, value: &Value, path: &str) {
+        let object = match value {
+            Value::Object(object) => object,
+            _ => {
+                return self.push(
+                    Severity::Error,
+                    "invalid-service",
+                    path,
+                    "Service must be an object".to_string(),
+                )
+            }
+        };
+        self.check_id(object, path);
+        let valid_type = match object.get("type") {
+            Some(Value::String(_)) => true,
+            Some(Value::Array(types)) => !types.is_empty() && types.iter().all(Value::is_string),
+            _ => false,
+        };
+        if !valid_type {
+            self.push(
+                Severity::Error,
+                "invalid-service-type",
+                path,
+                "Service type must be a string or a non-empty array of strings".to_string(),
+            );
+        }
+        let endpoint_path = format!("{path}/serviceEndpoint");
+        match object.get("serviceEndpoint") {
+            None => self.push(
+                Severity::Error,
+                "missing-service-endpoint",
+                path,
+                "Service is missing a serviceEndpoint".to_string(),
+            ),
+            Some(Value::Array(endpoints)) => {
+                if endpoints.is_empty() {
+                    self.push(
+                        Severity::Warning,
+                        "invalid-service-endpoint",
+                        &endpoint_path,
+                        "serviceEndpoint is an empty array".to_string(),
+                    );
+                }
+                for (i, endpoint) in endpoints.iter().enumerate() {
+                    self.check_service_endpoint(endpoint, &format!("{endpoint_path}/{i}"));
+                }
+            }
+            Some(endpoint) => self.check_service_endpoint(endpoint, &endpoint_path),
+        }
+    }
+
+    fn check_service_endpoint(&mut self, value: &Value, path: &str) {
+        match value {
+            Value::String(uri) => {
+                if url::Url::parse(uri).is_err() {
+                    self.push(
+                        Severity::Error,
+                        "invalid-service-endpoint",
+                        path,
+                        format!("serviceEndpoint is not a URI: {uri}"),
+                    );
+                }
+            }
+            Value::Object(_) => {}
+            _ => self.push(
+                Severity::Error,
+                "invalid-service-endpoint",
+                path,
+                "serviceEndpoint must be a URI, a map, or an array of these".to_string(),
+            ),
+        }
+    }
+}
+
+/// Lint a DID document given as JSON, returning findings ordered from most to least severe
+pub fn lint(document: &Value) -> Vec<Finding> {
+    let mut linter = Linter {
+        did: String::new(),
+        findings: Vec::new(),
+        ids: HashMap::new(),
+        verification_method_ids: HashSet::new(),
+    };
+    let object = match document {
+        Value::Object(object) => object,
+        _ => {
+            linter.push(
+                Severity::Error,
+                "invalid-document",
+                "",
+                "DID document must be a JSON object".to_string(),
+            );
+            return linter.findings;
+        }
+    };
+    linter.check_context(object);
+    match object.get("id") {
+        Some(id) => {
+            linter.check_did(id, "/id", "id");
+            linter.did = id.as_str().unwrap_or_default().to_string();
+        }
+        None => linter.push(
+            Severity::Error,
+            "missing-id",
+            "",
+            "DID document is missing an id".to_string(),
+        ),
+    }
+    if let Some(controller) = object.get("controller") {
+        linter.check_controller(controller, "/controller");
+    }
+    if let Some(also_known_as) = object.get("alsoKnownAs") {
+        let valid = match also_known_as {
+            Value::Array(uris) => uris.iter().all(|uri| {
+                uri.as_str()
+                    .map_or(false, |uri| url::Url::parse(uri).is_ok())
+            }),
+            _ => false,
+        };
+        if !valid {
+            linter.push(
+                Severity::Error,
+                "invalid-also-known-as",
+                "/alsoKnownAs",
+                "alsoKnownAs must be an array of URIs".to_string(),
+            );
+        }
+    }
+    match object.get("verificationMethod") {
+        Some(Value::Array(vms)) => {
+            for (i, vm) in vms.iter().enumerate() {
+                linter.check_verification_method(vm, &format!("/verificationMethod/{i}"));
+            }
+        }
+        Some(_) => linter.push(
+            Severity::Error,
+            "invalid-verification-method",
+            "/verificationMethod",
+            "verificationMethod must be an array".to_string(),
+        ),
+        None => {}
+    }
+    for property in VERIFICATION_RELATIONSHIPS {
+        if let Some(value) = object.get(property) {
+            linter.check_relationship(property, value);
+        }
+    }
+    match object.get("service") {
+        Some(Value::Array(services)) => {
+            for (i, service) in services.iter().enumerate() {
+                linter.check_service(service, &format!("/service/{i}"));
+            }
+        }
+        Some(_) => linter.push(
+            Severity::Error,
+            "invalid-service",
+            "/service",
+            "service must be an array".to_string(),
+        ),
+        None => {}
+    }
+    for property in VERIFICATION_RELATIONSHIPS {
+        if let Some(value) = object.get(property) {
+            linter.check_references(property, value);
+        }
+    }
+    let mut findings = linter.findings;
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+    findings
+}
+
+/// Lint a parsed DID document
+pub fn lint_document(document: &Document) -> Result<Vec<Finding>, serde_json::Error> {
+    Ok(lint(&serde_json::to_value(document)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn codes(document: Value) -> Vec<&'static str> {
+        let mut codes: Vec<&'static str> = lint(&document)
+            .into_iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .map(|finding| finding.code)
+            .collect();
+        codes.sort_unstable();
+        codes
+    }
+
+    #[test]
+    fn did_syntax() {
+        assert!(is_valid_did("did:example:123"));
+        assert!(is_valid_did("did:web:example.com:user%3Aalice"));
+        assert!(!is_valid_did("did:Example:123"));
+        assert!(!is_valid_did("did:example:"));
+        assert!(is_valid_did_url("did:example:123#key-1"));
+        assert!(!is_valid_did_url("#key-1"));
+    }
+
+    #[test]
+    fn lint_documents() {
+        let valid = json!({
+            "@context": [DEFAULT_CONTEXT, "https://w3id.org/security/suites/jws-2020/v1"],
+            "id": "did:example:123",
+            "verificationMethod": [{
+                "id": "did:example:123#key-1",
+                "type": "JsonWebKey2020",
+                "controller": "did:example:123",
+                "publicKeyJwk": {"kty": "OKP", "crv": "Ed25519", "x": "G80iskrv_nE69qbGLSpeOHJgmV4MKIzsy5l5iT6pCww"}
+            }],
+            "assertionMethod": ["#key-1", "did:example:456#key-1"],
+            "service": [{
+                "id": "#hub",
+                "type": "Hub",
+                "serviceEndpoint": "https://hub.example.com/"
+            }]
+        });
+        assert_eq!(codes(valid.clone()), Vec::<&str>::new());
+
+        // Verification relationships may only reference verification methods.
+        let mut service_reference = valid.clone();
+        service_reference["authentication"] = json!(["#hub"]);
+        assert_eq!(codes(service_reference), ["unresolved-reference"]);
+
+        let mut invalid = valid;
+        invalid["@context"] = json!("https://www.w3.org/2018/credentials/v1");
+        invalid["controller"] = json!("not-a-did");
+        invalid["verificationMethod"][0]["type"] = json!("Ed25519VerificationKey2020");
+        invalid["verificationMethod"][0]["publicKeyJwk"]["d"] = json!("AAAA");
+        invalid["authentication"] = json!(["#key-2"]);
+        invalid["service"] = json!([
+            {"id": "#key-1", "type": "Hub", "serviceEndpoint": 1}
+        ]);
+        assert_eq!(
+            codes(invalid),
+            [
+                "duplicate-id",
+                "invalid-context",
+                "invalid-did",
+                "invalid-service-endpoint",
+                "key-type-mismatch",
+                "private-key",
+                "unresolved-reference"
+            ]
+        );
+    }
+}
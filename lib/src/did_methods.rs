@@ -8,47 +8,252 @@ use did_jwk::DIDJWK;
 use did_tz::DIDTz;
 use did_web::DIDWeb;
 use did_webkey::DIDWebKey;
+use serde::{Deserialize, Serialize};
 use ssi::did::DIDMethods;
 use std::env::VarError;
+use thiserror::Error;
 
-lazy_static! {
-    static ref DIDTZ: DIDTz = DIDTz::default();
-    static ref DIDONION: DIDOnion = {
-        let mut onion = DIDOnion::default();
-        if let Some(url) = match std::env::var("DID_ONION_PROXY_URL") {
-            Ok(url) => Some(url),
-            Err(VarError::NotPresent) => None,
-            Err(VarError::NotUnicode(err)) => {
-                eprintln!("Unable to parse DID_ONION_PROXY_URL: {:?}", err);
-                None
-            }
-        } {
-            onion.proxy_url = url;
+use crate::did_peer::DIDPeer;
+
+/// Names of the DID methods supported by DIDKit
+pub const SUPPORTED_METHODS: [&str; 10] = [
+    "key", "tz", "ethr", "web", "webkey", "pkh", "onion", "ion", "jwk", "peer",
+];
+
+#[derive(Error, Debug)]
+pub enum DIDMethodRegistryError {
+    #[error("Unknown DID method: {0}")]
+    UnknownMethod(String),
+    #[error("did:ethr DIDs are resolved offline, so an Ethereum RPC endpoint is not supported")]
+    EthrRPCUnsupported,
+}
+
+/// did:ion settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct IONConfig {
+    /// ION node API endpoint, for resolution and submitting operations
+    pub api_url: Option<String>,
+}
+
+/// did:onion settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OnionConfig {
+    /// Tor SOCKS proxy URL
+    pub proxy_url: Option<String>,
+}
+
+/// did:ethr settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct EthrConfig {
+    /// Ethereum JSON-RPC endpoint
+    ///
+    /// Reserved: did:ethr DIDs are currently resolved without querying the ERC-1056 registry, and
+    /// setting this is an error.
+    pub rpc_url: Option<String>,
+}
+
+/// did:tz settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TzConfig {
+    /// TzKT indexer API endpoint, used to find DID manager contracts and updates
+    pub indexer_url: Option<String>,
+}
+
+/// Configuration of the DID methods of a [`DIDMethodRegistry`], as in the `[did-methods]` table of
+/// `didkit.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DIDMethodsConfig {
+    /// DID methods to enable. Default: all supported methods
+    pub enable: Option<Vec<String>>,
+    /// DID methods to disable
+    #[serde(default)]
+    pub disable: Vec<String>,
+    #[serde(default)]
+    pub ion: IONConfig,
+    #[serde(default)]
+    pub onion: OnionConfig,
+    #[serde(default)]
+    pub ethr: EthrConfig,
+    #[serde(default)]
+    pub tz: TzConfig,
+}
+
+/// Builder for a set of DID methods with their settings
+///
+/// ```
+/// use didkit::did_methods::DIDMethodRegistry;
+///
+/// let methods = DIDMethodRegistry::new()
+///     .disable("onion")
+///     .ion_api_url("https://ion.example.org/")
+///     .build()
+///     .unwrap();
+/// assert!(methods.get("onion").is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DIDMethodRegistry {
+    config: DIDMethodsConfig,
+}
+
+fn env_var(name: &str) -> Option<String> {
+    match std::env::var(name) {
+        Ok(value) => Some(value),
+        Err(VarError::NotPresent) => None,
+        Err(VarError::NotUnicode(err)) => {
+            eprintln!("Unable to parse {}: {:?}", name, err);
+            None
         }
-        onion
-    };
-    static ref ION: DIDION = DIDION::new(
-        match std::env::var("DID_ION_API_URL") {
-            Ok(string) => Some(string),
-            Err(VarError::NotPresent) => None,
-            Err(VarError::NotUnicode(err)) => {
-                eprintln!("Unable to parse DID_ION_API_URL: {:?}", err);
-                None
+    }
+}
+
+impl DIDMethodRegistry {
+    /// Registry with all supported DID methods and their default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_config(config: DIDMethodsConfig) -> Self {
+        Self { config }
+    }
+
+    /// Registry configured from the `DID_ONION_PROXY_URL` and `DID_ION_API_URL` environment
+    /// variables, as used for [`DID_METHODS`]
+    pub fn from_env() -> Self {
+        let mut registry = Self::new();
+        registry.config.onion.proxy_url = env_var("DID_ONION_PROXY_URL");
+        registry.config.ion.api_url = env_var("DID_ION_API_URL");
+        registry
+    }
+
+    pub fn config(&self) -> &DIDMethodsConfig {
+        &self.config
+    }
+
+    /// Enable a DID method. Once a method is explicitly enabled, only explicitly enabled methods
+    /// are registered.
+    pub fn enable(mut self, method: &str) -> Self {
+        self.config
+            .enable
+            .get_or_insert_with(Vec::new)
+            .push(method.to_string());
+        self.config.disable.retain(|name| name != method);
+        self
+    }
+
+    pub fn disable(mut self, method: &str) -> Self {
+        self.config.disable.push(method.to_string());
+        self
+    }
+
+    pub fn ion_api_url(mut self, url: &str) -> Self {
+        self.config.ion.api_url = Some(url.to_string());
+        self
+    }
+
+    pub fn onion_proxy_url(mut self, url: &str) -> Self {
+        self.config.onion.proxy_url = Some(url.to_string());
+        self
+    }
+
+    pub fn ethr_rpc_url(mut self, url: &str) -> Self {
+        self.config.ethr.rpc_url = Some(url.to_string());
+        self
+    }
+
+    pub fn tz_indexer_url(mut self, url: &str) -> Self {
+        self.config.tz.indexer_url = Some(url.to_string());
+        self
+    }
+
+    fn is_enabled(&self, method: &str) -> bool {
+        let enabled = match &self.config.enable {
+            Some(enable) => enable.iter().any(|name| name == method),
+            None => true,
+        };
+        enabled && !self.config.disable.iter().any(|name| name == method)
+    }
+
+    /// Build the set of enabled DID methods
+    pub fn build(&self) -> Result<DIDMethods<'static>, DIDMethodRegistryError> {
+        let config = &self.config;
+        let names = config.enable.iter().flatten().chain(config.disable.iter());
+        for name in names {
+            if !SUPPORTED_METHODS.contains(&name.as_str()) {
+                return Err(DIDMethodRegistryError::UnknownMethod(name.to_string()));
             }
         }
-    );
-    pub static ref DID_METHODS: DIDMethods<'static> = {
+        if config.ethr.rpc_url.is_some() && self.is_enabled("ethr") {
+            return Err(DIDMethodRegistryError::EthrRPCUnsupported);
+        }
         let mut methods = DIDMethods::default();
-        methods.insert(Box::new(DIDKey));
-        methods.insert(Box::new(DIDTZ.clone()));
-        methods.insert(Box::new(DIDEthr));
-        // methods.insert(&DIDSol);
-        methods.insert(Box::new(DIDWeb));
-        methods.insert(Box::new(DIDWebKey));
-        methods.insert(Box::new(DIDPKH));
-        methods.insert(Box::new(DIDONION.clone()));
-        methods.insert(Box::new(ION.clone()));
-        methods.insert(Box::new(DIDJWK));
-        methods
-    };
+        for name in SUPPORTED_METHODS {
+            if !self.is_enabled(name) {
+                continue;
+            }
+            match name {
+                "key" => methods.insert(Box::new(DIDKey)),
+                "tz" => methods.insert(Box::new(DIDTz::new(config.tz.indexer_url.clone()))),
+                "ethr" => methods.insert(Box::new(DIDEthr)),
+                // "sol" => methods.insert(Box::new(DIDSol)),
+                "web" => methods.insert(Box::new(DIDWeb)),
+                "webkey" => methods.insert(Box::new(DIDWebKey)),
+                "pkh" => methods.insert(Box::new(DIDPKH)),
+                "onion" => {
+                    let mut onion = DIDOnion::default();
+                    if let Some(url) = &config.onion.proxy_url {
+                        onion.proxy_url = url.clone();
+                    }
+                    methods.insert(Box::new(onion))
+                }
+                "ion" => methods.insert(Box::new(DIDION::new(config.ion.api_url.clone()))),
+                "jwk" => methods.insert(Box::new(DIDJWK)),
+                "peer" => methods.insert(Box::new(DIDPeer)),
+                _ => unreachable!("unhandled supported DID method {}", name),
+            };
+        }
+        Ok(methods)
+    }
+}
+
+lazy_static! {
+    pub static ref DID_METHODS: DIDMethods<'static> = DIDMethodRegistry::from_env()
+        .build()
+        .expect("Unable to build DID methods");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry() {
+        let methods = DIDMethodRegistry::new().build().unwrap();
+        assert_eq!(methods.methods.len(), SUPPORTED_METHODS.len());
+        let methods = DIDMethodRegistry::new()
+            .enable("key")
+            .enable("web")
+            .build()
+            .unwrap();
+        let mut names: Vec<&str> = methods.methods.keys().copied().collect();
+        names.sort_unstable();
+        assert_eq!(names, ["key", "web"]);
+        let methods = DIDMethodRegistry::new().disable("ion").build().unwrap();
+        assert!(methods.get("ion").is_none());
+        assert!(methods.get("key").is_some());
+        assert!(DIDMethodRegistry::new().disable("foo").build().is_err());
+        assert!(DIDMethodRegistry::new()
+            .ethr_rpc_url("http://localhost:8545")
+            .build()
+            .is_err());
+        assert!(DIDMethodRegistry::new()
+            .disable("ethr")
+            .ethr_rpc_url("http://localhost:8545")
+            .build()
+            .is_ok());
+    }
 }
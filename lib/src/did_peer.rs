@@ -0,0 +1,523 @@
+//! did:peer DID method, numalgo 0, 2 and 4.
+//!
+//! did:peer DIDs are resolved locally, without a network or registry. Numalgo 0 encodes a single
+//! key, numalgo 2 encodes multiple keys and services, and numalgo 4 encodes a whole DID document
+//! along with its hash (the short form DID).
+//!
+//! See <https://identity.foundation/peer-did-method-spec/>.
+
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use ssi::did::{DIDMethod, Document, Source, VerificationRelationship, DEFAULT_CONTEXT};
+use ssi::did_resolve::{
+    DIDResolver, DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata, ERROR_INVALID_DID,
+    ERROR_NOT_FOUND, TYPE_DID_LD_JSON,
+};
+use ssi::jwk::{Base64urlUInt, OctetParams, Params, JWK};
+use thiserror::Error;
+
+use crate::did_document::VERIFICATION_RELATIONSHIPS;
+use crate::key_format::{self, KeyFormatError};
+
+const MULTICODEC_X25519_PUB: [u8; 2] = [0xec, 0x01];
+const MULTICODEC_JSON: [u8; 2] = [0x80, 0x04];
+const MULTIHASH_SHA2_256: [u8; 2] = [0x12, 0x20];
+
+const JWS_2020_CONTEXT: &str = "https://w3id.org/security/suites/jws-2020/v1";
+
+/// Service property abbreviations used in numalgo 2 DIDs
+const SERVICE_ABBREVIATIONS: [(&str, &str); 4] = [
+    ("type", "t"),
+    ("serviceEndpoint", "s"),
+    ("routingKeys", "r"),
+    ("accept", "a"),
+];
+const DIDCOMM_MESSAGING: (&str, &str) = ("DIDCommMessaging", "dm");
+
+#[derive(Error, Debug)]
+pub enum DIDPeerError {
+    #[error("Invalid did:peer DID")]
+    InvalidDID,
+    #[error("Unsupported numalgo: {0}")]
+    UnsupportedNumalgo(char),
+    #[error("Unsupported purpose code: {0}")]
+    UnsupportedPurpose(char),
+    #[error("Unsupported verification relationship: {0:?}")]
+    UnsupportedRelationship(VerificationRelationship),
+    #[error("Document hash does not match")]
+    HashMismatch,
+    #[error("Input document must be a JSON object without an id")]
+    InvalidInputDocument,
+    #[error(transparent)]
+    KeyFormat(#[from] KeyFormatError),
+    #[error(transparent)]
+    Multibase(#[from] multibase::Error),
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// did:peer DID method
+#[derive(Default, Clone)]
+pub struct DIDPeer;
+
+fn purpose_code(relationship: &VerificationRelationship) -> Result<char, DIDPeerError> {
+    Ok(match relationship {
+        VerificationRelationship::AssertionMethod => 'A',
+        VerificationRelationship::KeyAgreement => 'E',
+        VerificationRelationship::Authentication => 'V',
+        VerificationRelationship::CapabilityInvocation => 'I',
+        VerificationRelationship::CapabilityDelegation => 'D',
+        _ => return Err(DIDPeerError::UnsupportedRelationship(relationship.clone())),
+    })
+}
+
+fn purpose_property(code: char) -> Result<&'static str, DIDPeerError> {
+    Ok(match code {
+        'A' => "assertionMethod",
+        'E' => "keyAgreement",
+        'V' => "authentication",
+        'I' => "capabilityInvocation",
+        'D' => "capabilityDelegation",
+        _ => return Err(DIDPeerError::UnsupportedPurpose(code)),
+    })
+}
+
+fn is_x25519(jwk: &JWK) -> bool {
+    matches!(&jwk.params, Params::OKP(okp) if okp.curve == "X25519")
+}
+
+/// Encode a public key as a multibase-encoded multicodec key. X25519 keys are supported in
+/// addition to the key types of [`key_format::jwk_to_multibase`].
+fn key_to_multibase(jwk: &JWK) -> Result<String, DIDPeerError> {
+    match &jwk.params {
+        Params::OKP(okp) if okp.curve == "X25519" => {
+            let mut bytes = MULTICODEC_X25519_PUB.to_vec();
+            bytes.extend_from_slice(&okp.public_key.0);
+            Ok(multibase::encode(multibase::Base::Base58Btc, bytes))
+        }
+        _ => Ok(key_format::jwk_to_multibase(jwk, false)?),
+    }
+}
+
+fn key_from_multibase(encoded: &str) -> Result<JWK, DIDPeerError> {
+    let (_, bytes) = multibase::decode(encoded)?;
+    match bytes.strip_prefix(&MULTICODEC_X25519_PUB[..]) {
+        Some(public_key) if public_key.len() == 32 => Ok(JWK::from(Params::OKP(OctetParams {
+            curve: "X25519".to_string(),
+            public_key: Base64urlUInt(public_key.to_vec()),
+            private_key: None,
+        }))),
+        Some(_) => Err(KeyFormatError::InvalidKey.into()),
+        None => Ok(key_format::jwk_from_multibase(encoded)?),
+    }
+}
+
+fn verification_method(did: &str, id: &str, jwk: &JWK) -> Value {
+    serde_json::json!({
+        "id": id,
+        "type": "JsonWebKey2020",
+        "controller": did,
+        "publicKeyJwk": jwk.to_public(),
+    })
+}
+
+fn new_document(did: &str) -> Map<String, Value> {
+    let mut document = Map::new();
+    document.insert(
+        "@context".to_string(),
+        serde_json::json!([DEFAULT_CONTEXT, JWS_2020_CONTEXT]),
+    );
+    document.insert("id".to_string(), Value::String(did.to_string()));
+    document
+}
+
+fn push(document: &mut Map<String, Value>, property: &str, value: Value) {
+    if let Value::Array(values) = document
+        .entry(property)
+        .or_insert_with(|| Value::Array(Vec::new()))
+    {
+        values.push(value);
+    }
+}
+
+/// Generate a numalgo 0 did:peer DID from a public key
+pub fn generate_numalgo0(jwk: &JWK) -> Result<String, DIDPeerError> {
+    Ok(format!("did:peer:0{}", key_to_multibase(jwk)?))
+}
+
+fn replace(string: String, abbreviations: &[(&str, &str)], expand: bool) -> String {
+    abbreviations
+        .iter()
+        .find(|(long, short)| string == if expand { *short } else { *long })
+        .map(|(long, short)| if expand { long } else { short }.to_string())
+        .unwrap_or(string)
+}
+
+/// Replace service properties and the `DIDCommMessaging` type with their abbreviation, or the
+/// reverse
+fn abbreviate(value: Value, expand: bool) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| {
+                    (
+                        replace(key, &SERVICE_ABBREVIATIONS, expand),
+                        abbreviate(value, expand),
+                    )
+                })
+                .collect(),
+        ),
+        Value::Array(array) => Value::Array(
+            array
+                .into_iter()
+                .map(|value| abbreviate(value, expand))
+                .collect(),
+        ),
+        Value::String(string) => Value::String(replace(string, &[DIDCOMM_MESSAGING], expand)),
+        value => value,
+    }
+}
+
+/// Generate a numalgo 2 did:peer DID from public keys with their verification relationships, and
+/// services
+pub fn generate_numalgo2(
+    keys: &[(JWK, VerificationRelationship)],
+    services: &[Value],
+) -> Result<String, DIDPeerError> {
+    let mut did = "did:peer:2".to_string();
+    for (jwk, relationship) in keys {
+        did.push('.');
+        did.push(purpose_code(relationship)?);
+        did.push_str(&key_to_multibase(jwk)?);
+    }
+    for service in services {
+        let abbreviated = serde_json::to_vec(&abbreviate(service.clone(), false))?;
+        did.push_str(".S");
+        did.push_str(&base64::encode_config(abbreviated, base64::URL_SAFE_NO_PAD));
+    }
+    Ok(did)
+}
+
+/// Generate a numalgo 4 did:peer DID (long form) from an input DID document, which must not have
+/// an id. Relative references such as `#key-1` are resolved against the DID.
+pub fn generate_numalgo4(document: &Value) -> Result<String, DIDPeerError> {
+    match document {
+        Value::Object(object) if !object.contains_key("id") => {}
+        _ => return Err(DIDPeerError::InvalidInputDocument),
+    }
+    let mut encoded = MULTICODEC_JSON.to_vec();
+    encoded.extend(serde_json::to_vec(document)?);
+    let encoded = multibase::encode(multibase::Base::Base58Btc, encoded);
+    let mut hash = MULTIHASH_SHA2_256.to_vec();
+    hash.extend_from_slice(&Sha256::digest(encoded.as_bytes()));
+    let hash = multibase::encode(multibase::Base::Base58Btc, hash);
+    Ok(format!("did:peer:4{hash}:{encoded}"))
+}
+
+/// Get the short form of a numalgo 4 did:peer DID
+pub fn short_form(did: &str) -> Option<&str> {
+    let rest = did.strip_prefix("did:peer:4")?;
+    let (hash, _) = rest.split_once(':')?;
+    Some(&did[.."did:peer:4".len() + hash.len()])
+}
+
+fn resolve_numalgo0(did: &str, encoded: &str) -> Result<Document, DIDPeerError> {
+    let jwk = key_from_multibase(encoded)?;
+    let mut document = new_document(did);
+    let id = format!("{did}#{encoded}");
+    document.insert(
+        "verificationMethod".to_string(),
+        Value::Array(vec![verification_method(did, &id, &jwk)]),
+    );
+    let relationships: &[&str] = if is_x25519(&jwk) {
+        &["keyAgreement"]
+    } else {
+        &[
+            "authentication",
+            "assertionMethod",
+            "capabilityInvocation",
+            "capabilityDelegation",
+        ]
+    };
+    for property in relationships {
+        push(&mut document, property, Value::String(id.clone()));
+    }
+    Ok(serde_json::from_value(Value::Object(document))?)
+}
+
+fn resolve_numalgo2(did: &str, elements: &str) -> Result<Document, DIDPeerError> {
+    let mut document = new_document(did);
+    let mut key_count = 0;
+    let mut service_count = 0;
+    for element in elements.split('.').skip(1) {
+        let mut chars = element.chars();
+        let code = chars.next().ok_or(DIDPeerError::InvalidDID)?;
+        let value = chars.as_str();
+        if code == 'S' {
+            let service = base64::decode_config(value, base64::URL_SAFE_NO_PAD)?;
+            let mut service = abbreviate(serde_json::from_slice(&service)?, true);
+            let object = service.as_object_mut().ok_or(DIDPeerError::InvalidDID)?;
+            if !object.contains_key("id") {
+                let id = match service_count {
+                    0 => "#service".to_string(),
+                    n => format!("#service-{n}"),
+                };
+                object.insert("id".to_string(), Value::String(id));
+            }
+            service_count += 1;
+            push(&mut document, "service", service);
+        } else {
+            let property = purpose_property(code)?;
+            let jwk = key_from_multibase(value)?;
+            key_count += 1;
+            let id = format!("{did}#key-{key_count}");
+            push(
+                &mut document,
+                "verificationMethod",
+                verification_method(did, &id, &jwk),
+            );
+            push(&mut document, property, Value::String(id));
+        }
+    }
+    Ok(serde_json::from_value(Value::Object(document))?)
+}
+
+fn resolve_numalgo4(did: &str, rest: &str) -> Result<Document, DIDPeerError> {
+    // Only the long form can be resolved locally.
+    let (hash, encoded) = rest.split_once(':').ok_or(DIDPeerError::InvalidDID)?;
+    let mut expected_hash = MULTIHASH_SHA2_256.to_vec();
+    expected_hash.extend_from_slice(&Sha256::digest(encoded.as_bytes()));
+    let (_, hash) = multibase::decode(hash)?;
+    if hash != expected_hash {
+        return Err(DIDPeerError::HashMismatch);
+    }
+    let (_, bytes) = multibase::decode(encoded)?;
+    let json = bytes
+        .strip_prefix(&MULTICODEC_JSON[..])
+        .ok_or(DIDPeerError::InvalidDID)?;
+    let mut document = match serde_json::from_slice(json)? {
+        Value::Object(object) if !object.contains_key("id") => object,
+        _ => return Err(DIDPeerError::InvalidInputDocument),
+    };
+    document.insert("id".to_string(), Value::String(did.to_string()));
+    let short_form = short_form(did).ok_or(DIDPeerError::InvalidDID)?;
+    document.insert("alsoKnownAs".to_string(), serde_json::json!([short_form]));
+    // Verification methods, including those embedded in verification relationships, are
+    // controlled by the DID unless stated otherwise.
+    let properties = std::iter::once("verificationMethod").chain(VERIFICATION_RELATIONSHIPS);
+    for property in properties {
+        if let Some(Value::Array(entries)) = document.get_mut(property) {
+            for vm in entries.iter_mut().filter_map(Value::as_object_mut) {
+                vm.entry("controller")
+                    .or_insert_with(|| Value::String(did.to_string()));
+            }
+        }
+    }
+    Ok(serde_json::from_value(Value::Object(document))?)
+}
+
+/// Resolve a did:peer DID to its DID document
+pub fn resolve_document(did: &str) -> Result<Document, DIDPeerError> {
+    let rest = did
+        .strip_prefix("did:peer:")
+        .ok_or(DIDPeerError::InvalidDID)?;
+    let mut chars = rest.chars();
+    let numalgo = chars.next().ok_or(DIDPeerError::InvalidDID)?;
+    let rest = chars.as_str();
+    match numalgo {
+        '0' => resolve_numalgo0(did, rest),
+        '2' => resolve_numalgo2(did, rest),
+        '4' => resolve_numalgo4(did, rest),
+        _ => Err(DIDPeerError::UnsupportedNumalgo(numalgo)),
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDResolver for DIDPeer {
+    async fn resolve(
+        &self,
+        did: &str,
+        _input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        if !did.starts_with("did:peer:") {
+            return (
+                ResolutionMetadata::from_error(ERROR_INVALID_DID),
+                None,
+                None,
+            );
+        }
+        if did.starts_with("did:peer:4") && short_form(did).is_none() {
+            // A short form numalgo 4 DID can only be resolved by a party that has seen the long form.
+            return (ResolutionMetadata::from_error(ERROR_NOT_FOUND), None, None);
+        }
+        match resolve_document(did) {
+            Ok(document) => (
+                ResolutionMetadata {
+                    content_type: Some(TYPE_DID_LD_JSON.to_string()),
+                    ..Default::default()
+                },
+                Some(document),
+                Some(DocumentMetadata::default()),
+            ),
+            Err(err) => (
+                ResolutionMetadata::from_error(&format!("{ERROR_INVALID_DID}: {err}")),
+                None,
+                None,
+            ),
+        }
+    }
+
+    fn to_did_method(&self) -> Option<&dyn DIDMethod> {
+        Some(self)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl DIDMethod for DIDPeer {
+    fn name(&self) -> &'static str {
+        "peer"
+    }
+
+    /// Generate a did:peer DID from a key. The pattern selects the numalgo: `0` (the default),
+    /// `2` or `4`. For numalgo 2 and 4, X25519 keys are used for key agreement and other keys for
+    /// authentication and assertions.
+    fn generate(&self, source: &Source) -> Option<String> {
+        let (jwk, numalgo) = match source {
+            Source::Key(jwk) => (*jwk, "0"),
+            Source::KeyAndPattern(jwk, pattern) => (*jwk, *pattern),
+            _ => return None,
+        };
+        let relationships = if is_x25519(jwk) {
+            vec![VerificationRelationship::KeyAgreement]
+        } else {
+            vec![
+                VerificationRelationship::Authentication,
+                VerificationRelationship::AssertionMethod,
+            ]
+        };
+        match numalgo {
+            "0" => generate_numalgo0(jwk).ok(),
+            "2" => {
+                let keys: Vec<_> = relationships
+                    .into_iter()
+                    .map(|relationship| (jwk.to_public(), relationship))
+                    .collect();
+                generate_numalgo2(&keys, &[]).ok()
+            }
+            "4" => {
+                let mut document = Map::new();
+                document.insert(
+                    "@context".to_string(),
+                    serde_json::json!([DEFAULT_CONTEXT, JWS_2020_CONTEXT]),
+                );
+                document.insert(
+                    "verificationMethod".to_string(),
+                    serde_json::json!([{
+                        "id": "#key-1",
+                        "type": "JsonWebKey2020",
+                        "publicKeyJwk": jwk.to_public(),
+                    }]),
+                );
+                for relationship in relationships {
+                    let property = match relationship {
+                        VerificationRelationship::KeyAgreement => "keyAgreement",
+                        VerificationRelationship::Authentication => "authentication",
+                        _ => "assertionMethod",
+                    };
+                    push(&mut document, property, Value::String("#key-1".to_string()));
+                }
+                generate_numalgo4(&Value::Object(document)).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn to_resolver(&self) -> &dyn DIDResolver {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numalgo0() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let did = DIDPeer.generate(&Source::Key(&jwk)).unwrap();
+        assert!(did.starts_with("did:peer:0z6Mk"));
+        let document = resolve_document(&did).unwrap();
+        let value = serde_json::to_value(&document).unwrap();
+        assert_eq!(
+            value["verificationMethod"][0]["publicKeyJwk"],
+            serde_json::to_value(jwk.to_public()).unwrap()
+        );
+        assert_eq!(
+            value["assertionMethod"][0],
+            value["verificationMethod"][0]["id"]
+        );
+    }
+
+    #[test]
+    fn numalgo2() {
+        let signing_key = JWK::generate_ed25519().unwrap();
+        let agreement_key = JWK::from(Params::OKP(OctetParams {
+            curve: "X25519".to_string(),
+            public_key: Base64urlUInt(vec![9; 32]),
+            private_key: None,
+        }));
+        let service = serde_json::json!({
+            "type": "DIDCommMessaging",
+            "serviceEndpoint": {"uri": "https://example.com/didcomm", "accept": ["didcomm/v2"]},
+        });
+        let did = generate_numalgo2(
+            &[
+                (
+                    agreement_key.clone(),
+                    VerificationRelationship::KeyAgreement,
+                ),
+                (signing_key, VerificationRelationship::Authentication),
+            ],
+            &[service],
+        )
+        .unwrap();
+        assert!(did.starts_with("did:peer:2.Ez6LS"));
+        let value = serde_json::to_value(resolve_document(&did).unwrap()).unwrap();
+        assert_eq!(value["keyAgreement"][0], format!("{did}#key-1"));
+        assert_eq!(value["authentication"][0], format!("{did}#key-2"));
+        assert_eq!(
+            value["verificationMethod"][0]["publicKeyJwk"],
+            serde_json::to_value(&agreement_key).unwrap()
+        );
+        assert_eq!(value["service"][0]["id"], "#service");
+        assert_eq!(value["service"][0]["type"], "DIDCommMessaging");
+        assert_eq!(
+            value["service"][0]["serviceEndpoint"]["accept"][0],
+            "didcomm/v2"
+        );
+    }
+
+    #[test]
+    fn numalgo4() {
+        let jwk = JWK::generate_p256().unwrap();
+        let did = DIDPeer.generate(&Source::KeyAndPattern(&jwk, "4")).unwrap();
+        let short = short_form(&did).unwrap();
+        assert!(short.starts_with("did:peer:4zQm"));
+        let value = serde_json::to_value(resolve_document(&did).unwrap()).unwrap();
+        assert_eq!(value["id"], did);
+        assert_eq!(value["alsoKnownAs"][0], short);
+        assert_eq!(value["verificationMethod"][0]["controller"], did);
+        let tampered = format!("{}x", did);
+        assert!(resolve_document(&tampered).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Generation of did:web DID documents.
+//!
+//! A did:web DID document is published as a file on a web server: `/.well-known/did.json` for a
+//! DID with only a domain name, or `/<path>/did.json` for a DID with a path.
+//!
+//! See <https://w3c-ccg.github.io/did-method-web/>.
+
+use std::path::PathBuf;
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::did_document::{self, DIDDocumentError};
+use crate::{DIDDocumentOperation, Document, VerificationRelationship, JWK};
+use ssi::did::{Service, VerificationMethodMap, DEFAULT_CONTEXT};
+
+/// Verification method type used for keys in generated DID documents
+pub const VERIFICATION_METHOD_TYPE: &str = "JsonWebKey2020";
+
+#[derive(Error, Debug)]
+pub enum DIDWebError {
+    #[error("Invalid domain name: {0}")]
+    InvalidDomain(String),
+    #[error("Invalid path segment: {0}")]
+    InvalidPath(String),
+    #[error("Not a did:web DID: {0}")]
+    NotDIDWeb(String),
+    #[error("Unable to compute key thumbprint: {0}")]
+    Key(#[from] ssi::jwk::Error),
+    #[error("DID document contains private key material")]
+    PrivateKey,
+    #[error(transparent)]
+    DIDDocument(#[from] DIDDocumentError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// A key to include in a DID document, with its verification relationships
+pub struct WebKey {
+    pub jwk: JWK,
+    pub purposes: Vec<VerificationRelationship>,
+}
+
+/// Construct a did:web DID from a domain name and optional colon-separated path, e.g.
+/// `example.com` or `example.com:user:alice`. A port must be percent-encoded, e.g.
+/// `localhost%3A8443`.
+pub fn did_from_domain(domain: &str) -> Result<String, DIDWebError> {
+    let mut segments = domain.split(':');
+    let host = segments.next().unwrap_or_default();
+    if host.is_empty()
+        || host.contains('/')
+        || host
+            .chars()
+            .any(|c| !(c.is_ascii_alphanumeric() || "-.%".contains(c)))
+    {
+        return Err(DIDWebError::InvalidDomain(domain.to_string()));
+    }
+    for segment in segments {
+        if segment.is_empty() || segment.contains('/') || segment == "." || segment == ".." {
+            return Err(DIDWebError::InvalidPath(segment.to_string()));
+        }
+    }
+    Ok(format!("did:web:{domain}"))
+}
+
+/// Get the path, relative to the web server root, at which the DID document of a did:web DID is
+/// published
+pub fn document_path(did: &str) -> Result<PathBuf, DIDWebError> {
+    let method_specific_id = did
+        .strip_prefix("did:web:")
+        .ok_or_else(|| DIDWebError::NotDIDWeb(did.to_string()))?;
+    let mut segments = method_specific_id.split(':');
+    segments.next();
+    let mut path: PathBuf = segments.collect();
+    if path.as_os_str().is_empty() {
+        path.push(".well-known");
+    }
+    path.push("did.json");
+    Ok(path)
+}
+
+/// Build a did:web DID document from keys and services
+///
+/// Each key is added as a `JsonWebKey2020` verification method whose fragment is the key's JWK
+/// thumbprint.
+pub fn build_document(
+    did: &str,
+    keys: Vec<WebKey>,
+    services: Vec<Service>,
+) -> Result<Document, DIDWebError> {
+    let mut document: Document = serde_json::from_value(serde_json::json!({
+        "@context": [DEFAULT_CONTEXT, "https://w3id.org/security/suites/jws-2020/v1"],
+        "id": did,
+    }))?;
+    for key in keys {
+        let public_jwk = key.jwk.to_public();
+        let vmm = VerificationMethodMap {
+            id: format!("{did}#{}", public_jwk.thumbprint()?),
+            type_: VERIFICATION_METHOD_TYPE.to_string(),
+            controller: did.to_string(),
+            public_key_jwk: Some(public_jwk),
+            ..Default::default()
+        };
+        document = did_document::apply_operation(
+            &document,
+            DIDDocumentOperation::SetVerificationMethod {
+                vmm,
+                purposes: key.purposes,
+            },
+        )?;
+    }
+    for service in services {
+        document =
+            did_document::apply_operation(&document, DIDDocumentOperation::SetService(service))?;
+    }
+    Ok(document)
+}
+
+/// Check that a DID document does not contain private key material
+pub fn check_public(document: &Document) -> Result<(), DIDWebError> {
+    fn contains_private_key(value: &Value) -> bool {
+        match value {
+            Value::Object(object) => {
+                (object.contains_key("kty") && object.contains_key("d"))
+                    || object.values().any(contains_private_key)
+            }
+            Value::Array(array) => array.iter().any(contains_private_key),
+            _ => false,
+        }
+    }
+    if contains_private_key(&serde_json::to_value(document)?) {
+        return Err(DIDWebError::PrivateKey);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn did_and_path() {
+        assert_eq!(
+            did_from_domain("example.com").unwrap(),
+            "did:web:example.com"
+        );
+        assert_eq!(
+            document_path("did:web:example.com").unwrap(),
+            PathBuf::from(".well-known/did.json")
+        );
+        let did = did_from_domain("example.com:user:alice").unwrap();
+        assert_eq!(
+            document_path(&did).unwrap(),
+            PathBuf::from("user/alice/did.json")
+        );
+        assert!(did_from_domain("example.com/user").is_err());
+        assert!(did_from_domain("example.com:..").is_err());
+        assert!(document_path("did:key:z6Mk").is_err());
+    }
+
+    #[test]
+    fn build() {
+        let jwk = JWK::generate_ed25519().unwrap();
+        let document = build_document(
+            "did:web:example.com",
+            vec![WebKey {
+                jwk: jwk.clone(),
+                purposes: vec![VerificationRelationship::AssertionMethod],
+            }],
+            vec![],
+        )
+        .unwrap();
+        let vm_id = format!("did:web:example.com#{}", jwk.thumbprint().unwrap());
+        let value = serde_json::to_value(&document).unwrap();
+        assert_eq!(value["verificationMethod"][0]["id"], vm_id);
+        assert_eq!(value["assertionMethod"][0], vm_id);
+        assert!(value["verificationMethod"][0]["publicKeyJwk"]
+            .get("d")
+            .is_none());
+        check_public(&document).unwrap();
+    }
+}
@@ -0,0 +1,1188 @@
+//! [DIDComm Messaging v2][didcomm]: plaintext, signed and encrypted messages, and discovery of
+//! messaging service endpoints.
+//!
+//! Encrypted messages use the JWE general JSON serialization with key wrapping (`A256KW`) per
+//! recipient. Anonymous encryption (anoncrypt) uses `ECDH-ES+A256KW`; authenticated encryption
+//! (authcrypt) uses `ECDH-1PU+A256KW` with `A256CBC-HS512`. Key agreement keys may be X25519 or
+//! P-256. Ed25519 keys are converted to X25519 when a DID document has no `keyAgreement` keys,
+//! as for `did:key`. When unpacking, a signing key must be in the signer's `authentication`
+//! verification relationship, and an authcrypt sender key in its `keyAgreement`.
+//!
+//! [didcomm]: https://identity.foundation/didcomm-messaging/spec/v2.0/
+
+use std::collections::HashSet;
+use std::convert::TryInto;
+
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use hmac::{Hmac, Mac};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256, Sha512};
+use ssi::jwk::{Algorithm, Base64urlUInt, OctetParams, Params};
+use thiserror::Error;
+
+use crate::{DIDResolver, ResolutionInputMetadata, JWK};
+
+/// Media type of plaintext DIDComm messages
+pub const TYP_PLAIN: &str = "application/didcomm-plain+json";
+/// Media type of signed DIDComm messages
+pub const TYP_SIGNED: &str = "application/didcomm-signed+json";
+/// Media type of encrypted DIDComm messages
+pub const TYP_ENCRYPTED: &str = "application/didcomm-encrypted+json";
+/// DID document service type for DIDComm v2 endpoints
+pub const SERVICE_TYPE: &str = "DIDCommMessaging";
+
+const ALG_ANONCRYPT: &str = "ECDH-ES+A256KW";
+const ALG_AUTHCRYPT: &str = "ECDH-1PU+A256KW";
+/// Maximum nesting of signed/encrypted envelopes accepted when unpacking
+const MAX_ENVELOPES: usize = 3;
+
+#[derive(Error, Debug)]
+pub enum DIDCommError {
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("JWS: {0}")]
+    JWS(#[from] ssi::jws::Error),
+    #[error("Unable to resolve DID '{0}': {1}")]
+    Resolution(String, String),
+    #[error("Verification method '{0}' not found")]
+    KeyNotFound(String),
+    #[error("Verification method '{0}' is not authorized for {1}")]
+    NotAuthorized(String, &'static str),
+    #[error("No key agreement keys for '{0}'")]
+    NoKeyAgreement(String),
+    #[error("Unable to use verification method: {0}")]
+    VerificationMethod(String),
+    #[error("Unsupported key type for key agreement")]
+    UnsupportedKey,
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+    #[error("Key agreement requires a private key")]
+    MissingPrivateKey,
+    #[error("Unsupported key management algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("Unsupported content encryption algorithm: {0}")]
+    UnsupportedEncryption(String),
+    #[error("Authenticated encryption requires A256CBC-HS512")]
+    AuthcryptEncryption,
+    #[error("Missing header parameter: {0}")]
+    MissingHeader(&'static str),
+    #[error("Recipients must use the same key type")]
+    CurveMismatch,
+    #[error("No recipients")]
+    NoRecipients,
+    #[error("Unable to decrypt message with the given keys")]
+    NoMatchingRecipient,
+    #[error("Decryption failed")]
+    Decryption,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Signed messages with more than one signature are not supported")]
+    MultipleSignatures,
+    #[error("Sender '{0}' does not match message 'from' ({1})")]
+    SenderMismatch(String, String),
+    #[error("Message is not a DIDComm message")]
+    InvalidMessage,
+    #[error("Too many nested envelopes")]
+    TooManyEnvelopes,
+}
+
+/// Content encryption algorithm for encrypted messages
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ContentEncryption {
+    #[serde(rename = "A256CBC-HS512")]
+    A256CBCHS512,
+    #[serde(rename = "A256GCM")]
+    A256GCM,
+}
+
+impl Default for ContentEncryption {
+    fn default() -> Self {
+        Self::A256CBCHS512
+    }
+}
+
+impl std::fmt::Display for ContentEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::A256CBCHS512 => write!(f, "A256CBC-HS512"),
+            Self::A256GCM => write!(f, "A256GCM"),
+        }
+    }
+}
+
+impl std::str::FromStr for ContentEncryption {
+    type Err = DIDCommError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "A256CBC-HS512" => Ok(Self::A256CBCHS512),
+            "A256GCM" => Ok(Self::A256GCM),
+            _ => Err(DIDCommError::UnsupportedEncryption(s.to_string())),
+        }
+    }
+}
+
+/// Plaintext DIDComm message
+/// <https://identity.foundation/didcomm-messaging/spec/v2.0/#message-headers>
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Message {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default)]
+    pub body: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pthid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<Value>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A key and the DID URL identifying it, for signing or authenticated encryption.
+pub struct SenderKey<'a> {
+    pub kid: &'a str,
+    pub key: &'a JWK,
+}
+
+/// How an unpacked message was protected.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct UnpackMetadata {
+    pub encrypted: bool,
+    pub authenticated: bool,
+    pub non_repudiation: bool,
+    pub anonymous_sender: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_from_kid: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub encrypted_to_kids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sign_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enc: Option<ContentEncryption>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UnpackedMessage {
+    pub message: Message,
+    pub metadata: UnpackMetadata,
+}
+
+/// DIDComm messaging service endpoint from a DID document
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MessagingService {
+    pub id: String,
+    pub uri: String,
+    #[serde(default)]
+    pub accept: Vec<String>,
+    #[serde(default)]
+    pub routing_keys: Vec<String>,
+}
+
+fn b64(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn b64_decode(data: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    base64::decode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn did_of(did_url: &str) -> &str {
+    did_url.split('#').next().unwrap_or(did_url)
+}
+
+// Key agreement
+
+#[derive(Clone)]
+enum AgreementKey {
+    X25519(x25519_dalek::PublicKey),
+    P256(p256::PublicKey),
+}
+
+enum AgreementSecret {
+    X25519(x25519_dalek::StaticSecret),
+    P256(p256::SecretKey),
+}
+
+fn okp_to_x25519(okp: &OctetParams) -> Result<[u8; 32], DIDCommError> {
+    let bytes: [u8; 32] = okp
+        .public_key
+        .0
+        .as_slice()
+        .try_into()
+        .map_err(|_| DIDCommError::InvalidPublicKey)?;
+    match &okp.curve[..] {
+        "X25519" => Ok(bytes),
+        "Ed25519" => Ok(curve25519_dalek::edwards::CompressedEdwardsY(bytes)
+            .decompress()
+            .ok_or(DIDCommError::InvalidPublicKey)?
+            .to_montgomery()
+            .to_bytes()),
+        _ => Err(DIDCommError::UnsupportedKey),
+    }
+}
+
+impl AgreementKey {
+    fn from_jwk(jwk: &JWK) -> Result<Self, DIDCommError> {
+        match &jwk.params {
+            Params::OKP(okp) => Ok(Self::X25519(x25519_dalek::PublicKey::from(okp_to_x25519(
+                okp,
+            )?))),
+            Params::EC(ec) if ec.curve.as_deref() == Some("P-256") => {
+                let (x, y) = match (&ec.x_coordinate, &ec.y_coordinate) {
+                    (Some(x), Some(y)) => (&x.0, &y.0),
+                    _ => return Err(DIDCommError::InvalidPublicKey),
+                };
+                let mut sec1 = vec![0x04];
+                sec1.extend_from_slice(x);
+                sec1.extend_from_slice(y);
+                let pk = p256::PublicKey::from_sec1_bytes(&sec1)
+                    .map_err(|_| DIDCommError::InvalidPublicKey)?;
+                Ok(Self::P256(pk))
+            }
+            _ => Err(DIDCommError::UnsupportedKey),
+        }
+    }
+
+    fn same_curve(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::X25519(_), Self::X25519(_)) | (Self::P256(_), Self::P256(_))
+        )
+    }
+
+    fn to_epk(&self) -> Value {
+        match self {
+            Self::X25519(pk) => json!({
+                "kty": "OKP",
+                "crv": "X25519",
+                "x": b64(pk.as_bytes()),
+            }),
+            Self::P256(pk) => {
+                let point = pk.to_encoded_point(false);
+                json!({
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "x": b64(point.x().unwrap()),
+                    "y": b64(point.y().unwrap()),
+                })
+            }
+        }
+    }
+}
+
+impl AgreementSecret {
+    fn from_jwk(jwk: &JWK) -> Result<Self, DIDCommError> {
+        match &jwk.params {
+            Params::OKP(okp) => {
+                let private_key = okp
+                    .private_key
+                    .as_ref()
+                    .ok_or(DIDCommError::MissingPrivateKey)?;
+                let mut bytes = [0u8; 32];
+                match &okp.curve[..] {
+                    "X25519" if private_key.0.len() == 32 => bytes.copy_from_slice(&private_key.0),
+                    // https://datatracker.ietf.org/doc/html/rfc8032#section-5.1.5
+                    "Ed25519" => bytes.copy_from_slice(&Sha512::digest(&private_key.0)[..32]),
+                    _ => return Err(DIDCommError::UnsupportedKey),
+                }
+                Ok(Self::X25519(x25519_dalek::StaticSecret::from(bytes)))
+            }
+            Params::EC(ec) if ec.curve.as_deref() == Some("P-256") => {
+                let private_key = ec
+                    .ecc_private_key
+                    .as_ref()
+                    .ok_or(DIDCommError::MissingPrivateKey)?;
+                let sk = p256::SecretKey::from_slice(&private_key.0)
+                    .map_err(|_| DIDCommError::UnsupportedKey)?;
+                Ok(Self::P256(sk))
+            }
+            _ => Err(DIDCommError::UnsupportedKey),
+        }
+    }
+
+    fn generate_like(key: &AgreementKey) -> Self {
+        match key {
+            AgreementKey::X25519(_) => {
+                Self::X25519(x25519_dalek::StaticSecret::random_from_rng(OsRng))
+            }
+            AgreementKey::P256(_) => Self::P256(p256::SecretKey::random(&mut OsRng)),
+        }
+    }
+
+    fn public(&self) -> AgreementKey {
+        match self {
+            Self::X25519(sk) => AgreementKey::X25519(x25519_dalek::PublicKey::from(sk)),
+            Self::P256(sk) => AgreementKey::P256(sk.public_key()),
+        }
+    }
+
+    fn diffie_hellman(&self, public: &AgreementKey) -> Result<Vec<u8>, DIDCommError> {
+        match (self, public) {
+            (Self::X25519(sk), AgreementKey::X25519(pk)) => {
+                let shared = sk.diffie_hellman(pk);
+                if !shared.was_contributory() {
+                    return Err(DIDCommError::InvalidPublicKey);
+                }
+                Ok(shared.as_bytes().to_vec())
+            }
+            (Self::P256(sk), AgreementKey::P256(pk)) => Ok(p256::ecdh::diffie_hellman(
+                sk.to_nonzero_scalar(),
+                pk.as_affine(),
+            )
+            .raw_secret_bytes()
+            .to_vec()),
+            _ => Err(DIDCommError::CurveMismatch),
+        }
+    }
+}
+
+/// Concat KDF with SHA-256, for a 256-bit key.
+/// <https://www.rfc-editor.org/rfc/rfc7518#section-4.6.2>,
+/// with the ECDH-1PU tag extension for key wrapping.
+fn concat_kdf(z: &[u8], alg: &str, apu: &[u8], apv: &[u8], cc_tag: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(1u32.to_be_bytes());
+    hasher.update(z);
+    for data in [alg.as_bytes(), apu, apv] {
+        hasher.update((data.len() as u32).to_be_bytes());
+        hasher.update(data);
+    }
+    hasher.update(256u32.to_be_bytes());
+    if !cc_tag.is_empty() {
+        hasher.update((cc_tag.len() as u32).to_be_bytes());
+        hasher.update(cc_tag);
+    }
+    hasher.finalize().into()
+}
+
+fn wrap_key(kek: &[u8; 32], cek: &[u8]) -> Result<Vec<u8>, DIDCommError> {
+    aes_kw::KekAes256::new(kek.into())
+        .wrap_vec(cek)
+        .map_err(|_| DIDCommError::Decryption)
+}
+
+fn unwrap_key(kek: &[u8; 32], encrypted_key: &[u8]) -> Option<Vec<u8>> {
+    aes_kw::KekAes256::new(kek.into())
+        .unwrap_vec(encrypted_key)
+        .ok()
+}
+
+// Content encryption
+
+type HmacSha512 = Hmac<Sha512>;
+
+fn cbc_hs512_tag(mac_key: &[u8], aad: &[u8], iv: &[u8], ciphertext: &[u8]) -> HmacSha512 {
+    let mut mac =
+        <HmacSha512 as Mac>::new_from_slice(mac_key).expect("HMAC accepts any key length");
+    mac.update(aad);
+    mac.update(iv);
+    mac.update(ciphertext);
+    mac.update(&((aad.len() as u64) * 8).to_be_bytes());
+    mac
+}
+
+impl ContentEncryption {
+    fn key_len(&self) -> usize {
+        match self {
+            Self::A256CBCHS512 => 64,
+            Self::A256GCM => 32,
+        }
+    }
+
+    /// Encrypt, returning (iv, ciphertext, tag)
+    fn encrypt(&self, cek: &[u8], aad: &[u8], plaintext: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        match self {
+            Self::A256CBCHS512 => {
+                let mut iv = [0u8; 16];
+                OsRng.fill_bytes(&mut iv);
+                let (mac_key, enc_key) = cek.split_at(32);
+                let ciphertext = cbc::Encryptor::<aes::Aes256>::new(enc_key.into(), &iv.into())
+                    .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+                let tag = cbc_hs512_tag(mac_key, aad, &iv, &ciphertext)
+                    .finalize()
+                    .into_bytes();
+                (iv.to_vec(), ciphertext, tag[..32].to_vec())
+            }
+            Self::A256GCM => {
+                let mut iv = [0u8; 12];
+                OsRng.fill_bytes(&mut iv);
+                let cipher = aes_gcm::Aes256Gcm::new(cek.into());
+                let mut ciphertext = cipher
+                    .encrypt(
+                        &iv.into(),
+                        Payload {
+                            msg: plaintext,
+                            aad,
+                        },
+                    )
+                    .expect("AES-GCM encryption of in-memory data");
+                let tag = ciphertext.split_off(ciphertext.len() - 16);
+                (iv.to_vec(), ciphertext, tag)
+            }
+        }
+    }
+
+    fn decrypt(
+        &self,
+        cek: &[u8],
+        aad: &[u8],
+        iv: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+    ) -> Result<Vec<u8>, DIDCommError> {
+        if cek.len() != self.key_len() {
+            return Err(DIDCommError::Decryption);
+        }
+        match self {
+            Self::A256CBCHS512 => {
+                if iv.len() != 16 {
+                    return Err(DIDCommError::Decryption);
+                }
+                let (mac_key, enc_key) = cek.split_at(32);
+                cbc_hs512_tag(mac_key, aad, iv, ciphertext)
+                    .verify_truncated_left(tag)
+                    .map_err(|_| DIDCommError::Decryption)?;
+                cbc::Decryptor::<aes::Aes256>::new(enc_key.into(), iv.into())
+                    .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+                    .map_err(|_| DIDCommError::Decryption)
+            }
+            Self::A256GCM => {
+                if iv.len() != 12 {
+                    return Err(DIDCommError::Decryption);
+                }
+                let cipher = aes_gcm::Aes256Gcm::new(cek.into());
+                let mut data = ciphertext.to_vec();
+                data.extend_from_slice(tag);
+                cipher
+                    .decrypt(iv.into(), Payload { msg: &data, aad })
+                    .map_err(|_| DIDCommError::Decryption)
+            }
+        }
+    }
+}
+
+// DID document key lookup
+
+async fn resolve_document(did: &str, resolver: &dyn DIDResolver) -> Result<Value, DIDCommError> {
+    let (res_meta, doc_opt, _) = resolver
+        .resolve(did, &ResolutionInputMetadata::default())
+        .await;
+    if let Some(error) = res_meta.error {
+        return Err(DIDCommError::Resolution(did.to_string(), error));
+    }
+    let doc = doc_opt.ok_or_else(|| {
+        DIDCommError::Resolution(did.to_string(), "missing DID document".to_string())
+    })?;
+    Ok(serde_json::to_value(doc)?)
+}
+
+fn absolute_id(doc_id: &str, id: &str) -> String {
+    if id.starts_with('#') {
+        format!("{}{}", doc_id, id)
+    } else {
+        id.to_string()
+    }
+}
+
+/// Verification methods declared in a DID document, including ones embedded in verification
+/// relationships, with absolute ids.
+fn verification_methods(doc: &Value) -> Vec<(String, Value)> {
+    let doc_id = doc.get("id").and_then(Value::as_str).unwrap_or_default();
+    let mut vms = Vec::new();
+    for property in [
+        "verificationMethod",
+        "keyAgreement",
+        "authentication",
+        "assertionMethod",
+    ] {
+        for vm in doc
+            .get(property)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(id) = vm.get("id").and_then(Value::as_str) {
+                vms.push((absolute_id(doc_id, id), vm.clone()));
+            }
+        }
+    }
+    vms
+}
+
+fn find_verification_method(doc: &Value, kid: &str) -> Result<Value, DIDCommError> {
+    verification_methods(doc)
+        .into_iter()
+        .find(|(id, _)| id == kid)
+        .map(|(_, vm)| vm)
+        .ok_or_else(|| DIDCommError::KeyNotFound(kid.to_string()))
+}
+
+/// Ids of the verification methods of a verification relationship, referenced or embedded, as
+/// absolute ids.
+fn relationship_ids(doc: &Value, property: &str) -> Vec<String> {
+    let doc_id = doc.get("id").and_then(Value::as_str).unwrap_or_default();
+    doc.get(property)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|vm| match vm {
+            Value::String(id) => Some(absolute_id(doc_id, id)),
+            vm => vm
+                .get("id")
+                .and_then(Value::as_str)
+                .map(|id| absolute_id(doc_id, id)),
+        })
+        .collect()
+}
+
+fn is_ed25519(jwk: &JWK) -> bool {
+    matches!(&jwk.params, Params::OKP(okp) if okp.curve == "Ed25519")
+}
+
+fn okp_jwk(curve: &str, public_key: Vec<u8>) -> JWK {
+    JWK::from(Params::OKP(OctetParams {
+        curve: curve.to_string(),
+        public_key: Base64urlUInt(public_key),
+        private_key: None,
+    }))
+}
+
+/// Get the public key of a verification method as a JWK.
+fn verification_method_jwk(vm: &Value) -> Result<JWK, DIDCommError> {
+    if let Some(jwk) = vm.get("publicKeyJwk") {
+        return Ok(serde_json::from_value(jwk.clone())?);
+    }
+    let type_ = vm.get("type").and_then(Value::as_str).unwrap_or_default();
+    if let Some(mb) = vm.get("publicKeyMultibase").and_then(Value::as_str) {
+        let (_, bytes) =
+            multibase::decode(mb).map_err(|e| DIDCommError::VerificationMethod(e.to_string()))?;
+        return match bytes.as_slice() {
+            [0xec, 0x01, key @ ..] => Ok(okp_jwk("X25519", key.to_vec())),
+            [0xed, 0x01, key @ ..] => Ok(okp_jwk("Ed25519", key.to_vec())),
+            key if type_.starts_with("X25519") => Ok(okp_jwk("X25519", key.to_vec())),
+            key if type_.starts_with("Ed25519") => Ok(okp_jwk("Ed25519", key.to_vec())),
+            _ => Err(DIDCommError::UnsupportedKey),
+        };
+    }
+    if let Some(b58) = vm.get("publicKeyBase58").and_then(Value::as_str) {
+        let bytes = bs58::decode(b58)
+            .into_vec()
+            .map_err(|e| DIDCommError::VerificationMethod(e.to_string()))?;
+        return if type_.starts_with("X25519") {
+            Ok(okp_jwk("X25519", bytes))
+        } else if type_.starts_with("Ed25519") {
+            Ok(okp_jwk("Ed25519", bytes))
+        } else {
+            Err(DIDCommError::UnsupportedKey)
+        };
+    }
+    Err(DIDCommError::VerificationMethod(
+        "no supported public key property".to_string(),
+    ))
+}
+
+/// Key agreement keys of a DID, or Ed25519 verification methods (converted to X25519) if the DID
+/// document has no `keyAgreement`.
+fn key_agreement_keys(doc: &Value) -> Vec<(String, AgreementKey)> {
+    let mut ids = relationship_ids(doc, "keyAgreement");
+    let fallback = ids.is_empty();
+    if fallback {
+        ids = verification_methods(doc)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+    }
+    let mut seen = HashSet::new();
+    ids.into_iter()
+        .filter(|id| seen.insert(id.clone()))
+        .filter_map(|id| {
+            let vm = find_verification_method(doc, &id).ok()?;
+            let jwk = verification_method_jwk(&vm).ok()?;
+            if fallback && !is_ed25519(&jwk) {
+                return None;
+            }
+            let key = AgreementKey::from_jwk(&jwk).ok()?;
+            Some((id, key))
+        })
+        .collect()
+}
+
+async fn resolve_agreement_key(
+    kid: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<AgreementKey, DIDCommError> {
+    let doc = resolve_document(did_of(kid), resolver).await?;
+    let not_authorized = || DIDCommError::NotAuthorized(kid.to_string(), "keyAgreement");
+    let key_agreement = relationship_ids(&doc, "keyAgreement");
+    let fallback = key_agreement.is_empty();
+    if !fallback && !key_agreement.iter().any(|id| id == kid) {
+        return Err(not_authorized());
+    }
+    let jwk = verification_method_jwk(&find_verification_method(&doc, kid)?)?;
+    if fallback && !is_ed25519(&jwk) {
+        return Err(not_authorized());
+    }
+    AgreementKey::from_jwk(&jwk)
+}
+
+/// Resolve the key agreement keys for a recipient, given as a DID or as a DID URL of a specific
+/// key.
+async fn recipient_keys(
+    to: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<Vec<(String, AgreementKey)>, DIDCommError> {
+    let doc = resolve_document(did_of(to), resolver).await?;
+    let keys = key_agreement_keys(&doc);
+    let keys: Vec<_> = if to.contains('#') {
+        keys.into_iter().filter(|(kid, _)| kid == to).collect()
+    } else {
+        keys
+    };
+    if keys.is_empty() {
+        return Err(DIDCommError::NoKeyAgreement(to.to_string()));
+    }
+    Ok(keys)
+}
+
+/// Find DIDComm v2 messaging service endpoints in a DID's document.
+pub async fn discover(
+    did: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<Vec<MessagingService>, DIDCommError> {
+    let doc = resolve_document(did, resolver).await?;
+    let doc_id = doc.get("id").and_then(Value::as_str).unwrap_or(did);
+    let mut services = Vec::new();
+    for service in doc
+        .get("service")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let is_didcomm = match service.get("type") {
+            Some(Value::String(type_)) => type_ == SERVICE_TYPE,
+            Some(Value::Array(types)) => types.iter().any(|t| t.as_str() == Some(SERVICE_TYPE)),
+            _ => false,
+        };
+        if !is_didcomm {
+            continue;
+        }
+        let id = absolute_id(
+            doc_id,
+            service
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        );
+        let endpoints = match service.get("serviceEndpoint") {
+            Some(Value::Array(endpoints)) => endpoints.clone(),
+            Some(endpoint) => vec![endpoint.clone()],
+            None => continue,
+        };
+        for endpoint in endpoints {
+            let strings = |key: &str| -> Vec<String> {
+                endpoint
+                    .get(key)
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect()
+            };
+            let uri = match &endpoint {
+                Value::String(uri) => uri.clone(),
+                endpoint => match endpoint.get("uri").and_then(Value::as_str) {
+                    Some(uri) => uri.to_string(),
+                    None => continue,
+                },
+            };
+            services.push(MessagingService {
+                id: id.clone(),
+                uri,
+                accept: strings("accept"),
+                routing_keys: strings("routingKeys"),
+            });
+        }
+    }
+    Ok(services)
+}
+
+// Packing
+
+/// Serialize a plaintext message.
+pub fn pack_plaintext(message: &Message) -> Result<String, DIDCommError> {
+    let mut message = message.clone();
+    message.typ = Some(TYP_PLAIN.to_string());
+    Ok(serde_json::to_string(&message)?)
+}
+
+/// Sign a message as a JWM (JWS general JSON serialization), for non-repudiation.
+pub fn pack_signed(message: &Message, signer: &SenderKey) -> Result<String, DIDCommError> {
+    let payload = b64(pack_plaintext(message)?.as_bytes());
+    let algorithm = signer
+        .key
+        .get_algorithm()
+        .ok_or(DIDCommError::UnsupportedKey)?;
+    let protected = json!({
+        "typ": TYP_SIGNED,
+        "alg": algorithm,
+        "kid": signer.kid,
+    });
+    let protected = b64(serde_json::to_string(&protected)?.as_bytes());
+    let signing_input = format!("{}.{}", protected, payload);
+    let signature = ssi::jws::sign_bytes(algorithm, signing_input.as_bytes(), signer.key)?;
+    Ok(serde_json::to_string(&json!({
+        "payload": payload,
+        "signatures": [{
+            "protected": protected,
+            "signature": b64(&signature),
+            "header": {"kid": signer.kid},
+        }],
+    }))?)
+}
+
+/// Encrypt a message for recipients, each given as a DID (all of its key agreement keys) or a
+/// DID URL of a key agreement key. With a sender key, the message is authenticated (authcrypt);
+/// otherwise it is anonymous (anoncrypt). With a signer, the message is signed before
+/// encryption.
+pub async fn pack_encrypted(
+    message: &Message,
+    to: &[String],
+    sender: Option<&SenderKey<'_>>,
+    signer: Option<&SenderKey<'_>>,
+    enc: ContentEncryption,
+    resolver: &dyn DIDResolver,
+) -> Result<String, DIDCommError> {
+    if to.is_empty() {
+        return Err(DIDCommError::NoRecipients);
+    }
+    let sender = match sender {
+        Some(sender) => {
+            if enc != ContentEncryption::A256CBCHS512 {
+                return Err(DIDCommError::AuthcryptEncryption);
+            }
+            let secret = AgreementSecret::from_jwk(sender.key)?;
+            // The sender's key must be published in its DID document.
+            let published = resolve_agreement_key(sender.kid, resolver).await?;
+            if secret.public().to_epk() != published.to_epk() {
+                return Err(DIDCommError::KeyNotFound(sender.kid.to_string()));
+            }
+            Some((sender.kid, secret))
+        }
+        None => None,
+    };
+    let mut recipients: Vec<(String, AgreementKey)> = Vec::new();
+    for recipient in to {
+        let keys = recipient_keys(recipient, resolver).await?;
+        // All recipients must share the key type of the sender, or of the first recipient.
+        let curve = match (&sender, recipients.first()) {
+            (Some((_, secret)), _) => secret.public(),
+            (None, Some((_, key))) => key.clone(),
+            (None, None) => keys[0].1.clone(),
+        };
+        let keys: Vec<_> = keys
+            .into_iter()
+            .filter(|(_, key)| key.same_curve(&curve))
+            .collect();
+        if keys.is_empty() {
+            return Err(DIDCommError::CurveMismatch);
+        }
+        recipients.extend(keys);
+    }
+
+    let plaintext = match signer {
+        Some(signer) => pack_signed(message, signer)?,
+        None => pack_plaintext(message)?,
+    };
+    let ephemeral = AgreementSecret::generate_like(&recipients[0].1);
+    let mut kids: Vec<&str> = recipients.iter().map(|(kid, _)| kid.as_str()).collect();
+    kids.sort_unstable();
+    let apv = Sha256::digest(kids.join(".").as_bytes()).to_vec();
+    let mut header = json!({
+        "typ": TYP_ENCRYPTED,
+        "alg": ALG_ANONCRYPT,
+        "enc": enc,
+        "apv": b64(&apv),
+        "epk": ephemeral.public().to_epk(),
+    });
+    let apu = match &sender {
+        Some((kid, _)) => {
+            header["alg"] = ALG_AUTHCRYPT.into();
+            header["skid"] = (*kid).into();
+            header["apu"] = b64(kid.as_bytes()).into();
+            kid.as_bytes().to_vec()
+        }
+        None => Vec::new(),
+    };
+    let protected = b64(serde_json::to_string(&header)?.as_bytes());
+
+    let mut cek = vec![0u8; enc.key_len()];
+    OsRng.fill_bytes(&mut cek);
+    let (iv, ciphertext, tag) = enc.encrypt(&cek, protected.as_bytes(), plaintext.as_bytes());
+
+    let mut recipients_json = Vec::new();
+    for (kid, key) in &recipients {
+        let (alg, z, cc_tag) = match &sender {
+            Some((_, secret)) => {
+                let mut z = ephemeral.diffie_hellman(key)?;
+                z.extend(secret.diffie_hellman(key)?);
+                (ALG_AUTHCRYPT, z, &tag[..])
+            }
+            None => (ALG_ANONCRYPT, ephemeral.diffie_hellman(key)?, &[][..]),
+        };
+        let kek = concat_kdf(&z, alg, &apu, &apv, cc_tag);
+        recipients_json.push(json!({
+            "header": {"kid": kid},
+            "encrypted_key": b64(&wrap_key(&kek, &cek)?),
+        }));
+    }
+    Ok(serde_json::to_string(&json!({
+        "protected": protected,
+        "recipients": recipients_json,
+        "iv": b64(&iv),
+        "ciphertext": b64(&ciphertext),
+        "tag": b64(&tag),
+    }))?)
+}
+
+// Unpacking
+
+fn header_str<'a>(header: &'a Value, name: &'static str) -> Result<&'a str, DIDCommError> {
+    header
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or(DIDCommError::MissingHeader(name))
+}
+
+fn json_str<'a>(value: &'a Value, name: &'static str) -> Result<&'a str, DIDCommError> {
+    value
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or(DIDCommError::InvalidMessage)
+}
+
+async fn decrypt(
+    jwe: &Value,
+    keys: &[JWK],
+    resolver: &dyn DIDResolver,
+    metadata: &mut UnpackMetadata,
+) -> Result<Vec<u8>, DIDCommError> {
+    let protected = json_str(jwe, "protected")?;
+    let header: Value = serde_json::from_slice(&b64_decode(protected)?)?;
+    let alg = header_str(&header, "alg")?;
+    let enc: ContentEncryption = header_str(&header, "enc")?.parse()?;
+    let epk: JWK = serde_json::from_value(
+        header
+            .get("epk")
+            .cloned()
+            .ok_or(DIDCommError::MissingHeader("epk"))?,
+    )?;
+    let epk = AgreementKey::from_jwk(&epk)?;
+    let apv = b64_decode(header_str(&header, "apv")?)?;
+    let iv = b64_decode(json_str(jwe, "iv")?)?;
+    let ciphertext = b64_decode(json_str(jwe, "ciphertext")?)?;
+    let tag = b64_decode(json_str(jwe, "tag")?)?;
+
+    let (sender, apu) = match alg {
+        ALG_ANONCRYPT => (None, Vec::new()),
+        ALG_AUTHCRYPT => {
+            if enc != ContentEncryption::A256CBCHS512 {
+                return Err(DIDCommError::AuthcryptEncryption);
+            }
+            let skid = header_str(&header, "skid")?;
+            let apu = b64_decode(header_str(&header, "apu")?)?;
+            if apu != skid.as_bytes() {
+                return Err(DIDCommError::MissingHeader("apu"));
+            }
+            let sender_key = resolve_agreement_key(skid, resolver).await?;
+            (Some((skid.to_string(), sender_key)), apu)
+        }
+        alg => return Err(DIDCommError::UnsupportedAlgorithm(alg.to_string())),
+    };
+
+    let secrets: Vec<AgreementSecret> = keys
+        .iter()
+        .filter_map(|key| AgreementSecret::from_jwk(key).ok())
+        .filter(|secret| secret.public().same_curve(&epk))
+        .collect();
+    let recipients = jwe
+        .get("recipients")
+        .and_then(Value::as_array)
+        .ok_or(DIDCommError::InvalidMessage)?;
+    metadata.encrypted_to_kids = recipients
+        .iter()
+        .filter_map(|r| r.pointer("/header/kid").and_then(Value::as_str))
+        .map(String::from)
+        .collect();
+    let mut cek = None;
+    'recipients: for recipient in recipients {
+        let encrypted_key = b64_decode(json_str(recipient, "encrypted_key")?)?;
+        for secret in &secrets {
+            let mut z = secret.diffie_hellman(&epk)?;
+            let cc_tag: &[u8] = match &sender {
+                Some((_, sender_key)) => {
+                    z.extend(secret.diffie_hellman(sender_key)?);
+                    &tag
+                }
+                None => &[],
+            };
+            let kek = concat_kdf(&z, alg, &apu, &apv, cc_tag);
+            if let Some(key) = unwrap_key(&kek, &encrypted_key) {
+                cek = Some(key);
+                break 'recipients;
+            }
+        }
+    }
+    let cek = cek.ok_or(DIDCommError::NoMatchingRecipient)?;
+    let plaintext = enc.decrypt(&cek, protected.as_bytes(), &iv, &ciphertext, &tag)?;
+    metadata.encrypted = true;
+    metadata.enc = Some(enc);
+    match sender {
+        Some((skid, _)) => {
+            metadata.authenticated = true;
+            metadata.encrypted_from_kid = Some(skid);
+        }
+        None => metadata.anonymous_sender = true,
+    }
+    Ok(plaintext)
+}
+
+async fn verify_signed(
+    jws: &Value,
+    resolver: &dyn DIDResolver,
+    metadata: &mut UnpackMetadata,
+) -> Result<Vec<u8>, DIDCommError> {
+    let payload = json_str(jws, "payload")?;
+    let signature = match jws
+        .get("signatures")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+    {
+        Some([signature]) => signature,
+        Some([_, _, ..]) => return Err(DIDCommError::MultipleSignatures),
+        _ => return Err(DIDCommError::InvalidMessage),
+    };
+    let protected = json_str(signature, "protected")?;
+    let header: Value = serde_json::from_slice(&b64_decode(protected)?)?;
+    let algorithm: Algorithm = serde_json::from_value(
+        header
+            .get("alg")
+            .cloned()
+            .ok_or(DIDCommError::MissingHeader("alg"))?,
+    )?;
+    let kid = header_str(&header, "kid")?;
+    let doc = resolve_document(did_of(kid), resolver).await?;
+    // The signing key must be authorized to authenticate the sender.
+    if !relationship_ids(&doc, "authentication")
+        .iter()
+        .any(|id| id == kid)
+    {
+        return Err(DIDCommError::NotAuthorized(
+            kid.to_string(),
+            "authentication",
+        ));
+    }
+    let jwk = verification_method_jwk(&find_verification_method(&doc, kid)?)?;
+    let sig = b64_decode(json_str(signature, "signature")?)?;
+    let signing_input = format!("{}.{}", protected, payload);
+    ssi::jws::verify_bytes(algorithm, signing_input.as_bytes(), &jwk, &sig)
+        .map_err(|_| DIDCommError::InvalidSignature)?;
+    metadata.authenticated = true;
+    metadata.non_repudiation = true;
+    metadata.sign_from = Some(kid.to_string());
+    Ok(b64_decode(payload)?)
+}
+
+/// Unpack a plaintext, signed or encrypted message, decrypting with any of the given key
+/// agreement keys and verifying signatures and sender authentication against the resolved DID
+/// documents.
+pub async fn unpack(
+    packed: &str,
+    keys: &[JWK],
+    resolver: &dyn DIDResolver,
+) -> Result<UnpackedMessage, DIDCommError> {
+    let mut metadata = UnpackMetadata::default();
+    let mut data: Value = serde_json::from_str(packed)?;
+    for _ in 0..MAX_ENVELOPES {
+        let inner = if data.get("ciphertext").is_some() {
+            decrypt(&data, keys, resolver, &mut metadata).await?
+        } else if data.get("signatures").is_some() {
+            verify_signed(&data, resolver, &mut metadata).await?
+        } else {
+            let message: Message = serde_json::from_value(data)?;
+            let from = message.from.as_deref();
+            for sender in metadata
+                .encrypted_from_kid
+                .iter()
+                .chain(metadata.sign_from.iter())
+            {
+                if Some(did_of(sender)) != from.map(did_of) {
+                    return Err(DIDCommError::SenderMismatch(
+                        sender.to_string(),
+                        from.unwrap_or("none").to_string(),
+                    ));
+                }
+            }
+            return Ok(UnpackedMessage { message, metadata });
+        };
+        data = serde_json::from_slice(&inner)?;
+    }
+    Err(DIDCommError::TooManyEnvelopes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Source, DID_METHODS};
+
+    fn message(from: &str, to: &str) -> Message {
+        serde_json::from_value(json!({
+            "id": "1234567890",
+            "type": "https://didcomm.org/basicmessage/2.0/message",
+            "from": from,
+            "to": [to],
+            "created_time": 1516269022,
+            "body": {"content": "Hello"}
+        }))
+        .unwrap()
+    }
+
+    fn did_key(key: &JWK) -> (String, String) {
+        let did = DID_METHODS
+            .generate(&Source::KeyAndPattern(key, "key"))
+            .unwrap();
+        let kid = format!("{}#{}", did, &did["did:key:".len()..]);
+        (did, kid)
+    }
+
+    #[test]
+    fn content_encryption_roundtrip() {
+        for enc in [ContentEncryption::A256CBCHS512, ContentEncryption::A256GCM] {
+            let cek = vec![7u8; enc.key_len()];
+            let (iv, ciphertext, tag) = enc.encrypt(&cek, b"aad", b"hello");
+            let plaintext = enc.decrypt(&cek, b"aad", &iv, &ciphertext, &tag).unwrap();
+            assert_eq!(plaintext, b"hello");
+            assert!(enc.decrypt(&cek, b"bad", &iv, &ciphertext, &tag).is_err());
+        }
+    }
+
+    #[test]
+    fn pack_unpack() {
+        let alice_key = JWK::generate_ed25519().unwrap();
+        let bob_key = JWK::generate_ed25519().unwrap();
+        let (alice, alice_kid) = did_key(&alice_key);
+        let (bob, _) = did_key(&bob_key);
+        let msg = message(&alice, &bob);
+        let resolver = DID_METHODS.to_resolver();
+        let rt = crate::runtime::get().unwrap();
+        let sender = SenderKey {
+            kid: &alice_kid,
+            key: &alice_key,
+        };
+
+        let packed = rt
+            .block_on(pack_encrypted(
+                &msg,
+                &[bob.clone()],
+                Some(&sender),
+                Some(&sender),
+                ContentEncryption::default(),
+                resolver,
+            ))
+            .unwrap();
+        let unpacked = rt
+            .block_on(unpack(&packed, &[bob_key.clone()], resolver))
+            .unwrap();
+        assert_eq!(unpacked.message.body, msg.body);
+        assert!(unpacked.metadata.authenticated && unpacked.metadata.non_repudiation);
+        assert_eq!(
+            unpacked.metadata.encrypted_from_kid,
+            Some(alice_kid.clone())
+        );
+        assert!(rt
+            .block_on(unpack(&packed, &[alice_key], resolver))
+            .is_err());
+
+        let packed = rt
+            .block_on(pack_encrypted(
+                &msg,
+                &[bob],
+                None,
+                None,
+                ContentEncryption::A256GCM,
+                resolver,
+            ))
+            .unwrap();
+        let unpacked = rt.block_on(unpack(&packed, &[bob_key], resolver)).unwrap();
+        assert!(unpacked.metadata.anonymous_sender && !unpacked.metadata.authenticated);
+    }
+
+    #[test]
+    fn signer_relationship() {
+        use crate::did_fixtures::{Fixture, FixtureResolver};
+
+        let key = JWK::generate_ed25519().unwrap();
+        let did = "did:example:alice";
+        let kid = "did:example:alice#key-1";
+        let document = |relationship: &str| {
+            let mut document = json!({
+                "@context": "https://www.w3.org/ns/did/v1",
+                "id": did,
+                "verificationMethod": [{
+                    "id": kid,
+                    "type": "JsonWebKey2020",
+                    "controller": did,
+                    "publicKeyJwk": key.to_public(),
+                }],
+            });
+            document[relationship] = json!([kid]);
+            let mut resolver = FixtureResolver::new();
+            resolver
+                .insert(Fixture::from_value(document).unwrap())
+                .unwrap();
+            resolver
+        };
+        let msg = message(did, "did:example:bob");
+        let signer = SenderKey { kid, key: &key };
+        let packed = pack_signed(&msg, &signer).unwrap();
+        let rt = crate::runtime::get().unwrap();
+
+        let resolver = document("authentication");
+        let unpacked = rt.block_on(unpack(&packed, &[], &resolver)).unwrap();
+        assert_eq!(unpacked.metadata.sign_from.as_deref(), Some(kid));
+
+        let resolver = document("assertionMethod");
+        assert!(matches!(
+            rt.block_on(unpack(&packed, &[], &resolver)),
+            Err(DIDCommError::NotAuthorized(_, "authentication"))
+        ));
+
+        // Only one signature is supported.
+        let mut jws: Value = serde_json::from_str(&packed).unwrap();
+        let signature = jws["signatures"][0].clone();
+        jws["signatures"].as_array_mut().unwrap().push(signature);
+        let resolver = document("authentication");
+        assert!(matches!(
+            rt.block_on(unpack(&jws.to_string(), &[], &resolver)),
+            Err(DIDCommError::MultipleSignatures)
+        ));
+    }
+}
@@ -25,6 +25,36 @@ pub enum Error {
     Borrow(#[from] std::cell::BorrowError),
     #[error(transparent)]
     IO(#[from] std::io::Error),
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    #[error(transparent)]
+    DIDMethod(#[from] ssi::did::DIDMethodError),
+    #[error(transparent)]
+    Config(#[from] crate::config::ConfigError),
+    #[error(transparent)]
+    Context(#[from] crate::context::ContextError),
+    #[error(transparent)]
+    DIDDocument(#[from] crate::did_document::DIDDocumentError),
+    #[error(transparent)]
+    DIDFixture(#[from] crate::did_fixtures::FixtureError),
+    #[error(transparent)]
+    DIDPeer(#[from] crate::did_peer::DIDPeerError),
+    #[error(transparent)]
+    DIDWeb(#[from] crate::did_web::DIDWebError),
+    #[error(transparent)]
+    DIDComm(#[from] crate::didcomm::DIDCommError),
+    #[error(transparent)]
+    KeyDerivation(#[from] crate::key_derive::KeyDerivationError),
+    #[error(transparent)]
+    KeyFormat(#[from] crate::key_format::KeyFormatError),
+    #[error(transparent)]
+    OfflineTx(#[from] crate::offline_tx::OfflineTxError),
+    #[error(transparent)]
+    OID4VP(#[from] crate::oid4vp::OID4VPError),
+    #[error(transparent)]
+    PresentationExchange(#[from] crate::presentation_exchange::PresentationExchangeError),
+    #[error(transparent)]
+    Capability(#[from] crate::zcap::ZcapError),
     #[error("Unable to generate DID")]
     UnableToGenerateDID,
     #[error("Unknown DID method")]
@@ -33,6 +63,11 @@ pub enum Error {
     UnableToGetVerificationMethod,
     #[error("Unknown proof format: {0}")]
     UnknownProofFormat(String),
+    #[error("Callback must not be NULL")]
+    NullCallback,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("JNI: {0}")]
+    JNI(#[from] jni::errors::Error),
 
     #[doc(hidden)]
     #[error("")]
@@ -49,15 +84,39 @@ impl Error {
         });
     }
 
-    fn get_code(&self) -> c_int {
-        // TODO: try to give each individual error its own number
+    /// Numeric code for the kind of error, as listed in the C library's README
+    pub(crate) fn get_code(&self) -> c_int {
         match self {
             Error::VC(_) => 1,
             Error::Null(_) => 2,
             Error::Utf8(_) => 3,
             Error::JWK(_) => 4,
             Error::Zcap(_) => 5,
-            _ => -1,
+            Error::Borrow(_) => 6,
+            Error::IO(_) => 7,
+            Error::Base64(_) => 8,
+            Error::DIDMethod(_) => 9,
+            Error::UnableToGenerateDID => 10,
+            Error::UnknownDIDMethod => 11,
+            Error::UnableToGetVerificationMethod => 12,
+            Error::UnknownProofFormat(_) => 13,
+            Error::Config(_) => 14,
+            Error::Context(_) => 15,
+            Error::DIDDocument(_) => 16,
+            Error::DIDFixture(_) => 17,
+            Error::DIDPeer(_) => 18,
+            Error::DIDWeb(_) => 19,
+            Error::DIDComm(_) => 20,
+            Error::KeyDerivation(_) => 21,
+            Error::KeyFormat(_) => 22,
+            Error::OfflineTx(_) => 23,
+            Error::OID4VP(_) => 24,
+            Error::PresentationExchange(_) => 25,
+            Error::Capability(_) => 26,
+            Error::NullCallback => 27,
+            #[cfg(not(target_arch = "wasm32"))]
+            Error::JNI(_) => 28,
+            Error::__Nonexhaustive => -1,
         }
     }
 }
@@ -118,7 +177,7 @@ mod tests {
             .to_str()
             .unwrap();
         let code = didkit_error_code();
-        assert_ne!(code, 0);
+        assert_eq!(code, 1);
         println!("code: {:?} msg: {:?}", code, msg);
     }
 }
@@ -1,11 +1,21 @@
+use std::collections::HashMap;
+use std::future::Future;
 use std::ptr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, RwLock};
 
-use jni::objects::{JClass, JString};
-use jni::sys::jstring;
+use jni::objects::{JClass, JObject, JString, JValue};
+use jni::sys::{jlong, jobject, jstring};
 use jni::JNIEnv;
 
+use crate::c;
+use crate::config::Config;
+use crate::context::{Context, ContextError};
 use crate::error::Error;
 use crate::get_verification_method;
+use crate::key_derive::{self, Curve};
+use crate::oid4vp::{self, AuthorizationRequest, AuthorizationResponse};
+use crate::presentation_exchange::{self, PresentationDefinition, PresentationSubmission};
 use crate::runtime;
 use crate::ResolutionResult;
 use crate::Source;
@@ -15,6 +25,8 @@ use crate::DID_METHODS;
 use crate::JWK;
 use crate::{dereference, DereferencingInputMetadata, ResolutionInputMetadata};
 use crate::{JWTOrLDPOptions, ProofFormat};
+use serde_json::Value;
+use ssi::did::DIDMethods;
 
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
 pub static DIDKIT_EXCEPTION_CLASS: &str = "com/spruceid/DIDKitException";
@@ -105,6 +117,76 @@ pub extern "system" fn Java_com_spruceid_DIDKit_keyToVerificationMethod(
     jstring_or_error(&env, key_to_verification_method(&env, method_pattern, jwk))
 }
 
+fn key_derive_from_mnemonic(
+    env: &JNIEnv,
+    mnemonic_jstring: JString,
+    passphrase_jstring: JString,
+    curve_jstring: JString,
+    path_jstring: JString,
+) -> Result<jstring, Error> {
+    let mnemonic: String = env.get_string(mnemonic_jstring).unwrap().into();
+    let passphrase: String = if passphrase_jstring.is_null() {
+        String::new()
+    } else {
+        env.get_string(passphrase_jstring).unwrap().into()
+    };
+    let curve_string: String = env.get_string(curve_jstring).unwrap().into();
+    let curve: Curve = curve_string.parse()?;
+    let path: String = if path_jstring.is_null() {
+        curve.default_path().to_string()
+    } else {
+        env.get_string(path_jstring).unwrap().into()
+    };
+    let jwk = key_derive::derive_from_mnemonic(&mnemonic, &passphrase, curve, &path)?;
+    let jwk_json = serde_json::to_string(&jwk)?;
+    Ok(env.new_string(jwk_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_keyDeriveFromMnemonic(
+    env: JNIEnv,
+    _class: JClass,
+    mnemonic: JString,
+    passphrase: JString,
+    curve: JString,
+    path: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        key_derive_from_mnemonic(&env, mnemonic, passphrase, curve, path),
+    )
+}
+
+fn key_derive_from_seed(
+    env: &JNIEnv,
+    seed_jstring: JString,
+    curve_jstring: JString,
+    path_jstring: JString,
+) -> Result<jstring, Error> {
+    let seed: String = env.get_string(seed_jstring).unwrap().into();
+    let curve_string: String = env.get_string(curve_jstring).unwrap().into();
+    let curve: Curve = curve_string.parse()?;
+    let path: String = if path_jstring.is_null() {
+        curve.default_path().to_string()
+    } else {
+        env.get_string(path_jstring).unwrap().into()
+    };
+    let jwk = key_derive::derive_from_seed_hex(&seed, curve, &path)?;
+    let jwk_json = serde_json::to_string(&jwk)?;
+    Ok(env.new_string(jwk_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_keyDeriveFromSeed(
+    env: JNIEnv,
+    _class: JClass,
+    seed: JString,
+    curve: JString,
+    path: JString,
+) -> jstring {
+    jstring_or_error(&env, key_derive_from_seed(&env, seed, curve, path))
+}
+
 fn issue_credential(
     env: &JNIEnv,
     credential_jstring: JString,
@@ -317,10 +399,22 @@ pub extern "system" fn Java_com_spruceid_DIDKit_verifyPresentation(
     jstring_or_error(&env, verify_presentation(&env, presentation, options))
 }
 
+fn did_methods_from_config(
+    env: &JNIEnv,
+    config_toml_jstring: JString,
+) -> Result<Option<DIDMethods<'static>>, Error> {
+    if config_toml_jstring.is_null() {
+        return Ok(None);
+    }
+    let config_toml: String = env.get_string(config_toml_jstring).unwrap().into();
+    Ok(Some(Config::from_toml(&config_toml)?.did_methods()?))
+}
+
 fn resolve_did(
     env: &JNIEnv,
     did_jstring: JString,
     input_metadata_jstring: JString,
+    config_toml_jstring: JString,
 ) -> Result<jstring, Error> {
     let did: String = env.get_string(did_jstring).unwrap().into();
     let input_metadata_json: String = if input_metadata_jstring.is_null() {
@@ -329,7 +423,8 @@ fn resolve_did(
         "{}".to_string()
     };
     let input_metadata: ResolutionInputMetadata = serde_json::from_str(&input_metadata_json)?;
-    let resolver = DID_METHODS.to_resolver();
+    let did_methods = did_methods_from_config(env, config_toml_jstring)?;
+    let resolver = did_methods.as_ref().unwrap_or(&DID_METHODS).to_resolver();
     let rt = runtime::get()?;
     let (res_meta, doc_opt, doc_meta_opt) = rt.block_on(resolver.resolve(&did, &input_metadata));
     let result = ResolutionResult {
@@ -349,13 +444,28 @@ pub extern "system" fn Java_com_spruceid_DIDKit_resolveDID(
     did: JString,
     input_metadata: JString,
 ) -> jstring {
-    jstring_or_error(&env, resolve_did(&env, did, input_metadata))
+    jstring_or_error(
+        &env,
+        resolve_did(&env, did, input_metadata, JObject::null().into()),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_resolveDIDWithConfig(
+    env: JNIEnv,
+    _class: JClass,
+    did: JString,
+    input_metadata: JString,
+    config: JString,
+) -> jstring {
+    jstring_or_error(&env, resolve_did(&env, did, input_metadata, config))
 }
 
 fn dereference_did_url(
     env: &JNIEnv,
     did_url_jstring: JString,
     input_metadata_jstring: JString,
+    config_toml_jstring: JString,
 ) -> Result<jstring, Error> {
     let did_url: String = env.get_string(did_url_jstring).unwrap().into();
     let input_metadata_json: String = if input_metadata_jstring.is_null() {
@@ -364,7 +474,8 @@ fn dereference_did_url(
         "{}".to_string()
     };
     let input_metadata: DereferencingInputMetadata = serde_json::from_str(&input_metadata_json)?;
-    let resolver = DID_METHODS.to_resolver();
+    let did_methods = did_methods_from_config(env, config_toml_jstring)?;
+    let resolver = did_methods.as_ref().unwrap_or(&DID_METHODS).to_resolver();
     let rt = runtime::get()?;
     let deref_result = rt.block_on(dereference(resolver, &did_url, &input_metadata));
     let result_json = serde_json::to_string(&deref_result)?;
@@ -378,5 +489,769 @@ pub extern "system" fn Java_com_spruceid_DIDKit_dereferenceDIDURL(
     did_url: JString,
     input_metadata: JString,
 ) -> jstring {
-    jstring_or_error(&env, dereference_did_url(&env, did_url, input_metadata))
+    jstring_or_error(
+        &env,
+        dereference_did_url(&env, did_url, input_metadata, JObject::null().into()),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_dereferenceDIDURLWithConfig(
+    env: JNIEnv,
+    _class: JClass,
+    did_url: JString,
+    input_metadata: JString,
+    config: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        dereference_did_url(&env, did_url, input_metadata, config),
+    )
+}
+
+fn oid4vp_create_request(
+    env: &JNIEnv,
+    request_jstring: JString,
+    key_jstring: JString,
+    verification_method_jstring: JString,
+) -> Result<jstring, Error> {
+    let request_json: String = env.get_string(request_jstring).unwrap().into();
+    let request: AuthorizationRequest = serde_json::from_str(&request_json)?;
+    let uri = if key_jstring.is_null() {
+        request.to_uri(oid4vp::DEFAULT_REQUEST_URI_BASE)?
+    } else {
+        let key_json: String = env.get_string(key_jstring).unwrap().into();
+        let verification_method: String =
+            env.get_string(verification_method_jstring).unwrap().into();
+        let key: JWK = serde_json::from_str(&key_json)?;
+        let request_object = oid4vp::sign_request_object(&request, &key, &verification_method)?;
+        oid4vp::request_object_uri(
+            &request.client_id,
+            &request_object,
+            oid4vp::DEFAULT_REQUEST_URI_BASE,
+        )?
+    };
+    Ok(env.new_string(uri).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_oid4vpCreateRequest(
+    env: JNIEnv,
+    _class: JClass,
+    request: JString,
+    key: JString,
+    verification_method: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        oid4vp_create_request(&env, request, key, verification_method),
+    )
+}
+
+fn oid4vp_parse_request(env: &JNIEnv, request_uri_jstring: JString) -> Result<jstring, Error> {
+    let request_uri: String = env.get_string(request_uri_jstring).unwrap().into();
+    let resolver = DID_METHODS.to_resolver();
+    let rt = runtime::get()?;
+    let request = rt.block_on(oid4vp::parse_request_uri(&request_uri, resolver))?;
+    let request_json = serde_json::to_string(&request)?;
+    Ok(env.new_string(request_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_oid4vpParseRequest(
+    env: JNIEnv,
+    _class: JClass,
+    request_uri: JString,
+) -> jstring {
+    jstring_or_error(&env, oid4vp_parse_request(&env, request_uri))
+}
+
+fn oid4vp_create_response(
+    env: &JNIEnv,
+    request_jstring: JString,
+    presentation_jstring: JString,
+    presentation_submission_jstring: JString,
+    proof_options_jstring: JString,
+    key_jstring: JString,
+) -> Result<jstring, Error> {
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let request_json: String = env.get_string(request_jstring).unwrap().into();
+    let presentation_json: String = env.get_string(presentation_jstring).unwrap().into();
+    let presentation_submission_json: String = env
+        .get_string(presentation_submission_jstring)
+        .unwrap()
+        .into();
+    let proof_options_json: String = env.get_string(proof_options_jstring).unwrap().into();
+    let key_json: String = env.get_string(key_jstring).unwrap().into();
+    let request: AuthorizationRequest = serde_json::from_str(&request_json)?;
+    let presentation = VerifiablePresentation::from_json_unsigned(&presentation_json)?;
+    let presentation_submission = serde_json::from_str(&presentation_submission_json)?;
+    let options: JWTOrLDPOptions = serde_json::from_str(&proof_options_json)?;
+    let key: JWK = serde_json::from_str(&key_json)?;
+    let rt = runtime::get()?;
+    let response = rt.block_on(oid4vp::create_response(
+        &request,
+        presentation,
+        presentation_submission,
+        Some(&key),
+        options,
+        resolver,
+        &mut context_loader,
+        None,
+    ))?;
+    let response_json = serde_json::to_string(&response)?;
+    Ok(env.new_string(response_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_oid4vpCreateResponse(
+    env: JNIEnv,
+    _class: JClass,
+    request: JString,
+    presentation: JString,
+    presentation_submission: JString,
+    options: JString,
+    key: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        oid4vp_create_response(
+            &env,
+            request,
+            presentation,
+            presentation_submission,
+            options,
+            key,
+        ),
+    )
+}
+
+fn oid4vp_verify_response(
+    env: &JNIEnv,
+    response_jstring: JString,
+    request_jstring: JString,
+) -> Result<jstring, Error> {
+    let response_json: String = env.get_string(response_jstring).unwrap().into();
+    let request_json: String = env.get_string(request_jstring).unwrap().into();
+    let response: AuthorizationResponse = serde_json::from_str(&response_json)?;
+    let request: AuthorizationRequest = serde_json::from_str(&request_json)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let rt = runtime::get()?;
+    let result = rt.block_on(oid4vp::verify_response(
+        &response,
+        &request,
+        resolver,
+        &mut context_loader,
+    ))?;
+    let result_json = serde_json::to_string(&result)?;
+    Ok(env.new_string(result_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_oid4vpVerifyResponse(
+    env: JNIEnv,
+    _class: JClass,
+    response: JString,
+    request: JString,
+) -> jstring {
+    jstring_or_error(&env, oid4vp_verify_response(&env, response, request))
+}
+
+fn presentation_exchange_evaluate(
+    env: &JNIEnv,
+    definition_jstring: JString,
+    credentials_jstring: JString,
+) -> Result<jstring, Error> {
+    let definition_json: String = env.get_string(definition_jstring).unwrap().into();
+    let credentials_json: String = env.get_string(credentials_jstring).unwrap().into();
+    let definition: PresentationDefinition = serde_json::from_str(&definition_json)?;
+    let credentials: Vec<Value> = serde_json::from_str(&credentials_json)?;
+    let evaluation = presentation_exchange::evaluate(&definition, &credentials)?;
+    let evaluation_json = serde_json::to_string(&evaluation)?;
+    Ok(env.new_string(evaluation_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_presentationExchangeEvaluate(
+    env: JNIEnv,
+    _class: JClass,
+    definition: JString,
+    credentials: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        presentation_exchange_evaluate(&env, definition, credentials),
+    )
+}
+
+fn presentation_exchange_create(
+    env: &JNIEnv,
+    definition_jstring: JString,
+    credentials_jstring: JString,
+    holder_jstring: JString,
+    proof_format_jstring: JString,
+) -> Result<jstring, Error> {
+    let definition_json: String = env.get_string(definition_jstring).unwrap().into();
+    let credentials_json: String = env.get_string(credentials_jstring).unwrap().into();
+    let holder: Option<String> = if holder_jstring.is_null() {
+        None
+    } else {
+        Some(env.get_string(holder_jstring).unwrap().into())
+    };
+    let proof_format = if proof_format_jstring.is_null() {
+        ProofFormat::default()
+    } else {
+        let proof_format: String = env.get_string(proof_format_jstring).unwrap().into();
+        proof_format
+            .parse()
+            .map_err(|_| Error::UnknownProofFormat(proof_format))?
+    };
+    let definition: PresentationDefinition = serde_json::from_str(&definition_json)?;
+    let credentials: Vec<Value> = serde_json::from_str(&credentials_json)?;
+    let (presentation, presentation_submission) = presentation_exchange::build_presentation(
+        &definition,
+        &credentials,
+        holder.as_deref(),
+        proof_format,
+    )?;
+    let result_json = serde_json::to_string(&serde_json::json!({
+        "presentation": presentation,
+        "presentation_submission": presentation_submission,
+    }))?;
+    Ok(env.new_string(result_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_presentationExchangeCreate(
+    env: JNIEnv,
+    _class: JClass,
+    definition: JString,
+    credentials: JString,
+    holder: JString,
+    proof_format: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        presentation_exchange_create(&env, definition, credentials, holder, proof_format),
+    )
+}
+
+fn presentation_exchange_verify(
+    env: &JNIEnv,
+    definition_jstring: JString,
+    submission_jstring: JString,
+    presentation_jstring: JString,
+) -> Result<jstring, Error> {
+    let definition_json: String = env.get_string(definition_jstring).unwrap().into();
+    let submission_json: String = env.get_string(submission_jstring).unwrap().into();
+    let presentation_string: String = env.get_string(presentation_jstring).unwrap().into();
+    let definition: PresentationDefinition = serde_json::from_str(&definition_json)?;
+    let submission: PresentationSubmission = serde_json::from_str(&submission_json)?;
+    let presentation = match serde_json::from_str(&presentation_string) {
+        Ok(presentation) => presentation,
+        Err(_) => Value::String(presentation_string),
+    };
+    let check = presentation_exchange::check_submission(&definition, &submission, &presentation)?;
+    let check_json = serde_json::to_string(&check)?;
+    Ok(env.new_string(check_json).unwrap().into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_presentationExchangeVerify(
+    env: JNIEnv,
+    _class: JClass,
+    definition: JString,
+    submission: JString,
+    presentation: JString,
+) -> jstring {
+    jstring_or_error(
+        &env,
+        presentation_exchange_verify(&env, definition, submission, presentation),
+    )
+}
+
+// Asynchronous methods return a CompletableFuture, completed from a thread of the shared runtime
+// once the operation finishes. Arguments are copied out of the JNI environment before the
+// operation is spawned, so the caller's local references are not used after returning. Classes
+// are looked up on the calling thread: on Android, application classes cannot be found from
+// threads attached by native code.
+
+static RUNTIME_EXCEPTION_CLASS: &str = "java/lang/RuntimeException";
+
+fn completable_future<Fut>(env: &JNIEnv, future: Fut) -> Result<jobject, Error>
+where
+    Fut: Future<Output = Result<String, Error>> + Send + 'static,
+{
+    let rt = runtime::shared()?;
+    let exception_class = env.find_class(DIDKIT_EXCEPTION_CLASS)?;
+    let exception_class_ref = env.new_global_ref(JObject::from(exception_class))?;
+    let completable = env.new_object("java/util/concurrent/CompletableFuture", "()V", &[])?;
+    let completable_ref = env.new_global_ref(completable)?;
+    let vm = env.get_java_vm()?;
+    rt.spawn(async move {
+        let result = future.await;
+        // Worker threads stay attached, rather than attaching and detaching for each call.
+        let env = match vm.attach_current_thread_permanently() {
+            Ok(env) => env,
+            Err(_) => return,
+        };
+        let completable = completable_ref.as_obj();
+        let completed = complete(&env, completable, exception_class_ref.as_obj(), result);
+        if let Err(err) = completed {
+            // Complete the future with a RuntimeException instead, so that it does not hang.
+            let _ = env.exception_clear();
+            let _ = complete_exceptionally(
+                &env,
+                completable,
+                JClass::from(JObject::null()),
+                RUNTIME_EXCEPTION_CLASS,
+                err.to_string(),
+            );
+            let _ = env.exception_clear();
+        }
+    });
+    Ok(completable.into_inner())
+}
+
+fn complete(
+    env: &JNIEnv,
+    completable: JObject,
+    exception_class: JObject,
+    result: Result<String, Error>,
+) -> jni::errors::Result<()> {
+    match result {
+        Ok(out) => {
+            let out = env.new_string(out)?;
+            env.call_method(
+                completable,
+                "complete",
+                "(Ljava/lang/Object;)Z",
+                &[JValue::Object(out.into())],
+            )?;
+            Ok(())
+        }
+        Err(err) => complete_exceptionally(
+            env,
+            completable,
+            JClass::from(exception_class),
+            DIDKIT_EXCEPTION_CLASS,
+            err.to_string(),
+        ),
+    }
+}
+
+// Complete a future with an exception of the given class, or of the named class if the class is
+// null
+fn complete_exceptionally(
+    env: &JNIEnv,
+    completable: JObject,
+    class: JClass,
+    class_name: &str,
+    message: String,
+) -> jni::errors::Result<()> {
+    let message = env.new_string(message)?;
+    let args = [JValue::Object(message.into())];
+    let exception = if class.is_null() {
+        env.new_object(class_name, "(Ljava/lang/String;)V", &args)?
+    } else {
+        env.new_object(class, "(Ljava/lang/String;)V", &args)?
+    };
+    env.call_method(
+        completable,
+        "completeExceptionally",
+        "(Ljava/lang/Throwable;)Z",
+        &[JValue::Object(exception)],
+    )?;
+    Ok(())
+}
+
+fn jobject_or_error(env: &JNIEnv, result: Result<jobject, Error>) -> jobject {
+    match result {
+        Ok(jobject) => jobject,
+        Err(err) => {
+            env.throw_new(DIDKIT_EXCEPTION_CLASS, err.to_string())
+                .unwrap();
+            ptr::null_mut()
+        }
+    }
+}
+
+fn string(env: &JNIEnv, jstring: JString) -> String {
+    env.get_string(jstring).unwrap().into()
+}
+
+fn string_opt(env: &JNIEnv, jstring: JString) -> Option<String> {
+    if jstring.is_null() {
+        return None;
+    }
+    Some(string(env, jstring))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_keyToVerificationMethodAsync(
+    env: JNIEnv,
+    _class: JClass,
+    method_pattern: JString,
+    key: JString,
+) -> jobject {
+    let future = c::key_to_verification_method(string(&env, method_pattern), string(&env, key));
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_issueCredentialAsync(
+    env: JNIEnv,
+    _class: JClass,
+    credential: JString,
+    options: JString,
+    key: JString,
+) -> jobject {
+    let future = c::issue_credential(
+        Context::shared_default(),
+        string(&env, credential),
+        string(&env, options),
+        string(&env, key),
+    );
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_verifyCredentialAsync(
+    env: JNIEnv,
+    _class: JClass,
+    credential: JString,
+    options: JString,
+) -> jobject {
+    let future = c::verify_credential(
+        Context::shared_default(),
+        string(&env, credential),
+        string(&env, options),
+    );
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_issuePresentationAsync(
+    env: JNIEnv,
+    _class: JClass,
+    presentation: JString,
+    options: JString,
+    key: JString,
+) -> jobject {
+    let future = c::issue_presentation(
+        Context::shared_default(),
+        string(&env, presentation),
+        string(&env, options),
+        string(&env, key),
+    );
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_DIDAuthAsync(
+    env: JNIEnv,
+    _class: JClass,
+    holder: JString,
+    options: JString,
+    key: JString,
+) -> jobject {
+    let future = c::did_auth(
+        Context::shared_default(),
+        string(&env, holder),
+        string(&env, options),
+        string(&env, key),
+    );
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_verifyPresentationAsync(
+    env: JNIEnv,
+    _class: JClass,
+    presentation: JString,
+    options: JString,
+) -> jobject {
+    let future = c::verify_presentation(
+        Context::shared_default(),
+        string(&env, presentation),
+        string(&env, options),
+    );
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_resolveDIDAsync(
+    env: JNIEnv,
+    _class: JClass,
+    did: JString,
+    input_metadata: JString,
+) -> jobject {
+    let future = c::resolve_did(string(&env, did), string_opt(&env, input_metadata), None);
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_resolveDIDWithConfigAsync(
+    env: JNIEnv,
+    _class: JClass,
+    did: JString,
+    input_metadata: JString,
+    config: JString,
+) -> jobject {
+    let future = c::resolve_did(
+        string(&env, did),
+        string_opt(&env, input_metadata),
+        string_opt(&env, config),
+    );
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_dereferenceDIDURLAsync(
+    env: JNIEnv,
+    _class: JClass,
+    did_url: JString,
+    input_metadata: JString,
+) -> jobject {
+    let future = c::dereference_did_url(
+        string(&env, did_url),
+        string_opt(&env, input_metadata),
+        None,
+    );
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_dereferenceDIDURLWithConfigAsync(
+    env: JNIEnv,
+    _class: JClass,
+    did_url: JString,
+    input_metadata: JString,
+    config: JString,
+) -> jobject {
+    let future = c::dereference_did_url(
+        string(&env, did_url),
+        string_opt(&env, input_metadata),
+        string_opt(&env, config),
+    );
+    jobject_or_error(&env, completable_future(&env, future))
+}
+
+// Contexts are created and freed through the DIDKitContext class, which holds the id of a
+// DIDKitContext of the C API. Contexts are kept by id, so that a call holds a reference to its
+// context, and closing a context while it is in use on another thread only frees it once the call
+// returns.
+
+lazy_static! {
+    static ref CONTEXTS: RwLock<HashMap<jlong, Arc<c::DIDKitContext>>> =
+        RwLock::new(HashMap::new());
+}
+static NEXT_CONTEXT_ID: AtomicI64 = AtomicI64::new(1);
+
+fn context_create(env: &JNIEnv, options_jstring: JString) -> Result<jlong, Error> {
+    let context = match string_opt(env, options_jstring) {
+        Some(options_json) => Context::from_json(&options_json)?,
+        None => Context::default(),
+    };
+    let context = c::DIDKitContext {
+        context: Arc::new(context),
+        runtime: runtime::new_multi_thread()?,
+    };
+    let id = NEXT_CONTEXT_ID.fetch_add(1, Ordering::Relaxed);
+    CONTEXTS
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(id, Arc::new(context));
+    Ok(id)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKitContext_create(
+    env: JNIEnv,
+    _class: JClass,
+    options: JString,
+) -> jlong {
+    match context_create(&env, options) {
+        Ok(handle) => handle,
+        Err(err) => {
+            env.throw_new(DIDKIT_EXCEPTION_CLASS, err.to_string())
+                .unwrap();
+            0
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKitContext_free(
+    _env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) {
+    let context = CONTEXTS
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .remove(&handle);
+    // Dropped after releasing the lock, or by the last call using the context
+    drop(context);
+}
+
+// Run a future on the runtime of a DIDKitContext object, blocking until it completes
+fn block_on_context<'a, F, Fut>(
+    env: &JNIEnv<'a>,
+    context_jobject: JObject<'a>,
+    make_future: F,
+) -> Result<jstring, Error>
+where
+    F: FnOnce(Arc<Context>) -> Fut,
+    Fut: Future<Output = Result<String, Error>>,
+{
+    let handle = env
+        .get_field(context_jobject, "handle", "J")
+        .and_then(|value| value.j())?;
+    let context = CONTEXTS
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(&handle)
+        .cloned()
+        .ok_or(ContextError::Closed)?;
+    let out = context
+        .runtime
+        .block_on(make_future(context.context.clone()))?;
+    Ok(env.new_string(out)?.into_inner())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_issueCredentialWithContext(
+    env: JNIEnv,
+    _class: JClass,
+    context: JObject,
+    credential: JString,
+    options: JString,
+    key: JString,
+) -> jstring {
+    let (credential, options, key) = (
+        string(&env, credential),
+        string(&env, options),
+        string(&env, key),
+    );
+    jstring_or_error(
+        &env,
+        block_on_context(&env, context, |context| {
+            c::issue_credential(context, credential, options, key)
+        }),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_verifyCredentialWithContext(
+    env: JNIEnv,
+    _class: JClass,
+    context: JObject,
+    credential: JString,
+    options: JString,
+) -> jstring {
+    let (credential, options) = (string(&env, credential), string(&env, options));
+    jstring_or_error(
+        &env,
+        block_on_context(&env, context, |context| {
+            c::verify_credential(context, credential, options)
+        }),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_issuePresentationWithContext(
+    env: JNIEnv,
+    _class: JClass,
+    context: JObject,
+    presentation: JString,
+    options: JString,
+    key: JString,
+) -> jstring {
+    let (presentation, options, key) = (
+        string(&env, presentation),
+        string(&env, options),
+        string(&env, key),
+    );
+    jstring_or_error(
+        &env,
+        block_on_context(&env, context, |context| {
+            c::issue_presentation(context, presentation, options, key)
+        }),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_DIDAuthWithContext(
+    env: JNIEnv,
+    _class: JClass,
+    context: JObject,
+    holder: JString,
+    options: JString,
+    key: JString,
+) -> jstring {
+    let (holder, options, key) = (
+        string(&env, holder),
+        string(&env, options),
+        string(&env, key),
+    );
+    jstring_or_error(
+        &env,
+        block_on_context(&env, context, |context| {
+            c::did_auth(context, holder, options, key)
+        }),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_verifyPresentationWithContext(
+    env: JNIEnv,
+    _class: JClass,
+    context: JObject,
+    presentation: JString,
+    options: JString,
+) -> jstring {
+    let (presentation, options) = (string(&env, presentation), string(&env, options));
+    jstring_or_error(
+        &env,
+        block_on_context(&env, context, |context| {
+            c::verify_presentation(context, presentation, options)
+        }),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_resolveDIDWithContext(
+    env: JNIEnv,
+    _class: JClass,
+    context: JObject,
+    did: JString,
+    input_metadata: JString,
+) -> jstring {
+    let (did, input_metadata) = (string(&env, did), string_opt(&env, input_metadata));
+    jstring_or_error(
+        &env,
+        block_on_context(&env, context, |context| {
+            c::resolve_did_with_context(context, did, input_metadata)
+        }),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_com_spruceid_DIDKit_dereferenceDIDURLWithContext(
+    env: JNIEnv,
+    _class: JClass,
+    context: JObject,
+    did_url: JString,
+    input_metadata: JString,
+) -> jstring {
+    let (did_url, input_metadata) = (string(&env, did_url), string_opt(&env, input_metadata));
+    jstring_or_error(
+        &env,
+        block_on_context(&env, context, |context| {
+            c::dereference_did_url_with_context(context, did_url, input_metadata)
+        }),
+    )
 }
@@ -0,0 +1,296 @@
+//! Deterministic derivation of keys from a seed or a BIP-39 mnemonic phrase.
+//!
+//! Ed25519 keys are derived using [SLIP-0010](https://github.com/satoshilabs/slips/blob/master/slip-0010.md),
+//! which only supports hardened derivation. secp256k1 keys are derived using
+//! [BIP-32](https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki). Each curve needs its
+//! crate feature: `ed25519` or `secp256k1`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+#[cfg(feature = "secp256k1")]
+use k256::elliptic_curve::{sec1::ToEncodedPoint, PrimeField};
+use sha2::Sha512;
+#[cfg(feature = "secp256k1")]
+use ssi::jwk::ECParams;
+#[cfg(feature = "ed25519")]
+use ssi::jwk::OctetParams;
+use ssi::jwk::{Base64urlUInt, Params};
+use thiserror::Error;
+
+use crate::JWK;
+
+type HmacSha512 = Hmac<Sha512>;
+
+const HARDENED: u32 = 0x8000_0000;
+
+/// Derivation path commonly used for Tezos (tz1) Ed25519 keys
+pub const DEFAULT_PATH_ED25519: &str = "m/44'/1729'/0'/0'";
+/// Derivation path commonly used for Ethereum secp256k1 keys
+pub const DEFAULT_PATH_SECP256K1: &str = "m/44'/60'/0'/0/0";
+
+#[derive(Error, Debug)]
+pub enum KeyDerivationError {
+    #[error("Invalid mnemonic: {0}")]
+    Mnemonic(#[from] bip39::Error),
+    #[error("Invalid seed: {0}")]
+    Seed(#[from] hex::FromHexError),
+    #[error("Seed must be between 16 and 64 bytes, got {0}")]
+    SeedLength(usize),
+    #[error("Invalid derivation path: {0}")]
+    Path(String),
+    #[error("Ed25519 only supports hardened derivation")]
+    NonHardened,
+    #[error("Derived key is invalid; use the next index")]
+    InvalidDerivedKey,
+    #[error("Unknown curve: {0}")]
+    UnknownCurve(String),
+    #[error("Curve not enabled in this build: {0}")]
+    CurveDisabled(Curve),
+}
+
+/// Curve of a derived key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    Ed25519,
+    Secp256k1,
+}
+
+impl Curve {
+    /// Default derivation path for the curve
+    pub fn default_path(&self) -> &'static str {
+        match self {
+            Curve::Ed25519 => DEFAULT_PATH_ED25519,
+            Curve::Secp256k1 => DEFAULT_PATH_SECP256K1,
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Curve::Ed25519 => write!(f, "ed25519"),
+            Curve::Secp256k1 => write!(f, "secp256k1"),
+        }
+    }
+}
+
+impl FromStr for Curve {
+    type Err = KeyDerivationError;
+    fn from_str(curve: &str) -> Result<Self, Self::Err> {
+        match &curve.to_lowercase()[..] {
+            "ed25519" => Ok(Curve::Ed25519),
+            "secp256k1" => Ok(Curve::Secp256k1),
+            _ => Err(KeyDerivationError::UnknownCurve(curve.to_string())),
+        }
+    }
+}
+
+/// Parse a derivation path such as `m/44'/60'/0'/0/0`. Hardened indices are marked with `'` or
+/// `h`.
+pub fn parse_path(path: &str) -> Result<Vec<u32>, KeyDerivationError> {
+    let invalid = || KeyDerivationError::Path(path.to_string());
+    let mut segments = path.trim().split('/');
+    if segments.next() != Some("m") {
+        return Err(invalid());
+    }
+    segments
+        .map(|segment| {
+            let (index, hardened) = match segment
+                .strip_suffix('\'')
+                .or_else(|| segment.strip_suffix('h'))
+            {
+                Some(index) => (index, true),
+                None => (segment, false),
+            };
+            let index: u32 = index.parse().map_err(|_| invalid())?;
+            if index >= HARDENED {
+                return Err(invalid());
+            }
+            Ok(if hardened { index | HARDENED } else { index })
+        })
+        .collect()
+}
+
+/// Compute the BIP-39 seed of a mnemonic phrase, with an optional passphrase
+pub fn seed_from_mnemonic(
+    mnemonic: &str,
+    passphrase: &str,
+) -> Result<[u8; 64], KeyDerivationError> {
+    let mnemonic = bip39::Mnemonic::parse_normalized(mnemonic)?;
+    Ok(mnemonic.to_seed_normalized(passphrase))
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = <HmacSha512 as Mac>::new_from_slice(key).expect("HMAC accepts any key size");
+    for part in data {
+        mac.update(part);
+    }
+    let result = mac.finalize().into_bytes();
+    let mut key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    key.copy_from_slice(&result[..32]);
+    chain_code.copy_from_slice(&result[32..]);
+    (key, chain_code)
+}
+
+#[cfg(feature = "ed25519")]
+fn derive_ed25519(seed: &[u8], path: &[u32]) -> Result<JWK, KeyDerivationError> {
+    let (mut key, mut chain_code) = hmac_sha512(b"ed25519 seed", &[seed]);
+    for index in path {
+        if index & HARDENED == 0 {
+            return Err(KeyDerivationError::NonHardened);
+        }
+        let (child_key, child_chain_code) =
+            hmac_sha512(&chain_code, &[&[0], &key, &index.to_be_bytes()]);
+        key = child_key;
+        chain_code = child_chain_code;
+    }
+    let secret = ed25519_dalek::SigningKey::from_bytes(&key);
+    Ok(JWK::from(Params::OKP(OctetParams {
+        curve: "Ed25519".to_string(),
+        public_key: Base64urlUInt(secret.verifying_key().to_bytes().to_vec()),
+        private_key: Some(Base64urlUInt(key.to_vec())),
+    })))
+}
+
+#[cfg(feature = "secp256k1")]
+fn secp256k1_scalar(bytes: &[u8; 32]) -> Option<k256::Scalar> {
+    Option::from(k256::Scalar::from_repr((*bytes).into()))
+}
+
+#[cfg(feature = "secp256k1")]
+fn derive_secp256k1(seed: &[u8], path: &[u32]) -> Result<JWK, KeyDerivationError> {
+    let (key, mut chain_code) = hmac_sha512(b"Bitcoin seed", &[seed]);
+    let mut secret = k256::SecretKey::from_bytes(&key.into())
+        .map_err(|_| KeyDerivationError::InvalidDerivedKey)?;
+    for index in path {
+        let (tweak, child_chain_code) = if index & HARDENED != 0 {
+            hmac_sha512(
+                &chain_code,
+                &[&[0], &secret.to_bytes(), &index.to_be_bytes()],
+            )
+        } else {
+            let public = secret.public_key().to_encoded_point(true);
+            hmac_sha512(&chain_code, &[public.as_bytes(), &index.to_be_bytes()])
+        };
+        let tweak = secp256k1_scalar(&tweak).ok_or(KeyDerivationError::InvalidDerivedKey)?;
+        let child = tweak + secret.to_nonzero_scalar().as_ref();
+        secret = k256::SecretKey::from_bytes(&child.to_bytes())
+            .map_err(|_| KeyDerivationError::InvalidDerivedKey)?;
+        chain_code = child_chain_code;
+    }
+    let point = secret.public_key().to_encoded_point(false);
+    Ok(JWK::from(Params::EC(ECParams {
+        curve: Some("secp256k1".to_string()),
+        x_coordinate: point.x().map(|x| Base64urlUInt(x.to_vec())),
+        y_coordinate: point.y().map(|y| Base64urlUInt(y.to_vec())),
+        ecc_private_key: Some(Base64urlUInt(secret.to_bytes().to_vec())),
+    })))
+}
+
+/// Derive a JWK from a seed, for the given curve and derivation path
+pub fn derive_from_seed(seed: &[u8], curve: Curve, path: &str) -> Result<JWK, KeyDerivationError> {
+    if seed.len() < 16 || seed.len() > 64 {
+        return Err(KeyDerivationError::SeedLength(seed.len()));
+    }
+    let path = parse_path(path)?;
+    match curve {
+        #[cfg(feature = "ed25519")]
+        Curve::Ed25519 => derive_ed25519(seed, &path),
+        #[cfg(feature = "secp256k1")]
+        Curve::Secp256k1 => derive_secp256k1(seed, &path),
+        #[allow(unreachable_patterns)]
+        _ => Err(KeyDerivationError::CurveDisabled(curve)),
+    }
+}
+
+/// Derive a JWK from a hex-encoded seed, for the given curve and derivation path
+pub fn derive_from_seed_hex(
+    seed: &str,
+    curve: Curve,
+    path: &str,
+) -> Result<JWK, KeyDerivationError> {
+    let seed = hex::decode(seed.trim().trim_start_matches("0x"))?;
+    derive_from_seed(&seed, curve, path)
+}
+
+/// Derive a JWK from a BIP-39 mnemonic phrase and optional passphrase, for the given curve and
+/// derivation path
+pub fn derive_from_mnemonic(
+    mnemonic: &str,
+    passphrase: &str,
+    curve: Curve,
+    path: &str,
+) -> Result<JWK, KeyDerivationError> {
+    let seed = seed_from_mnemonic(mnemonic, passphrase)?;
+    derive_from_seed(&seed, curve, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn private_key_hex(jwk: &JWK) -> String {
+        match &jwk.params {
+            Params::OKP(okp) => hex::encode(&okp.private_key.as_ref().unwrap().0),
+            Params::EC(ec) => hex::encode(&ec.ecc_private_key.as_ref().unwrap().0),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn path() {
+        assert_eq!(
+            parse_path("m/44'/60h/0'/0/1").unwrap(),
+            vec![44 | HARDENED, 60 | HARDENED, HARDENED, 0, 1]
+        );
+        assert_eq!(parse_path("m").unwrap(), Vec::<u32>::new());
+        assert!(parse_path("44'/0").is_err());
+        assert!(parse_path("m/x").is_err());
+        assert!(parse_path("m/2147483648").is_err());
+    }
+
+    #[test]
+    fn slip10_ed25519() {
+        // SLIP-0010 test vector 1
+        let seed = "000102030405060708090a0b0c0d0e0f";
+        let jwk = derive_from_seed_hex(seed, Curve::Ed25519, "m").unwrap();
+        assert_eq!(
+            private_key_hex(&jwk),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        let jwk = derive_from_seed_hex(seed, Curve::Ed25519, "m/0'").unwrap();
+        assert_eq!(
+            private_key_hex(&jwk),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert!(derive_from_seed_hex(seed, Curve::Ed25519, "m/0").is_err());
+    }
+
+    #[test]
+    fn bip32_secp256k1() {
+        // BIP-32 test vector 1
+        let seed = "000102030405060708090a0b0c0d0e0f";
+        let jwk = derive_from_seed_hex(seed, Curve::Secp256k1, "m/0'").unwrap();
+        assert_eq!(
+            private_key_hex(&jwk),
+            "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea"
+        );
+        let jwk = derive_from_seed_hex(seed, Curve::Secp256k1, "m/0'/1").unwrap();
+        assert_eq!(
+            private_key_hex(&jwk),
+            "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368"
+        );
+    }
+
+    #[test]
+    fn mnemonic() {
+        // BIP-39 test vector
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = seed_from_mnemonic(mnemonic, "TREZOR").unwrap();
+        assert_eq!(hex::encode(seed), "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04");
+        assert!(seed_from_mnemonic("abandon abandon", "").is_err());
+    }
+}
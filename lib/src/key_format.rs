@@ -0,0 +1,784 @@
+//! Conversion of JWKs to and from other key formats: PEM (PKCS#8, SPKI, SEC1 and PKCS#1), DER
+//! PKCS#8/SPKI, multibase (multicodec-prefixed) keys and OpenSSH keys.
+//!
+//! Supported key types are Ed25519, secp256k1, P-256, P-384 and RSA. OpenSSH does not support
+//! secp256k1. Except for P-256, each key type is only supported with its crate feature enabled:
+//! `ed25519`, `secp256k1`, `secp384r1` or `rsa`.
+
+use std::convert::{TryFrom, TryInto};
+
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+#[cfg(feature = "rsa")]
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey, EncodeRsaPublicKey};
+#[cfg(feature = "rsa")]
+use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+#[cfg(feature = "rsa")]
+use rsa::BigUint;
+#[cfg(feature = "ed25519")]
+use ssi::jwk::OctetParams;
+#[cfg(feature = "rsa")]
+use ssi::jwk::RSAParams;
+use ssi::jwk::{Base64urlUInt, ECParams, Params};
+use thiserror::Error;
+
+use crate::JWK;
+
+const PEM_PRIVATE_KEY: &str = "PRIVATE KEY";
+const PEM_PUBLIC_KEY: &str = "PUBLIC KEY";
+const PEM_EC_PRIVATE_KEY: &str = "EC PRIVATE KEY";
+const PEM_EC_PARAMETERS: &str = "EC PARAMETERS";
+const PEM_RSA_PRIVATE_KEY: &str = "RSA PRIVATE KEY";
+const PEM_RSA_PUBLIC_KEY: &str = "RSA PUBLIC KEY";
+const PEM_OPENSSH_PRIVATE_KEY: &str = "OPENSSH PRIVATE KEY";
+const PEM_ENCRYPTED_PRIVATE_KEY: &str = "ENCRYPTED PRIVATE KEY";
+
+// https://github.com/multiformats/multicodec/blob/master/table.csv
+#[cfg(feature = "ed25519")]
+const MULTICODEC_ED25519_PUB: u64 = 0xed;
+#[cfg(feature = "secp256k1")]
+const MULTICODEC_SECP256K1_PUB: u64 = 0xe7;
+const MULTICODEC_P256_PUB: u64 = 0x1200;
+#[cfg(feature = "secp384r1")]
+const MULTICODEC_P384_PUB: u64 = 0x1201;
+#[cfg(feature = "rsa")]
+const MULTICODEC_RSA_PUB: u64 = 0x1205;
+#[cfg(feature = "ed25519")]
+const MULTICODEC_ED25519_PRIV: u64 = 0x1300;
+#[cfg(feature = "secp256k1")]
+const MULTICODEC_SECP256K1_PRIV: u64 = 0x1301;
+const MULTICODEC_P256_PRIV: u64 = 0x1306;
+#[cfg(feature = "secp384r1")]
+const MULTICODEC_P384_PRIV: u64 = 0x1307;
+
+#[derive(Error, Debug)]
+pub enum KeyFormatError {
+    #[error("Unsupported key type")]
+    UnsupportedKeyType,
+    #[error("Invalid key")]
+    InvalidKey,
+    #[error("Missing private key")]
+    MissingPrivateKey,
+    #[error("Unrecognized PEM label: {0}")]
+    PemLabel(String),
+    #[error("Encrypted private keys are not supported; decrypt the key first")]
+    Encrypted,
+    #[error("PEM: {0}")]
+    Pem(String),
+    #[error("PKCS#8: {0}")]
+    Pkcs8(String),
+    #[error("Multibase: {0}")]
+    Multibase(#[from] multibase::Error),
+    #[error("Unsupported multicodec: {0:#x}")]
+    Multicodec(u64),
+    #[error("OpenSSH: {0}")]
+    OpenSSH(#[from] ssh_key::Error),
+    #[cfg(feature = "rsa")]
+    #[error("RSA: {0}")]
+    RSA(#[from] rsa::Error),
+    #[error("RSA keys must be at least 2048 bits, got {0}")]
+    RSAKeySize(usize),
+}
+
+/// A key, parsed from a JWK or another format
+enum Key {
+    #[cfg(feature = "ed25519")]
+    Ed25519 {
+        public: ed25519_dalek::VerifyingKey,
+        secret: Option<ed25519_dalek::SigningKey>,
+    },
+    #[cfg(feature = "secp256k1")]
+    Secp256k1(k256::PublicKey, Option<k256::SecretKey>),
+    P256(p256::PublicKey, Option<p256::SecretKey>),
+    #[cfg(feature = "secp384r1")]
+    P384(p384::PublicKey, Option<p384::SecretKey>),
+    #[cfg(feature = "rsa")]
+    RSA(rsa::RsaPublicKey, Option<rsa::RsaPrivateKey>),
+}
+
+fn bytes(value: &Option<Base64urlUInt>) -> Result<&[u8], KeyFormatError> {
+    value
+        .as_ref()
+        .map(|v| v.0.as_slice())
+        .ok_or(KeyFormatError::InvalidKey)
+}
+
+#[cfg(feature = "rsa")]
+fn uint(value: &Option<Base64urlUInt>) -> Result<BigUint, KeyFormatError> {
+    Ok(BigUint::from_bytes_be(bytes(value)?))
+}
+
+#[cfg(feature = "rsa")]
+fn b64uint(value: &BigUint) -> Option<Base64urlUInt> {
+    Some(Base64urlUInt(value.to_bytes_be()))
+}
+
+macro_rules! ec_key {
+    ($variant:ident, $curve:ident, $ec:expr) => {{
+        let mut sec1 = vec![0x04];
+        sec1.extend_from_slice(bytes(&$ec.x_coordinate)?);
+        sec1.extend_from_slice(bytes(&$ec.y_coordinate)?);
+        let public =
+            $curve::PublicKey::from_sec1_bytes(&sec1).map_err(|_| KeyFormatError::InvalidKey)?;
+        let secret = match &$ec.ecc_private_key {
+            Some(d) => {
+                Some($curve::SecretKey::from_slice(&d.0).map_err(|_| KeyFormatError::InvalidKey)?)
+            }
+            None => None,
+        };
+        Key::$variant(public, secret)
+    }};
+}
+
+macro_rules! ec_params {
+    ($curve:expr, $public:expr, $secret:expr) => {{
+        let point = $public.to_encoded_point(false);
+        Params::EC(ECParams {
+            curve: Some($curve.to_string()),
+            x_coordinate: point.x().map(|x| Base64urlUInt(x.to_vec())),
+            y_coordinate: point.y().map(|y| Base64urlUInt(y.to_vec())),
+            ecc_private_key: $secret
+                .as_ref()
+                .map(|secret| Base64urlUInt(secret.to_bytes().to_vec())),
+        })
+    }};
+}
+
+impl TryFrom<&JWK> for Key {
+    type Error = KeyFormatError;
+    fn try_from(jwk: &JWK) -> Result<Self, Self::Error> {
+        Ok(match &jwk.params {
+            #[cfg(feature = "ed25519")]
+            Params::OKP(okp) if okp.curve == "Ed25519" => {
+                let public: [u8; 32] = okp
+                    .public_key
+                    .0
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| KeyFormatError::InvalidKey)?;
+                let public = ed25519_dalek::VerifyingKey::from_bytes(&public)
+                    .map_err(|_| KeyFormatError::InvalidKey)?;
+                let secret = match &okp.private_key {
+                    Some(private_key) => {
+                        let seed: [u8; 32] = private_key
+                            .0
+                            .as_slice()
+                            .try_into()
+                            .map_err(|_| KeyFormatError::InvalidKey)?;
+                        Some(ed25519_dalek::SigningKey::from_bytes(&seed))
+                    }
+                    None => None,
+                };
+                Key::Ed25519 { public, secret }
+            }
+            Params::EC(ec) => match ec.curve.as_deref() {
+                #[cfg(feature = "secp256k1")]
+                Some("secp256k1") => ec_key!(Secp256k1, k256, ec),
+                Some("P-256") => ec_key!(P256, p256, ec),
+                #[cfg(feature = "secp384r1")]
+                Some("P-384") => ec_key!(P384, p384, ec),
+                _ => return Err(KeyFormatError::UnsupportedKeyType),
+            },
+            #[cfg(feature = "rsa")]
+            Params::RSA(rsa_params) => {
+                let n = uint(&rsa_params.modulus)?;
+                let e = uint(&rsa_params.exponent)?;
+                let public = rsa::RsaPublicKey::new(n.clone(), e.clone())?;
+                let secret = match &rsa_params.private_exponent {
+                    Some(d) => {
+                        let primes = vec![
+                            uint(&rsa_params.first_prime_factor)?,
+                            uint(&rsa_params.second_prime_factor)?,
+                        ];
+                        let mut secret = rsa::RsaPrivateKey::from_components(
+                            n,
+                            e,
+                            BigUint::from_bytes_be(&d.0),
+                            primes,
+                        )?;
+                        secret.precompute()?;
+                        Some(secret)
+                    }
+                    None => None,
+                };
+                Key::RSA(public, secret)
+            }
+            _ => return Err(KeyFormatError::UnsupportedKeyType),
+        })
+    }
+}
+
+impl Key {
+    fn to_jwk(&self) -> JWK {
+        let params = match self {
+            #[cfg(feature = "ed25519")]
+            Key::Ed25519 { public, secret } => Params::OKP(OctetParams {
+                curve: "Ed25519".to_string(),
+                public_key: Base64urlUInt(public.to_bytes().to_vec()),
+                private_key: secret
+                    .as_ref()
+                    .map(|secret| Base64urlUInt(secret.to_bytes().to_vec())),
+            }),
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(public, secret) => ec_params!("secp256k1", public, secret),
+            Key::P256(public, secret) => ec_params!("P-256", public, secret),
+            #[cfg(feature = "secp384r1")]
+            Key::P384(public, secret) => ec_params!("P-384", public, secret),
+            #[cfg(feature = "rsa")]
+            Key::RSA(public, secret) => Params::RSA(RSAParams {
+                modulus: b64uint(public.n()),
+                exponent: b64uint(public.e()),
+                private_exponent: secret.as_ref().and_then(|s| b64uint(s.d())),
+                first_prime_factor: secret.as_ref().and_then(|s| b64uint(&s.primes()[0])),
+                second_prime_factor: secret.as_ref().and_then(|s| b64uint(&s.primes()[1])),
+                first_prime_factor_crt_exponent: secret
+                    .as_ref()
+                    .and_then(|s| s.dp())
+                    .and_then(b64uint),
+                second_prime_factor_crt_exponent: secret
+                    .as_ref()
+                    .and_then(|s| s.dq())
+                    .and_then(b64uint),
+                first_crt_coefficient: secret
+                    .as_ref()
+                    .and_then(|s| s.crt_coefficient())
+                    .and_then(|c| b64uint(&c)),
+                other_primes_info: None,
+            }),
+        };
+        JWK::from(params)
+    }
+
+    fn has_secret(&self) -> bool {
+        match self {
+            #[cfg(feature = "ed25519")]
+            Key::Ed25519 { secret, .. } => secret.is_some(),
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(_, secret) => secret.is_some(),
+            Key::P256(_, secret) => secret.is_some(),
+            #[cfg(feature = "secp384r1")]
+            Key::P384(_, secret) => secret.is_some(),
+            #[cfg(feature = "rsa")]
+            Key::RSA(_, secret) => secret.is_some(),
+        }
+    }
+
+    #[cfg(feature = "ed25519")]
+    fn from_ed25519_secret(secret: ed25519_dalek::SigningKey) -> Self {
+        Key::Ed25519 {
+            public: secret.verifying_key(),
+            secret: Some(secret),
+        }
+    }
+
+    #[cfg(feature = "rsa")]
+    fn from_rsa_secret(secret: rsa::RsaPrivateKey) -> Self {
+        Key::RSA(secret.to_public_key(), Some(secret))
+    }
+
+    /// Parse a DER-encoded PKCS#8 private key
+    fn from_pkcs8_der(der: &[u8]) -> Result<Self, KeyFormatError> {
+        #[cfg(feature = "ed25519")]
+        if let Ok(secret) = ed25519_dalek::SigningKey::from_pkcs8_der(der) {
+            return Ok(Self::from_ed25519_secret(secret));
+        }
+        #[cfg(feature = "secp256k1")]
+        if let Ok(secret) = k256::SecretKey::from_pkcs8_der(der) {
+            return Ok(Key::Secp256k1(secret.public_key(), Some(secret)));
+        }
+        if let Ok(secret) = p256::SecretKey::from_pkcs8_der(der) {
+            return Ok(Key::P256(secret.public_key(), Some(secret)));
+        }
+        #[cfg(feature = "secp384r1")]
+        if let Ok(secret) = p384::SecretKey::from_pkcs8_der(der) {
+            return Ok(Key::P384(secret.public_key(), Some(secret)));
+        }
+        #[cfg(feature = "rsa")]
+        if let Ok(secret) = rsa::RsaPrivateKey::from_pkcs8_der(der) {
+            return Ok(Self::from_rsa_secret(secret));
+        }
+        pkcs8::PrivateKeyInfo::try_from(der).map_err(|e| KeyFormatError::Pkcs8(e.to_string()))?;
+        Err(KeyFormatError::UnsupportedKeyType)
+    }
+
+    /// Parse a DER-encoded SubjectPublicKeyInfo
+    fn from_public_key_der(der: &[u8]) -> Result<Self, KeyFormatError> {
+        #[cfg(feature = "ed25519")]
+        if let Ok(public) = ed25519_dalek::VerifyingKey::from_public_key_der(der) {
+            return Ok(Key::Ed25519 {
+                public,
+                secret: None,
+            });
+        }
+        #[cfg(feature = "secp256k1")]
+        if let Ok(public) = k256::PublicKey::from_public_key_der(der) {
+            return Ok(Key::Secp256k1(public, None));
+        }
+        if let Ok(public) = p256::PublicKey::from_public_key_der(der) {
+            return Ok(Key::P256(public, None));
+        }
+        #[cfg(feature = "secp384r1")]
+        if let Ok(public) = p384::PublicKey::from_public_key_der(der) {
+            return Ok(Key::P384(public, None));
+        }
+        #[cfg(feature = "rsa")]
+        if let Ok(public) = rsa::RsaPublicKey::from_public_key_der(der) {
+            return Ok(Key::RSA(public, None));
+        }
+        pkcs8::spki::SubjectPublicKeyInfoRef::try_from(der)
+            .map_err(|e| KeyFormatError::Pkcs8(e.to_string()))?;
+        Err(KeyFormatError::UnsupportedKeyType)
+    }
+
+    /// Parse a DER-encoded SEC1 EC private key
+    fn from_sec1_der(der: &[u8]) -> Result<Self, KeyFormatError> {
+        #[cfg(feature = "secp256k1")]
+        if let Ok(secret) = k256::SecretKey::from_sec1_der(der) {
+            return Ok(Key::Secp256k1(secret.public_key(), Some(secret)));
+        }
+        if let Ok(secret) = p256::SecretKey::from_sec1_der(der) {
+            return Ok(Key::P256(secret.public_key(), Some(secret)));
+        }
+        #[cfg(feature = "secp384r1")]
+        if let Ok(secret) = p384::SecretKey::from_sec1_der(der) {
+            return Ok(Key::P384(secret.public_key(), Some(secret)));
+        }
+        Err(KeyFormatError::UnsupportedKeyType)
+    }
+
+    fn to_pkcs8_der(&self) -> Result<Vec<u8>, KeyFormatError> {
+        let der = match self {
+            #[cfg(feature = "ed25519")]
+            Key::Ed25519 {
+                secret: Some(secret),
+                ..
+            } => secret.to_pkcs8_der(),
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(_, Some(secret)) => secret.to_pkcs8_der(),
+            Key::P256(_, Some(secret)) => secret.to_pkcs8_der(),
+            #[cfg(feature = "secp384r1")]
+            Key::P384(_, Some(secret)) => secret.to_pkcs8_der(),
+            #[cfg(feature = "rsa")]
+            Key::RSA(_, Some(secret)) => secret.to_pkcs8_der(),
+            _ => return Err(KeyFormatError::MissingPrivateKey),
+        }
+        .map_err(|e| KeyFormatError::Pkcs8(e.to_string()))?;
+        Ok(der.as_bytes().to_vec())
+    }
+
+    fn to_public_key_der(&self) -> Result<Vec<u8>, KeyFormatError> {
+        let der = match self {
+            #[cfg(feature = "ed25519")]
+            Key::Ed25519 { public, .. } => public.to_public_key_der(),
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(public, _) => public.to_public_key_der(),
+            Key::P256(public, _) => public.to_public_key_der(),
+            #[cfg(feature = "secp384r1")]
+            Key::P384(public, _) => public.to_public_key_der(),
+            #[cfg(feature = "rsa")]
+            Key::RSA(public, _) => public.to_public_key_der(),
+        }
+        .map_err(|e| KeyFormatError::Pkcs8(e.to_string()))?;
+        Ok(der.into_vec())
+    }
+
+    fn to_multicodec(&self, private: bool) -> Result<(u64, Vec<u8>), KeyFormatError> {
+        Ok(match (self, private) {
+            #[cfg(feature = "ed25519")]
+            (Key::Ed25519 { public, .. }, false) => {
+                (MULTICODEC_ED25519_PUB, public.to_bytes().to_vec())
+            }
+            #[cfg(feature = "ed25519")]
+            (
+                Key::Ed25519 {
+                    secret: Some(secret),
+                    ..
+                },
+                true,
+            ) => (MULTICODEC_ED25519_PRIV, secret.to_bytes().to_vec()),
+            #[cfg(feature = "secp256k1")]
+            (Key::Secp256k1(public, _), false) => (
+                MULTICODEC_SECP256K1_PUB,
+                public.to_encoded_point(true).as_bytes().to_vec(),
+            ),
+            #[cfg(feature = "secp256k1")]
+            (Key::Secp256k1(_, Some(secret)), true) => {
+                (MULTICODEC_SECP256K1_PRIV, secret.to_bytes().to_vec())
+            }
+            (Key::P256(public, _), false) => (
+                MULTICODEC_P256_PUB,
+                public.to_encoded_point(true).as_bytes().to_vec(),
+            ),
+            (Key::P256(_, Some(secret)), true) => {
+                (MULTICODEC_P256_PRIV, secret.to_bytes().to_vec())
+            }
+            #[cfg(feature = "secp384r1")]
+            (Key::P384(public, _), false) => (
+                MULTICODEC_P384_PUB,
+                public.to_encoded_point(true).as_bytes().to_vec(),
+            ),
+            #[cfg(feature = "secp384r1")]
+            (Key::P384(_, Some(secret)), true) => {
+                (MULTICODEC_P384_PRIV, secret.to_bytes().to_vec())
+            }
+            #[cfg(feature = "rsa")]
+            (Key::RSA(public, _), false) => (
+                MULTICODEC_RSA_PUB,
+                public
+                    .to_pkcs1_der()
+                    .map_err(|e| KeyFormatError::Pkcs8(e.to_string()))?
+                    .into_vec(),
+            ),
+            #[cfg(feature = "rsa")]
+            (Key::RSA(..), true) => return Err(KeyFormatError::UnsupportedKeyType),
+            (_, true) => return Err(KeyFormatError::MissingPrivateKey),
+        })
+    }
+
+    fn from_multicodec(codec: u64, data: &[u8]) -> Result<Self, KeyFormatError> {
+        let invalid = |_| KeyFormatError::InvalidKey;
+        Ok(match codec {
+            #[cfg(feature = "ed25519")]
+            MULTICODEC_ED25519_PUB => {
+                let public: [u8; 32] = data.try_into().map_err(|_| KeyFormatError::InvalidKey)?;
+                Key::Ed25519 {
+                    public: ed25519_dalek::VerifyingKey::from_bytes(&public)
+                        .map_err(|_| KeyFormatError::InvalidKey)?,
+                    secret: None,
+                }
+            }
+            #[cfg(feature = "ed25519")]
+            MULTICODEC_ED25519_PRIV => {
+                let seed: [u8; 32] = data.try_into().map_err(|_| KeyFormatError::InvalidKey)?;
+                Self::from_ed25519_secret(ed25519_dalek::SigningKey::from_bytes(&seed))
+            }
+            #[cfg(feature = "secp256k1")]
+            MULTICODEC_SECP256K1_PUB => Key::Secp256k1(
+                k256::PublicKey::from_sec1_bytes(data).map_err(invalid)?,
+                None,
+            ),
+            #[cfg(feature = "secp256k1")]
+            MULTICODEC_SECP256K1_PRIV => {
+                let secret = k256::SecretKey::from_slice(data).map_err(invalid)?;
+                Key::Secp256k1(secret.public_key(), Some(secret))
+            }
+            MULTICODEC_P256_PUB => Key::P256(
+                p256::PublicKey::from_sec1_bytes(data).map_err(invalid)?,
+                None,
+            ),
+            MULTICODEC_P256_PRIV => {
+                let secret = p256::SecretKey::from_slice(data).map_err(invalid)?;
+                Key::P256(secret.public_key(), Some(secret))
+            }
+            #[cfg(feature = "secp384r1")]
+            MULTICODEC_P384_PUB => Key::P384(
+                p384::PublicKey::from_sec1_bytes(data).map_err(invalid)?,
+                None,
+            ),
+            #[cfg(feature = "secp384r1")]
+            MULTICODEC_P384_PRIV => {
+                let secret = p384::SecretKey::from_slice(data).map_err(invalid)?;
+                Key::P384(secret.public_key(), Some(secret))
+            }
+            #[cfg(feature = "rsa")]
+            MULTICODEC_RSA_PUB => Key::RSA(
+                rsa::RsaPublicKey::from_pkcs1_der(data)
+                    .map_err(|e| KeyFormatError::Pkcs8(e.to_string()))?,
+                None,
+            ),
+            codec => return Err(KeyFormatError::Multicodec(codec)),
+        })
+    }
+
+    fn to_ssh_key_data(&self) -> Result<ssh_key::public::KeyData, KeyFormatError> {
+        #[cfg(feature = "ed25519")]
+        use ssh_key::public::Ed25519PublicKey;
+        use ssh_key::public::{EcdsaPublicKey, KeyData};
+        Ok(match self {
+            #[cfg(feature = "ed25519")]
+            Key::Ed25519 { public, .. } => KeyData::Ed25519(Ed25519PublicKey(public.to_bytes())),
+            Key::P256(public, _) => KeyData::Ecdsa(EcdsaPublicKey::from(*public)),
+            #[cfg(feature = "secp384r1")]
+            Key::P384(public, _) => KeyData::Ecdsa(EcdsaPublicKey::from(*public)),
+            #[cfg(feature = "rsa")]
+            Key::RSA(public, _) => KeyData::Rsa(public.try_into()?),
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(..) => return Err(KeyFormatError::UnsupportedKeyType),
+        })
+    }
+
+    fn to_ssh_keypair_data(&self) -> Result<ssh_key::private::KeypairData, KeyFormatError> {
+        #[cfg(feature = "ed25519")]
+        use ssh_key::private::Ed25519Keypair;
+        use ssh_key::private::{EcdsaKeypair, KeypairData};
+        Ok(match self {
+            #[cfg(feature = "ed25519")]
+            Key::Ed25519 {
+                secret: Some(secret),
+                ..
+            } => KeypairData::Ed25519(Ed25519Keypair::from_seed(&secret.to_bytes())),
+            Key::P256(_, Some(secret)) => KeypairData::Ecdsa(EcdsaKeypair::from(secret.clone())),
+            #[cfg(feature = "secp384r1")]
+            Key::P384(_, Some(secret)) => KeypairData::Ecdsa(EcdsaKeypair::from(secret.clone())),
+            #[cfg(feature = "rsa")]
+            Key::RSA(_, Some(secret)) => KeypairData::Rsa(secret.clone().try_into()?),
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(..) => return Err(KeyFormatError::UnsupportedKeyType),
+            _ => return Err(KeyFormatError::MissingPrivateKey),
+        })
+    }
+
+    fn from_ssh_keypair_data(data: &ssh_key::private::KeypairData) -> Result<Self, KeyFormatError> {
+        use ssh_key::private::{EcdsaKeypair, KeypairData};
+        let invalid = |_| KeyFormatError::InvalidKey;
+        Ok(match data {
+            #[cfg(feature = "ed25519")]
+            KeypairData::Ed25519(keypair) => Self::from_ed25519_secret(
+                ed25519_dalek::SigningKey::from_bytes(&keypair.private.to_bytes()),
+            ),
+            KeypairData::Ecdsa(EcdsaKeypair::NistP256 { private, .. }) => {
+                let secret = p256::SecretKey::from_slice(private.as_slice()).map_err(invalid)?;
+                Key::P256(secret.public_key(), Some(secret))
+            }
+            #[cfg(feature = "secp384r1")]
+            KeypairData::Ecdsa(EcdsaKeypair::NistP384 { private, .. }) => {
+                let secret = p384::SecretKey::from_slice(private.as_slice()).map_err(invalid)?;
+                Key::P384(secret.public_key(), Some(secret))
+            }
+            #[cfg(feature = "rsa")]
+            KeypairData::Rsa(keypair) => {
+                Self::from_rsa_secret(rsa::RsaPrivateKey::try_from(keypair)?)
+            }
+            KeypairData::Encrypted(_) => return Err(KeyFormatError::Encrypted),
+            _ => return Err(KeyFormatError::UnsupportedKeyType),
+        })
+    }
+}
+
+/// Split PEM text into (label, DER) blocks
+fn pem_blocks(pem: &str) -> Result<Vec<(String, Vec<u8>)>, KeyFormatError> {
+    let mut blocks = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = rest.find("-----BEGIN ") {
+        let block = &rest[start..];
+        let label_end = block[11..]
+            .find("-----")
+            .ok_or_else(|| KeyFormatError::Pem("unterminated label".to_string()))?;
+        let label = &block[11..11 + label_end];
+        let end_marker = format!("-----END {}-----", label);
+        let end = block
+            .find(&end_marker)
+            .ok_or_else(|| KeyFormatError::Pem(format!("missing END for {}", label)))?
+            + end_marker.len();
+        if label == PEM_OPENSSH_PRIVATE_KEY {
+            // Parsed by ssh-key, which needs the PEM text itself.
+            blocks.push((label.to_string(), block[..end].as_bytes().to_vec()));
+        } else {
+            let (_, der) = pkcs8::der::pem::decode_vec(block[..end].as_bytes())
+                .map_err(|e| KeyFormatError::Pem(e.to_string()))?;
+            blocks.push((label.to_string(), der));
+        }
+        rest = &block[end..];
+    }
+    Ok(blocks)
+}
+
+/// Parse a key from PEM: PKCS#8 (`PRIVATE KEY`), SubjectPublicKeyInfo (`PUBLIC KEY`), SEC1 (`EC
+/// PRIVATE KEY`), PKCS#1 (`RSA PRIVATE KEY`, `RSA PUBLIC KEY`) or OpenSSH (`OPENSSH PRIVATE KEY`).
+pub fn jwk_from_pem(pem: &str) -> Result<JWK, KeyFormatError> {
+    let (label, der) = pem_blocks(pem)?
+        .into_iter()
+        // OpenSSL may output EC parameters before the key.
+        .find(|(label, _)| label != PEM_EC_PARAMETERS)
+        .ok_or_else(|| KeyFormatError::Pem("no PEM block found".to_string()))?;
+    let key = match &label[..] {
+        PEM_PRIVATE_KEY => Key::from_pkcs8_der(&der)?,
+        PEM_PUBLIC_KEY => Key::from_public_key_der(&der)?,
+        PEM_EC_PRIVATE_KEY => Key::from_sec1_der(&der)?,
+        #[cfg(feature = "rsa")]
+        PEM_RSA_PRIVATE_KEY => Key::from_rsa_secret(
+            rsa::RsaPrivateKey::from_pkcs1_der(&der)
+                .map_err(|e| KeyFormatError::Pkcs8(e.to_string()))?,
+        ),
+        #[cfg(feature = "rsa")]
+        PEM_RSA_PUBLIC_KEY => Key::RSA(
+            rsa::RsaPublicKey::from_pkcs1_der(&der)
+                .map_err(|e| KeyFormatError::Pkcs8(e.to_string()))?,
+            None,
+        ),
+        #[cfg(not(feature = "rsa"))]
+        PEM_RSA_PRIVATE_KEY | PEM_RSA_PUBLIC_KEY => return Err(KeyFormatError::UnsupportedKeyType),
+        PEM_OPENSSH_PRIVATE_KEY => {
+            return jwk_from_openssh_private(std::str::from_utf8(&der).unwrap_or_default())
+        }
+        PEM_ENCRYPTED_PRIVATE_KEY => return Err(KeyFormatError::Encrypted),
+        _ => return Err(KeyFormatError::PemLabel(label)),
+    };
+    Ok(key.to_jwk())
+}
+
+/// Parse a DER-encoded PKCS#8 private key or SubjectPublicKeyInfo public key.
+pub fn jwk_from_der(der: &[u8]) -> Result<JWK, KeyFormatError> {
+    match Key::from_pkcs8_der(der) {
+        Ok(key) => Ok(key.to_jwk()),
+        Err(_) => Ok(Key::from_public_key_der(der)?.to_jwk()),
+    }
+}
+
+/// Parse a multibase-encoded, multicodec-prefixed key, as in `publicKeyMultibase`.
+pub fn jwk_from_multibase(multibase: &str) -> Result<JWK, KeyFormatError> {
+    let (_, data) = multibase::decode(multibase)?;
+    let (codec, data) = read_varint(&data).ok_or(KeyFormatError::InvalidKey)?;
+    Ok(Key::from_multicodec(codec, data)?.to_jwk())
+}
+
+/// Parse an unencrypted OpenSSH private key.
+pub fn jwk_from_openssh_private(openssh: &str) -> Result<JWK, KeyFormatError> {
+    let private_key = ssh_key::PrivateKey::from_openssh(openssh)?;
+    if private_key.is_encrypted() {
+        return Err(KeyFormatError::Encrypted);
+    }
+    Ok(Key::from_ssh_keypair_data(private_key.key_data())?.to_jwk())
+}
+
+/// Encode a key as PEM: PKCS#8 for a private key, or SubjectPublicKeyInfo for the public key. With
+/// `sec1`, EC private keys are encoded as SEC1 (`EC PRIVATE KEY`) instead of PKCS#8.
+pub fn jwk_to_pem(jwk: &JWK, private: bool, sec1: bool) -> Result<String, KeyFormatError> {
+    let key = Key::try_from(jwk)?;
+    if !private {
+        return Ok(pem_encode(PEM_PUBLIC_KEY, &key.to_public_key_der()?));
+    }
+    if sec1 {
+        let pem = match &key {
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(_, Some(secret)) => secret.to_sec1_pem(LineEnding::LF),
+            Key::P256(_, Some(secret)) => secret.to_sec1_pem(LineEnding::LF),
+            #[cfg(feature = "secp384r1")]
+            Key::P384(_, Some(secret)) => secret.to_sec1_pem(LineEnding::LF),
+            #[cfg(feature = "secp256k1")]
+            Key::Secp256k1(..) => return Err(KeyFormatError::MissingPrivateKey),
+            Key::P256(..) => return Err(KeyFormatError::MissingPrivateKey),
+            #[cfg(feature = "secp384r1")]
+            Key::P384(..) => return Err(KeyFormatError::MissingPrivateKey),
+            _ => return Err(KeyFormatError::UnsupportedKeyType),
+        }
+        .map_err(|e| KeyFormatError::Pem(e.to_string()))?;
+        return Ok(pem.to_string());
+    }
+    Ok(pem_encode(PEM_PRIVATE_KEY, &key.to_pkcs8_der()?))
+}
+
+fn pem_encode(label: &str, der: &[u8]) -> String {
+    pkcs8::der::pem::encode_string(label, LineEnding::LF, der)
+        .expect("PEM encoding of in-memory DER")
+}
+
+/// Encode a key as DER: PKCS#8 for a private key, or SubjectPublicKeyInfo for the public key.
+pub fn jwk_to_der(jwk: &JWK, private: bool) -> Result<Vec<u8>, KeyFormatError> {
+    let key = Key::try_from(jwk)?;
+    if private {
+        key.to_pkcs8_der()
+    } else {
+        key.to_public_key_der()
+    }
+}
+
+/// Encode a key as a base58btc multibase, multicodec-prefixed key.
+pub fn jwk_to_multibase(jwk: &JWK, private: bool) -> Result<String, KeyFormatError> {
+    let (codec, data) = Key::try_from(jwk)?.to_multicodec(private)?;
+    let mut bytes = write_varint(codec);
+    bytes.extend(data);
+    Ok(multibase::encode(multibase::Base::Base58Btc, bytes))
+}
+
+/// Encode a key in OpenSSH format: an OpenSSH private key, or a public key line as in
+/// `authorized_keys`.
+pub fn jwk_to_openssh(jwk: &JWK, private: bool) -> Result<String, KeyFormatError> {
+    let key = Key::try_from(jwk)?;
+    if private {
+        let private_key = ssh_key::PrivateKey::new(key.to_ssh_keypair_data()?, "")?;
+        Ok(private_key.to_openssh(LineEnding::LF)?.to_string())
+    } else {
+        let public_key = ssh_key::PublicKey::new(key.to_ssh_key_data()?, "");
+        Ok(public_key.to_openssh()?)
+    }
+}
+
+/// Whether the JWK is of a supported type and contains a private key
+pub fn jwk_has_private_key(jwk: &JWK) -> Result<bool, KeyFormatError> {
+    Ok(Key::try_from(jwk)?.has_secret())
+}
+
+/// Minimum RSA key size, in bits, for generated keys
+#[cfg(feature = "rsa")]
+pub const RSA_MIN_BITS: usize = 2048;
+
+/// Generate an RSA key pair as a JWK.
+#[cfg(feature = "rsa")]
+pub fn generate_rsa(bits: usize) -> Result<JWK, KeyFormatError> {
+    if bits < RSA_MIN_BITS {
+        return Err(KeyFormatError::RSAKeySize(bits));
+    }
+    let secret = rsa::RsaPrivateKey::new(&mut rand_core::OsRng, bits)?;
+    Ok(Key::from_rsa_secret(secret).to_jwk())
+}
+
+fn read_varint(data: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+fn write_varint(mut value: u64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint() {
+        assert_eq!(write_varint(0xed), vec![0xed, 0x01]);
+        assert_eq!(write_varint(0x1200), vec![0x80, 0x24]);
+        assert_eq!(
+            read_varint(&[0x80, 0x24, 0xff]),
+            Some((0x1200, &[0xff][..]))
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        for jwk in [
+            JWK::generate_ed25519().unwrap(),
+            JWK::generate_secp256k1().unwrap(),
+            JWK::generate_p256().unwrap(),
+        ] {
+            let pem = jwk_to_pem(&jwk, true, false).unwrap();
+            assert_eq!(jwk_from_pem(&pem).unwrap(), jwk);
+            let der = jwk_to_der(&jwk, true).unwrap();
+            assert_eq!(jwk_from_der(&der).unwrap(), jwk);
+            let public_pem = jwk_to_pem(&jwk, false, false).unwrap();
+            assert_eq!(jwk_from_pem(&public_pem).unwrap(), jwk.to_public());
+            let mb = jwk_to_multibase(&jwk, false).unwrap();
+            assert_eq!(jwk_from_multibase(&mb).unwrap(), jwk.to_public());
+        }
+        let jwk = JWK::generate_ed25519().unwrap();
+        let openssh = jwk_to_openssh(&jwk, true).unwrap();
+        assert_eq!(jwk_from_openssh_private(&openssh).unwrap(), jwk);
+    }
+}
@@ -1,13 +1,27 @@
 #[cfg(not(target_arch = "wasm32"))]
 pub mod c;
-mod did_methods;
+pub mod config;
+pub mod context;
+pub mod did_document;
+pub mod did_fixtures;
+pub mod did_lint;
+pub mod did_methods;
+pub mod did_peer;
+pub mod did_web;
+pub mod didcomm;
 pub mod error;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod jni;
+pub mod key_derive;
+pub mod key_format;
+pub mod offline_tx;
+pub mod oid4vp;
+pub mod presentation_exchange;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod runtime;
 #[cfg(not(any(target_arch = "wasm32", target_os = "windows")))]
 pub mod ssh_agent;
+pub mod zcap;
 
 #[macro_use]
 extern crate lazy_static;
@@ -131,6 +145,29 @@ pub async fn generate_proof(
     resolver: &dyn DIDResolver,
     context_loader: &mut ContextLoader,
     ssh_agent_sock_path_opt: Option<&str>,
+) -> Result<ssi::ldp::Proof, GenerateProofError> {
+    generate_proof_with_properties(
+        document,
+        key,
+        options,
+        resolver,
+        context_loader,
+        ssh_agent_sock_path_opt,
+        None,
+    )
+    .await
+}
+
+/// Generate a proof with additional proof properties, such as the capability chain of a
+/// capability delegation.
+pub async fn generate_proof_with_properties(
+    document: &(dyn ssi::ldp::LinkedDataDocument + Sync),
+    key: Option<&JWK>,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+    ssh_agent_sock_path_opt: Option<&str>,
+    extra_proof_properties: Option<serde_json::Map<String, serde_json::Value>>,
 ) -> Result<ssi::ldp::Proof, GenerateProofError> {
     use ssi::ldp::LinkedDataProofs;
     let proof = match ssh_agent_sock_path_opt {
@@ -146,19 +183,28 @@ pub async fn generate_proof(
         Some(sock_path) => {
             use tokio::net::UnixStream;
             let mut ssh_agent_sock = UnixStream::connect(sock_path).await?;
-            crate::ssh_agent::generate_proof(
+            crate::ssh_agent::generate_proof_with_properties(
                 &mut ssh_agent_sock,
                 document,
                 options,
                 resolver,
                 context_loader,
                 key,
+                extra_proof_properties,
             )
             .await?
         }
         None => {
             let jwk = key.expect("JWK, Key Path, or SSH Agent option is required.");
-            LinkedDataProofs::sign(document, &options, resolver, context_loader, &jwk, None).await?
+            LinkedDataProofs::sign(
+                document,
+                &options,
+                resolver,
+                context_loader,
+                &jwk,
+                extra_proof_properties,
+            )
+            .await?
         }
     };
 
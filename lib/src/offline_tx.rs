@@ -0,0 +1,515 @@
+//! Offline DID method transactions.
+//!
+//! Builds DID method transactions from public keys only, so that they can be prepared on one host,
+//! signed on another host holding the private keys (or by an external signer), and submitted from
+//! an online host.
+//!
+//! Supported for did:ion ([Sidetree](https://identity.foundation/sidetree/spec/)) operations.
+//! Create operations need no signature and are output as complete transactions. Update, recover
+//! and deactivate operations are output as [`UnsignedTransaction`]s, which contain the JWS signing
+//! input to sign with the update or recovery key.
+
+use std::convert::TryFrom;
+
+use did_ion::sidetree::{DIDStatePatch, PublicKeyJwk, Sidetree};
+use did_ion::ION;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use ssi::did::{DIDMethodTransaction, VerificationMethodMap, DIDURL};
+use ssi::jwk::{Algorithm, Params, JWK};
+use thiserror::Error;
+
+use crate::did_document::{relationship_property, DIDDocumentError};
+use crate::DIDDocumentOperation;
+
+#[derive(Error, Debug)]
+pub enum OfflineTxError {
+    #[error("Offline transactions are not supported for DID method: {0}")]
+    UnsupportedMethod(String),
+    #[error("Not a did:ion DID: {0}")]
+    NotDIDION(String),
+    #[error("ION keys must be secp256k1 keys")]
+    KeyType,
+    #[error("The {0} key must differ from the current key, to avoid reusing a commitment")]
+    KeyReuse(&'static str),
+    #[error("Verification method must have a publicKeyJwk: {0}")]
+    MissingPublicKeyJwk(String),
+    #[error("Operation not supported by did:ion: {0}")]
+    UnsupportedOperation(&'static str),
+    #[error("Service must have a single type: {0}")]
+    ServiceType(String),
+    #[error("Key does not match the public key of the unsigned transaction")]
+    KeyMismatch,
+    #[error("Invalid signature JSON Pointer: {0}")]
+    SignaturePointer(String),
+    #[error("Sidetree: {0}")]
+    Sidetree(String),
+    #[error(transparent)]
+    DIDDocument(#[from] DIDDocumentError),
+    #[error(transparent)]
+    JWK(#[from] ssi::jwk::Error),
+    #[error(transparent)]
+    JWS(#[from] ssi::jws::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// DID method transaction awaiting a signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsignedTransaction {
+    pub did_method: String,
+    /// Transaction value, with the signature missing
+    pub value: Value,
+    /// JSON Pointer to the property of the value that takes the compact JWS
+    pub signature_pointer: String,
+    /// JWS signing input (base64url-encoded header and payload, separated by a period)
+    pub signing_input: String,
+    pub algorithm: Algorithm,
+    /// Public key of the key that must sign the transaction
+    pub public_key: JWK,
+}
+
+impl UnsignedTransaction {
+    /// Sign the transaction with a private key
+    pub fn sign(self, key: &JWK) -> Result<DIDMethodTransaction, OfflineTxError> {
+        if key.thumbprint()? != self.public_key.thumbprint()? {
+            return Err(OfflineTxError::KeyMismatch);
+        }
+        let signature = ssi::jws::sign_bytes(self.algorithm, self.signing_input.as_bytes(), key)?;
+        self.complete(&signature)
+    }
+
+    /// Complete the transaction with a signature of the signing input, e.g. from an external
+    /// signer. The signature is verified against the public key.
+    pub fn complete(self, signature: &[u8]) -> Result<DIDMethodTransaction, OfflineTxError> {
+        ssi::jws::verify_bytes(
+            self.algorithm,
+            self.signing_input.as_bytes(),
+            &self.public_key,
+            signature,
+        )?;
+        let jws = format!("{}.{}", self.signing_input, b64_encode(signature));
+        let mut value = self.value;
+        let property = value
+            .pointer_mut(&self.signature_pointer)
+            .ok_or_else(|| OfflineTxError::SignaturePointer(self.signature_pointer.clone()))?;
+        *property = Value::String(jws);
+        Ok(DIDMethodTransaction {
+            did_method: self.did_method,
+            value,
+        })
+    }
+}
+
+fn b64_encode(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn sidetree_error(err: impl std::fmt::Display) -> OfflineTxError {
+    OfflineTxError::Sidetree(err.to_string())
+}
+
+/// Sidetree hash of the canonical JSON of a value
+fn canonical_hash<T: Serialize + ?Sized>(value: &T) -> Result<String, OfflineTxError> {
+    let canonical = ION::json_canonicalization_scheme(value).map_err(sidetree_error)?;
+    Ok(ION::hash(canonical.as_bytes()))
+}
+
+/// Public key in the form used in Sidetree operations
+fn ion_public_key(jwk: &JWK) -> Result<PublicKeyJwk, OfflineTxError> {
+    match &jwk.params {
+        Params::EC(ec) if ec.curve.as_deref() == Some("secp256k1") => {
+            PublicKeyJwk::try_from(jwk.to_public()).map_err(sidetree_error)
+        }
+        _ => Err(OfflineTxError::KeyType),
+    }
+}
+
+/// Sidetree reveal value of a public key
+fn reveal_value(public_key: &PublicKeyJwk) -> Result<String, OfflineTxError> {
+    canonical_hash(public_key)
+}
+
+/// Sidetree commitment to a public key
+fn commitment(public_key: &PublicKeyJwk) -> Result<String, OfflineTxError> {
+    ION::commitment_scheme(public_key).map_err(sidetree_error)
+}
+
+/// Get the unique suffix of a short or long form did:ion DID
+fn did_suffix(did: &str) -> Result<String, OfflineTxError> {
+    let rest = did
+        .strip_prefix("did:ion:")
+        .ok_or_else(|| OfflineTxError::NotDIDION(did.to_string()))?;
+    let rest = rest.strip_prefix("test:").unwrap_or(rest);
+    match rest.split(':').next() {
+        Some(suffix) if !suffix.is_empty() => Ok(suffix.to_string()),
+        _ => Err(OfflineTxError::NotDIDION(did.to_string())),
+    }
+}
+
+fn fragment(id: &str) -> &str {
+    id.rsplit_once('#').map_or(id, |(_, fragment)| fragment)
+}
+
+fn did_url_fragment(did_url: &DIDURL) -> String {
+    fragment(&did_url.to_string()).to_string()
+}
+
+fn public_key_entry(
+    vmm: VerificationMethodMap,
+    purposes: Vec<&str>,
+) -> Result<Value, OfflineTxError> {
+    let jwk = match vmm.public_key_jwk {
+        Some(jwk) => jwk,
+        None => return Err(OfflineTxError::MissingPublicKeyJwk(vmm.id)),
+    };
+    Ok(json!({
+        "id": fragment(&vmm.id),
+        "type": vmm.type_,
+        "publicKeyJwk": jwk.to_public(),
+        "purposes": purposes,
+    }))
+}
+
+fn verification_key_document(verification_key: Option<&JWK>) -> Result<Value, OfflineTxError> {
+    let mut public_keys = Vec::new();
+    if let Some(jwk) = verification_key {
+        let vmm = VerificationMethodMap {
+            id: jwk.thumbprint()?,
+            type_: "JsonWebSignature2020".to_string(),
+            public_key_jwk: Some(jwk.to_public()),
+            ..Default::default()
+        };
+        public_keys.push(public_key_entry(
+            vmm,
+            vec!["authentication", "assertionMethod"],
+        )?);
+    }
+    Ok(json!({ "publicKeys": public_keys, "services": [] }))
+}
+
+/// Convert a DID document operation to a Sidetree patch
+fn ion_patch(operation: DIDDocumentOperation) -> Result<Value, OfflineTxError> {
+    Ok(match operation {
+        DIDDocumentOperation::SetVerificationMethod { vmm, purposes } => {
+            let purposes = purposes
+                .iter()
+                .map(relationship_property)
+                .collect::<Result<Vec<&str>, DIDDocumentError>>()?;
+            json!({
+                "action": "add-public-keys",
+                "publicKeys": [public_key_entry(vmm, purposes)?],
+            })
+        }
+        DIDDocumentOperation::SetService(service) => {
+            let type_ = match &service.type_ {
+                ssi::vc::OneOrMany::One(type_) => type_.clone(),
+                ssi::vc::OneOrMany::Many(_) => {
+                    return Err(OfflineTxError::ServiceType(service.id.clone()))
+                }
+            };
+            let mut entry = serde_json::to_value(&service)?;
+            entry["id"] = Value::String(fragment(&service.id).to_string());
+            entry["type"] = Value::String(type_);
+            json!({
+                "action": "add-services",
+                "services": [entry],
+            })
+        }
+        DIDDocumentOperation::RemoveVerificationMethod(did_url) => json!({
+            "action": "remove-public-keys",
+            "ids": [did_url_fragment(&did_url)],
+        }),
+        DIDDocumentOperation::RemoveService(did_url) => json!({
+            "action": "remove-services",
+            "ids": [did_url_fragment(&did_url)],
+        }),
+        DIDDocumentOperation::SetDidDocument(_) => {
+            return Err(OfflineTxError::UnsupportedOperation("setDidDocument"))
+        }
+        DIDDocumentOperation::AddToDidDocument(_) => {
+            return Err(OfflineTxError::UnsupportedOperation("addToDidDocument"))
+        }
+        DIDDocumentOperation::RemoveFromDidDocument(_) => {
+            return Err(OfflineTxError::UnsupportedOperation(
+                "removeFromDidDocument",
+            ))
+        }
+    })
+}
+
+/// Parse patches built as JSON into Sidetree patches
+fn sidetree_patches(patches: Value) -> Result<Vec<DIDStatePatch>, OfflineTxError> {
+    Ok(serde_json::from_value(patches)?)
+}
+
+fn ion_transaction(operation: Value) -> DIDMethodTransaction {
+    DIDMethodTransaction {
+        did_method: "ion".to_string(),
+        value: json!({ "sidetreeOperation": operation }),
+    }
+}
+
+fn unsigned_ion_transaction(
+    operation: Value,
+    claims: &Value,
+    public_key: &JWK,
+) -> Result<UnsignedTransaction, OfflineTxError> {
+    let header = b64_encode(br#"{"alg":"ES256K"}"#);
+    let payload = b64_encode(serde_json::to_string(claims)?.as_bytes());
+    Ok(UnsignedTransaction {
+        did_method: "ion".to_string(),
+        value: json!({ "sidetreeOperation": operation }),
+        signature_pointer: "/sidetreeOperation/signedData".to_string(),
+        signing_input: format!("{header}.{payload}"),
+        algorithm: Algorithm::ES256K,
+        public_key: public_key.to_public(),
+    })
+}
+
+/// Build a did:ion create transaction from public keys
+///
+/// The DID (in long form) can be derived from the transaction with
+/// [`DIDMethod::did_from_transaction`](ssi::did::DIDMethod::did_from_transaction).
+pub fn ion_create(
+    update_key: &JWK,
+    recovery_key: &JWK,
+    verification_key: Option<&JWK>,
+) -> Result<DIDMethodTransaction, OfflineTxError> {
+    let update_pk = ion_public_key(update_key)?;
+    let recovery_pk = ion_public_key(recovery_key)?;
+    if commitment(&update_pk)? == commitment(&recovery_pk)? {
+        return Err(OfflineTxError::KeyReuse("update"));
+    }
+    let patches = sidetree_patches(json!([{
+        "action": "replace",
+        "document": verification_key_document(verification_key)?,
+    }]))?;
+    let operation =
+        ION::create_existing(&update_pk, &recovery_pk, patches).map_err(sidetree_error)?;
+    Ok(ion_transaction(serde_json::to_value(operation)?))
+}
+
+/// Build an unsigned did:ion update transaction, to be signed with the update key
+///
+/// Unlike [`DIDMethod::update`](ssi::did::DIDMethod::update), multiple operations can be applied
+/// in one update.
+pub fn ion_update(
+    did: &str,
+    update_key: &JWK,
+    new_update_key: &JWK,
+    operations: Vec<DIDDocumentOperation>,
+) -> Result<UnsignedTransaction, OfflineTxError> {
+    let did_suffix = did_suffix(did)?;
+    let update_pk = ion_public_key(update_key)?;
+    let new_update_pk = ion_public_key(new_update_key)?;
+    if commitment(&update_pk)? == commitment(&new_update_pk)? {
+        return Err(OfflineTxError::KeyReuse("new update"));
+    }
+    let patches = operations
+        .into_iter()
+        .map(ion_patch)
+        .collect::<Result<Vec<Value>, OfflineTxError>>()?;
+    let patches = sidetree_patches(Value::Array(patches))?;
+    let delta = json!({
+        "patches": patches,
+        "updateCommitment": commitment(&new_update_pk)?,
+    });
+    let claims = json!({
+        "updateKey": update_pk,
+        "deltaHash": canonical_hash(&delta)?,
+    });
+    let operation = json!({
+        "type": "update",
+        "didSuffix": did_suffix,
+        "revealValue": reveal_value(&update_pk)?,
+        "delta": delta,
+        "signedData": "",
+    });
+    unsigned_ion_transaction(operation, &claims, update_key)
+}
+
+/// Build an unsigned did:ion recover transaction, to be signed with the recovery key
+///
+/// The DID document is replaced with one containing only the new verification key, if any.
+pub fn ion_recover(
+    did: &str,
+    recovery_key: &JWK,
+    new_recovery_key: &JWK,
+    new_update_key: &JWK,
+    new_verification_key: Option<&JWK>,
+) -> Result<UnsignedTransaction, OfflineTxError> {
+    let did_suffix = did_suffix(did)?;
+    let recovery_pk = ion_public_key(recovery_key)?;
+    let new_recovery_pk = ion_public_key(new_recovery_key)?;
+    let new_update_pk = ion_public_key(new_update_key)?;
+    if commitment(&recovery_pk)? == commitment(&new_recovery_pk)? {
+        return Err(OfflineTxError::KeyReuse("new recovery"));
+    }
+    let patches = sidetree_patches(json!([{
+        "action": "replace",
+        "document": verification_key_document(new_verification_key)?,
+    }]))?;
+    let delta = json!({
+        "patches": patches,
+        "updateCommitment": commitment(&new_update_pk)?,
+    });
+    let claims = json!({
+        "recoveryCommitment": commitment(&new_recovery_pk)?,
+        "recoveryKey": recovery_pk,
+        "deltaHash": canonical_hash(&delta)?,
+    });
+    let operation = json!({
+        "type": "recover",
+        "didSuffix": did_suffix,
+        "revealValue": reveal_value(&recovery_pk)?,
+        "delta": delta,
+        "signedData": "",
+    });
+    unsigned_ion_transaction(operation, &claims, recovery_key)
+}
+
+/// Build an unsigned did:ion deactivate transaction, to be signed with the recovery key
+pub fn ion_deactivate(
+    did: &str,
+    recovery_key: &JWK,
+) -> Result<UnsignedTransaction, OfflineTxError> {
+    let did_suffix = did_suffix(did)?;
+    let recovery_pk = ion_public_key(recovery_key)?;
+    let claims = json!({
+        "didSuffix": did_suffix,
+        "recoveryKey": recovery_pk,
+    });
+    let operation = json!({
+        "type": "deactivate",
+        "didSuffix": did_suffix,
+        "revealValue": reveal_value(&recovery_pk)?,
+        "signedData": "",
+    });
+    unsigned_ion_transaction(operation, &claims, recovery_key)
+}
+
+fn check_method(did: &str) -> Result<(), OfflineTxError> {
+    match did.split(':').nth(1) {
+        Some("ion") => Ok(()),
+        Some(method) => Err(OfflineTxError::UnsupportedMethod(method.to_string())),
+        None => Err(OfflineTxError::UnsupportedMethod(did.to_string())),
+    }
+}
+
+/// Build a create transaction from public keys, for a DID method name
+pub fn create(
+    method: &str,
+    update_key: &JWK,
+    recovery_key: &JWK,
+    verification_key: Option<&JWK>,
+) -> Result<DIDMethodTransaction, OfflineTxError> {
+    match method {
+        "ion" => ion_create(update_key, recovery_key, verification_key),
+        _ => Err(OfflineTxError::UnsupportedMethod(method.to_string())),
+    }
+}
+
+/// Build an unsigned update transaction from public keys
+pub fn update(
+    did: &str,
+    update_key: &JWK,
+    new_update_key: &JWK,
+    operations: Vec<DIDDocumentOperation>,
+) -> Result<UnsignedTransaction, OfflineTxError> {
+    check_method(did)?;
+    ion_update(did, update_key, new_update_key, operations)
+}
+
+/// Build an unsigned recover transaction from public keys
+pub fn recover(
+    did: &str,
+    recovery_key: &JWK,
+    new_recovery_key: &JWK,
+    new_update_key: &JWK,
+    new_verification_key: Option<&JWK>,
+) -> Result<UnsignedTransaction, OfflineTxError> {
+    check_method(did)?;
+    ion_recover(
+        did,
+        recovery_key,
+        new_recovery_key,
+        new_update_key,
+        new_verification_key,
+    )
+}
+
+/// Build an unsigned deactivate transaction from a public key
+pub fn deactivate(did: &str, recovery_key: &JWK) -> Result<UnsignedTransaction, OfflineTxError> {
+    check_method(did)?;
+    ion_deactivate(did, recovery_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VerificationRelationship;
+
+    #[test]
+    fn create_ion() {
+        use ssi::did::DIDMethod;
+        let update_key = JWK::generate_secp256k1().unwrap();
+        let recovery_key = JWK::generate_secp256k1().unwrap();
+        let tx = create(
+            "ion",
+            &update_key.to_public(),
+            &recovery_key.to_public(),
+            None,
+        )
+        .unwrap();
+        let operation = &tx.value["sidetreeOperation"];
+        assert_eq!(operation["type"], "create");
+        assert_eq!(
+            operation["suffixData"]["deltaHash"],
+            canonical_hash(&operation["delta"]).unwrap()
+        );
+        let did = did_ion::DIDION::new(None).did_from_transaction(tx).unwrap();
+        assert!(did.starts_with("did:ion:"));
+        assert!(create("ion", &update_key, &update_key, None).is_err());
+    }
+
+    #[test]
+    fn sign_update() {
+        let update_key = JWK::generate_secp256k1().unwrap();
+        let new_update_key = JWK::generate_secp256k1().unwrap();
+        let vmm = VerificationMethodMap {
+            id: "#key-2".to_string(),
+            type_: "JsonWebSignature2020".to_string(),
+            public_key_jwk: Some(JWK::generate_secp256k1().unwrap().to_public()),
+            ..Default::default()
+        };
+        let unsigned = update(
+            "did:ion:test:EiAtsyzkYPJd2vVbc4YXlDe9CRuL5SbzI6TZ3RxLpSM8rA",
+            &update_key.to_public(),
+            &new_update_key.to_public(),
+            vec![DIDDocumentOperation::SetVerificationMethod {
+                vmm,
+                purposes: vec![VerificationRelationship::AssertionMethod],
+            }],
+        )
+        .unwrap();
+        assert_eq!(
+            unsigned.value["sidetreeOperation"]["didSuffix"],
+            "EiAtsyzkYPJd2vVbc4YXlDe9CRuL5SbzI6TZ3RxLpSM8rA"
+        );
+        assert!(unsigned.clone().sign(&new_update_key).is_err());
+        assert!(unsigned.clone().complete(&[0; 64]).is_err());
+        let tx = unsigned.sign(&update_key).unwrap();
+        let jws = tx.value["sidetreeOperation"]["signedData"]
+            .as_str()
+            .unwrap();
+        let (_, payload) = ssi::jws::decode_verify(jws, &update_key.to_public()).unwrap();
+        let claims: Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(
+            claims["deltaHash"],
+            canonical_hash(&tx.value["sidetreeOperation"]["delta"]).unwrap()
+        );
+        assert!(deactivate("did:example:123", &update_key).is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! [OpenID for Verifiable Presentations][oid4vp] (OID4VP) authorization requests and responses.
+//!
+//! Verifiers identify themselves using `client_id_scheme=did`: the `client_id` is a DID, and the
+//! authorization request is passed by value as a request object signed with one of that DID's
+//! keys. Wallets answer with a `vp_token` whose proof is bound to the request's `nonce` (as
+//! `challenge`) and `client_id` (as `domain`/`aud`).
+//!
+//! [oid4vp]: https://openid.net/specs/openid-4-verifiable-presentations-1_0.html
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+use url::Url;
+
+use crate::presentation_exchange::{
+    self, PresentationDefinition, PresentationExchangeError, PresentationSubmission,
+};
+use crate::{
+    generate_proof, resolve_key, ContextLoader, DIDResolver, GenerateProofError, JWTOrLDPOptions,
+    LinkedDataProofOptions, ProofFormat, VerifiablePresentation, VerificationRelationship,
+    VerificationResult, JWK,
+};
+
+/// Default scheme for authorization request URIs.
+pub const DEFAULT_REQUEST_URI_BASE: &str = "openid4vp://";
+/// `response_type` requesting a Verifiable Presentation token.
+pub const RESPONSE_TYPE_VP_TOKEN: &str = "vp_token";
+/// `client_id_scheme` for verifiers identified by a DID.
+pub const CLIENT_ID_SCHEME_DID: &str = "did";
+/// JOSE `typ` header value for request objects
+/// <https://www.rfc-editor.org/rfc/rfc9101#section-10.8>
+pub const REQUEST_OBJECT_TYPE: &str = "oauth-authz-req+jwt";
+
+/// Request parameters whose values are JSON objects, encoded as strings in URIs.
+const JSON_PARAMETERS: &[&str] = &["presentation_definition", "client_metadata"];
+
+#[derive(Error, Debug)]
+pub enum OID4VPError {
+    #[error("Invalid authorization request URI: {0}")]
+    URI(#[from] url::ParseError),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("JWS: {0}")]
+    JWS(#[from] ssi::jws::Error),
+    #[error("Unable to generate proof: {0}")]
+    Proof(#[from] GenerateProofError),
+    #[error("Unable to generate JWT: {0}")]
+    JWT(#[from] ssi::vc::Error),
+    #[error("Unable to resolve request object key: {0}")]
+    ResolveKey(String),
+    #[error("Request object must use client_id_scheme \"did\"")]
+    ClientIdScheme,
+    #[error("Request object is missing key id")]
+    MissingKeyId,
+    #[error("Unable to determine algorithm for key")]
+    MissingAlgorithm,
+    #[error("Key id '{0}' is not a DID URL of client_id '{1}'")]
+    KeyIdMismatch(String, String),
+    #[error("client_id in request object does not match client_id in URI")]
+    ClientIdMismatch,
+    #[error("Fetching request objects by reference (request_uri) is not supported")]
+    RequestUriNotSupported,
+    #[error("Unsupported response_type: {0}")]
+    ResponseType(String),
+    #[error("State in response does not match authorization request")]
+    StateMismatch,
+    #[error("Presentation submission does not refer to the requested presentation definition")]
+    DefinitionMismatch,
+    #[error("vp_token must be a JWT string or a JSON-LD presentation object")]
+    InvalidVPToken,
+    #[error("Key or ssh-agent is required for JWT VP token")]
+    MissingKey,
+    #[error(transparent)]
+    PresentationExchange(#[from] PresentationExchangeError),
+}
+
+/// Authorization request parameters
+/// <https://openid.net/specs/openid-4-verifiable-presentations-1_0.html#name-authorization-request>
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AuthorizationRequest {
+    pub response_type: String,
+    pub client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id_scheme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mode: Option<String>,
+    pub nonce: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presentation_definition: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presentation_definition_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_metadata: Option<Value>,
+}
+
+/// Authorization response parameters
+/// <https://openid.net/specs/openid-4-verifiable-presentations-1_0.html#name-response-parameters>
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuthorizationResponse {
+    /// JWT VP (string) or JSON-LD VP (object)
+    pub vp_token: Value,
+    pub presentation_submission: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+}
+
+impl AuthorizationRequest {
+    /// Encode the request parameters by value in a URI, e.g. for a QR code.
+    pub fn to_uri(&self, base: &str) -> Result<String, OID4VPError> {
+        let mut url = Url::parse(base)?;
+        let params = match serde_json::to_value(self)? {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        };
+        {
+            let mut query = url.query_pairs_mut();
+            for (name, value) in params {
+                match value {
+                    Value::String(string) => query.append_pair(&name, &string),
+                    value => query.append_pair(&name, &value.to_string()),
+                };
+            }
+        }
+        Ok(url.to_string())
+    }
+}
+
+fn did_from_did_url(did_url: &str) -> &str {
+    did_url
+        .split(|c| c == '#' || c == '?' || c == '/')
+        .next()
+        .unwrap_or(did_url)
+}
+
+/// Sign an authorization request as a request object, using a key of the verifier's DID
+/// (`client_id`). `verification_method` is the DID URL of the key, used as the JWS `kid`.
+pub fn sign_request_object(
+    request: &AuthorizationRequest,
+    key: &JWK,
+    verification_method: &str,
+) -> Result<String, OID4VPError> {
+    if request.client_id_scheme.as_deref() != Some(CLIENT_ID_SCHEME_DID) {
+        return Err(OID4VPError::ClientIdScheme);
+    }
+    if did_from_did_url(verification_method) != request.client_id {
+        return Err(OID4VPError::KeyIdMismatch(
+            verification_method.to_string(),
+            request.client_id.clone(),
+        ));
+    }
+    let algorithm = key.get_algorithm().ok_or(OID4VPError::MissingAlgorithm)?;
+    let header = ssi::jws::Header {
+        algorithm,
+        key_id: Some(verification_method.to_string()),
+        type_: Some(REQUEST_OBJECT_TYPE.to_string()),
+        ..Default::default()
+    };
+    let payload = serde_json::to_string(request)?;
+    let jws = ssi::jws::encode_sign_custom_header(&payload, key, &header)?;
+    Ok(jws)
+}
+
+/// Encode a signed request object by value in a URI.
+pub fn request_object_uri(
+    client_id: &str,
+    request_object: &str,
+    base: &str,
+) -> Result<String, OID4VPError> {
+    let mut url = Url::parse(base)?;
+    url.query_pairs_mut()
+        .append_pair("client_id", client_id)
+        .append_pair("request", request_object);
+    Ok(url.to_string())
+}
+
+/// Verify a request object signed with a key of the DID in its `client_id`, and return the
+/// authorization request it contains.
+pub async fn verify_request_object(
+    request_object: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<AuthorizationRequest, OID4VPError> {
+    let (header, _) = ssi::jws::decode_unverified(request_object)?;
+    let key_id = header.key_id.ok_or(OID4VPError::MissingKeyId)?;
+    let key = resolve_key(&key_id, resolver)
+        .await
+        .map_err(|e| OID4VPError::ResolveKey(e.to_string()))?;
+    let (_, payload) = ssi::jws::decode_verify(request_object, &key)?;
+    let request: AuthorizationRequest = serde_json::from_slice(&payload)?;
+    if request.client_id_scheme.as_deref() != Some(CLIENT_ID_SCHEME_DID) {
+        return Err(OID4VPError::ClientIdScheme);
+    }
+    if did_from_did_url(&key_id) != request.client_id {
+        return Err(OID4VPError::KeyIdMismatch(key_id, request.client_id));
+    }
+    if request.response_type != RESPONSE_TYPE_VP_TOKEN {
+        return Err(OID4VPError::ResponseType(request.response_type));
+    }
+    Ok(request)
+}
+
+/// Parse an authorization request URI. Request objects passed by value (`request`) are verified
+/// against the verifier's DID; requests by reference (`request_uri`) are not supported.
+pub async fn parse_request_uri(
+    uri: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<AuthorizationRequest, OID4VPError> {
+    let url = Url::parse(uri)?;
+    let mut params = Map::new();
+    for (name, value) in url.query_pairs() {
+        let value = if JSON_PARAMETERS.contains(&name.as_ref()) {
+            serde_json::from_str(&value)?
+        } else {
+            Value::String(value.into_owned())
+        };
+        params.insert(name.into_owned(), value);
+    }
+    if params.contains_key("request_uri") {
+        return Err(OID4VPError::RequestUriNotSupported);
+    }
+    if let Some(Value::String(request_object)) = params.get("request") {
+        let request = verify_request_object(request_object, resolver).await?;
+        if let Some(client_id) = params.get("client_id") {
+            if client_id.as_str() != Some(&request.client_id) {
+                return Err(OID4VPError::ClientIdMismatch);
+            }
+        }
+        return Ok(request);
+    }
+    let request: AuthorizationRequest = serde_json::from_value(Value::Object(params))?;
+    if request.response_type != RESPONSE_TYPE_VP_TOKEN {
+        return Err(OID4VPError::ResponseType(request.response_type));
+    }
+    Ok(request)
+}
+
+/// Linked data proof options binding a presentation to an authorization request.
+fn binding_options(
+    request: &AuthorizationRequest,
+    mut options: LinkedDataProofOptions,
+) -> LinkedDataProofOptions {
+    options.challenge = Some(request.nonce.clone());
+    options.domain = Some(request.client_id.clone());
+    if options.proof_purpose.is_none() {
+        options.proof_purpose = Some(VerificationRelationship::Authentication);
+    }
+    options
+}
+
+/// Create an authorization response for a request, as a wallet. The presentation is signed with
+/// the request's `nonce` as `challenge` and its `client_id` as `domain` (`aud` for JWT).
+#[allow(clippy::too_many_arguments)]
+pub async fn create_response(
+    request: &AuthorizationRequest,
+    mut presentation: VerifiablePresentation,
+    presentation_submission: Value,
+    key: Option<&JWK>,
+    options: JWTOrLDPOptions,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+    ssh_agent_sock_path_opt: Option<&str>,
+) -> Result<AuthorizationResponse, OID4VPError> {
+    let proof_format = options.proof_format.unwrap_or_default();
+    let ldp_options = binding_options(request, options.ldp_options);
+    let vp_token = match proof_format {
+        ProofFormat::JWT => {
+            let key = key.ok_or(OID4VPError::MissingKey)?;
+            let jwt = presentation
+                .generate_jwt(Some(key), &ldp_options, resolver)
+                .await?;
+            Value::String(jwt)
+        }
+        ProofFormat::LDP => {
+            let proof = generate_proof(
+                &presentation,
+                key,
+                ldp_options,
+                resolver,
+                context_loader,
+                ssh_agent_sock_path_opt,
+            )
+            .await?;
+            presentation.add_proof(proof);
+            serde_json::to_value(&presentation)?
+        }
+    };
+    Ok(AuthorizationResponse {
+        vp_token,
+        presentation_submission,
+        state: request.state.clone(),
+    })
+}
+
+fn proof_values(vp: &Value) -> Vec<&Value> {
+    match vp.get("proof") {
+        Some(Value::Array(proofs)) => proofs.iter().collect(),
+        Some(proof @ Value::Object(_)) => vec![proof],
+        _ => vec![],
+    }
+}
+
+fn audience_contains(aud: Option<&Value>, client_id: &str) -> bool {
+    match aud {
+        Some(Value::String(aud)) => aud == client_id,
+        Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(client_id)),
+        _ => false,
+    }
+}
+
+/// Verify an authorization response against the request it answers, as a verifier. Checks the
+/// `state`, the presentation submission against the presentation definition, the presentation
+/// proof, and that the proof is bound to the request's `nonce` and `client_id`.
+pub async fn verify_response(
+    response: &AuthorizationResponse,
+    request: &AuthorizationRequest,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+) -> Result<VerificationResult, OID4VPError> {
+    if response.state != request.state {
+        return Err(OID4VPError::StateMismatch);
+    }
+    if let Some(definition_id) = request
+        .presentation_definition
+        .as_ref()
+        .and_then(|definition| definition.get("id"))
+    {
+        if response.presentation_submission.get("definition_id") != Some(definition_id) {
+            return Err(OID4VPError::DefinitionMismatch);
+        }
+    }
+    let options = binding_options(request, LinkedDataProofOptions::default());
+    let mut result = match &response.vp_token {
+        Value::String(jwt) => {
+            let mut result =
+                VerifiablePresentation::verify_jwt(jwt, Some(options), resolver, context_loader)
+                    .await;
+            let claims: Value = ssi::jwt::decode_unverified(jwt)?;
+            if claims.get("nonce").and_then(Value::as_str) != Some(&request.nonce) {
+                result
+                    .errors
+                    .push("JWT nonce does not match request".to_string());
+            }
+            if !audience_contains(claims.get("aud"), &request.client_id) {
+                result
+                    .errors
+                    .push("JWT audience does not match client_id".to_string());
+            }
+            result
+        }
+        vp @ Value::Object(_) => {
+            let bound = proof_values(vp).into_iter().any(|proof| {
+                proof.get("challenge").and_then(Value::as_str) == Some(&request.nonce)
+                    && proof.get("domain").and_then(Value::as_str) == Some(&request.client_id)
+            });
+            let presentation: VerifiablePresentation = serde_json::from_value(vp.clone())?;
+            let mut result = match presentation.validate_unsigned() {
+                Ok(()) => {
+                    presentation
+                        .verify(Some(options), resolver, context_loader)
+                        .await
+                }
+                Err(e) => VerificationResult::error(&e.to_string()),
+            };
+            if !bound {
+                result
+                    .errors
+                    .push("No proof bound to request nonce and client_id".to_string());
+            }
+            result
+        }
+        _ => return Err(OID4VPError::InvalidVPToken),
+    };
+    if let Some(definition) = &request.presentation_definition {
+        let definition: PresentationDefinition = serde_json::from_value(definition.clone())?;
+        let submission: PresentationSubmission =
+            serde_json::from_value(response.presentation_submission.clone())?;
+        let check =
+            presentation_exchange::check_submission(&definition, &submission, &response.vp_token)?;
+        result.errors.extend(check.errors);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_uri_roundtrip() {
+        let request = AuthorizationRequest {
+            response_type: RESPONSE_TYPE_VP_TOKEN.to_string(),
+            client_id: "https://verifier.example.org/cb".to_string(),
+            response_uri: Some("https://verifier.example.org/cb".to_string()),
+            response_mode: Some("direct_post".to_string()),
+            nonce: "n-0S6_WzA2Mj".to_string(),
+            state: Some("af0ifjsldkj".to_string()),
+            presentation_definition: Some(serde_json::json!({
+                "id": "example",
+                "input_descriptors": []
+            })),
+            ..Default::default()
+        };
+        let uri = request.to_uri(DEFAULT_REQUEST_URI_BASE).unwrap();
+        assert!(uri.starts_with(DEFAULT_REQUEST_URI_BASE));
+        let resolver = crate::DID_METHODS.to_resolver();
+        let rt = crate::runtime::get().unwrap();
+        let parsed = rt.block_on(parse_request_uri(&uri, resolver)).unwrap();
+        assert_eq!(parsed, request);
+    }
+
+    #[test]
+    fn signed_request_object() {
+        let key = JWK::generate_ed25519().unwrap();
+        let did = crate::DID_METHODS
+            .generate(&crate::Source::KeyAndPattern(&key, "key"))
+            .unwrap();
+        let vm = format!("{}#{}", did, &did["did:key:".len()..]);
+        let request = AuthorizationRequest {
+            response_type: RESPONSE_TYPE_VP_TOKEN.to_string(),
+            client_id: did.clone(),
+            client_id_scheme: Some(CLIENT_ID_SCHEME_DID.to_string()),
+            nonce: "123".to_string(),
+            ..Default::default()
+        };
+        let jws = sign_request_object(&request, &key, &vm).unwrap();
+        let uri = request_object_uri(&did, &jws, DEFAULT_REQUEST_URI_BASE).unwrap();
+        let resolver = crate::DID_METHODS.to_resolver();
+        let rt = crate::runtime::get().unwrap();
+        let parsed = rt.block_on(parse_request_uri(&uri, resolver)).unwrap();
+        assert_eq!(parsed, request);
+
+        let other_key = JWK::generate_ed25519().unwrap();
+        let other_did = crate::DID_METHODS
+            .generate(&crate::Source::KeyAndPattern(&other_key, "key"))
+            .unwrap();
+        let forged = AuthorizationRequest {
+            client_id: other_did,
+            ..request
+        };
+        assert!(sign_request_object(&forged, &key, &vm).is_err());
+    }
+}
@@ -0,0 +1,739 @@
+//! [DIF Presentation Exchange][pe] (v2) evaluation.
+//!
+//! Input descriptors are evaluated against credentials using JSONPath and a subset of JSON Schema
+//! for `filter` (`type`, `const`, `enum`, `pattern`, numeric and length bounds, `contains`,
+//! `items`, `not` and the `allOf`/`anyOf`/`oneOf` combinators). JWT credentials are evaluated
+//! against their (unverified) JWT claims, per the specification.
+//!
+//! [pe]: https://identity.foundation/presentation-exchange/spec/v2.0.0/
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serde_json_path::JsonPath;
+use thiserror::Error;
+
+use crate::{ProofFormat, VerifiablePresentation};
+
+/// Claim format designation for JSON-LD credentials
+pub const FORMAT_LDP_VC: &str = "ldp_vc";
+/// Claim format designation for JWT credentials
+pub const FORMAT_JWT_VC: &str = "jwt_vc";
+/// Alternative claim format designation for JWT credentials, used by OID4VP
+pub const FORMAT_JWT_VC_JSON: &str = "jwt_vc_json";
+
+#[derive(Error, Debug)]
+pub enum PresentationExchangeError {
+    #[error("Invalid JSONPath '{0}': {1}")]
+    Path(String, String),
+    #[error("Invalid filter pattern '{0}': {1}")]
+    Pattern(String, regex::Error),
+    #[error("Unable to decode JWT credential: {0}")]
+    JWT(#[from] ssi::jws::Error),
+    #[error("JSON: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("Submission requirement refers to unknown group '{0}'")]
+    UnknownGroup(String),
+    #[error("Credentials do not satisfy presentation definition: {0}")]
+    Unsatisfied(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PresentationDefinition {
+    pub id: String,
+    pub input_descriptors: Vec<InputDescriptor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<BTreeMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submission_requirements: Option<Vec<SubmissionRequirement>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InputDescriptor {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<BTreeMap<String, Value>>,
+    #[serde(default)]
+    pub constraints: Constraints,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Constraints {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<Field>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_disclosure: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Field {
+    pub path: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Value>,
+    #[serde(default)]
+    pub optional: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rule {
+    All,
+    Pick,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SubmissionRequirement {
+    pub rule: Rule,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_nested: Option<Vec<SubmissionRequirement>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PresentationSubmission {
+    pub id: String,
+    pub definition_id: String,
+    pub descriptor_map: Vec<DescriptorMapEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DescriptorMapEntry {
+    pub id: String,
+    pub format: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_nested: Option<Box<DescriptorMapEntry>>,
+}
+
+/// Credentials matching an input descriptor, by index in the evaluated credential list.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DescriptorMatch {
+    pub descriptor_id: String,
+    pub credentials: Vec<usize>,
+}
+
+/// Result of evaluating a presentation definition against a set of credentials.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Evaluation {
+    pub matches: Vec<DescriptorMatch>,
+    /// Input descriptors selected for submission, if the definition can be satisfied.
+    pub selected: Option<Vec<String>>,
+}
+
+/// Result of checking a presentation against a presentation definition.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct SubmissionCheck {
+    pub errors: Vec<String>,
+}
+
+fn credential_format(credential: &Value) -> &'static str {
+    match credential {
+        Value::String(_) => FORMAT_JWT_VC,
+        _ => FORMAT_LDP_VC,
+    }
+}
+
+/// The JSON against which input descriptor fields are evaluated: the credential itself, or the
+/// claims of a JWT credential.
+fn credential_claims(credential: &Value) -> Result<Value, PresentationExchangeError> {
+    match credential {
+        Value::String(jwt) => Ok(ssi::jwt::decode_unverified(jwt)?),
+        value => Ok(value.clone()),
+    }
+}
+
+fn format_allowed(formats: Option<&BTreeMap<String, Value>>, format: &str) -> bool {
+    match formats {
+        None => true,
+        Some(formats) => {
+            formats.contains_key(format)
+                || (format == FORMAT_JWT_VC && formats.contains_key(FORMAT_JWT_VC_JSON))
+        }
+    }
+}
+
+fn query<'a>(path: &str, value: &'a Value) -> Result<Vec<&'a Value>, PresentationExchangeError> {
+    let json_path = JsonPath::parse(path)
+        .map_err(|e| PresentationExchangeError::Path(path.to_string(), e.to_string()))?;
+    Ok(json_path.query(value).all())
+}
+
+fn json_type_matches(type_: &str, value: &Value) -> bool {
+    match type_ {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+/// Check a value against a JSON Schema filter.
+pub fn filter_matches(filter: &Value, value: &Value) -> Result<bool, PresentationExchangeError> {
+    let schema = match filter {
+        Value::Bool(b) => return Ok(*b),
+        Value::Object(schema) => schema,
+        _ => return Ok(true),
+    };
+    for (keyword, arg) in schema {
+        let ok = match keyword.as_str() {
+            "type" => match arg {
+                Value::String(type_) => json_type_matches(type_, value),
+                Value::Array(types) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|type_| json_type_matches(type_, value)),
+                _ => true,
+            },
+            "const" => arg == value,
+            "enum" => arg.as_array().map_or(true, |values| values.contains(value)),
+            "pattern" => match (arg.as_str(), value.as_str()) {
+                (Some(pattern), Some(string)) => Regex::new(pattern)
+                    .map_err(|e| PresentationExchangeError::Pattern(pattern.to_string(), e))?
+                    .is_match(string),
+                _ => true,
+            },
+            "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" => {
+                match (arg.as_f64(), value.as_f64()) {
+                    (Some(bound), Some(number)) => match keyword.as_str() {
+                        "minimum" => number >= bound,
+                        "maximum" => number <= bound,
+                        "exclusiveMinimum" => number > bound,
+                        _ => number < bound,
+                    },
+                    _ => true,
+                }
+            }
+            "minLength" | "maxLength" => match (arg.as_u64(), value.as_str()) {
+                (Some(bound), Some(string)) => {
+                    let len = string.chars().count() as u64;
+                    if keyword == "minLength" {
+                        len >= bound
+                    } else {
+                        len <= bound
+                    }
+                }
+                _ => true,
+            },
+            "contains" => match value.as_array() {
+                Some(items) => {
+                    let mut found = false;
+                    for item in items {
+                        if filter_matches(arg, item)? {
+                            found = true;
+                            break;
+                        }
+                    }
+                    found
+                }
+                None => true,
+            },
+            "items" => match value.as_array() {
+                Some(items) => {
+                    let mut all = true;
+                    for item in items {
+                        if !filter_matches(arg, item)? {
+                            all = false;
+                            break;
+                        }
+                    }
+                    all
+                }
+                None => true,
+            },
+            "not" => !filter_matches(arg, value)?,
+            "allOf" | "anyOf" | "oneOf" => {
+                let schemas = arg.as_array().map(Vec::as_slice).unwrap_or_default();
+                let mut matched = 0;
+                for schema in schemas {
+                    if filter_matches(schema, value)? {
+                        matched += 1;
+                    }
+                }
+                match keyword.as_str() {
+                    "allOf" => matched == schemas.len(),
+                    "anyOf" => matched > 0,
+                    _ => matched == 1,
+                }
+            }
+            // Annotations and unsupported keywords do not constrain the value.
+            _ => true,
+        };
+        if !ok {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn field_matches(field: &Field, claims: &Value) -> Result<bool, PresentationExchangeError> {
+    for path in &field.path {
+        for node in query(path, claims)? {
+            match &field.filter {
+                None => return Ok(true),
+                Some(filter) => {
+                    if filter_matches(filter, node)? {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+    }
+    Ok(field.optional)
+}
+
+/// Check whether a credential (JSON-LD object or JWT string) satisfies an input descriptor.
+pub fn descriptor_matches(
+    definition: &PresentationDefinition,
+    descriptor: &InputDescriptor,
+    credential: &Value,
+) -> Result<bool, PresentationExchangeError> {
+    let format = credential_format(credential);
+    if !format_allowed(definition.format.as_ref(), format)
+        || !format_allowed(descriptor.format.as_ref(), format)
+    {
+        return Ok(false);
+    }
+    let claims = credential_claims(credential)?;
+    for field in descriptor.constraints.fields.iter().flatten() {
+        if !field_matches(field, &claims)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn in_group<'a>(
+    definition: &'a PresentationDefinition,
+    group: &str,
+) -> Result<Vec<&'a InputDescriptor>, PresentationExchangeError> {
+    let members: Vec<_> = definition
+        .input_descriptors
+        .iter()
+        .filter(|descriptor| {
+            descriptor
+                .group
+                .as_ref()
+                .map_or(false, |groups| groups.iter().any(|g| g == group))
+        })
+        .collect();
+    if members.is_empty() {
+        return Err(PresentationExchangeError::UnknownGroup(group.to_string()));
+    }
+    Ok(members)
+}
+
+fn pick_bounds(requirement: &SubmissionRequirement, available: usize) -> Option<usize> {
+    if let Some(count) = requirement.count {
+        return if available >= count {
+            Some(count)
+        } else {
+            None
+        };
+    }
+    if available < requirement.min.unwrap_or(0) {
+        return None;
+    }
+    Some(requirement.max.map_or(available, |max| available.min(max)))
+}
+
+/// Evaluate a submission requirement given the set of input descriptors that can be (or were)
+/// fulfilled. Returns the descriptors to submit, or `None` if the requirement can't be satisfied.
+fn evaluate_requirement(
+    definition: &PresentationDefinition,
+    requirement: &SubmissionRequirement,
+    fulfilled: &BTreeSet<String>,
+) -> Result<Option<BTreeSet<String>>, PresentationExchangeError> {
+    if let Some(group) = &requirement.from {
+        let members = in_group(definition, group)?;
+        let available: Vec<&String> = members
+            .iter()
+            .map(|descriptor| &descriptor.id)
+            .filter(|id| fulfilled.contains(*id))
+            .collect();
+        return Ok(match requirement.rule {
+            Rule::All if available.len() == members.len() => {
+                Some(available.into_iter().cloned().collect())
+            }
+            Rule::All => None,
+            Rule::Pick => pick_bounds(requirement, available.len())
+                .map(|n| available.into_iter().take(n).cloned().collect()),
+        });
+    }
+    let nested = requirement.from_nested.as_deref().unwrap_or_default();
+    let mut satisfied = Vec::new();
+    for nested_requirement in nested {
+        if let Some(ids) = evaluate_requirement(definition, nested_requirement, fulfilled)? {
+            satisfied.push(ids);
+        }
+    }
+    let chosen = match requirement.rule {
+        Rule::All if satisfied.len() == nested.len() => satisfied,
+        Rule::All => return Ok(None),
+        Rule::Pick => match pick_bounds(requirement, satisfied.len()) {
+            Some(n) => satisfied.into_iter().take(n).collect(),
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(chosen.into_iter().flatten().collect()))
+}
+
+/// Given the fulfilled input descriptors, determine which to submit to satisfy the definition.
+fn select_descriptors(
+    definition: &PresentationDefinition,
+    fulfilled: &BTreeSet<String>,
+) -> Result<Option<BTreeSet<String>>, PresentationExchangeError> {
+    match &definition.submission_requirements {
+        None => {
+            let all_fulfilled = definition
+                .input_descriptors
+                .iter()
+                .all(|descriptor| fulfilled.contains(&descriptor.id));
+            Ok(if all_fulfilled {
+                Some(fulfilled.clone())
+            } else {
+                None
+            })
+        }
+        Some(requirements) => {
+            let mut selected = BTreeSet::new();
+            for requirement in requirements {
+                match evaluate_requirement(definition, requirement, fulfilled)? {
+                    Some(ids) => selected.extend(ids),
+                    None => return Ok(None),
+                }
+            }
+            Ok(Some(selected))
+        }
+    }
+}
+
+/// Evaluate the input descriptors of a presentation definition against credentials.
+pub fn evaluate(
+    definition: &PresentationDefinition,
+    credentials: &[Value],
+) -> Result<Evaluation, PresentationExchangeError> {
+    let mut matches = Vec::new();
+    let mut fulfilled = BTreeSet::new();
+    for descriptor in &definition.input_descriptors {
+        let mut indexes = Vec::new();
+        for (i, credential) in credentials.iter().enumerate() {
+            if descriptor_matches(definition, descriptor, credential)? {
+                indexes.push(i);
+            }
+        }
+        if !indexes.is_empty() {
+            fulfilled.insert(descriptor.id.clone());
+        }
+        matches.push(DescriptorMatch {
+            descriptor_id: descriptor.id.clone(),
+            credentials: indexes,
+        });
+    }
+    let selected = select_descriptors(definition, &fulfilled)?.map(|ids| {
+        // Keep definition order.
+        definition
+            .input_descriptors
+            .iter()
+            .map(|descriptor| descriptor.id.clone())
+            .filter(|id| ids.contains(id))
+            .collect()
+    });
+    Ok(Evaluation { matches, selected })
+}
+
+/// Select credentials satisfying a presentation definition, and build an unsigned presentation
+/// containing them along with the corresponding presentation submission. `vp_format` determines
+/// the descriptor map paths: JWT VPs nest the presentation in the `vp` claim.
+pub fn build_presentation(
+    definition: &PresentationDefinition,
+    credentials: &[Value],
+    holder: Option<&str>,
+    vp_format: ProofFormat,
+) -> Result<(VerifiablePresentation, PresentationSubmission), PresentationExchangeError> {
+    let evaluation = evaluate(definition, credentials)?;
+    let selected = evaluation.selected.ok_or_else(|| {
+        let unmatched: Vec<&str> = evaluation
+            .matches
+            .iter()
+            .filter(|m| m.credentials.is_empty())
+            .map(|m| m.descriptor_id.as_str())
+            .collect();
+        PresentationExchangeError::Unsatisfied(format!(
+            "no matching credentials for input descriptors: {}",
+            unmatched.join(", ")
+        ))
+    })?;
+    let prefix = match vp_format {
+        ProofFormat::JWT => "$.vp",
+        _ => "$",
+    };
+    let mut included: Vec<usize> = Vec::new();
+    let mut descriptor_map = Vec::new();
+    for descriptor_id in selected {
+        let m = evaluation
+            .matches
+            .iter()
+            .find(|m| m.descriptor_id == descriptor_id)
+            .unwrap();
+        // Prefer a credential already included for another descriptor.
+        let credential_index = m
+            .credentials
+            .iter()
+            .find(|i| included.contains(i))
+            .or_else(|| m.credentials.first())
+            .copied()
+            .unwrap();
+        let position = match included.iter().position(|i| *i == credential_index) {
+            Some(position) => position,
+            None => {
+                included.push(credential_index);
+                included.len() - 1
+            }
+        };
+        descriptor_map.push(DescriptorMapEntry {
+            id: descriptor_id,
+            format: credential_format(&credentials[credential_index]).to_string(),
+            path: format!("{}.verifiableCredential[{}]", prefix, position),
+            path_nested: None,
+        });
+    }
+    let mut presentation = json!({
+        "@context": ["https://www.w3.org/2018/credentials/v1"],
+        "type": ["VerifiablePresentation"],
+        "verifiableCredential": included
+            .iter()
+            .map(|i| credentials[*i].clone())
+            .collect::<Vec<Value>>(),
+    });
+    if let Some(holder) = holder {
+        presentation["holder"] = Value::String(holder.to_string());
+    }
+    let presentation: VerifiablePresentation = serde_json::from_value(presentation)?;
+    let submission = PresentationSubmission {
+        id: uuid::Uuid::new_v4().to_string(),
+        definition_id: definition.id.clone(),
+        descriptor_map,
+    };
+    Ok((presentation, submission))
+}
+
+/// Check that a presentation satisfies a presentation definition, given its presentation
+/// submission. `presentation` is the JSON-LD presentation object or the JWT VP string. Proofs are
+/// not verified here.
+pub fn check_submission(
+    definition: &PresentationDefinition,
+    submission: &PresentationSubmission,
+    presentation: &Value,
+) -> Result<SubmissionCheck, PresentationExchangeError> {
+    let mut check = SubmissionCheck::default();
+    if submission.definition_id != definition.id {
+        check.errors.push(format!(
+            "Submission is for definition '{}', expected '{}'",
+            submission.definition_id, definition.id
+        ));
+        return Ok(check);
+    }
+    let root = credential_claims(presentation)?;
+    let mut fulfilled = BTreeSet::new();
+    for entry in &submission.descriptor_map {
+        let descriptor = match definition
+            .input_descriptors
+            .iter()
+            .find(|descriptor| descriptor.id == entry.id)
+        {
+            Some(descriptor) => descriptor,
+            None => {
+                check
+                    .errors
+                    .push(format!("Unknown input descriptor '{}'", entry.id));
+                continue;
+            }
+        };
+        // Follow path_nested, e.g. into a JWT VC inside the VP.
+        let mut current = entry;
+        let mut value = root.clone();
+        let credential = loop {
+            let node = match query(&current.path, &value)?.first() {
+                Some(node) => (*node).clone(),
+                None => break None,
+            };
+            match &current.path_nested {
+                Some(nested) => {
+                    value = credential_claims(&node)?;
+                    current = nested;
+                }
+                None => break Some(node),
+            }
+        };
+        let credential = match credential {
+            Some(credential) => credential,
+            None => {
+                check.errors.push(format!(
+                    "Path '{}' for input descriptor '{}' does not select a credential",
+                    entry.path, entry.id
+                ));
+                continue;
+            }
+        };
+        if credential_format(&credential) != current.format
+            && !(current.format == FORMAT_JWT_VC_JSON && credential.is_string())
+        {
+            check.errors.push(format!(
+                "Credential for input descriptor '{}' is not in format '{}'",
+                entry.id, current.format
+            ));
+            continue;
+        }
+        if descriptor_matches(definition, descriptor, &credential)? {
+            fulfilled.insert(entry.id.clone());
+        } else {
+            check.errors.push(format!(
+                "Credential does not satisfy input descriptor '{}'",
+                entry.id
+            ));
+        }
+    }
+    if select_descriptors(definition, &fulfilled)?.is_none() {
+        check
+            .errors
+            .push("Submission does not satisfy the presentation definition".to_string());
+    }
+    Ok(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition() -> PresentationDefinition {
+        serde_json::from_value(json!({
+            "id": "32f54163-7166-48f1-93d8-ff217bdb0653",
+            "input_descriptors": [{
+                "id": "degree",
+                "group": ["A"],
+                "constraints": {
+                    "fields": [{
+                        "path": ["$.type"],
+                        "filter": {"type": "array", "contains": {"const": "UniversityDegreeCredential"}}
+                    }, {
+                        "path": ["$.credentialSubject.degree.year", "$.vc.credentialSubject.degree.year"],
+                        "filter": {"type": "integer", "minimum": 2000}
+                    }]
+                }
+            }, {
+                "id": "license",
+                "group": ["A"],
+                "constraints": {
+                    "fields": [{
+                        "path": ["$.type"],
+                        "filter": {"type": "array", "contains": {"pattern": "^Driv"}}
+                    }]
+                }
+            }],
+            "submission_requirements": [{"rule": "pick", "count": 1, "from": "A"}]
+        }))
+        .unwrap()
+    }
+
+    fn credential(type_: &str, year: u64) -> Value {
+        json!({
+            "@context": "https://www.w3.org/2018/credentials/v1",
+            "id": "urn:uuid:1",
+            "type": ["VerifiableCredential", type_],
+            "issuer": "did:example:issuer",
+            "issuanceDate": "2021-01-01T00:00:00Z",
+            "credentialSubject": {"id": "did:example:holder", "degree": {"year": year}}
+        })
+    }
+
+    #[test]
+    fn filters() {
+        assert!(
+            filter_matches(&json!({"type": "string", "pattern": "^a"}), &json!("abc")).unwrap()
+        );
+        assert!(!filter_matches(&json!({"enum": [1, 2]}), &json!(3)).unwrap());
+        assert!(filter_matches(&json!({"not": {"const": 1}}), &json!(2)).unwrap());
+        assert!(filter_matches(
+            &json!({"anyOf": [{"maximum": 1}, {"minimum": 5}]}),
+            &json!(6)
+        )
+        .unwrap());
+        assert!(!filter_matches(&json!({"minLength": 4}), &json!("abc")).unwrap());
+    }
+
+    #[test]
+    fn evaluate_and_check() {
+        let definition = definition();
+        let credentials = vec![
+            credential("UniversityDegreeCredential", 1999),
+            credential("DriversLicense", 2010),
+        ];
+        let evaluation = evaluate(&definition, &credentials).unwrap();
+        assert_eq!(evaluation.matches[0].credentials, Vec::<usize>::new());
+        assert_eq!(evaluation.matches[1].credentials, vec![1]);
+        assert_eq!(evaluation.selected, Some(vec!["license".to_string()]));
+
+        let (presentation, submission) = build_presentation(
+            &definition,
+            &credentials,
+            Some("did:example:holder"),
+            ProofFormat::LDP,
+        )
+        .unwrap();
+        assert_eq!(submission.descriptor_map.len(), 1);
+        assert_eq!(
+            submission.descriptor_map[0].path,
+            "$.verifiableCredential[0]"
+        );
+        let presentation = serde_json::to_value(presentation).unwrap();
+        let check = check_submission(&definition, &submission, &presentation).unwrap();
+        assert_eq!(check.errors, Vec::<String>::new());
+
+        let mut wrong = submission;
+        wrong.descriptor_map[0].id = "degree".to_string();
+        let check = check_submission(&definition, &wrong, &presentation).unwrap();
+        assert!(!check.errors.is_empty());
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        let mut definition = definition();
+        definition.submission_requirements = None;
+        let credentials = vec![credential("DriversLicense", 2010)];
+        assert_eq!(evaluate(&definition, &credentials).unwrap().selected, None);
+        assert!(build_presentation(&definition, &credentials, None, ProofFormat::LDP).is_err());
+    }
+}
@@ -1,10 +1,38 @@
+use std::io;
+
 use tokio::runtime::{Builder, Runtime};
 
 use crate::error::Error;
 
+lazy_static! {
+    static ref SHARED: io::Result<Runtime> = build_multi_thread();
+}
+
+fn build_multi_thread() -> io::Result<Runtime> {
+    Builder::new_multi_thread()
+        .thread_name("didkit")
+        .enable_all()
+        .build()
+}
+
 /// Get a [Tokio runtime] for the current thread.
 /// [Tokio runtime]: https://docs.rs/tokio/1.2.0/tokio/runtime/struct.Runtime.html
 pub fn get() -> Result<Runtime, Error> {
     let rt = Builder::new_current_thread().enable_all().build()?;
     Ok(rt)
 }
+
+/// Get the multi-threaded [Tokio runtime] shared by asynchronous FFI calls. It is created on first
+/// use and lives for the rest of the process.
+/// [Tokio runtime]: https://docs.rs/tokio/1.2.0/tokio/runtime/struct.Runtime.html
+pub fn shared() -> Result<&'static Runtime, Error> {
+    SHARED
+        .as_ref()
+        .map_err(|err| Error::IO(io::Error::new(err.kind(), err.to_string())))
+}
+
+/// Create a multi-threaded [Tokio runtime], for a [`DIDKitContext`](crate::c::DIDKitContext).
+/// [Tokio runtime]: https://docs.rs/tokio/1.2.0/tokio/runtime/struct.Runtime.html
+pub fn new_multi_thread() -> Result<Runtime, Error> {
+    Ok(build_multi_thread()?)
+}
@@ -303,11 +303,40 @@ pub async fn generate_proof(
     resolver: &dyn DIDResolver,
     context_loader: &mut ContextLoader,
     jwk_opt: Option<&JWK>,
+) -> Result<ssi::ldp::Proof, SignError> {
+    generate_proof_with_properties(
+        ssh_agent_sock,
+        document,
+        options,
+        resolver,
+        context_loader,
+        jwk_opt,
+        None,
+    )
+    .await
+}
+
+/// Generate Linked Data Proof with additional proof properties using signature from SSH Agent.
+pub async fn generate_proof_with_properties(
+    ssh_agent_sock: &mut tokio::net::UnixStream,
+    document: &(dyn ssi::ldp::LinkedDataDocument + Sync),
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+    jwk_opt: Option<&JWK>,
+    extra_proof_properties: Option<serde_json::Map<String, serde_json::Value>>,
 ) -> Result<ssi::ldp::Proof, SignError> {
     let keys = list_keys(ssh_agent_sock).await?;
     let (jwk, pk) = select_key(keys, jwk_opt)?;
-    let prep =
-        LinkedDataProofs::prepare(document, &options, resolver, context_loader, &jwk, None).await?;
+    let prep = LinkedDataProofs::prepare(
+        document,
+        &options,
+        resolver,
+        context_loader,
+        &jwk,
+        extra_proof_properties,
+    )
+    .await?;
     let signing_input_bytes = match prep.signing_input {
         ssi::ldp::SigningInput::Bytes(ref bytes) => bytes.0.to_vec(),
         _ => Err(SignError::UnsupportedSigningInputFormat)?,
@@ -0,0 +1,1216 @@
+//! Authorization capabilities (ZCAP-LD): signing capability delegations and invocations, and
+//! verifying their delegation chains.
+//!
+//! Wraps [`ssi::zcap`] so that delegations and invocations can be signed the same ways as
+//! credentials, with a JWK or with a key held by SSH Agent.
+//!
+//! [`ssi::zcap`] verifies a single proof. Chain verification follows `parentCapability` from a
+//! capability back to a root capability (one without a proof), looking up each parent in a
+//! [`CapabilityStore`], and checks each hop: that its signer was authorized by the parent, that
+//! its `capabilityChain` matches, that it does not outlive or widen its parent's [`Caveats`], and
+//! that it has not been revoked. Since anyone can write a capability without a proof, the chain
+//! must end at the root capability that the verifier trusts, given by id.
+//!
+//! Revocation lists are JWSs signed by a verification method of the revoking DID, listing the ids
+//! of revoked capabilities. A revocation is honored if the revoking DID signed the revoked
+//! capability's delegation, or may delegate or invoke its parent capability.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ssi::did_resolve::DIDResolver;
+use ssi::jsonld::ContextLoader;
+use ssi::jwk::JWK;
+use ssi::ldp::{LinkedDataProofs, Proof, ProofPreparation};
+use ssi::vc::{LinkedDataProofOptions, ProofPurpose, VerificationResult, URI};
+use ssi::zcap::{Delegation, Invocation};
+use thiserror::Error;
+
+use crate::{generate_proof_with_properties, resolve_key, GenerateProofError};
+
+#[derive(Error, Debug)]
+pub enum ZcapError {
+    #[error("Unable to read capability {0}: {1}")]
+    IO(PathBuf, std::io::Error),
+    #[error("Unable to parse capability {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    #[error("Capability {0} has an invalid capabilityChain")]
+    InvalidCapabilityChain(String),
+    #[error("Expected proof purpose {0:?}")]
+    ProofPurpose(ProofPurpose),
+    #[error(transparent)]
+    GenerateProof(#[from] GenerateProofError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("JWS: {0}")]
+    JWS(#[from] ssi::jws::Error),
+    #[error("Unable to resolve revocation list key: {0}")]
+    ResolveKey(String),
+    #[error("Revocation list is missing key id")]
+    MissingKeyId,
+    #[error("Unable to determine algorithm for key")]
+    MissingAlgorithm,
+    #[error("Key id '{0}' is not a DID URL of revocation list issuer '{1}'")]
+    KeyIdMismatch(String, String),
+    #[error("Invalid caveat: {0}")]
+    InvalidCaveat(String),
+}
+
+fn uri_string(uri: &URI) -> String {
+    match uri {
+        URI::String(uri) => uri.clone(),
+    }
+}
+
+/// Get the capability chain for a delegation from the given parent capability
+///
+/// This is the parent's own capability chain followed by the parent's id, or just the parent's
+/// id if the parent is a root capability (has no proof).
+pub fn capability_chain(parent: &Delegation<Value, Value>) -> Result<Vec<String>, ZcapError> {
+    let parent_id = uri_string(&parent.id);
+    let mut chain = Vec::new();
+    if let Some(proof) = &parent.proof {
+        let parent_chain = proof
+            .property_set
+            .as_ref()
+            .and_then(|props| props.get("capabilityChain"))
+            .ok_or_else(|| ZcapError::InvalidCapabilityChain(parent_id.clone()))?;
+        for entry in parent_chain
+            .as_array()
+            .ok_or_else(|| ZcapError::InvalidCapabilityChain(parent_id.clone()))?
+        {
+            // The last entry may be the embedded parent capability.
+            match entry {
+                Value::String(id) => chain.push(id.clone()),
+                Value::Object(capability) => match capability.get("id") {
+                    Some(Value::String(id)) => chain.push(id.clone()),
+                    _ => return Err(ZcapError::InvalidCapabilityChain(parent_id)),
+                },
+                _ => return Err(ZcapError::InvalidCapabilityChain(parent_id)),
+            }
+        }
+    }
+    chain.push(parent_id);
+    Ok(chain)
+}
+
+fn with_proof_purpose(
+    mut options: LinkedDataProofOptions,
+    proof_purpose: ProofPurpose,
+) -> Result<LinkedDataProofOptions, ZcapError> {
+    match &options.proof_purpose {
+        None => options.proof_purpose = Some(proof_purpose),
+        Some(purpose) if *purpose == proof_purpose => {}
+        Some(_) => return Err(ZcapError::ProofPurpose(proof_purpose)),
+    }
+    Ok(options)
+}
+
+/// Generate a capabilityDelegation proof for a delegation
+///
+/// `capability_chain` lists the ids of the capabilities the delegation is derived from, starting
+/// with the root capability.
+pub async fn delegate(
+    delegation: &Delegation<Value, Value>,
+    capability_chain: &[String],
+    key: Option<&JWK>,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+    ssh_agent_sock_path_opt: Option<&str>,
+) -> Result<Proof, ZcapError> {
+    let options = with_proof_purpose(options, ProofPurpose::CapabilityDelegation)?;
+    let mut props = Map::new();
+    props.insert(
+        "capabilityChain".to_string(),
+        serde_json::to_value(capability_chain)?,
+    );
+    let proof = generate_proof_with_properties(
+        delegation,
+        key,
+        options,
+        resolver,
+        context_loader,
+        ssh_agent_sock_path_opt,
+        Some(props),
+    )
+    .await?;
+    Ok(proof)
+}
+
+/// Proof properties for an invocation of the given capability
+fn invocation_properties(
+    capability: &URI,
+    action: Option<&str>,
+    invocation_target: Option<&str>,
+) -> Result<Map<String, Value>, ZcapError> {
+    let mut props = Map::new();
+    props.insert("capability".to_string(), serde_json::to_value(capability)?);
+    if let Some(action) = action {
+        props.insert("capabilityAction".to_string(), Value::from(action));
+    }
+    if let Some(invocation_target) = invocation_target {
+        props.insert(
+            "invocationTarget".to_string(),
+            Value::from(invocation_target),
+        );
+    }
+    Ok(props)
+}
+
+/// Generate a capabilityInvocation proof for an invocation of the given capability
+///
+/// The action and target of the invocation, if given, are included in the proof as
+/// `capabilityAction` and `invocationTarget`, for checking against the capability's caveats.
+#[allow(clippy::too_many_arguments)]
+pub async fn invoke(
+    invocation: &Invocation<Value>,
+    capability: &URI,
+    action: Option<&str>,
+    invocation_target: Option<&str>,
+    key: Option<&JWK>,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+    ssh_agent_sock_path_opt: Option<&str>,
+) -> Result<Proof, ZcapError> {
+    let options = with_proof_purpose(options, ProofPurpose::CapabilityInvocation)?;
+    let props = invocation_properties(capability, action, invocation_target)?;
+    let proof = generate_proof_with_properties(
+        invocation,
+        key,
+        options,
+        resolver,
+        context_loader,
+        ssh_agent_sock_path_opt,
+        Some(props),
+    )
+    .await?;
+    Ok(proof)
+}
+
+/// Prepare a capabilityInvocation proof as for [`invoke`], to be signed externally with the
+/// private key of the given public key
+#[allow(clippy::too_many_arguments)]
+pub async fn prepare_invoke(
+    invocation: &Invocation<Value>,
+    capability: &URI,
+    action: Option<&str>,
+    invocation_target: Option<&str>,
+    public_key: &JWK,
+    options: LinkedDataProofOptions,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+) -> Result<ProofPreparation, ZcapError> {
+    let options = with_proof_purpose(options, ProofPurpose::CapabilityInvocation)?;
+    let props = invocation_properties(capability, action, invocation_target)?;
+    let preparation = LinkedDataProofs::prepare(
+        invocation,
+        &options,
+        resolver,
+        context_loader,
+        public_key,
+        Some(props),
+    )
+    .await
+    .map_err(GenerateProofError::from)?;
+    Ok(preparation)
+}
+
+/// Capabilities by id, for looking up the parents of a delegation
+#[derive(Debug, Clone, Default)]
+pub struct CapabilityStore {
+    capabilities: HashMap<String, Delegation<Value, Value>>,
+}
+
+impl CapabilityStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load capabilities from the `.json` files in a directory and its subdirectories
+    pub fn load(dir: &Path) -> Result<Self, ZcapError> {
+        let mut store = Self::new();
+        store.load_dir(dir)?;
+        Ok(store)
+    }
+
+    fn load_dir(&mut self, dir: &Path) -> Result<(), ZcapError> {
+        let read_dir = std::fs::read_dir(dir).map_err(|e| ZcapError::IO(dir.to_owned(), e))?;
+        for entry in read_dir {
+            let path = entry.map_err(|e| ZcapError::IO(dir.to_owned(), e))?.path();
+            if path.is_dir() {
+                self.load_dir(&path)?;
+            } else if path.extension().map_or(false, |ext| ext == "json") {
+                let data = std::fs::read(&path).map_err(|e| ZcapError::IO(path.clone(), e))?;
+                let capability =
+                    serde_json::from_slice(&data).map_err(|e| ZcapError::Parse(path, e))?;
+                self.insert(capability);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse capabilities from a JSON array of capabilities, or an object mapping ids to
+    /// capabilities
+    pub fn from_value(value: Value) -> Result<Self, ZcapError> {
+        let mut store = Self::new();
+        match value {
+            Value::Object(map) => {
+                for (id, capability) in map {
+                    store
+                        .capabilities
+                        .insert(id, serde_json::from_value(capability)?);
+                }
+            }
+            value => {
+                let capabilities: Vec<Delegation<Value, Value>> = serde_json::from_value(value)?;
+                for capability in capabilities {
+                    store.insert(capability);
+                }
+            }
+        }
+        Ok(store)
+    }
+
+    /// Add a capability, keyed by its id
+    pub fn insert(&mut self, capability: Delegation<Value, Value>) {
+        self.capabilities
+            .insert(uri_string(&capability.id), capability);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Delegation<Value, Value>> {
+        self.capabilities.get(id)
+    }
+}
+
+/// Verification result for one capability or invocation in a chain
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HopReport {
+    /// Id of the capability or invocation
+    pub id: String,
+    /// Verification method of the hop's proof, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// Checks that passed
+    pub checks: Vec<String>,
+    /// Checks that failed
+    pub errors: Vec<String>,
+}
+
+/// Result of verifying a delegation chain
+///
+/// Hops are ordered from the verified invocation or delegation back to the root capability.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChainReport {
+    pub valid: bool,
+    pub hops: Vec<HopReport>,
+}
+
+/// Id of a capability reference, which may be an id or an embedded capability
+fn reference_id(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(id) => Some(id),
+        Value::Object(object) => object.get("id").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+/// Values of a property that may be a string or array of strings
+fn property_strings(value: &Value, property: &str) -> Option<Vec<String>> {
+    match value.get(property)? {
+        Value::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(reference_id)
+                .map(String::from)
+                .collect(),
+        ),
+        value => reference_id(value).map(|id| vec![id.to_string()]),
+    }
+}
+
+/// Principals (DIDs or verification method ids) that may invoke a capability
+fn invokers(capability: &Value) -> Vec<String> {
+    property_strings(capability, "invoker")
+        .or_else(|| property_strings(capability, "controller"))
+        .unwrap_or_default()
+}
+
+/// Principals that may delegate a capability
+fn delegators(capability: &Value) -> Vec<String> {
+    property_strings(capability, "delegator").unwrap_or_else(|| invokers(capability))
+}
+
+fn is_authorized(verification_method: &str, principals: &[String]) -> bool {
+    let did = verification_method
+        .split('#')
+        .next()
+        .unwrap_or(verification_method);
+    principals
+        .iter()
+        .any(|principal| principal == verification_method || principal == did)
+}
+
+/// Caveats restricting the use of a capability, and of capabilities delegated from it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Caveats {
+    /// Time after which the capability may not be used (`expires`)
+    pub expires: Option<DateTime<Utc>>,
+    /// Actions that the capability may be invoked for (`allowedAction`)
+    pub allowed_action: Option<Vec<String>>,
+    /// URI of the target that the capability may be invoked on (`invocationTarget`). Targets
+    /// within it, i.e. starting with it followed by `/`, `?` or `#`, are also allowed.
+    pub invocation_target: Option<String>,
+}
+
+impl Caveats {
+    /// Read the caveats of a capability
+    pub fn from_capability(capability: &Value) -> Result<Self, ZcapError> {
+        let expires = match capability.get("expires") {
+            Some(Value::String(expires)) => Some(
+                DateTime::parse_from_rfc3339(expires)
+                    .map_err(|e| ZcapError::InvalidCaveat(format!("expires: {}", e)))?
+                    .with_timezone(&Utc),
+            ),
+            Some(_) => {
+                return Err(ZcapError::InvalidCaveat(
+                    "expires must be a string".to_string(),
+                ))
+            }
+            None => None,
+        };
+        let invocation_target = match capability.get("invocationTarget") {
+            Some(target) => Some(
+                reference_id(target)
+                    .ok_or_else(|| {
+                        ZcapError::InvalidCaveat("invocationTarget must be a URI".to_string())
+                    })?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        Ok(Self {
+            expires,
+            allowed_action: property_strings(capability, "allowedAction"),
+            invocation_target,
+        })
+    }
+
+    /// Set the caveats as properties of a capability delegation
+    pub fn apply(&self, delegation: &mut Delegation<Value, Value>) {
+        if !delegation.additional_properties.is_object() {
+            delegation.additional_properties = Value::Object(Map::new());
+        }
+        if let Value::Object(props) = &mut delegation.additional_properties {
+            if let Some(expires) = &self.expires {
+                props.insert(
+                    "expires".to_string(),
+                    Value::from(expires.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+                );
+            }
+            if let Some(allowed_action) = &self.allowed_action {
+                props.insert(
+                    "allowedAction".to_string(),
+                    Value::from(allowed_action.clone()),
+                );
+            }
+            if let Some(invocation_target) = &self.invocation_target {
+                props.insert(
+                    "invocationTarget".to_string(),
+                    Value::from(invocation_target.as_str()),
+                );
+            }
+        }
+    }
+
+    /// Check that the capability has not expired
+    fn check_expiry(&self, now: DateTime<Utc>, checks: &mut Vec<String>, errors: &mut Vec<String>) {
+        if let Some(expires) = self.expires {
+            if expires <= now {
+                errors.push(format!("Capability expired at {}", expires));
+            } else {
+                checks.push("expires".to_string());
+            }
+        }
+    }
+
+    /// Check that these caveats are no wider than those of a parent capability
+    fn check_attenuation(
+        &self,
+        parent: &Caveats,
+        checks: &mut Vec<String>,
+        errors: &mut Vec<String>,
+    ) {
+        if let Some(parent_expires) = parent.expires {
+            match self.expires {
+                Some(expires) if expires > parent_expires => {
+                    errors.push("Capability expires after its parent capability".to_string())
+                }
+                Some(_) => {}
+                None => errors
+                    .push("Capability does not expire, but its parent capability does".to_string()),
+            }
+        }
+        if let Some(parent_actions) = &parent.allowed_action {
+            match &self.allowed_action {
+                Some(actions) if actions.iter().all(|action| parent_actions.contains(action)) => {
+                    checks.push("allowedAction".to_string());
+                }
+                _ => errors.push(
+                    "Capability allows actions not allowed by its parent capability".to_string(),
+                ),
+            }
+        }
+        if let Some(parent_target) = &parent.invocation_target {
+            match &self.invocation_target {
+                Some(target) if target_within(target, parent_target) => {
+                    checks.push("invocationTarget".to_string());
+                }
+                _ => errors.push(
+                    "Capability invocationTarget is not within its parent capability's".to_string(),
+                ),
+            }
+        }
+    }
+
+    /// Check the action and target of an invocation of the capability
+    pub fn check_invocation(
+        &self,
+        action: Option<&str>,
+        invocation_target: Option<&str>,
+        checks: &mut Vec<String>,
+        errors: &mut Vec<String>,
+    ) {
+        if let Some(actions) = &self.allowed_action {
+            match action {
+                Some(action) if actions.iter().any(|allowed| allowed == action) => {
+                    checks.push("capabilityAction".to_string());
+                }
+                _ => errors.push("Invocation action is not allowed by the capability".to_string()),
+            }
+        }
+        if let Some(prefix) = &self.invocation_target {
+            match invocation_target {
+                Some(target) if target_within(target, prefix) => {
+                    checks.push("invocationTarget".to_string());
+                }
+                _ => errors.push("Invocation target is not allowed by the capability".to_string()),
+            }
+        }
+    }
+}
+
+/// Check that a target URI is the given URI, or a path, query or fragment under it
+fn target_within(target: &str, uri: &str) -> bool {
+    match target.strip_prefix(uri) {
+        Some("") => true,
+        Some(rest) => uri.ends_with(&['/', '?', '#'][..]) || rest.starts_with(&['/', '?', '#'][..]),
+        None => false,
+    }
+}
+
+/// Check a capability's own caveats, and that they are no wider than its parent's
+fn check_attenuation(
+    capability: &Value,
+    parent: Option<&Value>,
+    now: DateTime<Utc>,
+    hop: &mut HopReport,
+) {
+    let caveats = match Caveats::from_capability(capability) {
+        Ok(caveats) => caveats,
+        Err(err) => {
+            hop.errors.push(err.to_string());
+            return;
+        }
+    };
+    caveats.check_expiry(now, &mut hop.checks, &mut hop.errors);
+    if let Some(parent) = parent {
+        match Caveats::from_capability(parent) {
+            Ok(parent_caveats) => {
+                caveats.check_attenuation(&parent_caveats, &mut hop.checks, &mut hop.errors)
+            }
+            Err(err) => hop.errors.push(format!("Parent capability: {}", err)),
+        }
+    }
+}
+
+/// Claims of a capability revocation list
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RevocationList {
+    /// DID revoking the capabilities
+    pub iss: String,
+    /// Time of issuance, in seconds since the Unix epoch
+    pub iat: i64,
+    /// Ids of the revoked capabilities
+    pub revoked: Vec<String>,
+}
+
+fn did_from_did_url(did_url: &str) -> &str {
+    did_url.split('#').next().unwrap_or(did_url)
+}
+
+/// Sign a revocation list of capability ids, with a verification method of the revoking DID
+pub fn sign_revocation_list(
+    revoked: Vec<String>,
+    verification_method: &str,
+    key: &JWK,
+    now: DateTime<Utc>,
+) -> Result<String, ZcapError> {
+    let list = RevocationList {
+        iss: did_from_did_url(verification_method).to_string(),
+        iat: now.timestamp(),
+        revoked,
+    };
+    let algorithm = key.get_algorithm().ok_or(ZcapError::MissingAlgorithm)?;
+    let header = ssi::jws::Header {
+        algorithm,
+        key_id: Some(verification_method.to_string()),
+        ..Default::default()
+    };
+    let payload = serde_json::to_string(&list)?;
+    let jws = ssi::jws::encode_sign_custom_header(&payload, key, &header)?;
+    Ok(jws)
+}
+
+/// Verify a revocation list signed by a verification method of its issuer
+pub async fn verify_revocation_list(
+    jws: &str,
+    resolver: &dyn DIDResolver,
+) -> Result<RevocationList, ZcapError> {
+    let (header, _) = ssi::jws::decode_unverified(jws)?;
+    let key_id = header.key_id.ok_or(ZcapError::MissingKeyId)?;
+    let key = resolve_key(&key_id, resolver)
+        .await
+        .map_err(|e| ZcapError::ResolveKey(e.to_string()))?;
+    let (_, payload) = ssi::jws::decode_verify(jws, &key)?;
+    let list: RevocationList = serde_json::from_slice(&payload)?;
+    if did_from_did_url(&key_id) != list.iss {
+        return Err(ZcapError::KeyIdMismatch(key_id, list.iss));
+    }
+    Ok(list)
+}
+
+/// Verified revocations, by capability id
+#[derive(Debug, Clone, Default)]
+pub struct Revocations {
+    revokers: HashMap<String, HashSet<String>>,
+}
+
+impl Revocations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the revocations of a verified revocation list
+    pub fn insert(&mut self, list: &RevocationList) {
+        for id in &list.revoked {
+            self.revokers
+                .entry(id.clone())
+                .or_default()
+                .insert(list.iss.clone());
+        }
+    }
+
+    /// Verify revocation lists and collect their revocations
+    pub async fn verify(lists: &[String], resolver: &dyn DIDResolver) -> Result<Self, ZcapError> {
+        let mut revocations = Self::new();
+        for jws in lists {
+            revocations.insert(&verify_revocation_list(jws, resolver).await?);
+        }
+        Ok(revocations)
+    }
+
+    /// Get a DID that has revoked the given capability, among the given principals (DIDs or
+    /// verification method ids)
+    pub fn revoked_by(&self, id: &str, principals: &[String]) -> Option<&str> {
+        let revokers = self.revokers.get(id)?;
+        principals
+            .iter()
+            .find_map(|principal| revokers.get(did_from_did_url(principal)))
+            .map(String::as_str)
+    }
+
+    fn check(
+        &self,
+        id: &str,
+        principals: &[String],
+        checks: &mut Vec<String>,
+        errors: &mut Vec<String>,
+    ) {
+        match self.revoked_by(id, principals) {
+            Some(revoker) => errors.push(format!("Capability {} was revoked by {}", id, revoker)),
+            None => checks.push("revocation".to_string()),
+        }
+    }
+}
+
+/// Walk a delegation chain from the given capability back to the trusted root capability
+///
+/// The given capability may itself be the root only if `allow_root` is set, i.e. if it is
+/// referenced by an invocation rather than being the delegation to verify.
+#[allow(clippy::too_many_arguments)]
+async fn verify_chain_from(
+    mut capability: Delegation<Value, Value>,
+    store: &CapabilityStore,
+    trusted_root: &str,
+    mut allow_root: bool,
+    revocations: &Revocations,
+    now: DateTime<Utc>,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+    report: &mut ChainReport,
+) {
+    let mut visited = HashSet::new();
+    loop {
+        let id = uri_string(&capability.id);
+        let mut hop = HopReport {
+            id: id.clone(),
+            ..Default::default()
+        };
+        if !visited.insert(id.clone()) {
+            hop.errors
+                .push("Delegation chain contains a cycle".to_string());
+            report.hops.push(hop);
+            return;
+        }
+        let value = match serde_json::to_value(&capability) {
+            Ok(value) => value,
+            Err(err) => {
+                hop.errors.push(err.to_string());
+                report.hops.push(hop);
+                return;
+            }
+        };
+        let proof = match &capability.proof {
+            Some(proof) => proof,
+            None if !allow_root => {
+                hop.errors.push("Capability has no proof".to_string());
+                report.hops.push(hop);
+                return;
+            }
+            None if id != trusted_root => {
+                hop.errors.push(format!(
+                    "Capability has no proof, and is not the trusted root capability {}",
+                    trusted_root
+                ));
+                report.hops.push(hop);
+                return;
+            }
+            None => {
+                // Root capability
+                if invokers(&value).is_empty() {
+                    hop.errors
+                        .push("Root capability has no controller or invoker".to_string());
+                } else {
+                    hop.checks.push("root".to_string());
+                }
+                check_attenuation(&value, None, now, &mut hop);
+                report.hops.push(hop);
+                return;
+            }
+        };
+        hop.signer = proof.verification_method.clone();
+        let result = capability.verify(None, resolver, context_loader).await;
+        if result.errors.is_empty() {
+            hop.checks.push("proof".to_string());
+        }
+        hop.errors.extend(result.errors);
+
+        let parent_id = uri_string(&capability.parent_capability);
+        let parent = match store.get(&parent_id) {
+            Some(parent) => parent.clone(),
+            None => {
+                hop.errors
+                    .push(format!("Parent capability {} not found", parent_id));
+                report.hops.push(hop);
+                return;
+            }
+        };
+        let parent_value = match serde_json::to_value(&parent) {
+            Ok(value) => value,
+            Err(err) => {
+                hop.errors.push(err.to_string());
+                report.hops.push(hop);
+                return;
+            }
+        };
+        match &hop.signer {
+            Some(signer) if is_authorized(signer, &delegators(&parent_value)) => {
+                hop.checks.push("delegator".to_string());
+            }
+            _ => hop.errors.push(format!(
+                "Signer is not authorized to delegate capability {}",
+                parent_id
+            )),
+        }
+        let chain: Option<Vec<&str>> = proof
+            .property_set
+            .as_ref()
+            .and_then(|props| props.get("capabilityChain"))
+            .and_then(Value::as_array)
+            .and_then(|chain| chain.iter().map(reference_id).collect());
+        match (chain, capability_chain(&parent)) {
+            (Some(chain), Ok(expected)) if chain == expected => {
+                hop.checks.push("capabilityChain".to_string());
+            }
+            _ => hop
+                .errors
+                .push("Proof capabilityChain does not match the delegation chain".to_string()),
+        }
+        check_attenuation(&value, Some(&parent_value), now, &mut hop);
+        let mut revokers = delegators(&parent_value);
+        revokers.extend(invokers(&parent_value));
+        revokers.extend(hop.signer.clone());
+        revocations.check(&id, &revokers, &mut hop.checks, &mut hop.errors);
+        report.hops.push(hop);
+        capability = parent;
+        allow_root = true;
+    }
+}
+
+/// Check the action and target in an invocation proof against the invoked capability's caveats
+fn check_invocation_caveats(
+    proof_props: Option<&Map<String, Value>>,
+    capability: &Value,
+    hop: &mut HopReport,
+) {
+    let property = |name: &str| {
+        proof_props
+            .and_then(|props| props.get(name))
+            .and_then(reference_id)
+    };
+    match Caveats::from_capability(capability) {
+        Ok(caveats) => caveats.check_invocation(
+            property("capabilityAction"),
+            property("invocationTarget"),
+            &mut hop.checks,
+            &mut hop.errors,
+        ),
+        Err(err) => hop.errors.push(err.to_string()),
+    }
+}
+
+/// Check a capability's expiry, and whether it was revoked by its delegator
+fn check_capability(
+    delegation: &Delegation<Value, Value>,
+    revocations: &Revocations,
+    now: DateTime<Utc>,
+    hop: &mut HopReport,
+) {
+    match serde_json::to_value(delegation) {
+        Ok(value) => check_attenuation(&value, None, now, hop),
+        Err(err) => hop.errors.push(err.to_string()),
+    }
+    let signers: Vec<String> = delegation
+        .proof
+        .iter()
+        .filter_map(|proof| proof.verification_method.clone())
+        .collect();
+    revocations.check(
+        &uri_string(&delegation.id),
+        &signers,
+        &mut hop.checks,
+        &mut hop.errors,
+    );
+}
+
+/// Verify a single capability delegation: its proof, its caveats, and whether it was revoked by
+/// its delegator
+///
+/// Unlike [`verify_delegation_chain`], this does not check the delegation against its parent.
+pub async fn verify_delegation(
+    delegation: &Delegation<Value, Value>,
+    options: Option<LinkedDataProofOptions>,
+    revocations: &Revocations,
+    now: DateTime<Utc>,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+) -> VerificationResult {
+    let mut result = delegation.verify(options, resolver, context_loader).await;
+    let mut hop = HopReport::default();
+    check_capability(delegation, revocations, now, &mut hop);
+    result.errors.extend(hop.errors);
+    result
+}
+
+/// Verify a capability invocation against the invoked capability: the invocation's proof, the
+/// capability's caveats, and whether the capability was revoked by its delegator
+pub async fn verify_invocation(
+    invocation: &Invocation<Value>,
+    delegation: &Delegation<Value, Value>,
+    options: Option<LinkedDataProofOptions>,
+    revocations: &Revocations,
+    now: DateTime<Utc>,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+) -> VerificationResult {
+    let mut result = invocation
+        .verify(options, resolver, context_loader, delegation)
+        .await;
+    let mut hop = HopReport::default();
+    check_capability(delegation, revocations, now, &mut hop);
+    match serde_json::to_value(delegation) {
+        Ok(capability) => check_invocation_caveats(
+            invocation
+                .proof
+                .as_ref()
+                .and_then(|proof| proof.property_set.as_ref()),
+            &capability,
+            &mut hop,
+        ),
+        Err(err) => hop.errors.push(err.to_string()),
+    }
+    result.errors.extend(hop.errors);
+    result
+}
+
+fn finish(mut report: ChainReport) -> ChainReport {
+    report.valid = !report.hops.is_empty() && report.hops.iter().all(|hop| hop.errors.is_empty());
+    report
+}
+
+/// Verify a capability delegation and its delegation chain, at the given time
+///
+/// The chain must end at the root capability with id `trusted_root`, which must be in the store.
+pub async fn verify_delegation_chain(
+    delegation: &Delegation<Value, Value>,
+    store: &CapabilityStore,
+    trusted_root: &str,
+    revocations: &Revocations,
+    now: DateTime<Utc>,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+) -> ChainReport {
+    let mut report = ChainReport::default();
+    verify_chain_from(
+        delegation.clone(),
+        store,
+        trusted_root,
+        false,
+        revocations,
+        now,
+        resolver,
+        context_loader,
+        &mut report,
+    )
+    .await;
+    finish(report)
+}
+
+/// Verify a capability invocation and the delegation chain of the invoked capability, at the
+/// given time
+///
+/// The chain must end at the root capability with id `trusted_root`, which may be the invoked
+/// capability itself.
+pub async fn verify_invocation_chain(
+    invocation: &Invocation<Value>,
+    store: &CapabilityStore,
+    trusted_root: &str,
+    revocations: &Revocations,
+    now: DateTime<Utc>,
+    resolver: &dyn DIDResolver,
+    context_loader: &mut ContextLoader,
+) -> ChainReport {
+    let mut report = ChainReport::default();
+    let mut hop = HopReport {
+        id: uri_string(&invocation.id),
+        ..Default::default()
+    };
+    let result = invocation
+        .verify_signature(None, resolver, context_loader)
+        .await;
+    if result.errors.is_empty() {
+        hop.checks.push("proof".to_string());
+    }
+    hop.errors.extend(result.errors);
+    let proof = match &invocation.proof {
+        Some(proof) => proof,
+        None => {
+            report.hops.push(hop);
+            return finish(report);
+        }
+    };
+    hop.signer = proof.verification_method.clone();
+    let props = proof.property_set.as_ref();
+    let capability_id = props
+        .and_then(|props| props.get("capability"))
+        .and_then(reference_id);
+    let capability = match capability_id.map(|id| (id, store.get(id))) {
+        Some((_, Some(capability))) => capability.clone(),
+        Some((id, None)) => {
+            hop.errors.push(format!("Capability {} not found", id));
+            report.hops.push(hop);
+            return finish(report);
+        }
+        None => {
+            hop.errors
+                .push("Invocation proof has no capability".to_string());
+            report.hops.push(hop);
+            return finish(report);
+        }
+    };
+    let capability_value = serde_json::to_value(&capability).unwrap_or_default();
+    match &hop.signer {
+        Some(signer) if is_authorized(signer, &invokers(&capability_value)) => {
+            hop.checks.push("invoker".to_string());
+        }
+        _ => hop
+            .errors
+            .push("Signer is not authorized to invoke the capability".to_string()),
+    }
+    check_invocation_caveats(props, &capability_value, &mut hop);
+    report.hops.push(hop);
+    verify_chain_from(
+        capability,
+        store,
+        trusted_root,
+        true,
+        revocations,
+        now,
+        resolver,
+        context_loader,
+        &mut report,
+    )
+    .await;
+    finish(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn chain_from_parent() {
+        let root: Delegation<Value, Value> = serde_json::from_value(json!({
+            "@context": "https://w3id.org/security/v2",
+            "id": "urn:uuid:root",
+            "parentCapability": "https://example.org/resource",
+            "invoker": "did:example:alice"
+        }))
+        .unwrap();
+        assert_eq!(capability_chain(&root).unwrap(), vec!["urn:uuid:root"]);
+
+        let child: Delegation<Value, Value> = serde_json::from_value(json!({
+            "@context": "https://w3id.org/security/v2",
+            "id": "urn:uuid:child",
+            "parentCapability": "urn:uuid:root",
+            "invoker": "did:example:bob",
+            "proof": {
+                "type": "Ed25519Signature2018",
+                "proofPurpose": "capabilityDelegation",
+                "verificationMethod": "did:example:alice#key-1",
+                "created": "2021-01-01T00:00:00Z",
+                "capabilityChain": ["urn:uuid:root"]
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            capability_chain(&child).unwrap(),
+            vec!["urn:uuid:root", "urn:uuid:child"]
+        );
+    }
+
+    #[test]
+    fn attenuation() {
+        let now = DateTime::parse_from_rfc3339("2022-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let parent = json!({
+            "id": "urn:uuid:parent",
+            "invoker": "did:example:alice",
+            "expires": "2023-01-01T00:00:00Z",
+            "allowedAction": ["read", "write"]
+        });
+        let check = |capability: Value| {
+            let mut hop = HopReport::default();
+            check_attenuation(&capability, Some(&parent), now, &mut hop);
+            hop.errors
+        };
+        assert!(check(json!({
+            "expires": "2022-06-01T00:00:00Z",
+            "allowedAction": "read"
+        }))
+        .is_empty());
+        // Outlives the parent
+        assert_eq!(
+            check(json!({
+                "expires": "2024-01-01T00:00:00Z",
+                "allowedAction": "read"
+            }))
+            .len(),
+            1
+        );
+        // Expired, and widens the allowed actions
+        assert_eq!(
+            check(json!({
+                "expires": "2021-01-01T00:00:00Z",
+                "allowedAction": ["read", "delete"]
+            }))
+            .len(),
+            2
+        );
+        // No caveats, under a parent with caveats
+        assert_eq!(check(json!({})).len(), 2);
+
+        assert!(is_authorized("did:example:alice#key-1", &invokers(&parent)));
+        assert!(!is_authorized(
+            "did:example:bob#key-1",
+            &delegators(&parent)
+        ));
+    }
+
+    #[test]
+    fn chain_root() {
+        let root = json!({
+            "@context": "https://w3id.org/security/v2",
+            "id": "urn:uuid:root",
+            "parentCapability": "https://example.org/resource",
+            "invoker": "did:example:alice"
+        });
+        // Unsigned delegation claiming to derive from the root capability
+        let forged: Delegation<Value, Value> = serde_json::from_value(json!({
+            "@context": "https://w3id.org/security/v2",
+            "id": "urn:uuid:forged",
+            "parentCapability": "urn:uuid:root",
+            "invoker": "did:example:mallory"
+        }))
+        .unwrap();
+        let store = CapabilityStore::from_value(json!([root.clone(), forged.clone()])).unwrap();
+        let root: Delegation<Value, Value> = serde_json::from_value(root).unwrap();
+        let now = Utc::now();
+        let resolver = crate::DID_METHODS.to_resolver();
+        let rt = crate::runtime::get().unwrap();
+        let verify_from = |capability: &Delegation<Value, Value>, trusted_root: &str| {
+            let mut report = ChainReport::default();
+            rt.block_on(verify_chain_from(
+                capability.clone(),
+                &store,
+                trusted_root,
+                true,
+                &Revocations::new(),
+                now,
+                resolver,
+                &mut ContextLoader::default(),
+                &mut report,
+            ));
+            finish(report)
+        };
+
+        let report = rt.block_on(verify_delegation_chain(
+            &forged,
+            &store,
+            "urn:uuid:forged",
+            &Revocations::new(),
+            now,
+            resolver,
+            &mut ContextLoader::default(),
+        ));
+        assert!(!report.valid);
+        assert!(!report.hops[0].checks.contains(&"root".to_string()));
+        // Reached as a parent, a capability without a proof is accepted only as the trusted root.
+        assert!(verify_from(&root, "urn:uuid:root").valid);
+        assert!(!verify_from(&forged, "urn:uuid:root").valid);
+        assert!(!verify_from(&root, "urn:uuid:other").valid);
+    }
+
+    #[test]
+    fn store_from_value() {
+        let capability = json!({
+            "@context": "https://w3id.org/security/v2",
+            "id": "urn:uuid:root",
+            "parentCapability": "https://example.org/resource",
+            "invoker": "did:example:alice"
+        });
+        let store = CapabilityStore::from_value(json!([capability.clone()])).unwrap();
+        assert!(store.get("urn:uuid:root").is_some());
+        let store = CapabilityStore::from_value(json!({ "urn:uuid:other": capability })).unwrap();
+        assert!(store.get("urn:uuid:other").is_some());
+        assert!(store.get("urn:uuid:root").is_none());
+    }
+
+    #[test]
+    fn caveats_and_revocations() {
+        let mut delegation: Delegation<Value, Value> = serde_json::from_value(json!({
+            "@context": "https://w3id.org/security/v2",
+            "id": "urn:uuid:child",
+            "parentCapability": "urn:uuid:root",
+            "invoker": "did:example:bob"
+        }))
+        .unwrap();
+        let caveats = Caveats {
+            expires: Some(
+                DateTime::parse_from_rfc3339("2023-01-01T00:00:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc),
+            ),
+            allowed_action: Some(vec!["read".to_string()]),
+            invocation_target: Some("https://example.org/files/".to_string()),
+        };
+        caveats.apply(&mut delegation);
+        let value = serde_json::to_value(&delegation).unwrap();
+        assert_eq!(value["expires"], json!("2023-01-01T00:00:00Z"));
+        assert_eq!(Caveats::from_capability(&value).unwrap(), caveats);
+
+        let check = |action: Option<&str>, target: Option<&str>| {
+            let (mut checks, mut errors) = (Vec::new(), Vec::new());
+            caveats.check_invocation(action, target, &mut checks, &mut errors);
+            errors.len()
+        };
+        assert_eq!(check(Some("read"), Some("https://example.org/files/a")), 0);
+        assert_eq!(check(Some("write"), Some("https://example.org/files/a")), 1);
+        assert_eq!(check(Some("read"), Some("https://example.org/other")), 1);
+        assert_eq!(check(None, None), 2);
+
+        // Targets must be within the caveat's target at a segment boundary.
+        assert!(target_within(
+            "https://example.org/files",
+            "https://example.org/files"
+        ));
+        assert!(target_within(
+            "https://example.org/files/a",
+            "https://example.org/files"
+        ));
+        assert!(target_within(
+            "https://example.org/files?a=1",
+            "https://example.org/files"
+        ));
+        assert!(target_within(
+            "https://example.org/files#a",
+            "https://example.org/files"
+        ));
+        assert!(!target_within(
+            "https://example.org/files-secret",
+            "https://example.org/files"
+        ));
+        assert!(!target_within(
+            "https://example.org/filesecret",
+            "https://example.org/files"
+        ));
+
+        let mut revocations = Revocations::new();
+        revocations.insert(&RevocationList {
+            iss: "did:example:alice".to_string(),
+            iat: 0,
+            revoked: vec!["urn:uuid:child".to_string()],
+        });
+        assert_eq!(
+            revocations.revoked_by("urn:uuid:child", &["did:example:alice#key-1".to_string()]),
+            Some("did:example:alice")
+        );
+        // Revocations by others are not honored.
+        assert_eq!(
+            revocations.revoked_by("urn:uuid:child", &["did:example:bob".to_string()]),
+            None
+        );
+        assert_eq!(
+            revocations.revoked_by("urn:uuid:root", &["did:example:alice".to_string()]),
+            None
+        );
+    }
+}
@@ -1,15 +1,39 @@
 use core::future::Future;
+use std::sync::Arc;
 
-use js_sys::Promise;
-use serde_json::Value;
+use async_trait::async_trait;
+#[cfg(feature = "jsonld")]
+use iref::IriBuf;
+use js_sys::{Array, Function, Promise, Uint8Array, JSON};
+#[cfg(feature = "jsonld")]
+use json_ld::JsonLdProcessor;
+#[cfg(feature = "did")]
+use serde_json::Map;
+use serde_json::{json, Value};
 use wasm_bindgen::prelude::*;
-use wasm_bindgen_futures::future_to_promise;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
 
+use didkit::context::{Context, ContextOptions};
 use didkit::error::Error;
 #[cfg(doc)]
 use didkit::error::{didkit_error_code, didkit_error_message};
 use didkit::get_verification_method;
+#[cfg(feature = "generate")]
+use didkit::key_derive::{self, Curve};
+use didkit::presentation_exchange::{self, PresentationDefinition, PresentationSubmission};
+use didkit::ssi::did_resolve::TYPE_DID_LD_JSON;
+#[cfg(feature = "jsonld")]
+use didkit::ssi::jsonld::{parse_ld_context, StaticLoader};
+#[cfg(feature = "issue")]
+use didkit::ssi::ldp::SigningInput;
+#[cfg(feature = "jsonld")]
+use didkit::ssi::rdf;
+#[cfg(feature = "issue")]
+use didkit::ssi::vc::Proof;
 use didkit::ssi::{self, ldp::ProofSuite};
+#[cfg(any(feature = "delegate", feature = "zcap", feature = "invoke"))]
+use didkit::zcap::{self, CapabilityStore, Revocations};
 use didkit::LinkedDataProofOptions;
 use didkit::ProofPreparation;
 use didkit::Source;
@@ -17,7 +41,12 @@ use didkit::VerifiableCredential;
 use didkit::VerifiablePresentation;
 use didkit::DID_METHODS;
 use didkit::JWK;
+use didkit::{dereference, DereferencingInputMetadata};
+use didkit::{ContextLoader, DIDResolver, Document};
+#[cfg(feature = "did")]
+use didkit::{DIDCreate, DIDDeactivate, DIDDocumentOperation, DIDUpdate};
 use didkit::{Delegation, Invocation};
+use didkit::{DocumentMetadata, ResolutionInputMetadata, ResolutionMetadata};
 use didkit::{JWTOrLDPOptions, ProofFormat, URI};
 
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -46,9 +75,20 @@ pub fn getVersion() -> String {
     VERSION.into()
 }
 
-async fn resolve_did(did: String, input_metadata: String) -> Result<String, String> {
-    let (res_meta, doc, _) = DID_METHODS
-        .to_resolver()
+async fn resolve_did(
+    context: Arc<Context>,
+    did: String,
+    input_metadata: String,
+) -> Result<String, String> {
+    resolve_did_with(context.resolver(), did, input_metadata).await
+}
+
+async fn resolve_did_with(
+    resolver: &dyn DIDResolver,
+    did: String,
+    input_metadata: String,
+) -> Result<String, String> {
+    let (res_meta, doc, _) = resolver
         .resolve(
             &did,
             &serde_json::from_str(&input_metadata).or_else(|e| Err(e.to_string()))?,
@@ -70,13 +110,109 @@ async fn resolve_did(did: String, input_metadata: String) -> Result<String, Stri
 #[allow(non_snake_case)]
 pub fn resolveDID(did: String, input_metadata: String) -> Promise {
     future_to_promise(async {
-        match resolve_did(did, input_metadata).await {
+        match resolve_did(Context::shared_default(), did, input_metadata).await {
+            Ok(string) => Ok(string.into()),
+            Err(err) => Err(err.into()),
+        }
+    })
+}
+
+/// Resolve a DID using DID methods configured by a `didkit.toml` configuration, given as a string
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn resolveDIDWithConfig(did: String, input_metadata: String, config: String) -> Promise {
+    future_to_promise(async {
+        let options = ContextOptions {
+            config: Some(config),
+            ..Default::default()
+        };
+        let context = Arc::new(Context::new(options).or_else(|e| Err(e.to_string()))?);
+        match resolve_did(context, did, input_metadata).await {
+            Ok(string) => Ok(string.into()),
+            Err(err) => Err(err.into()),
+        }
+    })
+}
+
+/// Configuration for the functions with a `WithContext` suffix: the DID resolver, JSON-LD
+/// contexts and caches that they use. Created from options given as a JSON object with optional
+/// properties `config` (DID methods configuration as `didkit.toml`), `didResolver` (URL of a
+/// fallback HTTP DID resolver), `contexts` (JSON-LD context documents by URL) and
+/// `cacheResolutions` (boolean).
+#[wasm_bindgen]
+pub struct DIDKitContext {
+    context: Arc<Context>,
+}
+
+#[wasm_bindgen]
+impl DIDKitContext {
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: Option<String>) -> Result<DIDKitContext, JsValue> {
+        let context = match options {
+            Some(options) => Context::from_json(&options).map_err(|e| e.to_string())?,
+            None => Context::default(),
+        };
+        Ok(DIDKitContext {
+            context: Arc::new(context),
+        })
+    }
+}
+
+/// Resolve a DID using a [`DIDKitContext`]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn resolveDIDWithContext(
+    context: &DIDKitContext,
+    did: String,
+    input_metadata: String,
+) -> Promise {
+    let context = context.context.clone();
+    future_to_promise(async {
+        match resolve_did(context, did, input_metadata).await {
             Ok(string) => Ok(string.into()),
             Err(err) => Err(err.into()),
         }
     })
 }
 
+async fn dereference_did_url(
+    context: Arc<Context>,
+    did_url: String,
+    input_metadata: String,
+) -> Result<String, Error> {
+    let input_metadata: DereferencingInputMetadata = serde_json::from_str(&input_metadata)?;
+    let deref_result = dereference(context.resolver(), &did_url, &input_metadata).await;
+    let result_json = serde_json::to_string(&json!(deref_result))?;
+    Ok(result_json)
+}
+
+/// Dereference a DID URL, returning a JSON array of the dereferencing metadata, the content and
+/// the content metadata
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn dereferenceDIDURL(did_url: String, input_metadata: String) -> Promise {
+    map_async_jsvalue(dereference_did_url(
+        Context::shared_default(),
+        did_url,
+        input_metadata,
+    ))
+}
+
+/// Dereference a DID URL using a [`DIDKitContext`]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn dereferenceDIDURLWithContext(
+    context: &DIDKitContext,
+    did_url: String,
+    input_metadata: String,
+) -> Promise {
+    map_async_jsvalue(dereference_did_url(
+        context.context.clone(),
+        did_url,
+        input_metadata,
+    ))
+}
+
 #[cfg(feature = "generate")]
 fn generate_ed25519_key() -> Result<String, Error> {
     let jwk = JWK::generate_ed25519()?;
@@ -91,6 +227,34 @@ pub fn generateEd25519Key() -> Result<String, JsValue> {
     map_jsvalue(generate_ed25519_key())
 }
 
+#[cfg(feature = "generate")]
+fn generate_secp256k1_key() -> Result<String, Error> {
+    let jwk = JWK::generate_secp256k1()?;
+    let jwk_json = serde_json::to_string(&jwk)?;
+    Ok(jwk_json)
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "generate")]
+pub fn generateSecp256k1Key() -> Result<String, JsValue> {
+    map_jsvalue(generate_secp256k1_key())
+}
+
+#[cfg(feature = "generate")]
+fn generate_p256_key() -> Result<String, Error> {
+    let jwk = JWK::generate_p256()?;
+    let jwk_json = serde_json::to_string(&jwk)?;
+    Ok(jwk_json)
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "generate")]
+pub fn generateP256Key() -> Result<String, JsValue> {
+    map_jsvalue(generate_p256_key())
+}
+
 fn key_to_did(method_pattern: String, jwk: String) -> Result<String, Error> {
     let key: JWK = serde_json::from_str(&jwk)?;
     let did = DID_METHODS
@@ -123,6 +287,61 @@ pub fn keyToVerificationMethod(method_pattern: String, jwk: String) -> Promise {
     map_async_jsvalue(key_to_verification_method(method_pattern, jwk))
 }
 
+#[cfg(feature = "generate")]
+fn key_derive_from_mnemonic(
+    mnemonic: String,
+    passphrase: Option<String>,
+    curve: String,
+    path: Option<String>,
+) -> Result<String, Error> {
+    let curve: Curve = curve.parse()?;
+    let path = path.unwrap_or_else(|| curve.default_path().to_string());
+    let jwk = key_derive::derive_from_mnemonic(
+        &mnemonic,
+        passphrase.as_deref().unwrap_or_default(),
+        curve,
+        &path,
+    )?;
+    let jwk_json = serde_json::to_string(&jwk)?;
+    Ok(jwk_json)
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "generate")]
+pub fn keyDeriveFromMnemonic(
+    mnemonic: String,
+    passphrase: Option<String>,
+    curve: String,
+    path: Option<String>,
+) -> Result<String, JsValue> {
+    map_jsvalue(key_derive_from_mnemonic(mnemonic, passphrase, curve, path))
+}
+
+#[cfg(feature = "generate")]
+fn key_derive_from_seed(
+    seed: String,
+    curve: String,
+    path: Option<String>,
+) -> Result<String, Error> {
+    let curve: Curve = curve.parse()?;
+    let path = path.unwrap_or_else(|| curve.default_path().to_string());
+    let jwk = key_derive::derive_from_seed_hex(&seed, curve, &path)?;
+    let jwk_json = serde_json::to_string(&jwk)?;
+    Ok(jwk_json)
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "generate")]
+pub fn keyDeriveFromSeed(
+    seed: String,
+    curve: String,
+    path: Option<String>,
+) -> Result<String, JsValue> {
+    map_jsvalue(key_derive_from_seed(seed, curve, path))
+}
+
 #[cfg(any(
     all(feature = "issue", feature = "credential"),
     all(feature = "issue", not(feature = "presentation")),
@@ -133,6 +352,7 @@ pub fn keyToVerificationMethod(method_pattern: String, jwk: String) -> Promise {
     )
 ))]
 async fn issue_credential(
+    context: Arc<Context>,
     credential: String,
     proof_options: String,
     key: String,
@@ -141,8 +361,8 @@ async fn issue_credential(
     let key: JWK = serde_json::from_str(&key)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let resolver = DID_METHODS.to_resolver();
-    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
     let vc_string = match proof_format {
         ProofFormat::JWT => {
             let vc_jwt = credential
@@ -175,7 +395,38 @@ async fn issue_credential(
     )
 ))]
 pub fn issueCredential(credential: String, proof_options: String, key: String) -> Promise {
-    map_async_jsvalue(issue_credential(credential, proof_options, key))
+    map_async_jsvalue(issue_credential(
+        Context::shared_default(),
+        credential,
+        proof_options,
+        key,
+    ))
+}
+
+/// Issue a credential using a [`DIDKitContext`]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "issue", feature = "credential"),
+    all(feature = "issue", not(feature = "presentation")),
+    all(
+        feature = "credential",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn issueCredentialWithContext(
+    context: &DIDKitContext,
+    credential: String,
+    proof_options: String,
+    key: String,
+) -> Promise {
+    map_async_jsvalue(issue_credential(
+        context.context.clone(),
+        credential,
+        proof_options,
+        key,
+    ))
 }
 
 async fn prepare_issue_credential(
@@ -251,11 +502,37 @@ pub fn completeIssueCredential(
         not(feature = "verify")
     )
 ))]
-async fn verify_credential(vc_string: String, proof_options: String) -> Result<String, Error> {
+async fn verify_credential(
+    context: Arc<Context>,
+    vc_string: String,
+    proof_options: String,
+) -> Result<String, Error> {
+    verify_credential_with(
+        context.resolver(),
+        context.context_loader(),
+        vc_string,
+        proof_options,
+    )
+    .await
+}
+
+#[cfg(any(
+    all(feature = "verify", feature = "credential"),
+    all(feature = "verify", not(feature = "presentation")),
+    all(
+        feature = "credential",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+async fn verify_credential_with(
+    resolver: &dyn DIDResolver,
+    mut context_loader: ContextLoader,
+    vc_string: String,
+    proof_options: String,
+) -> Result<String, Error> {
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let resolver = DID_METHODS.to_resolver();
-    let mut context_loader = ssi::jsonld::ContextLoader::default();
     let result = match proof_format {
         ProofFormat::JWT => {
             VerifiableCredential::verify_jwt(
@@ -289,7 +566,35 @@ async fn verify_credential(vc_string: String, proof_options: String) -> Result<S
     )
 ))]
 pub fn verifyCredential(vc: String, proof_options: String) -> Promise {
-    map_async_jsvalue(verify_credential(vc, proof_options))
+    map_async_jsvalue(verify_credential(
+        Context::shared_default(),
+        vc,
+        proof_options,
+    ))
+}
+
+/// Verify a credential using a [`DIDKitContext`]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "verify", feature = "credential"),
+    all(feature = "verify", not(feature = "presentation")),
+    all(
+        feature = "credential",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn verifyCredentialWithContext(
+    context: &DIDKitContext,
+    vc: String,
+    proof_options: String,
+) -> Promise {
+    map_async_jsvalue(verify_credential(
+        context.context.clone(),
+        vc,
+        proof_options,
+    ))
 }
 
 #[cfg(any(
@@ -302,6 +607,7 @@ pub fn verifyCredential(vc: String, proof_options: String) -> Promise {
     )
 ))]
 async fn issue_presentation(
+    context: Arc<Context>,
     presentation: String,
     proof_options: String,
     key: String,
@@ -310,8 +616,8 @@ async fn issue_presentation(
     let key: JWK = serde_json::from_str(&key)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let resolver = DID_METHODS.to_resolver();
-    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
     let vp_string = match proof_format {
         ProofFormat::JWT => {
             presentation
@@ -342,7 +648,38 @@ async fn issue_presentation(
     )
 ))]
 pub fn issuePresentation(presentation: String, proof_options: String, key: String) -> Promise {
-    map_async_jsvalue(issue_presentation(presentation, proof_options, key))
+    map_async_jsvalue(issue_presentation(
+        Context::shared_default(),
+        presentation,
+        proof_options,
+        key,
+    ))
+}
+
+/// Issue a presentation using a [`DIDKitContext`]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "issue", feature = "presentation"),
+    all(feature = "issue", not(feature = "credential")),
+    all(
+        feature = "presentation",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn issuePresentationWithContext(
+    context: &DIDKitContext,
+    presentation: String,
+    proof_options: String,
+    key: String,
+) -> Promise {
+    map_async_jsvalue(issue_presentation(
+        context.context.clone(),
+        presentation,
+        proof_options,
+        key,
+    ))
 }
 
 async fn prepare_issue_presentation(
@@ -418,11 +755,37 @@ pub fn completeIssuePresentation(
         not(feature = "verify")
     )
 ))]
-async fn verify_presentation(vp_string: String, proof_options: String) -> Result<String, Error> {
+async fn verify_presentation(
+    context: Arc<Context>,
+    vp_string: String,
+    proof_options: String,
+) -> Result<String, Error> {
+    verify_presentation_with(
+        context.resolver(),
+        context.context_loader(),
+        vp_string,
+        proof_options,
+    )
+    .await
+}
+
+#[cfg(any(
+    all(feature = "verify", feature = "presentation"),
+    all(feature = "verify", not(feature = "credential")),
+    all(
+        feature = "presentation",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+async fn verify_presentation_with(
+    resolver: &dyn DIDResolver,
+    mut context_loader: ContextLoader,
+    vp_string: String,
+    proof_options: String,
+) -> Result<String, Error> {
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let resolver = DID_METHODS.to_resolver();
-    let mut context_loader = ssi::jsonld::ContextLoader::default();
     let result = match proof_format {
         ProofFormat::JWT => {
             VerifiablePresentation::verify_jwt(
@@ -456,7 +819,35 @@ async fn verify_presentation(vp_string: String, proof_options: String) -> Result
     )
 ))]
 pub fn verifyPresentation(vp: String, proof_options: String) -> Promise {
-    map_async_jsvalue(verify_presentation(vp, proof_options))
+    map_async_jsvalue(verify_presentation(
+        Context::shared_default(),
+        vp,
+        proof_options,
+    ))
+}
+
+/// Verify a presentation using a [`DIDKitContext`]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "verify", feature = "presentation"),
+    all(feature = "verify", not(feature = "credential")),
+    all(
+        feature = "presentation",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn verifyPresentationWithContext(
+    context: &DIDKitContext,
+    vp: String,
+    proof_options: String,
+) -> Promise {
+    map_async_jsvalue(verify_presentation(
+        context.context.clone(),
+        vp,
+        proof_options,
+    ))
 }
 
 #[cfg(any(
@@ -468,14 +859,19 @@ pub fn verifyPresentation(vp: String, proof_options: String) -> Promise {
         not(feature = "verify")
     )
 ))]
-async fn did_auth(holder: String, proof_options: String, key: String) -> Result<String, Error> {
+async fn did_auth(
+    context: Arc<Context>,
+    holder: String,
+    proof_options: String,
+    key: String,
+) -> Result<String, Error> {
     let mut presentation = VerifiablePresentation::default();
     presentation.holder = Some(ssi::vc::URI::String(holder));
     let key: JWK = serde_json::from_str(&key)?;
     let options: JWTOrLDPOptions = serde_json::from_str(&proof_options)?;
     let proof_format = options.proof_format.unwrap_or_default();
-    let resolver = DID_METHODS.to_resolver();
-    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let resolver = context.resolver();
+    let mut context_loader = context.context_loader();
     let vp_string = match proof_format {
         ProofFormat::JWT => {
             presentation
@@ -506,7 +902,575 @@ async fn did_auth(holder: String, proof_options: String, key: String) -> Result<
     )
 ))]
 pub fn DIDAuth(holder: String, linked_data_proof_options: String, key: String) -> Promise {
-    map_async_jsvalue(did_auth(holder, linked_data_proof_options, key))
+    map_async_jsvalue(did_auth(
+        Context::shared_default(),
+        holder,
+        linked_data_proof_options,
+        key,
+    ))
+}
+
+/// Create a DIDAuth presentation using a [`DIDKitContext`]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "issue", feature = "presentation"),
+    all(feature = "issue", not(feature = "credential")),
+    all(
+        feature = "presentation",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn DIDAuthWithContext(
+    context: &DIDKitContext,
+    holder: String,
+    linked_data_proof_options: String,
+    key: String,
+) -> Promise {
+    map_async_jsvalue(did_auth(
+        context.context.clone(),
+        holder,
+        linked_data_proof_options,
+        key,
+    ))
+}
+
+#[cfg(feature = "did")]
+fn jwk_opt(key: Option<String>) -> Result<Option<JWK>, Error> {
+    match key {
+        Some(key) => Ok(Some(serde_json::from_str(&key)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(feature = "did")]
+fn did_method_options(options: Option<String>) -> Result<Map<String, Value>, Error> {
+    match options {
+        Some(options) => Ok(serde_json::from_str(&options)?),
+        None => Ok(Map::new()),
+    }
+}
+
+#[cfg(feature = "did")]
+fn create_did(
+    method_name: String,
+    update_key: Option<String>,
+    recovery_key: Option<String>,
+    verification_key: Option<String>,
+    options: Option<String>,
+) -> Result<String, Error> {
+    let method = DID_METHODS
+        .get(&method_name)
+        .ok_or(Error::UnknownDIDMethod)?;
+    let tx = method.create(DIDCreate {
+        update_key: jwk_opt(update_key)?,
+        recovery_key: jwk_opt(recovery_key)?,
+        verification_key: jwk_opt(verification_key)?,
+        options: did_method_options(options)?,
+    })?;
+    let tx_json = serde_json::to_string(&tx)?;
+    Ok(tx_json)
+}
+
+/// Build a DID Create transaction for a DID method (e.g. `ion`). The keys are JWKs, each of which
+/// may be omitted if the method does not need it. Options are DID method-specific, as a JSON
+/// object.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "did")]
+pub fn createDID(
+    method: String,
+    update_key: Option<String>,
+    recovery_key: Option<String>,
+    verification_key: Option<String>,
+    options: Option<String>,
+) -> Result<String, JsValue> {
+    map_jsvalue(create_did(
+        method,
+        update_key,
+        recovery_key,
+        verification_key,
+        options,
+    ))
+}
+
+#[cfg(feature = "did")]
+fn update_did(
+    did: String,
+    update_key: Option<String>,
+    new_update_key: Option<String>,
+    operation: String,
+    options: Option<String>,
+) -> Result<String, Error> {
+    let operation: DIDDocumentOperation = serde_json::from_str(&operation)?;
+    let method = DID_METHODS
+        .get_method(&did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.update(DIDUpdate {
+        did,
+        update_key: jwk_opt(update_key)?,
+        new_update_key: jwk_opt(new_update_key)?,
+        operation,
+        options: did_method_options(options)?,
+    })?;
+    let tx_json = serde_json::to_string(&tx)?;
+    Ok(tx_json)
+}
+
+/// Build a DID Update transaction. The operation is a DID document operation as JSON, e.g.
+/// `{"didDocumentOperation": "setDidDocument", "didDocument": {...}}`.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "did")]
+pub fn updateDID(
+    did: String,
+    update_key: Option<String>,
+    new_update_key: Option<String>,
+    operation: String,
+    options: Option<String>,
+) -> Result<String, JsValue> {
+    map_jsvalue(update_did(
+        did,
+        update_key,
+        new_update_key,
+        operation,
+        options,
+    ))
+}
+
+#[cfg(feature = "did")]
+fn deactivate_did(
+    did: String,
+    key: Option<String>,
+    options: Option<String>,
+) -> Result<String, Error> {
+    let method = DID_METHODS
+        .get_method(&did)
+        .map_err(|_| Error::UnknownDIDMethod)?;
+    let tx = method.deactivate(DIDDeactivate {
+        did,
+        key: jwk_opt(key)?,
+        options: did_method_options(options)?,
+    })?;
+    let tx_json = serde_json::to_string(&tx)?;
+    Ok(tx_json)
+}
+
+/// Build a DID Deactivate transaction
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "did")]
+pub fn deactivateDID(
+    did: String,
+    key: Option<String>,
+    options: Option<String>,
+) -> Result<String, JsValue> {
+    map_jsvalue(deactivate_did(did, key, options))
+}
+
+#[cfg(feature = "jsonld")]
+fn debug_error<E: std::fmt::Debug>(err: E) -> String {
+    format!("{:?}", err)
+}
+
+// As the `didkit jsonld to rdfurdna` command, using only the built-in JSON-LD contexts
+#[cfg(feature = "jsonld")]
+async fn to_rdf_urdna2015(
+    json: String,
+    base: Option<String>,
+    expand_context: Option<String>,
+    more_context_json: Option<String>,
+) -> Result<String, String> {
+    let mut loader = StaticLoader;
+    let expand_context = match (more_context_json, expand_context) {
+        (Some(more_context_json), Some(expand_context)) => {
+            let more_context: Value =
+                serde_json::from_str(&more_context_json).map_err(|e| e.to_string())?;
+            Some(json!([expand_context, more_context]).to_string())
+        }
+        (Some(more_context_json), None) => Some(more_context_json),
+        (None, expand_context) => expand_context,
+    };
+    let json = ssi::jsonld::syntax::to_value_with(
+        serde_json::from_str::<Value>(&json).map_err(|e| e.to_string())?,
+        Default::default,
+    )
+    .map_err(debug_error)?;
+    let expand_context = match expand_context {
+        Some(context) => Some(parse_ld_context(&context).map_err(debug_error)?),
+        None => None,
+    };
+    let base = match base {
+        Some(base) => Some(IriBuf::from_string(base).map_err(debug_error)?),
+        None => None,
+    };
+    let options = ssi::jsonld::Options {
+        base,
+        expand_context,
+        ..Default::default()
+    };
+    let doc = ssi::jsonld::RemoteDocument::new(None, None, json);
+    let mut generator =
+        rdf_types::generator::Blank::new_with_prefix("b".to_string()).with_default_metadata();
+    let mut to_rdf = doc
+        .to_rdf_using(&mut generator, &mut loader, options)
+        .await
+        .map_err(debug_error)?;
+    let dataset: rdf::DataSet = to_rdf
+        .cloned_quads()
+        .map(|rdf_types::Quad(subject, predicate, object, graph)| {
+            let predicate = predicate
+                .into_iri()
+                .ok_or_else(|| "Blank node predicates are not supported".to_string())?;
+            Ok(rdf_types::Quad(subject, predicate, object, graph))
+        })
+        .collect::<Result<_, String>>()?;
+    let dataset_normalized = ssi::urdna2015::normalize(dataset.quads().map(Into::into));
+    Ok(dataset_normalized.into_nquads())
+}
+
+/// Convert a JSON-LD document to URDNA2015-canonicalized RDF N-Quads. Only the built-in JSON-LD
+/// contexts are loaded. Optional arguments are the base IRI, an IRI for the `expandContext` option,
+/// and additional values for the `@context` property, as JSON.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "jsonld")]
+pub fn toRDFURDNA2015(
+    json: String,
+    base: Option<String>,
+    expand_context: Option<String>,
+    more_context_json: Option<String>,
+) -> Promise {
+    future_to_promise(async move {
+        match to_rdf_urdna2015(json, base, expand_context, more_context_json).await {
+            Ok(string) => Ok(string.into()),
+            Err(err) => Err(err.into()),
+        }
+    })
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CallbackError {
+    #[error("{0}")]
+    JS(String),
+    #[error("Unsupported signing input for external signing")]
+    UnsupportedSigningInput,
+    #[error("Unable to determine signature algorithm")]
+    MissingAlgorithm,
+    #[error("Expected signature as string or Uint8Array")]
+    InvalidSignature,
+    #[error(transparent)]
+    DIDKit(#[from] Error),
+}
+
+fn js_error_message(value: JsValue) -> String {
+    if let Some(error) = value.dyn_ref::<js_sys::Error>() {
+        return error.message().into();
+    }
+    value.as_string().unwrap_or_else(|| format!("{:?}", value))
+}
+
+// Call a JS function, awaiting its result if it returns a promise
+async fn call_js(function: &Function, args: &Array) -> Result<JsValue, String> {
+    let value = function
+        .apply(&JsValue::NULL, args)
+        .map_err(js_error_message)?;
+    JsFuture::from(Promise::resolve(&value))
+        .await
+        .map_err(js_error_message)
+}
+
+// JS values can only be used on the thread that created them. WASM runs on a single thread, so
+// DIDResolver's thread-safety bounds are met.
+struct JsFunction(Function);
+unsafe impl Send for JsFunction {}
+unsafe impl Sync for JsFunction {}
+
+/// DID resolver calling a JS function `resolve(did, inputMetadata)`, with the DID resolution input
+/// metadata (e.g. `versionId` or `versionTime`) as an object, returning (or resolving to) a DID
+/// document, as an object or JSON string, or `null`/`undefined` to fall back to the built-in DID
+/// methods
+struct JsResolver {
+    resolve: Option<JsFunction>,
+    fallback: Arc<Context>,
+}
+
+impl JsResolver {
+    fn new(resolve: Option<Function>) -> Self {
+        Self {
+            resolve: resolve.map(JsFunction),
+            fallback: Context::shared_default(),
+        }
+    }
+
+    async fn resolve_js(
+        &self,
+        resolve: &Function,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> Result<Option<Document>, String> {
+        let input_metadata = serde_json::to_string(input_metadata).map_err(|e| e.to_string())?;
+        let input_metadata = JSON::parse(&input_metadata).map_err(js_error_message)?;
+        let value = call_js(
+            resolve,
+            &Array::of2(&JsValue::from_str(did), &input_metadata),
+        )
+        .await?;
+        if value.is_null() || value.is_undefined() {
+            return Ok(None);
+        }
+        let document_json = match value.as_string() {
+            Some(document_json) => document_json,
+            None => String::from(JSON::stringify(&value).map_err(js_error_message)?),
+        };
+        let document = serde_json::from_str(&document_json).map_err(|e| e.to_string())?;
+        Ok(Some(document))
+    }
+}
+
+#[async_trait(?Send)]
+impl DIDResolver for JsResolver {
+    async fn resolve(
+        &self,
+        did: &str,
+        input_metadata: &ResolutionInputMetadata,
+    ) -> (
+        ResolutionMetadata,
+        Option<Document>,
+        Option<DocumentMetadata>,
+    ) {
+        let resolve = match &self.resolve {
+            Some(JsFunction(resolve)) => resolve,
+            None => return self.fallback.resolve(did, input_metadata).await,
+        };
+        match self.resolve_js(resolve, did, input_metadata).await {
+            Ok(Some(document)) => (
+                ResolutionMetadata {
+                    content_type: Some(TYPE_DID_LD_JSON.to_string()),
+                    ..Default::default()
+                },
+                Some(document),
+                Some(DocumentMetadata::default()),
+            ),
+            Ok(None) => self.fallback.resolve(did, input_metadata).await,
+            Err(err) => (ResolutionMetadata::from_error(&err), None, None),
+        }
+    }
+}
+
+/// Signer calling a JS function `sign(bytes, alg)`, with the signing input as a `Uint8Array` and
+/// the JOSE algorithm name, returning (or resolving to) the signature as a `Uint8Array`, or as a
+/// string in the encoding expected by the proof type
+#[cfg(feature = "issue")]
+struct JsSigner {
+    sign: Function,
+}
+
+#[cfg(feature = "issue")]
+impl JsSigner {
+    fn new(sign: Function) -> Self {
+        Self { sign }
+    }
+
+    /// Sign a prepared proof, completing it
+    async fn sign_proof(
+        &self,
+        preparation: &ProofPreparation,
+        public_key: &JWK,
+    ) -> Result<Proof, CallbackError> {
+        let bytes = match &preparation.signing_input {
+            SigningInput::Bytes(bytes) => &bytes.0,
+            _ => return Err(CallbackError::UnsupportedSigningInput),
+        };
+        let algorithm = preparation
+            .jws_header
+            .as_ref()
+            .map(|header| header.algorithm)
+            .or_else(|| public_key.get_algorithm())
+            .ok_or(CallbackError::MissingAlgorithm)?;
+        let alg = match serde_json::to_value(algorithm).map_err(Error::from)? {
+            Value::String(alg) => alg,
+            _ => return Err(CallbackError::MissingAlgorithm),
+        };
+        let args = Array::of2(
+            &Uint8Array::from(bytes.as_slice()).into(),
+            &JsValue::from_str(&alg),
+        );
+        let value = call_js(&self.sign, &args)
+            .await
+            .map_err(CallbackError::JS)?;
+        let signature = if let Some(signature) = value.as_string() {
+            signature
+        } else if let Some(signature) = value.dyn_ref::<Uint8Array>() {
+            base64::encode_config(signature.to_vec(), base64::URL_SAFE_NO_PAD)
+        } else {
+            return Err(CallbackError::InvalidSignature);
+        };
+        let proof = preparation
+            .proof
+            .type_
+            .complete(preparation, &signature)
+            .await
+            .map_err(Error::from)?;
+        Ok(proof)
+    }
+}
+
+/// Resolve a DID using a JS `resolve(did, inputMetadata)` function, falling back to the built-in DID methods if
+/// it returns `null` or `undefined`
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn resolveDIDWithResolver(did: String, input_metadata: String, resolve: Function) -> Promise {
+    future_to_promise(async move {
+        let resolver = JsResolver::new(Some(resolve));
+        match resolve_did_with(&resolver, did, input_metadata).await {
+            Ok(string) => Ok(string.into()),
+            Err(err) => Err(err.into()),
+        }
+    })
+}
+
+/// Verify a credential, resolving DIDs using a JS `resolve(did, inputMetadata)` function
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "verify", feature = "credential"),
+    all(feature = "verify", not(feature = "presentation")),
+    all(
+        feature = "credential",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn verifyCredentialWithResolver(
+    vc: String,
+    proof_options: String,
+    resolve: Function,
+) -> Promise {
+    map_async_jsvalue(async move {
+        let resolver = JsResolver::new(Some(resolve));
+        verify_credential_with(&resolver, ContextLoader::default(), vc, proof_options).await
+    })
+}
+
+/// Verify a presentation, resolving DIDs using a JS `resolve(did, inputMetadata)` function
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(
+    all(feature = "verify", feature = "presentation"),
+    all(feature = "verify", not(feature = "credential")),
+    all(
+        feature = "presentation",
+        not(feature = "issue"),
+        not(feature = "verify")
+    )
+))]
+pub fn verifyPresentationWithResolver(
+    vp: String,
+    proof_options: String,
+    resolve: Function,
+) -> Promise {
+    map_async_jsvalue(async move {
+        let resolver = JsResolver::new(Some(resolve));
+        verify_presentation_with(&resolver, ContextLoader::default(), vp, proof_options).await
+    })
+}
+
+#[cfg(feature = "issue")]
+async fn issue_credential_with_signer(
+    credential: String,
+    linked_data_proof_options: String,
+    public_key: String,
+    sign: Function,
+    resolve: Option<Function>,
+) -> Result<String, CallbackError> {
+    let public_key: JWK = serde_json::from_str(&public_key).map_err(Error::from)?;
+    let mut credential =
+        VerifiableCredential::from_json_unsigned(&credential).map_err(Error::from)?;
+    let options: LinkedDataProofOptions =
+        serde_json::from_str(&linked_data_proof_options).map_err(Error::from)?;
+    let resolver = JsResolver::new(resolve);
+    let mut context_loader = ContextLoader::default();
+    let preparation = credential
+        .prepare_proof(&public_key, &options, &resolver, &mut context_loader)
+        .await
+        .map_err(Error::from)?;
+    let proof = JsSigner::new(sign)
+        .sign_proof(&preparation, &public_key)
+        .await?;
+    credential.add_proof(proof);
+    Ok(serde_json::to_string(&credential).map_err(Error::from)?)
+}
+
+/// Issue a credential with a linked data proof, signing with a JS `sign(bytes, alg)` function
+/// instead of a private key. The public key is given as a JWK. DIDs are resolved using an optional
+/// JS `resolve(did, inputMetadata)` function.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "issue")]
+pub fn issueCredentialWithSigner(
+    credential: String,
+    linked_data_proof_options: String,
+    public_key: String,
+    sign: Function,
+    resolve: Option<Function>,
+) -> Promise {
+    map_async_jsvalue(issue_credential_with_signer(
+        credential,
+        linked_data_proof_options,
+        public_key,
+        sign,
+        resolve,
+    ))
+}
+
+#[cfg(feature = "issue")]
+async fn issue_presentation_with_signer(
+    presentation: String,
+    linked_data_proof_options: String,
+    public_key: String,
+    sign: Function,
+    resolve: Option<Function>,
+) -> Result<String, CallbackError> {
+    let public_key: JWK = serde_json::from_str(&public_key).map_err(Error::from)?;
+    let mut presentation =
+        VerifiablePresentation::from_json_unsigned(&presentation).map_err(Error::from)?;
+    let options: LinkedDataProofOptions =
+        serde_json::from_str(&linked_data_proof_options).map_err(Error::from)?;
+    let resolver = JsResolver::new(resolve);
+    let mut context_loader = ContextLoader::default();
+    let preparation = presentation
+        .prepare_proof(&public_key, &options, &resolver, &mut context_loader)
+        .await
+        .map_err(Error::from)?;
+    let proof = JsSigner::new(sign)
+        .sign_proof(&preparation, &public_key)
+        .await?;
+    presentation.add_proof(proof);
+    Ok(serde_json::to_string(&presentation).map_err(Error::from)?)
+}
+
+/// Issue a presentation with a linked data proof, signing with a JS `sign(bytes, alg)` function
+/// instead of a private key. The public key is given as a JWK. DIDs are resolved using an optional
+/// JS `resolve(did, inputMetadata)` function.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(feature = "issue")]
+pub fn issuePresentationWithSigner(
+    presentation: String,
+    linked_data_proof_options: String,
+    public_key: String,
+    sign: Function,
+    resolve: Option<Function>,
+) -> Promise {
+    map_async_jsvalue(issue_presentation_with_signer(
+        presentation,
+        linked_data_proof_options,
+        public_key,
+        sign,
+        resolve,
+    ))
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -646,26 +1610,81 @@ pub fn completeDelegateCapability(
 }
 
 #[cfg(any(feature = "delegate", feature = "zcap", feature = "invoke"))]
-async fn verify_delegation(delegation: String) -> Result<String, Error> {
+async fn revocations(
+    revocation_lists: Option<String>,
+    resolver: &dyn DIDResolver,
+) -> Result<Revocations, Error> {
+    let lists: Vec<String> = match revocation_lists {
+        Some(lists) => serde_json::from_str(&lists)?,
+        None => Vec::new(),
+    };
+    Ok(Revocations::verify(&lists, resolver).await?)
+}
+
+#[cfg(any(feature = "delegate", feature = "zcap", feature = "invoke"))]
+async fn verify_delegation(
+    delegation: String,
+    revocation_lists: Option<String>,
+) -> Result<String, Error> {
     let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation)?;
     let resolver = DID_METHODS.to_resolver();
     let mut context_loader = ssi::jsonld::ContextLoader::default();
-    let result = delegation.verify(None, resolver, &mut context_loader).await;
+    let revocations = revocations(revocation_lists, resolver).await?;
+    let result = zcap::verify_delegation(
+        &delegation,
+        None,
+        &revocations,
+        chrono::Utc::now(),
+        resolver,
+        &mut context_loader,
+    )
+    .await;
     let result_json = serde_json::to_string(&result)?;
     Ok(result_json)
 }
 
+/// Verify a capability delegation, including its caveats.
+///
+/// `revocationLists` is an optional JSON array of revocation lists, as returned by
+/// `revokeCapabilities`.
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 #[cfg(any(feature = "delegate", feature = "zcap", feature = "invoke"))]
-pub fn verifyDelegation(delegation: String) -> Promise {
-    map_async_jsvalue(verify_delegation(delegation))
+pub fn verifyDelegation(delegation: String, revocation_lists: Option<String>) -> Promise {
+    map_async_jsvalue(verify_delegation(delegation, revocation_lists))
+}
+
+#[cfg(any(feature = "delegate", feature = "zcap"))]
+fn revoke_capabilities(
+    ids: String,
+    verification_method: String,
+    key: String,
+) -> Result<String, Error> {
+    let ids: Vec<String> = serde_json::from_str(&ids)?;
+    let key: JWK = serde_json::from_str(&key)?;
+    let jws = zcap::sign_revocation_list(ids, &verification_method, &key, chrono::Utc::now())?;
+    Ok(jws)
+}
+
+/// Sign a revocation list for the capabilities with the given ids (a JSON array), using a
+/// verification method of the revoking DID.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(feature = "delegate", feature = "zcap"))]
+pub fn revokeCapabilities(
+    ids: String,
+    verification_method: String,
+    key: String,
+) -> Result<String, JsValue> {
+    map_jsvalue(revoke_capabilities(ids, verification_method, key))
 }
 
 #[cfg(any(feature = "invoke", feature = "zcap"))]
 async fn invoke_capability(
     invocation: String,
     target_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
     linked_data_proof_options: String,
     key: String,
 ) -> Result<String, Error> {
@@ -674,31 +1693,41 @@ async fn invoke_capability(
     let options: LinkedDataProofOptions = serde_json::from_str(&linked_data_proof_options)?;
     let resolver = DID_METHODS.to_resolver();
     let mut context_loader = ssi::jsonld::ContextLoader::default();
-    let proof = invocation
-        .generate_proof(
-            &key,
-            &options,
-            resolver,
-            &mut context_loader,
-            &URI::String(target_id),
-        )
-        .await?;
+    let proof = zcap::invoke(
+        &invocation,
+        &URI::String(target_id),
+        action.as_deref(),
+        invocation_target.as_deref(),
+        Some(&key),
+        options,
+        resolver,
+        &mut context_loader,
+        None,
+    )
+    .await?;
     let json = serde_json::to_string(&invocation.set_proof(proof))?;
     Ok(json)
 }
 
+/// Invoke the capability with id `targetId`. The optional `action` and `invocationTarget` are
+/// included in the proof as `capabilityAction` and `invocationTarget`, to be checked against the
+/// capability's caveats.
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 #[cfg(any(feature = "invoke", feature = "zcap"))]
 pub fn invokeCapability(
     invocation: String,
     target_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
     linked_data_proof_options: String,
     key: String,
 ) -> Promise {
     map_async_jsvalue(invoke_capability(
         invocation,
         target_id,
+        action,
+        invocation_target,
         linked_data_proof_options,
         key,
     ))
@@ -707,6 +1736,8 @@ pub fn invokeCapability(
 async fn prepare_invoke_capability(
     invocation: String,
     target_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
     linked_data_proof_options: String,
     public_key: String,
 ) -> Result<String, Error> {
@@ -715,31 +1746,39 @@ async fn prepare_invoke_capability(
     let options: LinkedDataProofOptions = serde_json::from_str(&linked_data_proof_options)?;
     let resolver = DID_METHODS.to_resolver();
     let mut context_loader = ssi::jsonld::ContextLoader::default();
-    let preparation = invocation
-        .prepare_proof(
-            &public_key,
-            &options,
-            resolver,
-            &mut context_loader,
-            &URI::String(target_id),
-        )
-        .await?;
+    let preparation = zcap::prepare_invoke(
+        &invocation,
+        &URI::String(target_id),
+        action.as_deref(),
+        invocation_target.as_deref(),
+        &public_key,
+        options,
+        resolver,
+        &mut context_loader,
+    )
+    .await?;
     let preparation_json = serde_json::to_string(&preparation)?;
     Ok(preparation_json)
 }
 
+/// Prepare a capability invocation proof, to be signed externally. Arguments are as for
+/// `invokeCapability`, with the public key of the signer instead of the private key.
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 #[cfg(any(feature = "invoke", feature = "zcap"))]
 pub fn prepareInvokeCapability(
     invocation: String,
     target_id: String,
+    action: Option<String>,
+    invocation_target: Option<String>,
     linked_data_proof_options: String,
     public_key: String,
 ) -> Promise {
     map_async_jsvalue(prepare_invoke_capability(
         invocation,
         target_id,
+        action,
+        invocation_target,
         linked_data_proof_options,
         public_key,
     ))
@@ -796,21 +1835,230 @@ pub fn verifyInvocationSignature(invocation: String) -> Promise {
 }
 
 #[cfg(any(feature = "invoke", feature = "zcap"))]
-async fn verify_invocation(invocation: String, delegation: String) -> Result<String, Error> {
+async fn verify_invocation(
+    invocation: String,
+    delegation: String,
+    revocation_lists: Option<String>,
+) -> Result<String, Error> {
     let invocation: Invocation<Value> = serde_json::from_str(&invocation)?;
     let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation)?;
     let resolver = DID_METHODS.to_resolver();
     let mut context_loader = ssi::jsonld::ContextLoader::default();
-    let result = invocation
-        .verify(None, resolver, &mut context_loader, &delegation)
-        .await;
+    let revocations = revocations(revocation_lists, resolver).await?;
+    let result = zcap::verify_invocation(
+        &invocation,
+        &delegation,
+        None,
+        &revocations,
+        chrono::Utc::now(),
+        resolver,
+        &mut context_loader,
+    )
+    .await;
     let result_json = serde_json::to_string(&result)?;
     Ok(result_json)
 }
 
+/// Verify a capability invocation against the invoked capability's delegation, including the
+/// capability's caveats.
+///
+/// `revocationLists` is as for `verifyDelegation`.
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 #[cfg(any(feature = "invoke", feature = "zcap"))]
-pub fn verifyInvocation(invocation: String, delegation: String) -> Promise {
-    map_async_jsvalue(verify_invocation(invocation, delegation))
+pub fn verifyInvocation(
+    invocation: String,
+    delegation: String,
+    revocation_lists: Option<String>,
+) -> Promise {
+    map_async_jsvalue(verify_invocation(invocation, delegation, revocation_lists))
+}
+
+#[cfg(any(feature = "delegate", feature = "zcap", feature = "invoke"))]
+async fn verify_delegation_chain(
+    delegation: String,
+    capabilities: String,
+    root: String,
+    revocation_lists: Option<String>,
+) -> Result<String, Error> {
+    let delegation: Delegation<Value, Value> = serde_json::from_str(&delegation)?;
+    let store = CapabilityStore::from_value(serde_json::from_str(&capabilities)?)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let revocations = revocations(revocation_lists, resolver).await?;
+    let report = zcap::verify_delegation_chain(
+        &delegation,
+        &store,
+        &root,
+        &revocations,
+        chrono::Utc::now(),
+        resolver,
+        &mut context_loader,
+    )
+    .await;
+    let report_json = serde_json::to_string(&report)?;
+    Ok(report_json)
+}
+
+/// Verify a capability delegation and its delegation chain.
+///
+/// `capabilities` is a JSON array of capabilities, or an object mapping capability ids to
+/// capabilities, in which to look up parent capabilities. The chain must end at the capability
+/// with id `root`, the root capability trusted by the verifier. `revocationLists` is as for
+/// `verifyDelegation`. Resolves to a report with the checks and errors for each hop of the chain.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(feature = "delegate", feature = "zcap", feature = "invoke"))]
+pub fn verifyDelegationChain(
+    delegation: String,
+    capabilities: String,
+    root: String,
+    revocation_lists: Option<String>,
+) -> Promise {
+    map_async_jsvalue(verify_delegation_chain(
+        delegation,
+        capabilities,
+        root,
+        revocation_lists,
+    ))
+}
+
+#[cfg(any(feature = "invoke", feature = "zcap"))]
+async fn verify_invocation_chain(
+    invocation: String,
+    capabilities: String,
+    root: String,
+    revocation_lists: Option<String>,
+) -> Result<String, Error> {
+    let invocation: Invocation<Value> = serde_json::from_str(&invocation)?;
+    let store = CapabilityStore::from_value(serde_json::from_str(&capabilities)?)?;
+    let resolver = DID_METHODS.to_resolver();
+    let mut context_loader = ssi::jsonld::ContextLoader::default();
+    let revocations = revocations(revocation_lists, resolver).await?;
+    let report = zcap::verify_invocation_chain(
+        &invocation,
+        &store,
+        &root,
+        &revocations,
+        chrono::Utc::now(),
+        resolver,
+        &mut context_loader,
+    )
+    .await;
+    let report_json = serde_json::to_string(&report)?;
+    Ok(report_json)
+}
+
+/// Verify a capability invocation and the delegation chain of the invoked capability.
+///
+/// `capabilities` and `root` are as for `verifyDelegationChain`. `capabilities` must include the
+/// invoked capability, which may be the root capability.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+#[cfg(any(feature = "invoke", feature = "zcap"))]
+pub fn verifyInvocationChain(
+    invocation: String,
+    capabilities: String,
+    root: String,
+    revocation_lists: Option<String>,
+) -> Promise {
+    map_async_jsvalue(verify_invocation_chain(
+        invocation,
+        capabilities,
+        root,
+        revocation_lists,
+    ))
+}
+
+fn presentation_exchange_evaluate(
+    definition: String,
+    credentials: String,
+) -> Result<String, Error> {
+    let definition: PresentationDefinition = serde_json::from_str(&definition)?;
+    let credentials: Vec<Value> = serde_json::from_str(&credentials)?;
+    let evaluation = presentation_exchange::evaluate(&definition, &credentials)?;
+    let evaluation_json = serde_json::to_string(&evaluation)?;
+    Ok(evaluation_json)
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn presentationExchangeEvaluate(
+    definition: String,
+    credentials: String,
+) -> Result<String, JsValue> {
+    map_jsvalue(presentation_exchange_evaluate(definition, credentials))
+}
+
+fn presentation_exchange_create(
+    definition: String,
+    credentials: String,
+    holder: Option<String>,
+    proof_format: Option<String>,
+) -> Result<String, Error> {
+    let definition: PresentationDefinition = serde_json::from_str(&definition)?;
+    let credentials: Vec<Value> = serde_json::from_str(&credentials)?;
+    let proof_format = match proof_format {
+        Some(proof_format) => proof_format
+            .parse()
+            .map_err(|_| Error::UnknownProofFormat(proof_format))?,
+        None => ProofFormat::default(),
+    };
+    let (presentation, presentation_submission) = presentation_exchange::build_presentation(
+        &definition,
+        &credentials,
+        holder.as_deref(),
+        proof_format,
+    )?;
+    let result_json = serde_json::to_string(&serde_json::json!({
+        "presentation": presentation,
+        "presentation_submission": presentation_submission,
+    }))?;
+    Ok(result_json)
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn presentationExchangeCreate(
+    definition: String,
+    credentials: String,
+    holder: Option<String>,
+    proof_format: Option<String>,
+) -> Result<String, JsValue> {
+    map_jsvalue(presentation_exchange_create(
+        definition,
+        credentials,
+        holder,
+        proof_format,
+    ))
+}
+
+fn presentation_exchange_verify(
+    definition: String,
+    submission: String,
+    presentation: String,
+) -> Result<String, Error> {
+    let definition: PresentationDefinition = serde_json::from_str(&definition)?;
+    let submission: PresentationSubmission = serde_json::from_str(&submission)?;
+    let presentation = match serde_json::from_str(&presentation) {
+        Ok(presentation) => presentation,
+        Err(_) => Value::String(presentation),
+    };
+    let check = presentation_exchange::check_submission(&definition, &submission, &presentation)?;
+    let check_json = serde_json::to_string(&check)?;
+    Ok(check_json)
+}
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn presentationExchangeVerify(
+    definition: String,
+    submission: String,
+    presentation: String,
+) -> Result<String, JsValue> {
+    map_jsvalue(presentation_exchange_verify(
+        definition,
+        submission,
+        presentation,
+    ))
 }
@@ -1,14 +1,23 @@
 import init, {
     getVersion,
     generateEd25519Key,
+    generateSecp256k1Key,
+    generateP256Key,
     keyToDID,
     keyToVerificationMethod,
     issueCredential,
+    issueCredentialWithSigner,
     verifyCredential,
     issuePresentation,
     verifyPresentation,
     DIDAuth,
     resolveDID,
+    resolveDIDWithContext,
+    resolveDIDWithResolver,
+    dereferenceDIDURL,
+    toRDFURDNA2015,
+    createDID,
+    DIDKitContext,
     JWKFromTezos,
   } from './pkg/web/didkit_wasm.js';
 
@@ -416,6 +425,125 @@ const emptyObj = JSON.stringify({});
     await resolveDID("did:web:vc.transmute.world", JSON.stringify({}));
   });
 
+  test("should resolve did:key with a context", async () => {
+    const context = new DIDKitContext(JSON.stringify({ cacheResolutions: true }));
+    const did = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+    for (let i = 0; i < 2; i++) {
+      const doc = JSON.parse(await resolveDIDWithContext(context, did, emptyObj));
+      if (doc.id !== did) throw doc.id;
+    }
+    context.free();
+  });
+
+  test("should resolve a DID with a JS resolver", async () => {
+    const did = "did:example:private";
+    const resolve = async (id) => id === did ? {
+      "@context": "https://www.w3.org/ns/did/v1",
+      id: did,
+    } : null;
+    const doc = JSON.parse(await resolveDIDWithResolver(did, emptyObj, resolve));
+    if (doc.id !== did) throw doc.id;
+    const keyDid = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+    const keyDoc = JSON.parse(await resolveDIDWithResolver(keyDid, emptyObj, resolve));
+    if (keyDoc.id !== keyDid) throw keyDoc.id;
+  });
+
+  test("should pass the input metadata to a JS resolver", async () => {
+    const did = "did:example:private";
+    let versionId;
+    const resolve = async (id, inputMetadata) => {
+      versionId = inputMetadata.versionId;
+      return { "@context": "https://www.w3.org/ns/did/v1", id };
+    };
+    await resolveDIDWithResolver(did, JSON.stringify({ versionId: "2" }), resolve);
+    if (versionId !== "2") throw versionId;
+  });
+
+  test("should verify a credential issued with a JS signer", async () => {
+    const privateKey = await crypto.subtle.importKey(
+      "jwk",
+      key,
+      { name: "Ed25519" },
+      false,
+      ["sign"]
+    );
+    const sign = async (bytes, alg) => {
+      if (alg !== "EdDSA") throw alg;
+      return new Uint8Array(await crypto.subtle.sign("Ed25519", privateKey, bytes));
+    };
+    const { d, ...publicKey } = key;
+    const credential = await issueCredentialWithSigner(
+      JSON.stringify({
+        "@context": "https://www.w3.org/2018/credentials/v1",
+        id: "http://example.org/credentials/3731",
+        type: ["VerifiableCredential"],
+        issuer: did,
+        issuanceDate: "2020-08-19T21:41:50Z",
+        credentialSubject: {
+          id: other.did,
+        },
+      }),
+      JSON.stringify({
+        proofPurpose: "assertionMethod",
+        verificationMethod: verificationMethod,
+      }),
+      JSON.stringify(publicKey),
+      sign
+    );
+
+    const verify = JSON.parse(
+      await verifyCredential(
+        credential,
+        JSON.stringify({
+          proofPurpose: "assertionMethod",
+        })
+      )
+    );
+
+    if (verify.errors.length > 0) throw verify.errors;
+  });
+
+  test("should generate secp256k1 and P-256 keys", () => {
+    const k1 = JSON.parse(generateSecp256k1Key());
+    if (k1.crv !== "secp256k1") throw k1.crv;
+    const p256 = JSON.parse(generateP256Key());
+    if (p256.crv !== "P-256") throw p256.crv;
+  });
+
+  test("should dereference a DID URL", async () => {
+    const did = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+    const result = JSON.parse(await dereferenceDIDURL(`${did}#z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp`, emptyObj));
+    if (!JSON.stringify(result[1]).includes("Ed25519VerificationKey2018")) throw result;
+  });
+
+  test("should canonicalize JSON-LD with URDNA2015", async () => {
+    const nquads = await toRDFURDNA2015(JSON.stringify({
+      "@context": { "@vocab": "https://example.org/vocab#" },
+      "@id": "https://example.org/thing",
+      "name": "Thing",
+    }));
+    if (nquads !== '<https://example.org/thing> <https://example.org/vocab#name> "Thing" .\n') throw nquads;
+  });
+
+  test("should build a did:ion create transaction", () => {
+    const tx = JSON.parse(
+      createDID("ion", generateSecp256k1Key(), generateSecp256k1Key(), generateSecp256k1Key())
+    );
+    if (tx.didMethod !== "ion") throw tx.didMethod;
+    const operation = tx.value.sidetreeOperation;
+    if (operation.type !== "create") throw operation.type;
+    if (!operation.suffixData.recoveryCommitment) throw "missing recovery commitment";
+  });
+
+  test("should fail to create a DID with an unknown method", () => {
+    try {
+      createDID("foo");
+    } catch (e) {
+      return;
+    }
+    throw "did not fail";
+  });
+
   test("should convert edpk to JWK", async () => {
     const jwkStr = await JWKFromTezos("edpkuxZ5AQVCeEJ9inUG3w6VFhio5KBwC22ekPLBzcvub3QY2DvJ7n");
     const jwk = JSON.parse(jwkStr);